@@ -1,6 +1,8 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Fields, GenericArgument, Lit, Meta, PathArguments, Type,
+};
 
 /// Derives a `FieldList` trait implementation that provides field names as a vector.
 ///
@@ -17,14 +19,22 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta};
 /// ```
 ///
 /// Will generate field names: `["infiniteMessage", "timeout"]`
-#[proc_macro_derive(FieldList)]
+///
+/// It also generates `nested_field_names(path)`, which follows a path of YAML keys
+/// into the structs held by each field, so callers can list the valid keys at any
+/// depth. `Option`, `Vec` and `Box` wrappers are looked through, and `HashMap` fields
+/// consume one path segment for the (user-chosen) map key. Every field type that is
+/// not a scalar such as `String`, `bool` or an integer must also derive `FieldList`,
+/// unless the field is marked `#[field_list(leaf)]`. Fields marked `#[serde(flatten)]`
+/// contribute the field names of their own `FieldList` struct in their place.
+#[proc_macro_derive(FieldList, attributes(field_list))]
 pub fn derive_field_list(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let name = &input.ident;
 
     // Extract field names from the struct
-    let field_names = match &input.data {
+    let fields = match &input.data {
         Data::Struct(data) => {
             match &data.fields {
                 Fields::Named(fields) => {
@@ -37,10 +47,11 @@ pub fn derive_field_list(input: TokenStream) -> TokenStream {
                             // Check for serde rename attribute
                             let renamed = extract_serde_rename(&f.attrs);
 
-                            match renamed {
+                            let key = match renamed {
                                 Some(rename) => rename,
                                 None => field_name.to_string(),
-                            }
+                            };
+                            (key, f)
                         })
                         .collect::<Vec<_>>()
                 }
@@ -64,13 +75,80 @@ pub fn derive_field_list(input: TokenStream) -> TokenStream {
         }
     };
 
+    // Flattened fields splice the names of their struct in at their position
+    let field_names_body = if fields
+        .iter()
+        .any(|(_, field)| has_serde_flag(&field.attrs, "flatten"))
+    {
+        let additions = fields.iter().map(|(key, field)| {
+            if has_serde_flag(&field.attrs, "flatten") {
+                let ty = &field.ty;
+                quote! { names.extend(<#ty>::field_names()); }
+            } else {
+                quote! { names.push(#key); }
+            }
+        });
+        quote! {
+            let mut names = Vec::new();
+            #(#additions)*
+            names
+        }
+    } else {
+        let keys = fields.iter().map(|(key, _)| key);
+        quote! { vec![#(#keys),*] }
+    };
+
+    // Keys that are not fields of this struct may belong to a flattened one
+    let flattened_lookups = fields
+        .iter()
+        .filter(|(_, field)| has_serde_flag(&field.attrs, "flatten"))
+        .map(|(_, field)| {
+            let ty = &field.ty;
+            quote! {
+                if let Some(names) = <#ty>::nested_field_names(path) {
+                    return Some(names);
+                }
+            }
+        });
+
+    // One match arm per field that holds a struct with its own fields
+    let nested_arms = fields.iter().filter_map(|(key, field)| {
+        if has_field_list_flag(&field.attrs, "leaf") || has_serde_flag(&field.attrs, "flatten") {
+            return None;
+        }
+        let (inner, map_keys) = unwrap_field_type(&field.ty);
+        if is_scalar_type(inner) {
+            return None;
+        }
+        Some(quote! {
+            #key => rest.get(#map_keys..).and_then(<#inner>::nested_field_names),
+        })
+    });
+
     // Generate the trait implementation
     let expanded = quote! {
         impl #name {
             /// Returns a vector of field names for this struct.
             /// Field names respect `#[serde(rename = "...")]` attributes.
             pub fn field_names() -> Vec<&'static str> {
-                vec![#(#field_names),*]
+                #field_names_body
+            }
+
+            /// Returns the field names of the struct reached by following `path`, a list
+            /// of YAML mapping keys (without sequence indices) starting at this struct.
+            /// Map fields consume one extra key for the map entry. Returns `None` when the
+            /// path does not lead to a struct.
+            pub fn nested_field_names(path: &[&str]) -> Option<Vec<&'static str>> {
+                let Some((first, rest)) = path.split_first() else {
+                    return Some(Self::field_names());
+                };
+                match *first {
+                    #(#nested_arms)*
+                    _ => {
+                        #(#flattened_lookups)*
+                        None
+                    }
+                }
             }
         }
     };
@@ -111,3 +189,97 @@ fn extract_serde_rename(attrs: &[syn::Attribute]) -> Option<String> {
 
     None
 }
+
+/// Checks for a bare flag such as `flatten` inside a field's `#[serde(...)]` attributes
+fn has_serde_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("serde")
+            && attr
+                .parse_args_with(
+                    syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated,
+                )
+                .is_ok_and(|metas| {
+                    metas
+                        .iter()
+                        .any(|meta| matches!(meta, Meta::Path(path) if path.is_ident(flag)))
+                })
+    })
+}
+
+/// Checks for a `#[field_list(flag)]` attribute on a field
+fn has_field_list_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("field_list")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == flag)
+    })
+}
+
+/// Strips `Option`, `Vec`, `Box` and map wrappers from a field type, returning the
+/// innermost type and the number of map keys passed on the way
+fn unwrap_field_type(ty: &Type) -> (&Type, usize) {
+    let mut ty = ty;
+    let mut map_keys = 0;
+
+    while let Type::Path(type_path) = ty {
+        let Some(segment) = type_path.path.segments.last() else {
+            break;
+        };
+        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+            break;
+        };
+        let type_args: Vec<&Type> = args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect();
+
+        match (segment.ident.to_string().as_str(), type_args.as_slice()) {
+            ("Option" | "Vec" | "Box", [inner]) => ty = inner,
+            ("HashMap" | "BTreeMap" | "IndexMap", [_, value]) => {
+                map_keys += 1;
+                ty = value;
+            }
+            _ => break,
+        }
+    }
+
+    (ty, map_keys)
+}
+
+/// Returns true for types that hold a single YAML value rather than a mapping
+fn is_scalar_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return true;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return true;
+    };
+    matches!(
+        segment.ident.to_string().as_str(),
+        "String"
+            | "str"
+            | "bool"
+            | "char"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "f32"
+            | "f64"
+            | "PathBuf"
+            | "Value"
+    )
+}
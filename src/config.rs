@@ -7,15 +7,98 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-/// Configuration for individual stop commands with optional messages
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
-#[serde(deny_unknown_fields)]
-pub struct StopCommand {
-    /// The shell command to execute
-    pub run: String,
-    /// Custom error message to display when the command fails (exits with non-zero status)
-    #[serde(default)]
-    pub message: Option<String>,
+/// Shell used to run a command's `run` string
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandShell {
+    /// `bash -c` (the default)
+    #[default]
+    Bash,
+    /// POSIX `sh -c`
+    Sh,
+    /// `zsh -c`
+    Zsh,
+    /// No shell: the command is split into arguments with shell quoting rules and executed directly
+    None,
+}
+
+impl CommandShell {
+    /// Program that runs the command with `-c`, or `None` when the command is executed directly
+    #[must_use]
+    pub fn program(self) -> Option<&'static str> {
+        match self {
+            CommandShell::Bash => Some("bash"),
+            CommandShell::Sh => Some("sh"),
+            CommandShell::Zsh => Some("zsh"),
+            CommandShell::None => None,
+        }
+    }
+}
+
+/// How a command's `run` block is executed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    /// The whole block runs as one script in a single shell that stops at the first failing line (the default)
+    #[default]
+    Script,
+    /// Every non-comment line runs as a separate command
+    Lines,
+}
+
+/// Which lines of long command output are kept when it exceeds `maxOutputLines`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputMode {
+    /// The first lines (the default)
+    #[default]
+    Head,
+    /// The last lines, where compilers and test runners print their summary
+    Tail,
+    /// The first and last lines, split evenly
+    HeadTail,
+    /// Context windows around lines matching `errorPatterns`, falling back to the last lines when nothing matches
+    Errors,
+}
+
+/// What a failing command does to its hook, from `onFailure` and `exitCodes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureAction {
+    /// Block the hook with the failure message
+    Block,
+    /// Show the failure message to the user without blocking
+    Warn,
+    /// Pass the failure message and shown output to Claude as additional context without blocking
+    Context,
+    /// Log the failure and carry on
+    Ignore,
+}
+
+/// Toolchain output format a command's `parser` reads diagnostics from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticParser {
+    /// `cargo check|clippy|build --message-format=json`
+    CargoJson,
+    /// `tsc`, plain or `--pretty`
+    Tsc,
+    /// `eslint --format json`
+    EslintJson,
+    /// A JUnit XML report, printed by the command such as `pytest --junitxml=/dev/stdout` or
+    /// written to its `reportFile`
+    JunitXml,
+    /// `path:line[:col]: message` lines, as printed by gcc, go vet, mypy and flake8
+    GenericFileLine,
+}
+
+/// Settings shared by every configured command, flattened into each command entry
+///
+/// Covers how the command and its output are shown, how it is launched and retried, and
+/// what a failure does to the hook.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, FieldList)]
+#[schemars(deny_unknown_fields)]
+pub struct LaunchOptions {
     /// Whether to show the command being executed to the user and Claude. Default: true
     #[serde(default = "default_option_true", rename = "showCommand")]
     pub show_command: Option<bool>,
@@ -29,13 +112,185 @@ pub struct StopCommand {
     #[serde(default, rename = "maxOutputLines")]
     #[schemars(range(min = 1, max = 10000))]
     pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated and the hook is blocked.
+    /// Which lines are kept when output exceeds `maxOutputLines`: "head" (default), "tail", "headTail" (the first and last lines), or "errors" (context around lines matching `errorPatterns`)
+    #[serde(default, rename = "outputMode")]
+    #[field_list(leaf)]
+    pub output_mode: Option<OutputMode>,
+    /// Regexes marking the lines `outputMode: errors` keeps context around. Default: lines containing the word error, fail, failed, failure or panicked, in any case
+    #[serde(default, rename = "errorPatterns")]
+    pub error_patterns: Option<Vec<String>>,
+    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated and counts as a failure.
     #[serde(default)]
     #[schemars(range(min = 1, max = 3600))]
     pub timeout: Option<u64>,
     /// Whether to send individual notifications for this command (start and completion). Default: false
     #[serde(default, rename = "notifyPerCommand")]
     pub notify_per_command: Option<bool>,
+    /// Extra environment variables for the command, set on top of the hook's CONCLAUDE_* variables and `envFile`
+    #[serde(default)]
+    pub env: Option<std::collections::HashMap<String, String>>,
+    /// Dotenv file to load variables from, relative to the configuration file's directory. Variables in `env` take precedence
+    #[serde(default, rename = "envFile")]
+    pub env_file: Option<String>,
+    /// Working directory for the command, relative to the configuration file's directory. Default: the configuration file's directory
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Shell that runs the command: "bash" (default), "sh", "zsh", or "none" to split the command into arguments and execute it directly
+    #[serde(default)]
+    #[field_list(leaf)]
+    pub shell: Option<CommandShell>,
+    /// How `run` is executed: "script" (default) runs the whole block in one shell with `set -euo pipefail`; "lines" runs every line as a separate command
+    #[serde(default)]
+    #[field_list(leaf)]
+    pub mode: Option<RunMode>,
+    /// Number of times to retry the command after it fails. Range: 0-10. Default: 0
+    #[serde(default)]
+    #[schemars(range(min = 0, max = 10))]
+    pub retries: Option<u32>,
+    /// Seconds to wait before the first retry. The delay doubles after every further failed attempt. Range: 0-300. Default: 1
+    #[serde(default, rename = "retryDelay")]
+    #[schemars(range(min = 0, max = 300))]
+    pub retry_delay: Option<u64>,
+    /// Exit codes that trigger a retry. Default: any failure, including a timeout
+    #[serde(default, rename = "retryOn")]
+    pub retry_on: Option<Vec<i32>>,
+    /// Seconds a timed-out command's process group gets to exit after SIGTERM before it is killed with SIGKILL. Range: 0-300. Default: 5
+    #[serde(default, rename = "gracePeriod")]
+    #[schemars(range(min = 0, max = 300))]
+    pub grace_period: Option<u64>,
+    /// What a failure does to the hook: "block", "warn" (show the message without blocking), "context" (pass the message and shown output to Claude without blocking) or "ignore". Default: the hook's own behaviour
+    #[serde(default, rename = "onFailure")]
+    #[field_list(leaf)]
+    pub on_failure: Option<FailureAction>,
+    /// Failure actions for specific exit codes, such as `{1: warn, 2: block}`. Takes precedence over `onFailure`
+    #[serde(default, rename = "exitCodes")]
+    #[field_list(leaf)]
+    pub exit_codes: Option<std::collections::BTreeMap<i32, FailureAction>>,
+}
+
+/// Seconds a timed-out command gets to exit after SIGTERM when `gracePeriod` is unset
+pub const DEFAULT_GRACE_PERIOD_SECS: u64 = 5;
+
+/// Lines `outputMode: errors` keeps context around when `errorPatterns` is unset: the ones
+/// compilers and test runners use to report problems
+pub const DEFAULT_ERROR_PATTERN: &str = r"(?i)\b(error|fail|failed|failure|panicked)\b";
+
+impl LaunchOptions {
+    /// Whether the command is shown; true unless `showCommand` is false
+    #[must_use]
+    pub fn show_command(&self) -> bool {
+        self.show_command.unwrap_or(true)
+    }
+
+    /// Whether the command's stdout is shown; false unless `showStdout` is true
+    #[must_use]
+    pub fn show_stdout(&self) -> bool {
+        self.show_stdout.unwrap_or(false)
+    }
+
+    /// Whether the command's stderr is shown; false unless `showStderr` is true
+    #[must_use]
+    pub fn show_stderr(&self) -> bool {
+        self.show_stderr.unwrap_or(false)
+    }
+
+    /// Whether the command sends its own notifications; false unless `notifyPerCommand` is true
+    #[must_use]
+    pub fn notify_per_command(&self) -> bool {
+        self.notify_per_command.unwrap_or(false)
+    }
+
+    /// Which lines of long output are kept
+    #[must_use]
+    pub fn output_mode(&self) -> OutputMode {
+        self.output_mode.unwrap_or_default()
+    }
+
+    /// Shell that runs the command
+    #[must_use]
+    pub fn shell(&self) -> CommandShell {
+        self.shell.unwrap_or_default()
+    }
+
+    /// How the `run` block is executed
+    #[must_use]
+    pub fn mode(&self) -> RunMode {
+        self.mode.unwrap_or_default()
+    }
+
+    /// Retries after the first attempt
+    #[must_use]
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or(0)
+    }
+
+    /// Seconds before the first retry
+    #[must_use]
+    pub fn retry_delay(&self) -> u64 {
+        self.retry_delay.unwrap_or(1)
+    }
+
+    /// Seconds between SIGTERM and SIGKILL when the command times out
+    #[must_use]
+    pub fn grace_period(&self) -> u64 {
+        self.grace_period.unwrap_or(DEFAULT_GRACE_PERIOD_SECS)
+    }
+
+    /// The options with every unset setting that has a default filled in with it
+    ///
+    /// Settings without one, such as `timeout` or `onFailure` whose default depends on the
+    /// hook and the exit code, stay unset.
+    #[must_use]
+    pub fn with_defaults(&self) -> Self {
+        Self {
+            show_command: Some(self.show_command()),
+            show_stdout: Some(self.show_stdout()),
+            show_stderr: Some(self.show_stderr()),
+            output_mode: Some(self.output_mode()),
+            error_patterns: Some(
+                self.error_patterns
+                    .clone()
+                    .unwrap_or_else(|| vec![DEFAULT_ERROR_PATTERN.to_string()]),
+            ),
+            notify_per_command: Some(self.notify_per_command()),
+            shell: Some(self.shell()),
+            mode: Some(self.mode()),
+            retries: Some(self.retries()),
+            retry_delay: Some(self.retry_delay()),
+            grace_period: Some(self.grace_period()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Configuration for individual stop commands with optional messages
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
+#[serde(deny_unknown_fields)]
+pub struct StopCommand {
+    /// The shell command to execute
+    pub run: String,
+    /// Custom error message to display when the command fails (exits with non-zero status)
+    #[serde(default)]
+    pub message: Option<String>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
+    /// Parses the output of a failing command into a list of file:line diagnostics that replaces the raw output in the block message: "cargo-json", "tsc", "eslint-json", "junit-xml" or "generic-file-line"
+    #[serde(default)]
+    #[field_list(leaf)]
+    pub parser: Option<DiagnosticParser>,
+    /// File the command writes its report to, relative to its working directory, such as `report.xml` for `pytest --junitxml=report.xml`. When the command writes it, `parser` reads this file instead of the command's output; a report left over from an earlier run is ignored
+    #[serde(default, rename = "reportFile")]
+    pub report_file: Option<String>,
+    /// Command that repairs what this command checks, such as `cargo fmt` or `eslint --fix`. When the command fails, the fix runs and the command is run again; the hook only blocks if it still fails, and the files the fix modified are reported
+    #[serde(default)]
+    pub fix: Option<String>,
+    /// Name other commands can list in `needs`. Must be unique within the command list
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Names of commands that must succeed before this one starts. The command is skipped if any of them fails
+    #[serde(default)]
+    pub needs: Option<Vec<String>>,
 }
 
 /// Configuration for individual subagent stop commands with optional messages
@@ -47,26 +302,19 @@ pub struct SubagentStopCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated and the hook is blocked.
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
+    /// Parses the output of a failing command into a list of file:line diagnostics that replaces the raw output in the block message: "cargo-json", "tsc", "eslint-json", "junit-xml" or "generic-file-line"
     #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command (start and completion). Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    #[field_list(leaf)]
+    pub parser: Option<DiagnosticParser>,
+    /// File the command writes its report to, relative to its working directory, such as `report.xml` for `pytest --junitxml=report.xml`. When the command writes it, `parser` reads this file instead of the command's output; a report left over from an earlier run is ignored
+    #[serde(default, rename = "reportFile")]
+    pub report_file: Option<String>,
+    /// Command that repairs what this command checks, such as `cargo fmt` or `eslint --fix`. When the command fails, the fix runs and the command is run again; the hook only blocks if it still fails, and the files the fix modified are reported
+    #[serde(default)]
+    pub fix: Option<String>,
 }
 
 /// Configuration for individual slash command entries with optional messages
@@ -78,26 +326,9 @@ pub struct SlashCommandEntry {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated and the hook is blocked.
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command (start and completion). Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual skill start commands with optional messages
@@ -109,26 +340,9 @@ pub struct SkillStartCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated and the hook is blocked.
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command (start and completion). Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual teammate idle commands with optional messages
@@ -140,26 +354,9 @@ pub struct TeammateIdleCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual task completed commands with optional messages
@@ -171,26 +368,9 @@ pub struct TaskCompletedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual setup commands with optional messages
@@ -202,26 +382,9 @@ pub struct SetupCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual config change commands with optional messages
@@ -233,26 +396,9 @@ pub struct ConfigChangeCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for subagent stop hooks with pattern-based command execution.
@@ -297,7 +443,7 @@ pub struct ConfigChangeCommand {
 ///     "*":
 ///       - run: "echo 'Subagent completed'"
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, FieldList)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
 #[serde(deny_unknown_fields)]
 pub struct SubagentStopConfig {
     /// Map of subagent name patterns to command configurations.
@@ -313,13 +459,30 @@ pub struct SubagentStopConfig {
     ///
     /// Command options (same as stop hook):
     /// - `run`: (required) Command to execute
+    /// - `message`: (optional) Custom error message on non-zero exit
+    /// - `showCommand`: (optional) Show the command in logs and messages. Default: true
     /// - `showStdout`: (optional) Show stdout to user/Claude. Default: false
     /// - `showStderr`: (optional) Show stderr to user/Claude. Default: false
-    /// - `message`: (optional) Custom error message on non-zero exit
-    /// - `maxOutputLines`: (optional) Limit output lines. Range: 1-10000
-    /// - `timeout`: (optional) Command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, command is terminated and hook is blocked.
+    /// - `maxOutputLines`, `outputMode`, `errorPatterns`: (optional) Limit output lines and choose which are kept. Range: 1-10000
+    /// - `timeout`: (optional) Command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, command is terminated and counts as a failure.
+    /// - `env`, `envFile`, `cwd`, `shell`, `mode`: (optional) How the command is launched
+    /// - `retries`, `retryDelay`, `retryOn`, `gracePeriod`: (optional) Retrying and terminating the command
+    /// - `onFailure`, `exitCodes`: (optional) What a failure does; `block` blocks the subagent. Default: ignore
+    /// - `parser`, `reportFile`, `fix`: (optional) Parse the output or a report file into diagnostics, and run a repair before the command is checked again
     #[serde(default)]
     pub commands: std::collections::HashMap<String, Vec<SubagentStopCommand>>,
+    /// Stop at the first failure that blocks the subagent and block with its message. Set to false to run every matching command and block with one report listing all blocking failures. Failures only block for commands whose `onFailure` or `exitCodes` is `block`; others are logged and the subagent finishes. Default: true
+    #[serde(default = "default_true", rename = "failFast")]
+    pub fail_fast: bool,
+}
+
+impl Default for SubagentStopConfig {
+    fn default() -> Self {
+        Self {
+            commands: std::collections::HashMap::new(),
+            fail_fast: true,
+        }
+    }
 }
 
 /// Configuration for slash command hooks that trigger when users invoke slash commands.
@@ -534,26 +697,9 @@ pub struct PostCompactCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual cwd-changed commands with optional messages.
@@ -565,26 +711,9 @@ pub struct CwdChangedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual file-changed commands with optional messages.
@@ -596,26 +725,9 @@ pub struct FileChangedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual instructions-loaded commands with optional messages.
@@ -627,26 +739,9 @@ pub struct InstructionsLoadedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for post-compact hooks with trigger-based command execution.
@@ -707,26 +802,9 @@ pub struct PostToolBatchCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual permission-denied commands with optional messages.
@@ -738,26 +816,9 @@ pub struct PermissionDeniedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual user-prompt-expansion commands with optional messages.
@@ -769,26 +830,9 @@ pub struct UserPromptExpansionCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for post-tool-batch hooks.
@@ -835,26 +879,9 @@ pub struct TaskCreatedCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual elicitation commands with optional messages.
@@ -866,26 +893,9 @@ pub struct ElicitationCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual elicitation-result commands with optional messages.
@@ -897,26 +907,9 @@ pub struct ElicitationResultCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for individual message-display commands with optional messages.
@@ -928,26 +921,9 @@ pub struct MessageDisplayCommand {
     /// Custom error message to display when the command fails (exits with non-zero status)
     #[serde(default)]
     pub message: Option<String>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command. Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
 }
 
 /// Configuration for task-created hooks with subject-based command execution.
@@ -1004,10 +980,10 @@ pub struct MessageDisplayConfig {
 }
 
 /// Configuration for stop hook commands that run when Claude is about to stop
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, FieldList)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
 #[serde(deny_unknown_fields)]
 pub struct StopConfig {
-    /// List of commands to execute when Claude is about to stop. Commands run in order and can provide custom error messages and control output display.
+    /// List of commands to execute when Claude is about to stop. Commands run in order, or concurrently with `parallel`, and can provide custom error messages and control output display.
     #[serde(default)]
     pub commands: Vec<StopCommand>,
     /// Infinite mode - when enabled, allows Claude to continue automatically instead of ending the session after stop hook commands succeed. Default: false
@@ -1016,6 +992,48 @@ pub struct StopConfig {
     /// Message to send to Claude when infinite mode is enabled and stop hook commands succeed. Claude receives this message to continue working.
     #[serde(default, rename = "infiniteMessage")]
     pub infinite_message: Option<String>,
+    /// Run commands concurrently instead of one after another. Commands still wait for the commands listed in their `needs`. Default: false
+    #[serde(default)]
+    pub parallel: bool,
+    /// Maximum number of commands running at once when `parallel` is enabled. Range: 1-64. Default: the number of CPUs
+    #[serde(default, rename = "maxParallel")]
+    #[schemars(range(min = 1, max = 64))]
+    pub max_parallel: Option<usize>,
+    /// Stop at the first failing command. Set to false to run every command and block with one report listing all failures, so Claude can fix them in one pass. Default: true
+    #[serde(default = "default_true", rename = "failFast")]
+    pub fail_fast: bool,
+}
+
+impl Default for StopConfig {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            infinite: false,
+            infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
+        }
+    }
+}
+
+impl StopConfig {
+    /// Number of stop commands that may run at once: 1 unless `parallel` is enabled
+    #[must_use]
+    pub fn concurrency(&self) -> usize {
+        if !self.parallel {
+            return 1;
+        }
+        self.max_parallel()
+    }
+
+    /// `maxParallel`, or the number of CPUs when it is unset
+    #[must_use]
+    pub fn max_parallel(&self) -> usize {
+        self.max_parallel.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+        })
+    }
 }
 
 /// Configuration for stop failure hook commands that run when a turn ends due to an API error
@@ -1032,7 +1050,7 @@ pub struct StopFailureConfig {
 /// Allows controlling which tools can be used on which files or with which command patterns.
 /// Rules are evaluated in order and the first matching rule determines the action.
 /// Supports optional agent scoping to apply rules only to specific agents.
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
 #[serde(deny_unknown_fields)]
 pub struct ToolUsageRule {
     /// The tool name to match against. Supports glob patterns (e.g., "*" for all tools, "Write", "Bash")
@@ -1270,6 +1288,7 @@ pub struct PreToolUseConfig {
     ///
     /// Default: `[".conclaude.yml", ".conclaude.yaml"]`
     #[serde(default, rename = "uneditableFiles")]
+    #[field_list(leaf)]
     pub uneditable_files: Vec<UnEditableFileRule>,
     /// Block Claude from modifying or creating files that match .gitignore patterns.
     ///
@@ -1440,6 +1459,20 @@ pub struct ContextInjectionRule {
     pub case_insensitive: Option<bool>,
 }
 
+impl ContextInjectionRule {
+    /// Whether the rule is active; true unless `enabled` is false
+    #[must_use]
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Whether the pattern ignores case; false unless `caseInsensitive` is true
+    #[must_use]
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive.unwrap_or(false)
+    }
+}
+
 /// Configuration for individual user prompt submit commands.
 ///
 /// These commands run when a user submits a prompt to Claude.
@@ -1482,26 +1515,17 @@ pub struct UserPromptSubmitCommand {
     /// Use case-insensitive pattern matching. Default: false
     #[serde(default, rename = "caseInsensitive")]
     pub case_insensitive: Option<bool>,
-    /// Whether to show the command being executed to the user and Claude. Default: true
-    #[serde(default = "default_option_true", rename = "showCommand")]
-    pub show_command: Option<bool>,
-    /// Whether to show the command's standard output to the user and Claude. Default: false
-    #[serde(default, rename = "showStdout")]
-    pub show_stdout: Option<bool>,
-    /// Whether to show the command's standard error output to the user and Claude. Default: false
-    #[serde(default, rename = "showStderr")]
-    pub show_stderr: Option<bool>,
-    /// Maximum number of output lines to display (limits both stdout and stderr). Range: 1-10000
-    #[serde(default, rename = "maxOutputLines")]
-    #[schemars(range(min = 1, max = 10000))]
-    pub max_output_lines: Option<u32>,
-    /// Optional command timeout in seconds. Range: 1-3600 (1 second to 1 hour). When timeout occurs, the command is terminated (but does not block prompt processing).
-    #[serde(default)]
-    #[schemars(range(min = 1, max = 3600))]
-    pub timeout: Option<u64>,
-    /// Whether to send individual notifications for this command (start and completion). Default: false
-    #[serde(default, rename = "notifyPerCommand")]
-    pub notify_per_command: Option<bool>,
+    /// How the command is shown, launched and retried, and what a failure does to the hook
+    #[serde(flatten)]
+    pub launch: LaunchOptions,
+}
+
+impl UserPromptSubmitCommand {
+    /// Whether `pattern` ignores case; false unless `caseInsensitive` is true
+    #[must_use]
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive.unwrap_or(false)
+    }
 }
 
 /// Configuration for user prompt submit hook with context injection rules and command execution.
@@ -1727,6 +1751,55 @@ fn default_show_system_events() -> bool {
     true
 }
 
+/// Behavior of hooks in projects that have no `.conclaude.yaml`.
+///
+/// Hooks are often installed globally, so they also fire in repositories that have not
+/// adopted conclaude. This section is read from the global user configuration
+/// (`~/.config/conclaude/config.yaml`); it has no effect in a project file, because a
+/// project file means the configuration is not missing.
+///
+/// # Examples
+///
+/// ```yaml
+/// # ~/.config/conclaude/config.yaml
+/// missingConfig:
+///   mode: allow
+///   sessionStartHint: true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, FieldList)]
+#[serde(deny_unknown_fields)]
+pub struct MissingConfigConfig {
+    /// What hooks do when no project configuration is found.
+    ///
+    /// Valid values:
+    /// - `"allow"` - Allow every operation and run no commands, logging the fact once
+    /// - `"block"` - Fail closed: every hook blocks with the configuration-not-found error
+    ///
+    /// Default: `"allow"`
+    #[serde(default = "default_missing_config_mode")]
+    pub mode: String,
+    /// Add a one-time hint to Claude's context at session start explaining that
+    /// conclaude is installed but the project has no configuration. Only used with
+    /// `mode: allow`.
+    ///
+    /// Default: `false`
+    #[serde(default, rename = "sessionStartHint")]
+    pub session_start_hint: bool,
+}
+
+impl Default for MissingConfigConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_missing_config_mode(),
+            session_start_hint: false,
+        }
+    }
+}
+
+fn default_missing_config_mode() -> String {
+    "allow".to_string()
+}
+
 impl NotificationsConfig {
     /// Check if notifications are enabled for a specific hook
     #[must_use]
@@ -1746,19 +1819,84 @@ impl NotificationsConfig {
 }
 
 /// Main configuration interface matching the TypeScript version
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, FieldList)]
 #[serde(deny_unknown_fields)]
 pub struct ConclaudeConfig {
+    /// Keeps the global user configuration out of this project.
+    ///
+    /// The global user configuration (`~/.config/conclaude/config.yaml`) is not merged
+    /// underneath a project whose files set `root: true`. Use it to keep a project's rules
+    /// self-contained regardless of what a user has configured for all their projects.
+    ///
+    /// # Examples
+    ///
+    /// ```yaml
+    /// root: true
+    /// ```
+    #[serde(default)]
+    pub root: bool,
+    /// Base configuration file that this file extends.
+    ///
+    /// The base is loaded first and this file is merged on top of it: lists append,
+    /// maps merge by key and scalars override. Relative paths are resolved against the
+    /// directory of the file that declares `extends`; `~/` expands to the home directory.
+    ///
+    /// # Examples
+    ///
+    /// ```yaml
+    /// extends: "../platform/conclaude-base.yaml"
+    /// ```
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Configuration fragments merged after this file, in the listed order.
+    ///
+    /// Entries may be files, directories (every `*.yaml`/`*.yml` file inside, sorted by
+    /// name) or glob patterns, relative to the directory of the declaring file.
+    ///
+    /// # Examples
+    ///
+    /// ```yaml
+    /// include:
+    ///   - ".conclaude.d"
+    ///   - "config/stop-checks.yaml"
+    /// ```
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Named profiles that overlay the base configuration when selected.
+    ///
+    /// A profile is selected with the `CONCLAUDE_PROFILE` environment variable or the
+    /// `--profile` flag on `conclaude Hooks <event>`. Its sections are merged on top of
+    /// the base configuration with the layering rules: lists append, maps merge by key
+    /// and scalars override. Profiles cannot use `profiles`, `extends` or `include`.
+    ///
+    /// # Examples
+    ///
+    /// ```yaml
+    /// profiles:
+    ///   strict:
+    ///     stop:
+    ///       commands:
+    ///         - run: "cargo test --all-features"
+    ///     preToolUse:
+    ///       preventUpdateGitIgnored: true
+    ///   overnight:
+    ///     stop:
+    ///       infinite: true
+    ///     notifications:
+    ///       enabled: true
+    /// ```
+    #[serde(default)]
+    pub profiles: std::collections::HashMap<String, ConclaudeConfig>,
+    #[serde(default)]
+    pub stop: StopConfig,
+    /// Configuration for StopFailure hook - commands to run when a turn ends due to an API error
+    #[serde(default, rename = "stopFailure")]
+    pub stop_failure: StopFailureConfig,
+    #[serde(default, rename = "subagentStop")]
+    pub subagent_stop: SubagentStopConfig,
+    #[serde(default, rename = "preToolUse")]
+    pub pre_tool_use: PreToolUseConfig,
     #[serde(default)]
-    pub stop: StopConfig,
-    /// Configuration for StopFailure hook - commands to run when a turn ends due to an API error
-    #[serde(default, rename = "stopFailure")]
-    pub stop_failure: StopFailureConfig,
-    #[serde(default, rename = "subagentStop")]
-    pub subagent_stop: SubagentStopConfig,
-    #[serde(default, rename = "preToolUse")]
-    pub pre_tool_use: PreToolUseConfig,
-    #[serde(default)]
     pub notifications: NotificationsConfig,
     #[serde(default, rename = "permissionRequest")]
     pub permission_request: Option<PermissionRequestConfig>,
@@ -1851,6 +1989,10 @@ pub struct ConclaudeConfig {
     /// Configuration for message-display hooks that run as assistant messages stream.
     #[serde(default, rename = "messageDisplay")]
     pub message_display: MessageDisplayConfig,
+    /// Behavior of hooks in projects without a configuration file. Only read from the
+    /// global user configuration.
+    #[serde(default, rename = "missingConfig")]
+    pub missing_config: MissingConfigConfig,
 }
 
 /// Extract the field name from an unknown field error message
@@ -1865,46 +2007,33 @@ fn extract_unknown_field(error_msg: &str) -> Option<String> {
     None
 }
 
-/// Suggest similar field names based on the unknown field
-///
-/// TODO: Use this to suggest similar fields during config validation
-pub fn suggest_similar_fields(unknown_field: &str, section: &str) -> Vec<String> {
-    let all_fields: Vec<(&str, Vec<&str>)> = vec![
-        ("stop", StopConfig::field_names()),
-        ("stopFailure", StopFailureConfig::field_names()),
-        ("subagentStop", SubagentStopConfig::field_names()),
-        ("preToolUse", PreToolUseConfig::field_names()),
-        ("notifications", NotificationsConfig::field_names()),
-        ("permissionRequest", PermissionRequestConfig::field_names()),
-        ("userPromptSubmit", UserPromptSubmitConfig::field_names()),
-        ("skillStart", SkillStartConfig::field_names()),
-        ("teammateIdle", TeammateIdleConfig::field_names()),
-        ("taskCompleted", TaskCompletedConfig::field_names()),
-        ("configChange", ConfigChangeConfig::field_names()),
-        ("worktreeCreate", WorktreeCreateConfig::field_names()),
-        ("setup", SetupConfig::field_names()),
-        ("commands", StopCommand::field_names()),
-        ("subagentStopCommands", SubagentStopCommand::field_names()),
-        ("slashCommands", SlashCommandEntry::field_names()),
-        ("skillStartCommands", SkillStartCommand::field_names()),
-        ("teammateIdleCommands", TeammateIdleCommand::field_names()),
-        ("taskCompletedCommands", TaskCompletedCommand::field_names()),
-        ("configChangeCommands", ConfigChangeCommand::field_names()),
-        ("setupCommands", SetupCommand::field_names()),
-        (
-            "userPromptSubmitCommands",
-            UserPromptSubmitCommand::field_names(),
-        ),
-    ];
+/// Suggest similar field names for an unknown field found in the mapping at `yaml_path`
+///
+/// The path is the one serde_yaml reports for the parent value (e.g. `stop.commands[0]`
+/// or `subagentStop.commands.coder[0]`), so typos are matched against the fields that
+/// are valid at that exact depth of the configuration.
+pub fn suggest_fields_at_path(unknown_field: &str, yaml_path: &str) -> Vec<String> {
+    field_names_at_path(yaml_path)
+        .map(|fields| rank_similar_fields(unknown_field, &fields))
+        .unwrap_or_default()
+}
 
-    // Find the section's valid fields
-    let empty_fields: Vec<&str> = vec![];
-    let valid_fields = all_fields
+/// Returns the valid field names of the mapping at `yaml_path`, or `None` when the path
+/// does not lead to a configuration section
+fn field_names_at_path(yaml_path: &str) -> Option<Vec<&'static str>> {
+    let segments = parse_yaml_path(yaml_path);
+    let keys: Vec<&str> = segments
         .iter()
-        .find(|(s, _)| *s == section)
-        .map(|(_, fields)| fields)
-        .unwrap_or(&empty_fields);
+        .filter_map(|segment| match segment {
+            YamlPathSegment::Key(key) => Some(key.as_str()),
+            YamlPathSegment::Index(_) => None,
+        })
+        .collect();
+    ConclaudeConfig::nested_field_names(&keys)
+}
 
+/// Rank candidate field names by edit distance, keeping the three closest matches
+fn rank_similar_fields(unknown_field: &str, valid_fields: &[&str]) -> Vec<String> {
     // Calculate Levenshtein distance and suggest close matches
     let mut suggestions: Vec<(usize, &str)> = valid_fields
         .iter()
@@ -1949,23 +2078,46 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     matrix[len1][len2]
 }
 
-/// Extract section name from error message (e.g., "stop.infinite" -> "stop")
-fn extract_section_from_error(error_msg: &str) -> Option<String> {
-    // Look for patterns like "stop:", "rules.", "notifications:"
-    if let Some(colon_idx) = error_msg.find(':') {
-        let before_colon = &error_msg[..colon_idx];
-        if let Some(last_word) = before_colon.split_whitespace().last() {
-            if let Some(section) = last_word.split('.').next() {
-                return Some(section.to_string());
-            }
-        }
+/// Split a serde_yaml error message into the path of the offending value and the rest
+///
+/// serde_yaml prefixes errors below the document root with that path (e.g.
+/// `stop.commands[0]: unknown field ...`); errors at the root have no prefix.
+fn split_error_path(error_msg: &str) -> (&str, &str) {
+    match error_msg.split_once(": ") {
+        Some((path, detail)) if !path.contains(' ') => (path, detail),
+        _ => ("", error_msg),
     }
-    None
+}
+
+/// Append the fallback list of valid field names for the most common sections
+fn push_static_field_names(parts: &mut Vec<String>) {
+    parts.push("Valid field names by section:".to_string());
+    parts.push(
+        "  stop: commands, infinite, infiniteMessage, parallel, maxParallel, failFast".to_string(),
+    );
+    parts.push("  subagentStop: commands, failFast".to_string());
+    parts.push(
+        "  preToolUse: preventAdditions, preventRootAdditions, preventRootAdditionsMessage, uneditableFiles, preventUpdateGitIgnored, toolUsageValidation"
+            .to_string(),
+    );
+    parts.push(
+        "  notifications: enabled, hooks, showErrors, showSuccess, showSystemEvents".to_string(),
+    );
+    parts.push("  permissionRequest: default, allow, deny".to_string());
+    parts.push(
+        "  commands (stop): run, message, showStdout, showStderr, maxOutputLines, timeout"
+            .to_string(),
+    );
+    parts.push(
+        "  commands (subagentStop): run, message, showStdout, showStderr, maxOutputLines, timeout"
+            .to_string(),
+    );
+    parts.push("  setup: commands".to_string());
+    parts.push("  commands (setup): run, message, showCommand, showStdout, showStderr, maxOutputLines, timeout".to_string());
 }
 
 /// Format a descriptive error message for YAML parsing failures
-fn format_parse_error(error: &serde_yaml::Error, config_path: &Path) -> String {
-    let base_error = error.to_string();
+fn format_parse_error(base_error: &str, config_path: &Path) -> String {
     let mut parts = vec![
         format!(
             "Failed to parse configuration file: {}",
@@ -1983,11 +2135,9 @@ fn format_parse_error(error: &serde_yaml::Error, config_path: &Path) -> String {
         parts.push(String::new());
 
         // Try to extract the unknown field and suggest alternatives
-        let unknown_field = extract_unknown_field(&base_error);
-        let section = extract_section_from_error(&base_error);
-
-        if let (Some(field), Some(sec)) = (unknown_field, section) {
-            let suggestions = suggest_similar_fields(&field, &sec);
+        let (parent, _) = split_error_path(base_error);
+        if let Some(field) = extract_unknown_field(base_error) {
+            let suggestions = suggest_fields_at_path(&field, parent);
             if !suggestions.is_empty() {
                 parts.push("Did you mean one of these?".to_string());
                 for suggestion in &suggestions {
@@ -2002,25 +2152,17 @@ fn format_parse_error(error: &serde_yaml::Error, config_path: &Path) -> String {
         parts.push("  • Using a field that doesn't exist in this section".to_string());
         parts.push("  • Using camelCase vs snake_case incorrectly (use camelCase)".to_string());
         parts.push(String::new());
-        parts.push("Valid field names by section:".to_string());
-        parts.push("  stop: commands, infinite, infiniteMessage".to_string());
-        parts.push("  subagentStop: commands".to_string());
-        parts.push(
-            "  preToolUse: preventAdditions, preventRootAdditions, preventRootAdditionsMessage, uneditableFiles, preventUpdateGitIgnored, toolUsageValidation"
-                .to_string(),
-        );
-        parts.push(
-            "  notifications: enabled, hooks, showErrors, showSuccess, showSystemEvents"
-                .to_string(),
-        );
-        parts.push("  permissionRequest: default, allow, deny".to_string());
-        parts.push(
-            "  commands (stop): run, message, showStdout, showStderr, maxOutputLines, timeout"
-                .to_string(),
-        );
-        parts.push("  commands (subagentStop): run, message, showStdout, showStderr, maxOutputLines, timeout".to_string());
-        parts.push("  setup: commands".to_string());
-        parts.push("  commands (setup): run, message, showCommand, showStdout, showStderr, maxOutputLines, timeout".to_string());
+        if let Some(fields) = field_names_at_path(parent) {
+            let section = if parent.is_empty() {
+                "the top level"
+            } else {
+                parent
+            };
+            parts.push(format!("Valid field names for {section}:"));
+            parts.push(format!("  {}", fields.join(", ")));
+        } else {
+            push_static_field_names(&mut parts);
+        }
     } else if base_error.contains("invalid type") {
         parts.push(String::new());
         parts.push("Type mismatch detected. Common causes:".to_string());
@@ -2076,392 +2218,2839 @@ fn format_parse_error(error: &serde_yaml::Error, config_path: &Path) -> String {
 ///
 /// Returns an error if YAML parsing fails or validation constraints are violated.
 pub fn parse_and_validate_config(content: &str, config_path: &Path) -> Result<ConclaudeConfig> {
-    let config: ConclaudeConfig = serde_yaml::from_str(content).map_err(|e| {
-        let error_msg = format_parse_error(&e, config_path);
-        anyhow::anyhow!(error_msg)
-    })?;
+    let mut config: ConclaudeConfig = serde_yaml::from_str(content)
+        .map_err(|e| ConfigParseError::new(&e, config_path, Some(content)))?;
+
+    if content.contains("${") {
+        let mut value = serde_yaml::to_value(&config)
+            .context("Failed to prepare configuration for variable interpolation")?;
+        interpolate_config_value(&mut value, config_path)?;
+        config = serde_yaml::from_value(value)
+            .context("Failed to apply variable interpolation to configuration")?;
+    }
 
-    validate_config_constraints(&config)?;
+    validate_config_constraints(&config, config_path, Some(content))?;
 
     Ok(config)
 }
 
-/// Validate configuration values against constraints
-fn validate_config_constraints(config: &ConclaudeConfig) -> Result<()> {
-    // Validate maxOutputLines range (1-10000)
-    for (idx, command) in config.stop.commands.iter().enumerate() {
-        if let Some(max_lines) = command.max_output_lines {
-            if !(1..=10000).contains(&max_lines) {
-                let error_msg = format!(
-                    "Range validation failed for stop.commands[{idx}].maxOutputLines\n\n\
-                     Error: Value {max_lines} is out of valid range\n\n\
-                     Valid range: 1 to 10000\n\n\
-                     Common causes:\n\
-                       • Value is too large (maximum is 10000)\n\
-                       • Value is too small (minimum is 1)\n\
-                       • Using a negative number\n\n\
-                     Example valid configurations:\n\
-                       maxOutputLines: 100      # default, good for most cases\n\
-                       maxOutputLines: 1000     # for verbose output\n\
-                       maxOutputLines: 10000    # maximum allowed\n\n\
-                     For a valid configuration template, run:\n\
-                       conclaude init"
-                );
-                return Err(anyhow::anyhow!(error_msg));
-            }
-        }
+/// Keys whose string values are expanded by [`interpolate_config_value`]
+const INTERPOLATED_KEYS: [&str; 5] = ["run", "fix", "message", "prompt", "infiniteMessage"];
 
-        // Validate timeout range (1-3600)
-        if let Some(timeout) = command.timeout {
-            if !(1..=3600).contains(&timeout) {
-                let error_msg = format!(
-                    "Range validation failed for stop.commands[{idx}].timeout\n\n\
-                     Error: Value {timeout} is out of valid range\n\n\
-                     Valid range: 1 to 3600 seconds (1 second to 1 hour)\n\n\
-                     Common causes:\n\
-                       • Value is too large (maximum is 3600 seconds / 1 hour)\n\
-                       • Value is too small (minimum is 1 second)\n\
-                       • Using a negative number\n\n\
-                     Example valid configurations:\n\
-                       timeout: 30       # 30 seconds\n\
-                       timeout: 300      # 5 minutes\n\
-                       timeout: 3600     # maximum allowed (1 hour)\n\n\
-                     For a valid configuration template, run:\n\
-                       conclaude init"
-                );
-                return Err(anyhow::anyhow!(error_msg));
-            }
-        }
-    }
+/// Values available to `${...}` references in a configuration file
+///
+/// Git-derived built-ins are resolved lazily so that configs which never reference
+/// them don't pay for spawning git.
+struct InterpolationContext {
+    config_dir: PathBuf,
+    git_root: std::cell::OnceCell<Option<String>>,
+    branch: std::cell::OnceCell<Option<String>>,
+}
 
-    // Validate permissionRequest.default if specified
-    if let Some(permission_request) = &config.permission_request {
-        let default_value = permission_request.default.to_lowercase();
-        if default_value != "allow" && default_value != "deny" {
-            let error_msg = format!(
-                "Validation failed for permissionRequest.default\n\n\
-                 Error: Invalid value '{}'\n\n\
-                 Valid values: \"allow\" or \"deny\"\n\n\
-                 Common causes:\n\
-                   • Typo in value (check spelling)\n\
-                   • Using a value other than allow or deny\n\n\
-                 Example valid configurations:\n\
-                   permissionRequest:\n\
-                     default: allow    # allow all tools by default\n\
-                   \n\
-                   permissionRequest:\n\
-                     default: deny     # deny all tools by default\n\n\
-                 For a valid configuration template, run:\n\
-                   conclaude init",
-                permission_request.default
-            );
-            return Err(anyhow::anyhow!(error_msg));
+impl InterpolationContext {
+    fn new(config_path: &Path) -> Self {
+        let base_dir = get_config_base_dir(config_path);
+        let config_dir = base_dir
+            .canonicalize()
+            .unwrap_or_else(|_| base_dir.to_path_buf());
+        Self {
+            config_dir,
+            git_root: std::cell::OnceCell::new(),
+            branch: std::cell::OnceCell::new(),
         }
     }
 
-    // Validate userPromptSubmit context rules regex patterns
-    for (idx, rule) in config.user_prompt_submit.context_rules.iter().enumerate() {
-        // Try to compile the regex pattern
-        let regex_result = if rule.case_insensitive.unwrap_or(false) {
-            regex::RegexBuilder::new(&rule.pattern)
-                .case_insensitive(true)
-                .build()
-        } else {
-            regex::Regex::new(&rule.pattern)
+    /// Looks up a variable: built-ins first, then the process environment.
+    /// Empty values are treated as unset, matching shell `${VAR:-default}` semantics.
+    fn lookup(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "CONFIG_DIR" => Some(self.config_dir.display().to_string()),
+            "GIT_ROOT" => self
+                .git_root
+                .get_or_init(|| {
+                    crate::gitignore::find_git_root(&self.config_dir)
+                        .map(|root| root.display().to_string())
+                })
+                .clone(),
+            "BRANCH" => self
+                .branch
+                .get_or_init(|| current_git_branch(&self.config_dir))
+                .clone(),
+            _ => std::env::var(name).ok(),
         };
+        value.filter(|v| !v.is_empty())
+    }
+}
 
-        if let Err(e) = regex_result {
-            let error_msg = format!(
-                "Invalid regex pattern in userPromptSubmit.contextRules[{idx}]\n\n\
-                 Error: Pattern '{}' failed to compile\n\n\
-                 Regex error: {}\n\n\
-                 Common causes:\n\
-                   • Unclosed brackets or parentheses\n\
-                   • Invalid escape sequences\n\
-                   • Incorrect regex syntax\n\n\
-                 Example valid patterns:\n\
-                   pattern: \"sidebar\"              # Simple text match\n\
-                   pattern: \"auth|login\"           # Multiple options (OR)\n\
-                   pattern: \"(?i)database\"         # Case-insensitive\n\
-                   pattern: \"test.*feature\"        # Wildcard matching\n\n\
-                 For regex help, see: https://docs.rs/regex/latest/regex/#syntax\n\n\
-                 For a valid configuration template, run:\n\
-                   conclaude init",
-                rule.pattern, e
-            );
-            return Err(anyhow::anyhow!(error_msg));
-        }
+/// Returns the checked-out branch name for the repository containing `dir`
+fn current_git_branch(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
     }
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!branch.is_empty()).then_some(branch)
+}
 
-    // Validate userPromptSubmit commands
-    for (idx, command) in config.user_prompt_submit.commands.iter().enumerate() {
-        // Validate pattern regex syntax if specified
-        if let Some(pattern) = &command.pattern {
-            let regex_result = if command.case_insensitive.unwrap_or(false) {
-                regex::RegexBuilder::new(pattern)
-                    .case_insensitive(true)
-                    .build()
-            } else {
-                regex::Regex::new(pattern)
-            };
+/// Expands `${VAR}`, `${VAR:-default}` and `${VAR:?}` references in a single string.
+///
+/// `$${` is an escape for a literal `${`, so shell parameter expansions can be passed
+/// through unchanged. References that aren't a plain variable name (e.g. `${f%.txt}`)
+/// and unset variables without a default are left as-is, since many are only set when
+/// the command runs, such as `${CONCLAUDE_SESSION_ID}`. Unset variables marked required
+/// with `:?` are pushed onto `missing`.
+fn interpolate_string(
+    input: &str,
+    ctx: &InterpolationContext,
+    missing: &mut Vec<String>,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(idx) = rest.find('$') {
+        output.push_str(&rest[..idx]);
+        let tail = &rest[idx..];
+
+        if let Some(after) = tail.strip_prefix("$${") {
+            output.push_str("${");
+            rest = after;
+            continue;
+        }
 
-            if let Err(e) = regex_result {
-                let error_msg = format!(
-                    "Invalid regex pattern in userPromptSubmit.commands[{idx}].pattern\n\n\
-                     Error: Pattern '{}' failed to compile\n\n\
-                     Regex error: {}\n\n\
-                     Common causes:\n\
-                       • Unclosed brackets or parentheses\n\
-                       • Invalid escape sequences\n\
-                       • Incorrect regex syntax\n\n\
-                     Example valid patterns:\n\
-                       pattern: \"deploy|release\"       # Multiple options (OR)\n\
-                       pattern: \"(?i)database\"         # Case-insensitive\n\
-                       pattern: \"test.*feature\"        # Wildcard matching\n\n\
-                     For regex help, see: https://docs.rs/regex/latest/regex/#syntax\n\n\
-                     For a valid configuration template, run:\n\
-                       conclaude init",
-                    pattern, e
-                );
-                return Err(anyhow::anyhow!(error_msg));
+        let Some(body_start) = tail.strip_prefix("${") else {
+            output.push('$');
+            rest = &tail[1..];
+            continue;
+        };
+        let Some(end) = body_start.find('}') else {
+            output.push_str(tail);
+            rest = "";
+            break;
+        };
+
+        let body = &body_start[..end];
+        let (name, default, required) = if let Some((name, default)) = body.split_once(":-") {
+            (name, Some(default), false)
+        } else if let Some((name, _)) = body.split_once(":?") {
+            (name, None, true)
+        } else {
+            (body, None, false)
+        };
+        let is_variable_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if is_variable_name {
+            match (ctx.lookup(name), default) {
+                (Some(value), _) => output.push_str(&value),
+                (None, Some(default)) => output.push_str(default),
+                (None, None) => {
+                    if required {
+                        missing.push(name.to_string());
+                    }
+                    output.push_str(&tail[..end + 3]);
+                }
             }
+        } else {
+            output.push_str(&tail[..end + 3]);
         }
+        rest = &body_start[end + 1..];
+    }
 
-        // Validate timeout range (1-3600)
-        if let Some(timeout) = command.timeout {
-            if !(1..=3600).contains(&timeout) {
-                let error_msg = format!(
-                    "Range validation failed for userPromptSubmit.commands[{idx}].timeout\n\n\
-                     Error: Value {timeout} is out of valid range\n\n\
-                     Valid range: 1 to 3600 seconds (1 second to 1 hour)\n\n\
-                     Common causes:\n\
-                       • Value is too large (maximum is 3600 seconds / 1 hour)\n\
-                       • Value is too small (minimum is 1 second)\n\
-                       • Using a negative number\n\n\
-                     Example valid configurations:\n\
-                       timeout: 30       # 30 seconds\n\
-                       timeout: 300      # 5 minutes\n\
-                       timeout: 3600     # maximum allowed (1 hour)\n\n\
-                     For a valid configuration template, run:\n\
-                       conclaude init"
-                );
-                return Err(anyhow::anyhow!(error_msg));
+    output.push_str(rest);
+    output
+}
+
+/// Walks a configuration value and expands variables in the interpolated keys
+/// (`run`, `fix`, `message`, `prompt`, `infiniteMessage` and `worktreeCreate.command`),
+/// recording `(yaml path, variable)` for every unset required variable.
+fn interpolate_value_at(
+    value: &mut serde_yaml::Value,
+    path: &str,
+    ctx: &InterpolationContext,
+    missing: &mut Vec<(String, String)>,
+) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, child) in map.iter_mut() {
+                let key = yaml_key_to_string(key);
+                // Inactive profiles may reference variables that are only set when the
+                // profile is used; the active profile is merged in before interpolation.
+                if path.is_empty() && key == "profiles" {
+                    continue;
+                }
+                let child_path = join_yaml_path(path, &key);
+                let interpolated = INTERPOLATED_KEYS.contains(&key.as_str())
+                    || (path == "worktreeCreate" && key == "command");
+
+                match child {
+                    serde_yaml::Value::String(text) if interpolated => {
+                        let mut unset = Vec::new();
+                        *text = interpolate_string(text, ctx, &mut unset);
+                        missing.extend(unset.into_iter().map(|var| (child_path.clone(), var)));
+                    }
+                    _ => interpolate_value_at(child, &child_path, ctx, missing),
+                }
             }
         }
-
-        // Validate maxOutputLines range (1-10000)
-        if let Some(max_lines) = command.max_output_lines {
-            if !(1..=10000).contains(&max_lines) {
-                let error_msg = format!(
-                    "Range validation failed for userPromptSubmit.commands[{idx}].maxOutputLines\n\n\
-                     Error: Value {max_lines} is out of valid range\n\n\
-                     Valid range: 1 to 10000\n\n\
-                     Common causes:\n\
-                       • Value is too large (maximum is 10000)\n\
-                       • Value is too small (minimum is 1)\n\
-                       • Using a negative number\n\n\
-                     Example valid configurations:\n\
-                       maxOutputLines: 100      # default, good for most cases\n\
-                       maxOutputLines: 1000     # for verbose output\n\
-                       maxOutputLines: 10000    # maximum allowed\n\n\
-                     For a valid configuration template, run:\n\
-                       conclaude init"
-                );
-                return Err(anyhow::anyhow!(error_msg));
+        serde_yaml::Value::Sequence(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                interpolate_value_at(item, &format!("{path}[{idx}]"), ctx, missing);
             }
         }
+        _ => {}
     }
+}
 
-    // Validate subagentStop configuration
-    for (pattern, commands) in &config.subagent_stop.commands {
-        // Validate pattern is not empty
-        if pattern.trim().is_empty() {
-            let error_msg = "Validation failed for subagentStop.commands\n\n\
-                 Error: Pattern key cannot be empty\n\n\
-                 Valid patterns: \"*\" (all), \"coder\" (exact), \"test*\" (prefix), \"*coder\" (suffix)\n\n\
-                 Example valid configurations:\n\
-                   subagentStop:\n\
-                     commands:\n\
-                       \"*\":\n\
-                         - run: \"echo all subagents\"\n\
-                       \"coder\":\n\
-                         - run: \"npm run lint\"\n\n\
-                 For a valid configuration template, run:\n\
-                   conclaude init"
-                .to_string();
-            return Err(anyhow::anyhow!(error_msg));
-        }
-
-        // Validate maxOutputLines range for each command
-        for (idx, command) in commands.iter().enumerate() {
-            if let Some(max_lines) = command.max_output_lines {
-                if !(1..=10000).contains(&max_lines) {
-                    let error_msg = format!(
-                        "Range validation failed for subagentStop.commands[\"{pattern}\"][{idx}].maxOutputLines\n\n\
-                         Error: Value {max_lines} is out of valid range\n\n\
-                         Valid range: 1 to 10000\n\n\
-                         Common causes:\n\
-                           • Value is too large (maximum is 10000)\n\
-                           • Value is too small (minimum is 1)\n\
-                           • Using a negative number\n\n\
-                         Example valid configurations:\n\
-                           maxOutputLines: 100      # default, good for most cases\n\
-                           maxOutputLines: 1000     # for verbose output\n\
-                           maxOutputLines: 10000    # maximum allowed\n\n\
-                         For a valid configuration template, run:\n\
-                           conclaude init"
-                    );
-                    return Err(anyhow::anyhow!(error_msg));
-                }
-            }
+/// Expands `${VAR}`, `${VAR:-default}`, `${VAR:?}` and the built-ins `${CONFIG_DIR}`,
+/// `${GIT_ROOT}` and `${BRANCH}` in a raw configuration value read from `config_path`.
+///
+/// # Errors
+///
+/// Returns an error listing every variable referenced as `${VAR:?}` that is unset.
+fn interpolate_config_value(value: &mut serde_yaml::Value, config_path: &Path) -> Result<()> {
+    let ctx = InterpolationContext::new(config_path);
+    let mut missing = Vec::new();
+    interpolate_value_at(value, "", &ctx, &mut missing);
+
+    if missing.is_empty() {
+        return Ok(());
+    }
 
-            // Validate timeout range (1-3600)
-            if let Some(timeout) = command.timeout {
-                if !(1..=3600).contains(&timeout) {
-                    let error_msg = format!(
-                        "Range validation failed for subagentStop.commands[\"{pattern}\"][{idx}].timeout\n\n\
-                         Error: Value {timeout} is out of valid range\n\n\
-                         Valid range: 1 to 3600 seconds (1 second to 1 hour)\n\n\
-                         Common causes:\n\
-                           • Value is too large (maximum is 3600 seconds / 1 hour)\n\
-                           • Value is too small (minimum is 1 second)\n\
-                           • Using a negative number\n\n\
-                         Example valid configurations:\n\
-                           timeout: 30       # 30 seconds\n\
-                           timeout: 300      # 5 minutes\n\
-                           timeout: 3600     # maximum allowed (1 hour)\n\n\
-                         For a valid configuration template, run:\n\
-                           conclaude init"
-                    );
-                    return Err(anyhow::anyhow!(error_msg));
-                }
-            }
+    let references: Vec<String> = missing
+        .iter()
+        .map(|(path, var)| format!("  • {path}: ${{{var}:?}}"))
+        .collect();
+    Err(anyhow::anyhow!(
+        "Unset variable in configuration file: {}\n\n\
+         Error: the following values require variables that are not set:\n{}\n\n\
+         Fix by doing one of the following:\n\
+         • Export the variable before starting Claude Code\n\
+         • Provide a default: ${{VAR:-default}}\n\
+         • Drop the `:?` to leave the reference to the shell\n\n\
+         Built-in variables: ${{CONFIG_DIR}}, ${{GIT_ROOT}}, ${{BRANCH}}\n\n\
+         For a valid configuration template, run:\n  \
+         conclaude init",
+        config_path.display(),
+        references.join("\n")
+    ))
+}
+
+/// Hook event names accepted in `notifications.hooks` (besides the `"*"` wildcard)
+const HOOK_EVENT_NAMES: [&str; 30] = [
+    "PreToolUse",
+    "PostToolUse",
+    "PermissionRequest",
+    "Notification",
+    "UserPromptSubmit",
+    "SessionStart",
+    "SessionEnd",
+    "Stop",
+    "StopFailure",
+    "SubagentStart",
+    "SubagentStop",
+    "PreCompact",
+    "PostToolUseFailure",
+    "TeammateIdle",
+    "TaskCompleted",
+    "ConfigChange",
+    "WorktreeCreate",
+    "WorktreeRemove",
+    "Setup",
+    "PostCompact",
+    "CwdChanged",
+    "FileChanged",
+    "InstructionsLoaded",
+    "PostToolBatch",
+    "PermissionDenied",
+    "UserPromptExpansion",
+    "TaskCreated",
+    "Elicitation",
+    "ElicitationResult",
+    "MessageDisplay",
+];
+
+/// How serious a configuration diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The configuration cannot be loaded
+    Error,
+    /// The configuration loads, but part of it has no effect
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
         }
     }
+}
 
-    // Validate setup configuration
-    for (pattern, commands) in &config.setup.commands {
-        if pattern.trim().is_empty() {
-            let error_msg = "Validation failed for setup.commands\n\n\
-                 Error: Pattern key cannot be empty\n\n\
-                 Valid patterns: \"*\" (all), \"install\" (exact), \"init*\" (prefix)\n\n\
-                 Example valid configurations:\n\
-                   setup:\n\
-                     commands:\n\
-                       \"*\":\n\
-                         - run: \"echo setup\"\n\n\
-                 For a valid configuration template, run:\n\
-                   conclaude init"
-                .to_string();
-            return Err(anyhow::anyhow!(error_msg));
-        }
-
-        for (idx, command) in commands.iter().enumerate() {
-            if let Some(max_lines) = command.max_output_lines {
-                if !(1..=10000).contains(&max_lines) {
-                    let error_msg = format!(
-                        "Range validation failed for setup.commands[\"{pattern}\"][{idx}].maxOutputLines\n\n\
-                         Error: Value {max_lines} is out of valid range\n\n\
-                         Valid range: 1 to 10000\n\n\
-                         For a valid configuration template, run:\n\
-                           conclaude init"
-                    );
-                    return Err(anyhow::anyhow!(error_msg));
-                }
-            }
+/// A single problem found while validating a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Stable identifier of the kind of problem, e.g. `invalid-glob`
+    pub code: &'static str,
+    pub severity: Severity,
+    /// YAML path of the offending value, e.g. `stop.commands[0].timeout`
+    pub path: String,
+    /// Description of the problem and how to fix it
+    pub message: String,
+    /// 1-based line of the offending value, when it could be located in the source
+    pub line: Option<usize>,
+    /// 1-based column of the offending value, when it could be located in the source
+    pub column: Option<usize>,
+    /// Replacement values that would fix the problem, best match first
+    pub suggestions: Vec<String>,
+}
 
-            if let Some(timeout) = command.timeout {
-                if !(1..=3600).contains(&timeout) {
-                    let error_msg = format!(
-                        "Range validation failed for setup.commands[\"{pattern}\"][{idx}].timeout\n\n\
-                         Error: Value {timeout} is out of valid range\n\n\
-                         Valid range: 1 to 3600 seconds (1 second to 1 hour)\n\n\
-                         For a valid configuration template, run:\n\
-                           conclaude init"
-                    );
-                    return Err(anyhow::anyhow!(error_msg));
-                }
-            }
+impl ConfigIssue {
+    /// Creates an error-level issue with no position or suggestions
+    #[must_use]
+    pub fn new(code: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            severity: Severity::Error,
+            path: path.into(),
+            message: message.into(),
+            line: None,
+            column: None,
+            suggestions: Vec::new(),
         }
     }
 
-    Ok(())
+    fn warning(code: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(code, path, message)
+        }
+    }
+
+    fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.suggestions = suggestions;
+        self
+    }
 }
 
-/// Load YAML configuration using native search strategies
-///
-/// Search strategy: searches up directory tree from the starting directory,
-/// checking for `.conclaude.yaml` or `.conclaude.yml` in each parent directory.
-/// The search stops when either:
-/// - A config file is found, OR
-/// - The filesystem root is reached, OR
-/// - 12 directory levels have been searched
+/// Every constraint violation found in one configuration file
 ///
-/// # Arguments
-///
-/// * `start_dir` - Optional starting directory for config search. If None, uses current directory.
-///
-/// # Errors
-///
-/// Returns an error if no configuration file is found, file reading fails, or YAML parsing fails.
-pub async fn load_conclaude_config(start_dir: Option<&Path>) -> Result<(ConclaudeConfig, PathBuf)> {
-    let search_paths = get_config_search_paths(start_dir)?;
-
-    for path in &search_paths {
-        if path.exists() {
-            let content = fs::read_to_string(path)
-                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+/// Validation does not stop at the first problem, so a single run of
+/// `conclaude validate` reports everything that needs fixing.
+#[derive(Debug)]
+pub struct ConfigValidationError {
+    /// The file that was validated, or the project file when `merged` is set
+    pub config_path: PathBuf,
+    /// Whether the issues were found in the merged result of several layers, in which
+    /// case they carry no line or column
+    pub merged: bool,
+    /// The problems found, in file order where they could be located
+    pub issues: Vec<ConfigIssue>,
+}
 
-            let config = parse_and_validate_config(&content, path)?;
+impl ConfigValidationError {
+    fn location(&self, issue: &ConfigIssue) -> String {
+        match (issue.line, issue.column) {
+            (Some(line), Some(column)) => {
+                format!("{}:{line}:{column}", self.config_path.display())
+            }
+            _ if self.merged => format!("{} (merged layers)", self.config_path.display()),
+            _ => self.config_path.display().to_string(),
+        }
+    }
+}
 
-            return Ok((config, path.clone()));
+impl std::fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let [issue] = self.issues.as_slice() {
+            writeln!(f, "{}", issue.message)?;
+            writeln!(f)?;
+            writeln!(f, "Location: {}", self.location(issue))?;
+        } else {
+            writeln!(
+                f,
+                "Configuration validation failed: {} problems in {}",
+                self.issues.len(),
+                self.config_path.display()
+            )?;
+            for (idx, issue) in self.issues.iter().enumerate() {
+                writeln!(f)?;
+                writeln!(f, "[{}] {}", idx + 1, self.location(issue))?;
+                writeln!(f, "{}", issue.message)?;
+            }
         }
+        writeln!(f)?;
+        write!(
+            f,
+            "For a valid configuration template, run:\n  conclaude init"
+        )
     }
+}
 
-    // If no config file is found, show search locations
-    let search_locations: Vec<String> = search_paths
-        .iter()
-        .map(|p| format!("  • {}", p.display()))
-        .collect();
+impl std::error::Error for ConfigValidationError {}
 
-    let error_message = format!(
-        "Configuration file not found.\n\nSearched the following locations:\n{}\n\nCreate a .conclaude.yaml or .conclaude.yml file with stop and preToolUse sections.\nRun 'conclaude init' to generate a template configuration.",
-        search_locations.join("\n")
-    );
+/// A configuration file that is not valid YAML or does not match the schema
+///
+/// Displays as the full guidance produced by [`format_parse_error`]; `issue` holds the
+/// same problem in structured form for machine-readable output.
+#[derive(Debug)]
+pub struct ConfigParseError {
+    pub config_path: PathBuf,
+    pub issue: ConfigIssue,
+    rendered: String,
+}
 
-    Err(anyhow::anyhow!(error_message))
+impl ConfigParseError {
+    /// Describes `error`, raised while parsing `content` when it is given
+    fn new(error: &serde_yaml::Error, config_path: &Path, content: Option<&str>) -> Self {
+        let mut base_error = error.to_string();
+        let mut location = error.location().map(|l| (l.line(), l.column()));
+
+        let (parent, detail) = split_error_path(&base_error);
+        let detail = detail.split(" at line ").next().unwrap_or(detail);
+
+        let mut issue = ConfigIssue::new("parse-error", parent, detail);
+        if let Some(field) = extract_unknown_field(&base_error) {
+            issue.code = "unknown-field";
+            issue.path = join_yaml_path(parent, &field);
+            issue.suggestions = suggest_fields_at_path(&field, parent);
+
+            // Unknown keys next to flattened fields are reported at the start of their
+            // mapping, so point at the key itself
+            let key_location = content
+                .and_then(|content| locate_yaml_path(&index_yaml_positions(content), &issue.path));
+            if let (Some((line, column)), Some(key)) = (location, key_location) {
+                base_error = base_error.replace(
+                    &format!("at line {line} column {column}"),
+                    &format!("at line {} column {}", key.0, key.1),
+                );
+                location = Some(key);
+            }
+        }
+        if let Some((line, column)) = location {
+            issue.line = Some(line);
+            issue.column = Some(column);
+        }
+
+        Self {
+            config_path: config_path.to_path_buf(),
+            issue,
+            rendered: format_parse_error(&base_error, config_path),
+        }
+    }
 }
 
-fn get_config_search_paths(start_dir: Option<&Path>) -> Result<Vec<PathBuf>> {
-    let mut paths = Vec::new();
-    let mut current_dir = match start_dir {
-        Some(dir) => dir.to_path_buf(),
-        None => std::env::current_dir()?,
-    };
-    let mut levels_searched = 0;
-    const MAX_SEARCH_LEVELS: u32 = 12;
+impl std::fmt::Display for ConfigParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.rendered)
+    }
+}
 
-    loop {
-        // Add .conclaude.yaml and .conclaude.yml to search paths
-        paths.push(current_dir.join(".conclaude.yaml"));
-        paths.push(current_dir.join(".conclaude.yml"));
+impl std::error::Error for ConfigParseError {}
 
-        // Move to parent directory first, then increment level count
-        match current_dir.parent() {
-            Some(parent) => {
-                current_dir = parent.to_path_buf();
-                levels_searched += 1;
+/// Validate configuration values against constraints
+///
+/// All violations are collected before failing. When `content` holds the YAML the
+/// configuration was parsed from, each violation is annotated with its line and column.
+fn validate_config_constraints(
+    config: &ConclaudeConfig,
+    config_path: &Path,
+    content: Option<&str>,
+) -> Result<()> {
+    let mut issues = collect_config_issues(config);
+    if issues.is_empty() {
+        return Ok(());
+    }
 
-                // Check if we've reached the maximum search level limit
-                if levels_searched >= MAX_SEARCH_LEVELS {
-                    break;
-                }
+    if let Some(content) = content {
+        let positions = index_yaml_positions(content);
+        for issue in &mut issues {
+            if let Some((line, column)) = locate_yaml_path(&positions, &issue.path) {
+                issue.line = Some(line);
+                issue.column = Some(column);
             }
-            None => break, // Reached filesystem root
+        }
+        issues.sort_by_key(|issue| issue.line.unwrap_or(usize::MAX));
+    }
+
+    Err(ConfigValidationError {
+        config_path: config_path.to_path_buf(),
+        merged: content.is_none(),
+        issues,
+    }
+    .into())
+}
+
+/// Checks every section of the configuration and returns all constraint violations
+fn collect_config_issues(config: &ConclaudeConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    // Profiles are flat overlays: they cannot nest or pull in other files
+    let mut profile_names: Vec<&String> = config.profiles.keys().collect();
+    profile_names.sort();
+    for name in profile_names {
+        let profile = &config.profiles[name];
+        let nested_key = if !profile.profiles.is_empty() {
+            Some("profiles")
+        } else if profile.root {
+            Some("root")
+        } else if profile.extends.is_some() {
+            Some("extends")
+        } else if !profile.include.is_empty() {
+            Some("include")
+        } else {
+            None
+        };
+        if let Some(key) = nested_key {
+            issues.push(ConfigIssue::new(
+                "invalid-profile",
+                format!("profiles.{name}.{key}"),
+                format!(
+                    "Invalid profile definition for profiles.{name}\n\n\
+                     Error: `{key}` is not allowed inside a profile\n\n\
+                     Profiles overlay configuration sections such as stop, preToolUse and\n\
+                     notifications. Declare `{key}` at the top level of the file instead."
+                ),
+            ));
         }
     }
 
-    Ok(paths)
+    // Command lists
+    for (idx, command) in config.stop.commands.iter().enumerate() {
+        let path = format!("stop.commands[{idx}]");
+        check_command_limits(&mut issues, &path, &command.launch);
+    }
+    check_command_needs(&mut issues, "stop.commands", &config.stop.commands);
+    if let Some(max_parallel) = config.stop.max_parallel {
+        if !(1..=64).contains(&max_parallel) {
+            issues.push(ConfigIssue::new(
+                "out-of-range",
+                "stop.maxParallel",
+                format!(
+                    "Range validation failed for stop.maxParallel\n\n\
+                     Error: Value {max_parallel} is out of valid range\n\n\
+                     Valid range: 1 to 64"
+                ),
+            ));
+        }
+    }
+    for (idx, command) in config.stop_failure.commands.iter().enumerate() {
+        let path = format!("stopFailure.commands[{idx}]");
+        check_command_limits(&mut issues, &path, &command.launch);
+    }
+    check_command_needs(
+        &mut issues,
+        "stopFailure.commands",
+        &config.stop_failure.commands,
+    );
+    for (idx, command) in config.post_tool_batch.commands.iter().enumerate() {
+        let path = format!("postToolBatch.commands[{idx}]");
+        check_command_limits(&mut issues, &path, &command.launch);
+    }
+    for (idx, command) in config.message_display.commands.iter().enumerate() {
+        let path = format!("messageDisplay.commands[{idx}]");
+        check_command_limits(&mut issues, &path, &command.launch);
+    }
+
+    // preToolUse file protection and tool rules
+    let pre_tool_use = &config.pre_tool_use;
+    for (idx, pattern) in pre_tool_use.prevent_additions.iter().enumerate() {
+        check_glob(
+            &mut issues,
+            &format!("preToolUse.preventAdditions[{idx}]"),
+            pattern,
+        );
+    }
+    for (idx, rule) in pre_tool_use.uneditable_files.iter().enumerate() {
+        let path = match rule {
+            UnEditableFileRule::Detailed { .. } => {
+                format!("preToolUse.uneditableFiles[{idx}].pattern")
+            }
+            UnEditableFileRule::Simple(_) => format!("preToolUse.uneditableFiles[{idx}]"),
+        };
+        check_glob(&mut issues, &path, rule.pattern());
+        if let Some(agent) = rule.agent() {
+            check_agent_pattern(
+                &mut issues,
+                &format!("preToolUse.uneditableFiles[{idx}].agent"),
+                agent,
+            );
+        }
+    }
+    for (idx, rule) in pre_tool_use.tool_usage_validation.iter().enumerate() {
+        let path = format!("preToolUse.toolUsageValidation[{idx}]");
+        check_glob(&mut issues, &format!("{path}.pattern"), &rule.pattern);
+        check_enum(
+            &mut issues,
+            &format!("{path}.action"),
+            &rule.action,
+            &["allow", "block"],
+        );
+        if let Some(command_pattern) = &rule.command_pattern {
+            check_glob(
+                &mut issues,
+                &format!("{path}.commandPattern"),
+                command_pattern,
+            );
+        }
+        if let Some(match_mode) = &rule.match_mode {
+            check_enum(
+                &mut issues,
+                &format!("{path}.matchMode"),
+                match_mode,
+                &["full", "prefix"],
+            );
+        }
+        if let Some(agent) = &rule.agent {
+            check_agent_pattern(&mut issues, &format!("{path}.agent"), agent);
+        }
+    }
+
+    // Validate notifications.hooks names
+    for (idx, hook) in config.notifications.hooks.iter().enumerate() {
+        if hook != "*" && !HOOK_EVENT_NAMES.contains(&hook.as_str()) {
+            let mut message = format!(
+                "Validation failed for notifications.hooks[{idx}]\n\n\
+                 Error: Unknown hook name '{hook}'"
+            );
+            let suggestions = suggest_hook_names(hook);
+            if !suggestions.is_empty() {
+                message.push_str(&format!("\n\nDid you mean: {}?", suggestions.join(", ")));
+            }
+            message.push_str(
+                "\n\nHook names are case-sensitive, e.g. \"Stop\", \"PreToolUse\", \"SessionStart\".\n\
+                 Use \"*\" to receive notifications for every hook.",
+            );
+            issues.push(
+                ConfigIssue::new(
+                    "unknown-hook",
+                    format!("notifications.hooks[{idx}]"),
+                    message,
+                )
+                .with_suggestions(suggestions.into_iter().map(String::from).collect()),
+            );
+        }
+    }
+
+    // Validate permissionRequest.default if specified
+    if let Some(permission_request) = &config.permission_request {
+        let default_value = permission_request.default.to_lowercase();
+        if default_value != "allow" && default_value != "deny" {
+            issues.push(ConfigIssue::new(
+                "invalid-value",
+                "permissionRequest.default",
+                format!(
+                    "Validation failed for permissionRequest.default\n\n\
+                     Error: Invalid value '{}'\n\n\
+                     Valid values: \"allow\" or \"deny\"\n\n\
+                     Common causes:\n\
+                       • Typo in value (check spelling)\n\
+                       • Using a value other than allow or deny\n\n\
+                     Example valid configurations:\n\
+                       permissionRequest:\n\
+                         default: allow    # allow all tools by default\n\
+                       \n\
+                       permissionRequest:\n\
+                         default: deny     # deny all tools by default",
+                    permission_request.default
+                ),
+            ));
+        }
+        for (key, patterns) in [
+            ("allow", &permission_request.allow),
+            ("deny", &permission_request.deny),
+        ] {
+            for (idx, pattern) in patterns.iter().flatten().enumerate() {
+                check_glob(
+                    &mut issues,
+                    &format!("permissionRequest.{key}[{idx}]"),
+                    pattern,
+                );
+            }
+        }
+    }
+
+    // Validate missingConfig.mode
+    let missing_mode = config.missing_config.mode.to_lowercase();
+    if missing_mode != "allow" && missing_mode != "block" {
+        issues.push(ConfigIssue::new(
+            "invalid-value",
+            "missingConfig.mode",
+            format!(
+                "Validation failed for missingConfig.mode\n\n\
+                 Error: Invalid value '{}'\n\n\
+                 Valid values: \"allow\" or \"block\"\n\n\
+                 Example valid configurations:\n\
+                   missingConfig:\n\
+                     mode: allow    # allow everything in projects without a config\n\
+                   \n\
+                   missingConfig:\n\
+                     mode: block    # fail closed in projects without a config",
+                config.missing_config.mode
+            ),
+        ));
+    }
+
+    // Validate userPromptSubmit context rules regex patterns
+    let user_prompt_submit = &config.user_prompt_submit;
+    for (idx, rule) in user_prompt_submit.context_rules.iter().enumerate() {
+        check_regex(
+            &mut issues,
+            &format!("userPromptSubmit.contextRules[{idx}].pattern"),
+            &rule.pattern,
+            rule.case_insensitive.unwrap_or(false),
+        );
+    }
+
+    // Validate userPromptSubmit commands
+    for (idx, command) in user_prompt_submit.commands.iter().enumerate() {
+        let path = format!("userPromptSubmit.commands[{idx}]");
+        if let Some(pattern) = &command.pattern {
+            check_regex(
+                &mut issues,
+                &format!("{path}.pattern"),
+                pattern,
+                command.case_insensitive.unwrap_or(false),
+            );
+        }
+        check_command_limits(&mut issues, &path, &command.launch);
+    }
+
+    // Pattern-keyed command maps
+    if let Some(slash_commands) = &user_prompt_submit.slash_commands {
+        check_pattern_map(
+            &mut issues,
+            "userPromptSubmit.slashCommands.commands",
+            &slash_commands.commands,
+            |c| &c.launch,
+        );
+    }
+    check_pattern_map(
+        &mut issues,
+        "subagentStop.commands",
+        &config.subagent_stop.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "skillStart.commands",
+        &config.skill_start.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "teammateIdle.commands",
+        &config.teammate_idle.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "taskCompleted.commands",
+        &config.task_completed.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "configChange.commands",
+        &config.config_change.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(&mut issues, "setup.commands", &config.setup.commands, |c| {
+        &c.launch
+    });
+    check_pattern_map(
+        &mut issues,
+        "postCompact.commands",
+        &config.post_compact.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "cwdChanged.commands",
+        &config.cwd_changed.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "fileChanged.commands",
+        &config.file_changed.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "instructionsLoaded.commands",
+        &config.instructions_loaded.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "permissionDenied.commands",
+        &config.permission_denied.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "userPromptExpansion.commands",
+        &config.user_prompt_expansion.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "taskCreated.commands",
+        &config.task_created.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "elicitation.commands",
+        &config.elicitation.commands,
+        |c| &c.launch,
+    );
+    check_pattern_map(
+        &mut issues,
+        "elicitationResult.commands",
+        &config.elicitation_result.commands,
+        |c| &c.launch,
+    );
+
+    // Validate worktreeCreate.timeout range (1-3600)
+    if let Some(timeout) = config.worktree_create.timeout {
+        check_timeout(&mut issues, "worktreeCreate.timeout", timeout);
+    }
+
+    issues
+}
+
+/// Finds settings that load fine but have no effect: duplicated or shadowed rules and
+/// options whose feature is switched off.
+///
+/// These are reported by `conclaude validate` as warnings and never fail loading. With
+/// `layered`, settings that are only read from the global layer are also reported when
+/// another layer sets them.
+#[must_use]
+pub fn collect_config_warnings(
+    config: &ConclaudeConfig,
+    layered: Option<&LayeredConfig>,
+) -> Vec<ConfigIssue> {
+    let mut warnings = Vec::new();
+
+    // Only a global file exists in projects without configuration, so only it is read there
+    let ignored_missing_config = layered.is_some_and(|layered| {
+        layered.sources.iter().any(|(path, layer)| {
+            (path == "missingConfig" || path.starts_with("missingConfig."))
+                && layered.layers[*layer].kind != ConfigLayerKind::Global
+        })
+    });
+    if ignored_missing_config {
+        warnings.push(ConfigIssue::warning(
+            "ignored-setting",
+            "missingConfig",
+            "missingConfig is only read from the global configuration and has no effect here",
+        ));
+    }
+
+    let pre_tool_use = &config.pre_tool_use;
+
+    for (idx, pattern) in pre_tool_use.prevent_additions.iter().enumerate() {
+        if let Some(first) = pre_tool_use.prevent_additions[..idx]
+            .iter()
+            .position(|p| p == pattern)
+        {
+            warnings.push(ConfigIssue::warning(
+                "duplicate-rule",
+                format!("preToolUse.preventAdditions[{idx}]"),
+                format!(
+                    "Pattern '{pattern}' duplicates preToolUse.preventAdditions[{first}] and has no effect"
+                ),
+            ));
+        }
+    }
+
+    // The first matching uneditableFiles rule blocks the edit with its own message
+    for (idx, rule) in pre_tool_use.uneditable_files.iter().enumerate() {
+        if let Some(first) = pre_tool_use.uneditable_files[..idx]
+            .iter()
+            .position(|r| r.pattern() == rule.pattern() && r.agent() == rule.agent())
+        {
+            warnings.push(ConfigIssue::warning(
+                "shadowed-rule",
+                format!("preToolUse.uneditableFiles[{idx}]"),
+                format!(
+                    "Rule for '{}' is shadowed by preToolUse.uneditableFiles[{first}], which \
+                     matches the same files first; this rule never takes effect",
+                    rule.pattern()
+                ),
+            ));
+        }
+    }
+
+    for (idx, rule) in pre_tool_use.tool_usage_validation.iter().enumerate() {
+        if let Some(first) = pre_tool_use.tool_usage_validation[..idx]
+            .iter()
+            .position(|r| {
+                r.tool == rule.tool
+                    && r.pattern == rule.pattern
+                    && r.action == rule.action
+                    && r.command_pattern == rule.command_pattern
+                    && r.match_mode == rule.match_mode
+                    && r.agent == rule.agent
+            })
+        {
+            warnings.push(ConfigIssue::warning(
+                "duplicate-rule",
+                format!("preToolUse.toolUsageValidation[{idx}]"),
+                format!(
+                    "Rule duplicates preToolUse.toolUsageValidation[{first}] and has no effect"
+                ),
+            ));
+        }
+    }
+
+    if pre_tool_use.prevent_root_additions_message.is_some() && !pre_tool_use.prevent_root_additions
+    {
+        warnings.push(ConfigIssue::warning(
+            "unused-setting",
+            "preToolUse.preventRootAdditionsMessage",
+            "preventRootAdditionsMessage has no effect while preventRootAdditions is false",
+        ));
+    }
+
+    if config.stop.max_parallel.is_some() && !config.stop.parallel {
+        warnings.push(ConfigIssue::warning(
+            "unused-setting",
+            "stop.maxParallel",
+            "maxParallel has no effect while stop.parallel is false",
+        ));
+    }
+
+    if config.stop.infinite_message.is_some() && !config.stop.infinite {
+        warnings.push(ConfigIssue::warning(
+            "unused-setting",
+            "stop.infiniteMessage",
+            "infiniteMessage has no effect while stop.infinite is false",
+        ));
+    }
+
+    if !config.notifications.enabled && !config.notifications.hooks.is_empty() {
+        warnings.push(ConfigIssue::warning(
+            "unused-setting",
+            "notifications.hooks",
+            "notifications.hooks has no effect while notifications.enabled is false",
+        ));
+    }
+
+    // Deny patterns are checked before allow patterns
+    if let Some(permission_request) = &config.permission_request {
+        let deny = permission_request.deny.as_deref().unwrap_or_default();
+        for (idx, allowed) in permission_request.allow.iter().flatten().enumerate() {
+            let shadowing = deny.iter().position(|denied| {
+                denied == allowed
+                    || glob::Pattern::new(denied).is_ok_and(|pattern| pattern.matches(allowed))
+            });
+            if let Some(deny_idx) = shadowing {
+                warnings.push(ConfigIssue::warning(
+                    "shadowed-rule",
+                    format!("permissionRequest.allow[{idx}]"),
+                    format!(
+                        "Allow pattern '{allowed}' is shadowed by permissionRequest.deny[{deny_idx}]; \
+                         deny patterns are checked first, so this tool is always denied"
+                    ),
+                ));
+            } else if permission_request.default.eq_ignore_ascii_case("allow") {
+                warnings.push(ConfigIssue::warning(
+                    "unused-setting",
+                    format!("permissionRequest.allow[{idx}]"),
+                    format!(
+                        "Allow pattern '{allowed}' has no effect because permissionRequest.default \
+                         is already allow"
+                    ),
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Checks the `maxOutputLines`, `timeout`, `retries`, `retryDelay` and `gracePeriod` ranges,
+/// the `errorPatterns` regexes and the `exitCodes` keys of the command at `path`
+fn check_command_limits(issues: &mut Vec<ConfigIssue>, path: &str, options: &LaunchOptions) {
+    let LaunchOptions {
+        max_output_lines,
+        timeout,
+        retries,
+        retry_delay,
+        grace_period,
+        ..
+    } = *options;
+    let error_patterns = options.error_patterns.as_deref();
+    let exit_codes = options.exit_codes.as_ref();
+
+    // Validate maxOutputLines range (1-10000)
+    if let Some(max_lines) = max_output_lines {
+        if !(1..=10000).contains(&max_lines) {
+            issues.push(ConfigIssue::new(
+                "out-of-range",
+                format!("{path}.maxOutputLines"),
+                format!(
+                    "Range validation failed for {path}.maxOutputLines\n\n\
+                     Error: Value {max_lines} is out of valid range\n\n\
+                     Valid range: 1 to 10000\n\n\
+                     Common causes:\n\
+                       • Value is too large (maximum is 10000)\n\
+                       • Value is too small (minimum is 1)\n\
+                       • Using a negative number\n\n\
+                     Example valid configurations:\n\
+                       maxOutputLines: 100      # default, good for most cases\n\
+                       maxOutputLines: 1000     # for verbose output\n\
+                       maxOutputLines: 10000    # maximum allowed"
+                ),
+            ));
+        }
+    }
+
+    for (idx, pattern) in error_patterns.unwrap_or_default().iter().enumerate() {
+        check_regex(
+            issues,
+            &format!("{path}.errorPatterns[{idx}]"),
+            pattern,
+            false,
+        );
+    }
+
+    if let Some(timeout) = timeout {
+        check_timeout(issues, &format!("{path}.timeout"), timeout);
+    }
+
+    if let Some(retries) = retries.filter(|retries| *retries > 10) {
+        issues.push(ConfigIssue::new(
+            "out-of-range",
+            format!("{path}.retries"),
+            format!(
+                "Range validation failed for {path}.retries\n\n\
+                 Error: Value {retries} is out of valid range\n\n\
+                 Valid range: 0 to 10"
+            ),
+        ));
+    }
+
+    if let Some(retry_delay) = retry_delay.filter(|delay| *delay > 300) {
+        issues.push(ConfigIssue::new(
+            "out-of-range",
+            format!("{path}.retryDelay"),
+            format!(
+                "Range validation failed for {path}.retryDelay\n\n\
+                 Error: Value {retry_delay} is out of valid range\n\n\
+                 Valid range: 0 to 300 seconds"
+            ),
+        ));
+    }
+
+    if let Some(grace_period) = grace_period.filter(|grace| *grace > 300) {
+        issues.push(ConfigIssue::new(
+            "out-of-range",
+            format!("{path}.gracePeriod"),
+            format!(
+                "Range validation failed for {path}.gracePeriod\n\n\
+                 Error: Value {grace_period} is out of valid range\n\n\
+                 Valid range: 0 to 300 seconds"
+            ),
+        ));
+    }
+
+    for code in exit_codes
+        .into_iter()
+        .flat_map(|codes| codes.keys())
+        .filter(|code| !(1..=255).contains(*code))
+    {
+        issues.push(ConfigIssue::new(
+            "out-of-range",
+            format!("{path}.exitCodes.{code}"),
+            format!(
+                "Range validation failed for {path}.exitCodes\n\n\
+                 Error: Exit code {code} never marks a failure\n\n\
+                 Valid range: 1 to 255"
+            ),
+        ));
+    }
+}
+
+/// Checks the `name`/`needs` graph of a stop command list
+fn check_command_needs(issues: &mut Vec<ConfigIssue>, path: &str, commands: &[StopCommand]) {
+    let mut names: Vec<&str> = commands.iter().filter_map(|c| c.name.as_deref()).collect();
+    names.sort_unstable();
+    names.dedup();
+    for error in resolve_command_needs(commands).err().unwrap_or_default() {
+        let issue = match &error {
+            CommandGraphError::DuplicateName { index, .. } => ConfigIssue::new(
+                "duplicate-name",
+                format!("{path}[{index}].name"),
+                error.to_string(),
+            ),
+            CommandGraphError::UnknownNeed { index, need } => ConfigIssue::new(
+                "unknown-reference",
+                format!("{path}[{index}].needs"),
+                error.to_string(),
+            )
+            .with_suggestions(rank_similar_fields(need, &names)),
+            CommandGraphError::Cycle { index } => ConfigIssue::new(
+                "dependency-cycle",
+                format!("{path}[{index}].needs"),
+                error.to_string(),
+            ),
+        };
+        issues.push(issue);
+    }
+}
+
+/// Checks that a timeout lies in the valid range (1-3600 seconds)
+fn check_timeout(issues: &mut Vec<ConfigIssue>, path: &str, timeout: u64) {
+    if !(1..=3600).contains(&timeout) {
+        issues.push(ConfigIssue::new(
+            "out-of-range",
+            path,
+            format!(
+                "Range validation failed for {path}\n\n\
+                 Error: Value {timeout} is out of valid range\n\n\
+                 Valid range: 1 to 3600 seconds (1 second to 1 hour)\n\n\
+                 Common causes:\n\
+                   • Value is too large (maximum is 3600 seconds / 1 hour)\n\
+                   • Value is too small (minimum is 1 second)\n\
+                   • Using a negative number\n\n\
+                 Example valid configurations:\n\
+                   timeout: 30       # 30 seconds\n\
+                   timeout: 300      # 5 minutes\n\
+                   timeout: 3600     # maximum allowed (1 hour)"
+            ),
+        ));
+    }
+}
+
+/// Checks the keys and commands of a pattern-keyed command map such as `subagentStop.commands`
+fn check_pattern_map<C>(
+    issues: &mut Vec<ConfigIssue>,
+    section: &str,
+    commands: &std::collections::HashMap<String, Vec<C>>,
+    options: impl Fn(&C) -> &LaunchOptions,
+) {
+    let mut patterns: Vec<&String> = commands.keys().collect();
+    patterns.sort();
+
+    for pattern in patterns {
+        let path = format!("{section}[\"{pattern}\"]");
+
+        // Validate pattern is not empty
+        if pattern.trim().is_empty() {
+            issues.push(ConfigIssue::new(
+                "empty-pattern",
+                path.clone(),
+                format!(
+                    "Validation failed for {section}\n\n\
+                     Error: Pattern key cannot be empty\n\n\
+                     Valid patterns: \"*\" (all), \"coder\" (exact), \"test*\" (prefix), \"*coder\" (suffix)"
+                ),
+            ));
+        } else if pattern != "*" {
+            // Slash command patterns may be written with their leading slash
+            let glob = if section.starts_with("userPromptSubmit.slashCommands") {
+                pattern.strip_prefix('/').unwrap_or(pattern)
+            } else {
+                pattern
+            };
+            check_glob(issues, &path, glob);
+        }
+
+        for (idx, command) in commands[pattern].iter().enumerate() {
+            check_command_limits(issues, &format!("{path}[{idx}]"), options(command));
+        }
+    }
+}
+
+/// Checks that a glob pattern compiles
+fn check_glob(issues: &mut Vec<ConfigIssue>, path: &str, pattern: &str) {
+    if let Err(e) = glob::Pattern::new(pattern) {
+        issues.push(ConfigIssue::new(
+            "invalid-glob",
+            path,
+            format!(
+                "Invalid glob pattern in {path}\n\n\
+                 Error: Pattern '{pattern}' failed to compile\n\n\
+                 Glob error: {e}\n\n\
+                 Common causes:\n\
+                   • Unclosed character class (e.g. \"[abc\")\n\
+                   • Three or more consecutive asterisks (e.g. \"***\")\n\
+                   • \"**\" not used as a whole path component (e.g. \"src/**.rs\")\n\n\
+                 Example valid patterns:\n\
+                   \"*.lock\"            # Matching file names\n\
+                   \"src/**/*.rs\"       # Recursive matching\n\
+                   \"docs/[a-m]*.md\"    # Character classes"
+            ),
+        ));
+    }
+}
+
+/// Checks an `agent:` scope, which is either `"*"` or a glob pattern
+fn check_agent_pattern(issues: &mut Vec<ConfigIssue>, path: &str, agent: &str) {
+    if agent != "*" {
+        check_glob(issues, path, agent);
+    }
+}
+
+/// Checks that a regex pattern compiles
+fn check_regex(issues: &mut Vec<ConfigIssue>, path: &str, pattern: &str, case_insensitive: bool) {
+    let regex_result = regex::RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build();
+
+    if let Err(e) = regex_result {
+        issues.push(ConfigIssue::new(
+            "invalid-regex",
+            path,
+            format!(
+                "Invalid regex pattern in {path}\n\n\
+                 Error: Pattern '{pattern}' failed to compile\n\n\
+                 Regex error: {e}\n\n\
+                 Common causes:\n\
+                   • Unclosed brackets or parentheses\n\
+                   • Invalid escape sequences\n\
+                   • Incorrect regex syntax\n\n\
+                 Example valid patterns:\n\
+                   pattern: \"sidebar\"              # Simple text match\n\
+                   pattern: \"auth|login\"           # Multiple options (OR)\n\
+                   pattern: \"(?i)database\"         # Case-insensitive\n\
+                   pattern: \"test.*feature\"        # Wildcard matching\n\n\
+                 For regex help, see: https://docs.rs/regex/latest/regex/#syntax"
+            ),
+        ));
+    }
+}
+
+/// Checks that a string field holds one of its allowed values
+fn check_enum(issues: &mut Vec<ConfigIssue>, path: &str, value: &str, valid: &[&str]) {
+    if !valid.contains(&value) {
+        let valid_list = valid
+            .iter()
+            .map(|v| format!("\"{v}\""))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        issues.push(
+            ConfigIssue::new(
+                "invalid-value",
+                path,
+                format!(
+                    "Validation failed for {path}\n\n\
+                 Error: Invalid value '{value}'\n\n\
+                 Valid values: {valid_list} (case-sensitive)"
+                ),
+            )
+            .with_suggestions(valid.iter().map(|v| (*v).to_string()).collect()),
+        );
+    }
+}
+
+/// Suggests known hook names close to an unknown one
+fn suggest_hook_names(hook: &str) -> Vec<&'static str> {
+    let lower = hook.to_lowercase();
+    let mut scored: Vec<(usize, &'static str)> = HOOK_EVENT_NAMES
+        .iter()
+        .map(|name| (levenshtein_distance(&lower, &name.to_lowercase()), *name))
+        .filter(|(distance, _)| *distance <= 3)
+        .collect();
+    scored.sort();
+    scored.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// One step of a YAML path: a mapping key or a sequence index
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum YamlPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits a YAML path such as `subagentStop.commands["coder"][0].timeout` into segments
+fn parse_yaml_path(path: &str) -> Vec<YamlPathSegment> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("[\"") {
+            let end = after.find("\"]").unwrap_or(after.len());
+            segments.push(YamlPathSegment::Key(after[..end].to_string()));
+            rest = after.get(end + 2..).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').unwrap_or(after.len());
+            let segment = match after[..end].parse() {
+                Ok(idx) => YamlPathSegment::Index(idx),
+                Err(_) => YamlPathSegment::Key(after[..end].to_string()),
+            };
+            segments.push(segment);
+            rest = after.get(end + 1..).unwrap_or("");
+        } else {
+            let key = rest.strip_prefix('.').unwrap_or(rest);
+            let end = key.find(['.', '[']).unwrap_or(key.len());
+            if end > 0 {
+                segments.push(YamlPathSegment::Key(key[..end].to_string()));
+            }
+            rest = &key[end..];
+        }
+    }
+
+    segments
+}
+
+/// Position of a mapping key or sequence item: its YAML path, 1-based line and column
+type YamlPosition = (Vec<YamlPathSegment>, usize, usize);
+
+/// Records the position of every mapping key and block sequence item in `content`.
+///
+/// This is a line-based scan of block-style YAML, which is enough to point validation
+/// errors at the right line. Entries inside flow collections (`[a, b]`) and block
+/// scalars are not indexed; lookups fall back to the nearest enclosing key.
+fn index_yaml_positions(content: &str) -> Vec<YamlPosition> {
+    struct Frame {
+        indent: usize,
+        segment: YamlPathSegment,
+        is_item: bool,
+        items: usize,
+    }
+
+    fn current_path(stack: &[Frame], last: YamlPathSegment) -> Vec<YamlPathSegment> {
+        stack
+            .iter()
+            .map(|frame| frame.segment.clone())
+            .chain(std::iter::once(last))
+            .collect()
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut positions = Vec::new();
+    // Indentation of the key that owns the block scalar currently being skipped
+    let mut block_scalar_owner: Option<usize> = None;
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let trimmed = line[indent..].trim_end();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(owner) = block_scalar_owner {
+            if indent > owner {
+                continue;
+            }
+            block_scalar_owner = None;
+        }
+        if trimmed == "---" {
+            stack.clear();
+            continue;
+        }
+
+        let mut column = indent;
+        let mut rest = trimmed;
+
+        // Sequence items, possibly nested on one line (`- - a`)
+        while rest == "-" || rest.starts_with("- ") {
+            while stack
+                .last()
+                .is_some_and(|f| f.indent > column || (f.indent == column && f.is_item))
+            {
+                stack.pop();
+            }
+            let index = stack.last_mut().map_or(0, |parent| {
+                parent.items += 1;
+                parent.items - 1
+            });
+            let segment = YamlPathSegment::Index(index);
+            positions.push((
+                current_path(&stack, segment.clone()),
+                line_idx + 1,
+                column + 1,
+            ));
+            stack.push(Frame {
+                indent: column,
+                segment,
+                is_item: true,
+                items: 0,
+            });
+
+            let after_dash = &rest[1..];
+            let item = after_dash.trim_start();
+            column += 1 + after_dash.len() - item.len();
+            rest = item;
+        }
+
+        if let Some((key, value)) = split_yaml_key(rest) {
+            while stack.last().is_some_and(|f| f.indent >= column) {
+                stack.pop();
+            }
+            let segment = YamlPathSegment::Key(key);
+            positions.push((
+                current_path(&stack, segment.clone()),
+                line_idx + 1,
+                column + 1,
+            ));
+            if value.starts_with('|') || value.starts_with('>') {
+                block_scalar_owner = Some(column);
+            }
+            stack.push(Frame {
+                indent: column,
+                segment,
+                is_item: false,
+                items: 0,
+            });
+        }
+    }
+
+    positions
+}
+
+/// Splits `key: value` into the (unquoted) key and the trimmed value text
+fn split_yaml_key(text: &str) -> Option<(String, &str)> {
+    let (key, after_key) =
+        if let Some(quote) = text.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            let body = &text[1..];
+            let end = body.find(quote)?;
+            (body[..end].to_string(), body[end + 1..].trim_start())
+        } else {
+            if text.starts_with(['[', '{', '?']) {
+                return None;
+            }
+            let end = text
+                .find(": ")
+                .or_else(|| text.strip_suffix(':').map(str::len))?;
+            (text[..end].trim_end().to_string(), &text[end..])
+        };
+
+    let value = after_key.strip_prefix(':')?;
+    if !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// Finds the line and column of a YAML path, falling back to its nearest located ancestor
+fn locate_yaml_path(positions: &[YamlPosition], path: &str) -> Option<(usize, usize)> {
+    locate_yaml_segments(positions, parse_yaml_path(path))
+}
+
+fn locate_yaml_segments(
+    positions: &[YamlPosition],
+    mut segments: Vec<YamlPathSegment>,
+) -> Option<(usize, usize)> {
+    while !segments.is_empty() {
+        if let Some((_, line, column)) = positions.iter().find(|(p, _, _)| *p == segments) {
+            return Some((*line, *column));
+        }
+        segments.pop();
+    }
+    None
+}
+
+/// Environment variable that overrides the location of the global user configuration
+pub const GLOBAL_CONFIG_ENV_VAR: &str = "CONCLAUDE_GLOBAL_CONFIG";
+
+/// File names of the untracked local override layer, looked up next to the project config
+pub const LOCAL_CONFIG_FILE_NAMES: [&str; 2] = [".conclaude.local.yaml", ".conclaude.local.yml"];
+
+/// The kind of file a configuration layer was loaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLayerKind {
+    /// Per-user configuration shared by every project (`~/.config/conclaude/config.yaml`)
+    Global,
+    /// The shared project configuration (`.conclaude.yaml`)
+    Project,
+    /// Untracked personal overrides next to the project config (`.conclaude.local.yaml`)
+    Local,
+    /// The selected entry under `profiles:`, applied on top of every file layer
+    Profile,
+}
+
+impl std::fmt::Display for ConfigLayerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            ConfigLayerKind::Global => "global",
+            ConfigLayerKind::Project => "project",
+            ConfigLayerKind::Local => "local",
+            ConfigLayerKind::Profile => "profile",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A single configuration file that took part in building the effective configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigLayer {
+    pub kind: ConfigLayerKind,
+    pub path: PathBuf,
+}
+
+/// The effective configuration together with the layers it was merged from
+///
+/// Layers are merged in precedence order (global, project, local): lists append,
+/// maps merge by key and scalars from later layers override earlier ones.
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: ConclaudeConfig,
+    /// Path of the project configuration file. Its directory is the config directory
+    /// that hook commands run from.
+    pub config_path: PathBuf,
+    /// Layers in the order they were merged, lowest precedence first
+    pub layers: Vec<ConfigLayer>,
+    /// YAML path of every merged value (e.g. `stop.commands[1]`) and the index into
+    /// `layers` of the layer that contributed it, in merge order
+    pub sources: Vec<(String, usize)>,
+    /// Name of the profile applied on top of the file layers, if any
+    pub profile: Option<String>,
+    /// How the project file was located, when it was found by searching up the directory tree
+    pub search: Option<ConfigSearch>,
+    /// Merged file layers before any profile was applied
+    base_value: serde_yaml::Value,
+}
+
+impl LayeredConfig {
+    /// Finds the file that contributed a value of the merged configuration, and the
+    /// line and column of the value within that file when it can be located.
+    ///
+    /// Sequence indices in `path` refer to the merged lists and are translated back to
+    /// positions within the contributing file.
+    #[must_use]
+    pub fn locate(&self, path: &str) -> (PathBuf, Option<(usize, usize)>) {
+        let segments = parse_yaml_path(path);
+        let sources: Vec<(Vec<YamlPathSegment>, usize)> = self
+            .sources
+            .iter()
+            .map(|(source, layer)| (parse_yaml_path(source), *layer))
+            .collect();
+
+        // Prefer the closest recorded ancestor, else the first recorded descendant
+        let layer = sources
+            .iter()
+            .filter(|(source, _)| segments.starts_with(source))
+            .max_by_key(|(source, _)| source.len())
+            .or_else(|| {
+                sources
+                    .iter()
+                    .find(|(source, _)| source.starts_with(&segments))
+            })
+            .map(|(_, layer)| *layer);
+        let Some(layer) = layer else {
+            let position = fs::read_to_string(&self.config_path)
+                .ok()
+                .and_then(|content| locate_yaml_path(&index_yaml_positions(&content), path));
+            return (self.config_path.clone(), position);
+        };
+
+        let mut local = Vec::with_capacity(segments.len() + 2);
+        if self.layers[layer].kind == ConfigLayerKind::Profile {
+            if let Some(profile) = &self.profile {
+                local.push(YamlPathSegment::Key("profiles".to_string()));
+                local.push(YamlPathSegment::Key(profile.clone()));
+            }
+        }
+        for (depth, segment) in segments.iter().enumerate() {
+            let YamlPathSegment::Index(merged_idx) = segment else {
+                local.push(segment.clone());
+                continue;
+            };
+            // Count the earlier items of this list that came from the same layer
+            let parent = &segments[..depth];
+            let siblings: Vec<(usize, bool)> = sources
+                .iter()
+                .filter_map(|(source, source_layer)| match source.split_last() {
+                    Some((YamlPathSegment::Index(idx), source_parent))
+                        if source_parent == parent =>
+                    {
+                        Some((*idx, *source_layer == layer))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let local_idx = if siblings.iter().any(|(idx, _)| idx == merged_idx) {
+                siblings
+                    .iter()
+                    .filter(|(idx, same_layer)| idx < merged_idx && *same_layer)
+                    .count()
+            } else {
+                *merged_idx
+            };
+            local.push(YamlPathSegment::Index(local_idx));
+        }
+
+        let file = self.layers[layer].path.clone();
+        let position = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| locate_yaml_segments(&index_yaml_positions(&content), local));
+        (file, position)
+    }
+
+    /// Builds the effective configuration with the named profile applied to the
+    /// merged file layers, independent of the profile that is currently active.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile does not exist or the merged result is invalid.
+    pub fn config_for_profile(&self, name: &str) -> Result<ConclaudeConfig> {
+        let mut value = self.base_value.clone();
+        let mut layers = self.layers.clone();
+        let mut sources = Vec::new();
+        apply_config_profile(&mut value, name, &mut layers, &mut sources)?;
+        build_config_from_value(value, &self.config_path)
+    }
+
+    /// Returns the merged YAML of every file layer, with the active profile applied,
+    /// before defaults are filled in. This is the configuration as written, with its
+    /// variables expanded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the active profile cannot be applied.
+    pub fn merged_value(&self) -> Result<serde_yaml::Value> {
+        let mut value = self.base_value.clone();
+        if let Some(name) = &self.profile {
+            let mut layers = self.layers.clone();
+            apply_config_profile(&mut value, name, &mut layers, &mut Vec::new())?;
+        }
+        Ok(value)
+    }
+
+    /// Files whose creation, removal or edit changes this configuration
+    ///
+    /// Covers every merged layer, including files pulled in through `extends:` and
+    /// `include:`, plus the override files that do not exist yet.
+    #[must_use]
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        let mut files = default_watched_files(&self.config_path);
+        for layer in &self.layers {
+            if !files.contains(&layer.path) {
+                files.push(layer.path.clone());
+            }
+        }
+        files
+    }
+}
+
+/// Environment variable that selects the active configuration profile
+pub const PROFILE_ENV_VAR: &str = "CONCLAUDE_PROFILE";
+
+/// Returns the profile selected through `CONCLAUDE_PROFILE`, if any
+#[must_use]
+pub fn active_profile() -> Option<String> {
+    std::env::var(PROFILE_ENV_VAR)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the sorted profile names declared under `profiles:` in a raw config value
+fn profile_names(value: &serde_yaml::Value) -> Vec<String> {
+    let mut names: Vec<String> = value
+        .get("profiles")
+        .and_then(serde_yaml::Value::as_mapping)
+        .map(|profiles| profiles.keys().map(yaml_key_to_string).collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Merges the named profile from `profiles:` on top of the merged file layers and
+/// records it as a [`ConfigLayerKind::Profile`] layer.
+fn apply_config_profile(
+    merged: &mut serde_yaml::Value,
+    name: &str,
+    layers: &mut Vec<ConfigLayer>,
+    sources: &mut Vec<(String, usize)>,
+) -> Result<()> {
+    let Some(mut overlay) = merged.get("profiles").and_then(|p| p.get(name)).cloned() else {
+        let available = profile_names(merged);
+        let available = if available.is_empty() {
+            "(none defined)".to_string()
+        } else {
+            available.join(", ")
+        };
+        return Err(anyhow::anyhow!(
+            "Unknown configuration profile: {name}\n\n\
+             Available profiles: {available}\n\n\
+             Select one of the profiles defined under `profiles:` with the {PROFILE_ENV_VAR}\n\
+             environment variable or the --profile flag."
+        ));
+    };
+
+    // Attribute the profile to the file that defined it
+    let prefix = format!("profiles.{name}");
+    let path = sources
+        .iter()
+        .find(|(p, _)| p == &prefix || p.starts_with(&format!("{prefix}.")))
+        .map(|(_, idx)| layers[*idx].path.clone())
+        .unwrap_or_default();
+    layers.push(ConfigLayer {
+        kind: ConfigLayerKind::Profile,
+        path,
+    });
+
+    let layer = layers.len() - 1;
+    interpolate_config_value(&mut overlay, &layers[layer].path)?;
+    merge_config_layer(merged, overlay, "", layer, sources);
+    Ok(())
+}
+
+/// Deserializes and validates a merged configuration value
+fn build_config_from_value(
+    value: serde_yaml::Value,
+    project_path: &Path,
+) -> Result<ConclaudeConfig> {
+    let config: ConclaudeConfig = serde_yaml::from_value(value).map_err(|e| {
+        anyhow::anyhow!(format_parse_error(
+            &e.to_string(),
+            Path::new(&format!("{} (merged layers)", project_path.display()))
+        ))
+    })?;
+    validate_config_constraints(&config, project_path, None)?;
+    Ok(config)
+}
+
+/// Returns the location of the global user configuration file
+///
+/// Uses `CONCLAUDE_GLOBAL_CONFIG` when set, otherwise `~/.config/conclaude/config.yaml`.
+#[must_use]
+pub fn global_config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(GLOBAL_CONFIG_ENV_VAR) {
+        if !path.is_empty() {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    dirs::home_dir().map(|home| home.join(".config/conclaude/config.yaml"))
+}
+
+/// Finds the local override file that sits next to a project configuration file
+fn find_local_config(project_path: &Path) -> Option<PathBuf> {
+    let dir = project_path.parent()?;
+    LOCAL_CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Reads a single configuration layer as raw YAML
+///
+/// Every non-empty layer is also validated on its own so that errors point at the
+/// file (and line) they came from rather than at the merged result.
+fn read_config_layer(path: &Path) -> Result<serde_yaml::Value> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(&content).map_err(|e| ConfigParseError::new(&e, path, None))?;
+
+    if value.is_null() {
+        return Ok(serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+    }
+
+    parse_and_validate_config(&content, path)?;
+
+    Ok(value)
+}
+
+/// Reads a configuration file together with everything it pulls in through
+/// `extends:` and `include:`, appending one fragment per file in merge order.
+///
+/// The merge order for a file is: its `extends` base (recursively), the file itself,
+/// then each `include` entry in the listed order. `chain` holds the files currently
+/// being resolved and is used to detect cycles. `visited` holds every file collected so
+/// far, so a file reached twice, as in a diamond of includes, is merged only once.
+fn collect_config_fragments(
+    path: &Path,
+    kind: ConfigLayerKind,
+    chain: &mut Vec<PathBuf>,
+    visited: &mut std::collections::HashSet<PathBuf>,
+    fragments: &mut Vec<(ConfigLayer, serde_yaml::Value)>,
+) -> Result<()> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(anyhow::anyhow!(
+            "Circular extends/include detected between configuration files:\n  {}\n\n\
+             Remove one of the references to break the cycle.",
+            cycle.join("\n  -> ")
+        ));
+    }
+    if !visited.insert(canonical.clone()) {
+        return Ok(());
+    }
+
+    let mut value = read_config_layer(path)?;
+    let (extends, includes) = take_composition_keys(&mut value);
+    let base_dir = get_config_base_dir(path);
+
+    chain.push(canonical);
+
+    if let Some(base) = extends {
+        let base_path = resolve_config_reference(&base, base_dir);
+        if !base_path.is_file() {
+            return Err(anyhow::anyhow!(
+                "Base configuration not found: {}\n\n\
+                 Referenced by `extends: {base}` in {}\n\n\
+                 Relative paths are resolved against the directory of the file that declares them.",
+                base_path.display(),
+                path.display()
+            ));
+        }
+        collect_config_fragments(&base_path, kind, chain, visited, fragments)?;
+    }
+
+    fragments.push((
+        ConfigLayer {
+            kind,
+            path: path.to_path_buf(),
+        },
+        value,
+    ));
+
+    for include in &includes {
+        for include_path in expand_config_include(include, base_dir, path)? {
+            collect_config_fragments(&include_path, kind, chain, visited, fragments)?;
+        }
+    }
+
+    chain.pop();
+    Ok(())
+}
+
+/// Removes the `extends` and `include` keys from a raw config value and returns them.
+///
+/// The value has already passed typed validation, so both keys have the expected shape.
+fn take_composition_keys(value: &mut serde_yaml::Value) -> (Option<String>, Vec<String>) {
+    let Some(map) = value.as_mapping_mut() else {
+        return (None, Vec::new());
+    };
+
+    let extends = map
+        .remove("extends")
+        .and_then(|v| v.as_str().map(str::to_string));
+    let includes = map
+        .remove("include")
+        .and_then(|v| v.as_sequence().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect();
+
+    (extends, includes)
+}
+
+/// Directory that relative `extends`/`include` references are resolved against
+fn get_config_base_dir(config_path: &Path) -> &Path {
+    match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Resolves an `extends`/`include` reference to a path, expanding a leading `~/`
+fn resolve_config_reference(reference: &str, base_dir: &Path) -> PathBuf {
+    if let Some(stripped) = reference.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(stripped);
+        }
+    }
+    base_dir.join(reference)
+}
+
+/// Expands a single `include` entry into the files it refers to, in merge order.
+///
+/// Glob patterns and directories may match no files; a plain file reference must exist.
+fn expand_config_include(
+    include: &str,
+    base_dir: &Path,
+    declared_in: &Path,
+) -> Result<Vec<PathBuf>> {
+    let resolved = resolve_config_reference(include, base_dir);
+    let is_yaml = |p: &Path| {
+        p.is_file()
+            && matches!(
+                p.extension().and_then(|ext| ext.to_str()),
+                Some("yaml" | "yml")
+            )
+    };
+
+    if include.contains(['*', '?', '[']) {
+        let pattern = resolved.to_string_lossy().to_string();
+        let mut paths: Vec<PathBuf> = glob::glob(&pattern)
+            .with_context(|| {
+                format!(
+                    "Invalid include pattern `{include}` in {}",
+                    declared_in.display()
+                )
+            })?
+            .filter_map(std::result::Result::ok)
+            .filter(|p| is_yaml(p))
+            .collect();
+        paths.sort();
+        return Ok(paths);
+    }
+
+    if resolved.is_dir() {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&resolved)
+            .with_context(|| format!("Failed to read include directory: {}", resolved.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| is_yaml(p))
+            .collect();
+        paths.sort();
+        return Ok(paths);
+    }
+
+    if resolved.is_file() {
+        return Ok(vec![resolved]);
+    }
+
+    Err(anyhow::anyhow!(
+        "Included configuration not found: {}\n\n\
+         Referenced by `include: {include}` in {}\n\n\
+         Relative paths are resolved against the directory of the file that declares them.",
+        resolved.display(),
+        declared_in.display()
+    ))
+}
+
+/// Joins a YAML path segment onto a parent path (`stop` + `commands` -> `stop.commands`)
+fn join_yaml_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+/// Renders YAML path segments, quoting keys that contain path separators
+fn render_yaml_path(segments: &[YamlPathSegment]) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            YamlPathSegment::Key(key) if key.contains(['.', '[', ']']) => {
+                path.push_str(&format!("[\"{key}\"]"));
+            }
+            YamlPathSegment::Key(key) => path = join_yaml_path(&path, key),
+            YamlPathSegment::Index(idx) => path.push_str(&format!("[{idx}]")),
+        }
+    }
+    path
+}
+
+/// Collects the paths of values in `effective` that have no counterpart in `explicit`
+///
+/// A mapping or list that is missing altogether is reported once rather than entry by
+/// entry.
+fn collect_default_paths(
+    effective: &serde_yaml::Value,
+    explicit: &serde_yaml::Value,
+    path: &mut Vec<YamlPathSegment>,
+    defaults: &mut Vec<Vec<YamlPathSegment>>,
+) {
+    match effective {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map {
+                path.push(YamlPathSegment::Key(yaml_key_to_string(key)));
+                match explicit.get(key) {
+                    Some(explicit_value) => {
+                        collect_default_paths(value, explicit_value, path, defaults);
+                    }
+                    None => defaults.push(path.clone()),
+                }
+                path.pop();
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                path.push(YamlPathSegment::Index(idx));
+                match explicit.get(idx) {
+                    Some(explicit_item) => {
+                        collect_default_paths(item, explicit_item, path, defaults);
+                    }
+                    None => defaults.push(path.clone()),
+                }
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Serializes the effective configuration with the defaults the hooks apply to unset
+/// settings filled in: each command entry's [`LaunchOptions::with_defaults`], the `stop`
+/// scheduling options and the context rules' switches
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be serialized.
+pub fn resolved_config_value(config: &ConclaudeConfig) -> Result<serde_yaml::Value> {
+    /// Fills in the launch options of every mapping with a `run` command
+    fn fill_commands(value: &mut serde_yaml::Value) -> Result<()> {
+        match value {
+            serde_yaml::Value::Mapping(map) => {
+                if map.contains_key("run") {
+                    let options: LaunchOptions =
+                        serde_yaml::from_value(serde_yaml::Value::Mapping(map.clone()))?;
+                    if let serde_yaml::Value::Mapping(filled) =
+                        serde_yaml::to_value(options.with_defaults())?
+                    {
+                        map.extend(filled);
+                    }
+                }
+                for child in map.values_mut() {
+                    fill_commands(child)?;
+                }
+            }
+            serde_yaml::Value::Sequence(items) => {
+                for item in items {
+                    fill_commands(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    let mut config = config.clone();
+    config.stop.max_parallel = Some(config.stop.max_parallel());
+    for rule in &mut config.user_prompt_submit.context_rules {
+        rule.enabled = Some(rule.enabled());
+        rule.case_insensitive = Some(rule.case_insensitive());
+    }
+    for command in &mut config.user_prompt_submit.commands {
+        command.case_insensitive = Some(command.case_insensitive());
+    }
+
+    let mut value = serde_yaml::to_value(&config).context("Failed to serialize configuration")?;
+    fill_commands(&mut value).context("Failed to fill in command defaults")?;
+    Ok(value)
+}
+
+/// Returns the YAML paths of values in the fully defaulted configuration `effective`
+/// that are not set in `explicit`, the configuration as written (see
+/// [`LayeredConfig::merged_value`])
+#[must_use]
+pub fn default_value_paths(
+    effective: &serde_yaml::Value,
+    explicit: &serde_yaml::Value,
+) -> Vec<String> {
+    let mut defaults = Vec::new();
+    collect_default_paths(effective, explicit, &mut Vec::new(), &mut defaults);
+    defaults.iter().map(|path| render_yaml_path(path)).collect()
+}
+
+/// Renders `effective` as YAML, marking each value that is not set in `explicit` with a
+/// trailing `# default` comment
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn render_yaml_with_default_markers(
+    effective: &serde_yaml::Value,
+    explicit: &serde_yaml::Value,
+) -> Result<String> {
+    let yaml = serde_yaml::to_string(effective).context("Failed to serialize configuration")?;
+    let mut defaults = Vec::new();
+    collect_default_paths(effective, explicit, &mut Vec::new(), &mut defaults);
+
+    let positions = index_yaml_positions(&yaml);
+    let marked_lines: std::collections::HashSet<usize> = defaults
+        .iter()
+        .filter_map(|path| {
+            positions
+                .iter()
+                .find(|(position_path, _, _)| position_path == path)
+                .map(|(_, line, _)| *line)
+        })
+        .collect();
+
+    let mut rendered = String::with_capacity(yaml.len());
+    for (idx, line) in yaml.lines().enumerate() {
+        rendered.push_str(line);
+        if marked_lines.contains(&(idx + 1)) {
+            rendered.push_str("  # default");
+        }
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
+
+/// Sorts the entries of user-keyed maps (e.g. `subagentStop.commands`) by key so that
+/// a serialized configuration prints in a stable order
+///
+/// Mappings that correspond to configuration structs keep their field order.
+pub fn sort_config_maps(value: &mut serde_yaml::Value) {
+    fn sort(value: &mut serde_yaml::Value, keys: &mut Vec<String>) {
+        match value {
+            serde_yaml::Value::Mapping(map) => {
+                // A map field is the one place where any key leads to a struct
+                let mut path: Vec<&str> = keys.iter().map(String::as_str).collect();
+                let is_struct = ConclaudeConfig::nested_field_names(&path).is_some();
+                path.push("*");
+                if !is_struct && ConclaudeConfig::nested_field_names(&path).is_some() {
+                    let mut entries: Vec<_> = std::mem::take(map).into_iter().collect();
+                    entries.sort_by_key(|(key, _)| yaml_key_to_string(key));
+                    map.extend(entries);
+                }
+                for (key, child) in map.iter_mut() {
+                    keys.push(yaml_key_to_string(key));
+                    sort(child, keys);
+                    keys.pop();
+                }
+            }
+            serde_yaml::Value::Sequence(items) => {
+                for item in items {
+                    sort(item, keys);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    sort(value, &mut Vec::new());
+}
+
+/// Records the layer that contributed `value` and everything beneath it.
+///
+/// Mapping values are recorded per key; list items are recorded as whole entries.
+fn record_value_sources(
+    value: &serde_yaml::Value,
+    path: &str,
+    layer: usize,
+    sources: &mut Vec<(String, usize)>,
+) {
+    match value {
+        serde_yaml::Value::Mapping(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let key = yaml_key_to_string(key);
+                record_value_sources(child, &join_yaml_path(path, &key), layer, sources);
+            }
+        }
+        serde_yaml::Value::Sequence(items) if !items.is_empty() => {
+            for (idx, _) in items.iter().enumerate() {
+                sources.push((format!("{path}[{idx}]"), layer));
+            }
+        }
+        _ => sources.push((path.to_string(), layer)),
+    }
+}
+
+/// Renders a YAML mapping key for use in a YAML path
+fn yaml_key_to_string(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Merges `overlay` into `base` following the layering rules.
+///
+/// - Mappings merge by key, recursing into keys present on both sides
+/// - Sequences append the overlay items after the base items
+/// - Anything else (scalars, or values of different shapes) is replaced by the overlay
+fn merge_config_layer(
+    base: &mut serde_yaml::Value,
+    overlay: serde_yaml::Value,
+    path: &str,
+    layer: usize,
+    sources: &mut Vec<(String, usize)>,
+) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base_map), serde_yaml::Value::Mapping(overlay_map)) => {
+            for (key, overlay_value) in overlay_map {
+                let child_path = join_yaml_path(path, &yaml_key_to_string(&key));
+                match base_map.get_mut(&key) {
+                    Some(base_value) => {
+                        merge_config_layer(base_value, overlay_value, &child_path, layer, sources);
+                    }
+                    None => {
+                        record_value_sources(&overlay_value, &child_path, layer, sources);
+                        base_map.insert(key, overlay_value);
+                    }
+                }
+            }
+        }
+        (serde_yaml::Value::Sequence(base_items), serde_yaml::Value::Sequence(overlay_items)) => {
+            let offset = base_items.len();
+            for (idx, item) in overlay_items.into_iter().enumerate() {
+                sources.push((format!("{path}[{}]", offset + idx), layer));
+                base_items.push(item);
+            }
+        }
+        (base, overlay) => {
+            sources.retain(|(existing, _)| {
+                !(existing == path
+                    || existing.starts_with(&format!("{path}."))
+                    || existing.starts_with(&format!("{path}[")))
+            });
+            record_value_sources(&overlay, path, layer, sources);
+            *base = overlay;
+        }
+    }
+}
+
+/// Returns the effective `root:` setting of the project fragments, the last one set winning
+fn is_root_config(fragments: &[(ConfigLayer, serde_yaml::Value)]) -> bool {
+    fragments
+        .iter()
+        .rev()
+        .find_map(|(_, value)| value.get("root").and_then(serde_yaml::Value::as_bool))
+        .unwrap_or(false)
+}
+
+/// Builds the effective configuration for a project config file by merging the
+/// global layer (if present and the project is not marked `root: true`), the project
+/// file itself and the local override file (if present). Each of these may pull in
+/// further files through `extends:` and `include:`; every such file becomes its own
+/// entry in [`LayeredConfig::layers`]. When `profile` is given, that entry of
+/// `profiles:` is merged on top last.
+///
+/// # Errors
+///
+/// Returns an error if any layer cannot be read, parsed or validated, or if the
+/// merged configuration violates a constraint.
+pub fn load_layered_config_from(
+    project_path: &Path,
+    global_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<LayeredConfig> {
+    let mut roots = vec![(ConfigLayerKind::Project, project_path.to_path_buf())];
+    if let Some(local) = find_local_config(project_path) {
+        roots.push((ConfigLayerKind::Local, local));
+    }
+
+    // Expand `extends:` and `include:` so that every file is merged as its own layer
+    let mut fragments = Vec::new();
+    let mut visited = std::collections::HashSet::new();
+    for (kind, path) in &roots {
+        collect_config_fragments(path, *kind, &mut Vec::new(), &mut visited, &mut fragments)?;
+    }
+
+    // A project marked `root: true` does not inherit the global user configuration
+    if !is_root_config(&fragments) {
+        if let Some(global) = global_path.filter(|p| p.is_file()) {
+            let mut global_fragments = Vec::new();
+            collect_config_fragments(
+                global,
+                ConfigLayerKind::Global,
+                &mut Vec::new(),
+                &mut visited,
+                &mut global_fragments,
+            )?;
+            fragments.splice(0..0, global_fragments);
+        }
+    }
+
+    let mut layers = Vec::with_capacity(fragments.len());
+    let mut merged = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    let mut sources = Vec::new();
+    for (idx, (layer, mut value)) in fragments.into_iter().enumerate() {
+        // `${CONFIG_DIR}` refers to the directory of the file the value was written in
+        interpolate_config_value(&mut value, &layer.path)?;
+        merge_config_layer(&mut merged, value, "", idx, &mut sources);
+        layers.push(layer);
+    }
+
+    let base_value = merged.clone();
+    if let Some(name) = profile {
+        apply_config_profile(&mut merged, name, &mut layers, &mut sources)?;
+    }
+    let config = build_config_from_value(merged, project_path)?;
+
+    Ok(LayeredConfig {
+        config,
+        config_path: project_path.to_path_buf(),
+        layers,
+        sources,
+        profile: profile.map(str::to_string),
+        search: None,
+        base_value,
+    })
+}
+
+/// Load YAML configuration using native search strategies
+///
+/// Search strategy: searches up directory tree from the starting directory,
+/// checking for `.conclaude.yaml` or `.conclaude.yml` in each parent directory.
+/// The search stops when either:
+/// - A config file is found, OR
+/// - The root of the enclosing git repository has been searched, OR
+/// - The filesystem root is reached, OR
+/// - 12 directory levels have been searched
+///
+/// The project file found this way is merged with the global user configuration
+/// and a `.conclaude.local.yaml` override, see [`load_layered_config_from`].
+///
+/// # Arguments
+///
+/// * `start_dir` - Optional starting directory for config search. If None, uses
+///   `CONCLAUDE_SEARCH_START` when set, otherwise the current directory.
+///
+/// # Errors
+///
+/// Returns an error if no configuration file is found, file reading fails, or YAML parsing fails.
+pub async fn load_conclaude_config(start_dir: Option<&Path>) -> Result<(ConclaudeConfig, PathBuf)> {
+    let layered = load_layered_config(start_dir).await?;
+    Ok((layered.config, layered.config_path))
+}
+
+/// Load the layered configuration, keeping track of where each value came from
+///
+/// # Errors
+///
+/// Returns an error if no configuration file is found, file reading fails, or YAML parsing fails.
+pub async fn load_layered_config(start_dir: Option<&Path>) -> Result<LayeredConfig> {
+    let search = search_config(start_dir)?;
+
+    if let SearchBoundary::ConfigFound(path) = &search.boundary {
+        let mut layered = load_layered_config_from(
+            path,
+            global_config_path().as_deref(),
+            active_profile().as_deref(),
+        )?;
+        layered.search = Some(search);
+        return Ok(layered);
+    }
+
+    // If no config file is found, show search locations
+    let search_locations: Vec<String> = search
+        .paths
+        .iter()
+        .map(|p| format!("  • {}", p.display()))
+        .collect();
+
+    let error_message = format!(
+        "Configuration file not found.\n\nSearched the following locations:\n{}\n\nThe search stopped because it {}.\n\nCreate a .conclaude.yaml or .conclaude.yml file with stop and preToolUse sections.\nRun 'conclaude init' to generate a template configuration.",
+        search_locations.join("\n"),
+        search.boundary
+    );
+
+    Err(ConfigNotFound(error_message).into())
+}
+
+/// Error returned when no project configuration file exists along the search path
+///
+/// Hook handlers downcast to this type to apply the `missingConfig` behavior instead of
+/// treating the missing file like any other configuration error.
+#[derive(Debug)]
+pub struct ConfigNotFound(String);
+
+impl std::fmt::Display for ConfigNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigNotFound {}
+
+/// Files that can change the configuration loaded from `project_path`, whether or not
+/// they exist yet: the project file, its local override files and the global file
+fn default_watched_files(project_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![project_path.to_path_buf()];
+    if let Some(dir) = project_path.parent() {
+        files.extend(LOCAL_CONFIG_FILE_NAMES.iter().map(|name| dir.join(name)));
+    }
+    files.extend(global_config_path());
+    files
+}
+
+/// The state of one watched file when a configuration was loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    path: PathBuf,
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+    /// Hash of the contents, or `None` when the file did not exist
+    hash: Option<u64>,
+}
+
+impl FileStamp {
+    fn capture(path: &Path) -> Self {
+        let (modified, len, hash) = match fs::metadata(path) {
+            Ok(meta) => (
+                meta.modified().ok(),
+                meta.len(),
+                fs::read(path).ok().map(|content| hash_content(&content)),
+            ),
+            Err(_) => (None, 0, None),
+        };
+        Self {
+            path: path.to_path_buf(),
+            modified,
+            len,
+            hash,
+        }
+    }
+
+    /// Checks whether the file was created, removed or edited since it was captured
+    ///
+    /// Contents are only hashed again when the modification time or size differ, so
+    /// touching a file without editing it does not count as a change.
+    fn has_changed(&self) -> bool {
+        match (fs::metadata(&self.path), self.hash) {
+            (Err(_), None) => false,
+            (Err(_), Some(_)) | (Ok(_), None) => true,
+            (Ok(meta), Some(hash)) => {
+                if meta.modified().ok() == self.modified && meta.len() == self.len {
+                    return false;
+                }
+                fs::read(&self.path).map_or(true, |content| hash_content(&content) != hash)
+            }
+        }
+    }
+}
+
+fn hash_content(content: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Snapshot of the files a configuration was loaded from, used to notice edits
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFingerprint {
+    files: Vec<FileStamp>,
+}
+
+impl ConfigFingerprint {
+    /// Records the modification time, size and content hash of each file; files that do
+    /// not exist are recorded as absent so that creating them is noticed
+    #[must_use]
+    pub fn capture(paths: &[PathBuf]) -> Self {
+        Self {
+            files: paths.iter().map(|path| FileStamp::capture(path)).collect(),
+        }
+    }
+
+    /// Returns true when any of the files was created, removed or edited
+    #[must_use]
+    pub fn has_changed(&self) -> bool {
+        self.files.iter().any(FileStamp::has_changed)
+    }
+
+    /// Returns true when `path` is one of the files in this fingerprint
+    #[must_use]
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.iter().any(|stamp| {
+            stamp.path == path
+                || matches!(
+                    (stamp.path.canonicalize(), path.canonicalize()),
+                    (Ok(a), Ok(b)) if a == b
+                )
+        })
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|stamp| stamp.path.clone()).collect()
+    }
+}
+
+/// A configuration held by a [`ConfigStore`]
+struct StoredConfig {
+    loaded: &'static (ConclaudeConfig, PathBuf),
+    /// State of the files the configuration came from, or `None` to force a reload
+    fingerprint: Option<ConfigFingerprint>,
+    /// Error of the most recent reload that was rejected in favour of this configuration
+    rejected: Option<String>,
+}
+
+/// Reloadable holder for the effective configuration
+///
+/// The configuration is served from memory until one of the files it was built from
+/// changes on disk. Callers then load it again and either [`replace`](Self::replace) it,
+/// which swaps the new configuration in atomically, or [`keep`](Self::keep) the last
+/// good one when the edit does not parse or validate.
+///
+/// Configurations are leaked so that readers can keep a `&'static` reference across a
+/// swap. A new one is only stored after a file was edited, so the leak stays small even
+/// in a long-lived process.
+pub struct ConfigStore {
+    current: std::sync::RwLock<Option<StoredConfig>>,
+}
+
+impl Default for ConfigStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigStore {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            current: std::sync::RwLock::new(None),
+        }
+    }
+
+    /// Returns the stored configuration when none of its files changed since it was loaded
+    #[must_use]
+    pub fn fresh(&self) -> Option<&'static (ConclaudeConfig, PathBuf)> {
+        let current = self.current.read().expect("config store poisoned");
+        let stored = current.as_ref()?;
+        match &stored.fingerprint {
+            Some(fingerprint) if !fingerprint.has_changed() => Some(stored.loaded),
+            _ => None,
+        }
+    }
+
+    /// Stores a newly loaded configuration in place of the current one
+    pub fn replace(
+        &self,
+        loaded: (ConclaudeConfig, PathBuf),
+        fingerprint: ConfigFingerprint,
+    ) -> &'static (ConclaudeConfig, PathBuf) {
+        let loaded: &'static _ = Box::leak(Box::new(loaded));
+        *self.current.write().expect("config store poisoned") = Some(StoredConfig {
+            loaded,
+            fingerprint: Some(fingerprint),
+            rejected: None,
+        });
+        loaded
+    }
+
+    /// Keeps serving the current configuration after a reload failed with `error`
+    ///
+    /// The files are fingerprinted again in their new state so that the broken edit is
+    /// reported once rather than on every lookup. Returns `None` when there is no
+    /// configuration to fall back to.
+    pub fn keep(&self, error: &anyhow::Error) -> Option<&'static (ConclaudeConfig, PathBuf)> {
+        let mut current = self.current.write().expect("config store poisoned");
+        let stored = current.as_mut()?;
+        let paths = match &stored.fingerprint {
+            Some(fingerprint) => fingerprint.paths(),
+            None => default_watched_files(&stored.loaded.1),
+        };
+        stored.fingerprint = Some(ConfigFingerprint::capture(&paths));
+        stored.rejected = Some(format!("{error:#}"));
+        Some(stored.loaded)
+    }
+
+    /// Forces the next [`fresh`](Self::fresh) lookup to miss, so the configuration is loaded again
+    pub fn invalidate(&self) {
+        if let Some(stored) = self
+            .current
+            .write()
+            .expect("config store poisoned")
+            .as_mut()
+        {
+            stored.fingerprint = None;
+        }
+    }
+
+    /// Returns true when `path` is one of the files the stored configuration depends on
+    #[must_use]
+    pub fn watches(&self, path: &Path) -> bool {
+        let current = self.current.read().expect("config store poisoned");
+        current
+            .as_ref()
+            .and_then(|stored| stored.fingerprint.as_ref())
+            .is_some_and(|fingerprint| fingerprint.contains(path))
+    }
+
+    /// Returns the error of the last reload that was rejected, if the stored
+    /// configuration is being served in place of a broken edit
+    #[must_use]
+    pub fn rejected_change(&self) -> Option<String> {
+        let current = self.current.read().expect("config store poisoned");
+        current.as_ref().and_then(|stored| stored.rejected.clone())
+    }
+}
+
+/// Load the configuration for a project that has no configuration file
+///
+/// This is the global user configuration on its own, together with the files it pulls in
+/// through `extends:` and `include:`, so the rules a user set for every project still
+/// apply. `preToolUse.preventRootAdditions` is off unless the global configuration sets
+/// it. Without a global configuration file every operation is allowed.
+///
+/// # Errors
+///
+/// Returns an error if the global configuration exists but cannot be read or is invalid.
+pub fn load_global_only_config() -> Result<ConclaudeConfig> {
+    let Some(global_path) = global_config_path().filter(|p| p.is_file()) else {
+        let mut config = ConclaudeConfig::default();
+        config.pre_tool_use.prevent_root_additions = false;
+        return Ok(config);
+    };
+
+    let mut fragments = Vec::new();
+    collect_config_fragments(
+        &global_path,
+        ConfigLayerKind::Global,
+        &mut Vec::new(),
+        &mut std::collections::HashSet::new(),
+        &mut fragments,
+    )?;
+    let mut merged = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+    for (idx, (layer, mut value)) in fragments.into_iter().enumerate() {
+        interpolate_config_value(&mut value, &layer.path)?;
+        merge_config_layer(&mut merged, value, "", idx, &mut Vec::new());
+    }
+
+    let sets_root_additions = merged
+        .get("preToolUse")
+        .and_then(|pre_tool_use| pre_tool_use.get("preventRootAdditions"))
+        .is_some();
+    let mut config = build_config_from_value(merged, &global_path)?;
+    if !sets_root_additions {
+        config.pre_tool_use.prevent_root_additions = false;
+    }
+    Ok(config)
+}
+
+/// Environment variable that overrides the directory the configuration search starts from
+pub const SEARCH_START_ENV_VAR: &str = "CONCLAUDE_SEARCH_START";
+
+/// Maximum number of directories, including the start directory, visited by the search
+const MAX_SEARCH_LEVELS: u32 = 12;
+
+/// The point at which the upward configuration search stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchBoundary {
+    /// A configuration file was found at this path
+    ConfigFound(PathBuf),
+    /// The root of the git repository containing the start directory was searched
+    GitRoot(PathBuf),
+    /// The filesystem root was searched
+    FilesystemRoot,
+    /// The maximum number of directory levels was searched
+    MaxDepth,
+}
+
+impl std::fmt::Display for SearchBoundary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConfigFound(path) => write!(f, "found {}", path.display()),
+            Self::GitRoot(root) => {
+                write!(f, "reached the git repository root at {}", root.display())
+            }
+            Self::FilesystemRoot => f.write_str("reached the filesystem root"),
+            Self::MaxDepth => write!(
+                f,
+                "searched the maximum of {MAX_SEARCH_LEVELS} directory levels"
+            ),
+        }
+    }
+}
+
+/// Result of searching up the directory tree for a configuration file
+#[derive(Debug, Clone)]
+pub struct ConfigSearch {
+    /// Directory the search started from
+    pub start_dir: PathBuf,
+    /// Every candidate path that was checked, in search order
+    pub paths: Vec<PathBuf>,
+    /// Why the search stopped
+    pub boundary: SearchBoundary,
+}
+
+/// Search up the directory tree for a configuration file
+///
+/// Starting from `start_dir` (or [`SEARCH_START_ENV_VAR`], or the current directory), each
+/// directory is checked for `.conclaude.yaml` and `.conclaude.yml`. The search stops at the
+/// first configuration file, at the root of the enclosing git repository, at the filesystem
+/// root, or after [`MAX_SEARCH_LEVELS`] directories, whichever comes first.
+///
+/// # Errors
+///
+/// Returns an error if the current directory cannot be determined.
+pub fn search_config(start_dir: Option<&Path>) -> Result<ConfigSearch> {
+    let start_dir = match start_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => {
+            let cwd = std::env::current_dir()?;
+            match std::env::var_os(SEARCH_START_ENV_VAR).filter(|v| !v.is_empty()) {
+                Some(dir) => cwd.join(dir),
+                None => cwd,
+            }
+        }
+    };
+    let git_root = crate::gitignore::find_git_root(&start_dir);
+
+    let mut paths = Vec::new();
+    let mut current_dir = start_dir.clone();
+    let mut levels_searched = 0;
+
+    let boundary = loop {
+        // Check .conclaude.yaml and .conclaude.yml in this directory
+        let candidates = [
+            current_dir.join(".conclaude.yaml"),
+            current_dir.join(".conclaude.yml"),
+        ];
+        paths.extend(candidates.iter().cloned());
+        if let Some(found) = candidates.into_iter().find(|p| p.is_file()) {
+            break SearchBoundary::ConfigFound(found);
+        }
+
+        // Never look above the repository the search started in
+        if git_root.as_deref() == Some(current_dir.as_path()) {
+            break SearchBoundary::GitRoot(current_dir);
+        }
+
+        // Move to parent directory first, then increment level count
+        match current_dir.parent() {
+            Some(parent) => {
+                current_dir = parent.to_path_buf();
+                levels_searched += 1;
+
+                // Check if we've reached the maximum search level limit
+                if levels_searched >= MAX_SEARCH_LEVELS {
+                    break SearchBoundary::MaxDepth;
+                }
+            }
+            None => break SearchBoundary::FilesystemRoot,
+        }
+    };
+
+    Ok(ConfigSearch {
+        start_dir,
+        paths,
+        boundary,
+    })
+}
+
+/// Find the configuration file closest to a directory
+///
+/// Walks up from `start_dir` using the same search rules as [`load_conclaude_config`] and
+/// returns the first `.conclaude.yaml` or `.conclaude.yml` that exists. In a monorepo this
+/// is the config owned by the package containing `start_dir`. A `start_dir` that does not
+/// exist yet, such as the directory of a file about to be created, is searched from its
+/// nearest existing ancestor.
+#[must_use]
+pub fn find_nearest_config(start_dir: &Path) -> Option<PathBuf> {
+    let start_dir = start_dir.ancestors().find(|dir| dir.is_dir())?;
+    match search_config(Some(start_dir)).ok()?.boundary {
+        SearchBoundary::ConfigFound(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// A problem with the `name`/`needs` dependency graph of a stop command list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandGraphError {
+    /// A second command uses a name that is already taken
+    DuplicateName { index: usize, name: String },
+    /// A command needs a name that no command in the list has
+    UnknownNeed { index: usize, need: String },
+    /// A command depends on itself through its `needs`
+    Cycle { index: usize },
+}
+
+impl std::fmt::Display for CommandGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandGraphError::DuplicateName { index, name } => write!(
+                f,
+                "Command {index} reuses the name '{name}'; names must be unique within a command list"
+            ),
+            CommandGraphError::UnknownNeed { index, need } => write!(
+                f,
+                "Command {index} needs '{need}', but no command in the list has that name"
+            ),
+            CommandGraphError::Cycle { index } => write!(
+                f,
+                "Command {index} is part of a dependency cycle; `needs` must not loop back to a command"
+            ),
+        }
+    }
+}
+
+/// Resolves the `needs` of every stop command to the indices of the commands it waits for
+///
+/// # Errors
+///
+/// Returns every duplicate name and unknown need, or the first command of a dependency
+/// cycle when names resolve but the graph loops.
+pub fn resolve_command_needs(
+    commands: &[StopCommand],
+) -> std::result::Result<Vec<Vec<usize>>, Vec<CommandGraphError>> {
+    let mut errors = Vec::new();
+    let mut names: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    for (index, command) in commands.iter().enumerate() {
+        if let Some(name) = command.name.as_deref() {
+            if names.insert(name, index).is_some() {
+                errors.push(CommandGraphError::DuplicateName {
+                    index,
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    let mut needs = Vec::with_capacity(commands.len());
+    for (index, command) in commands.iter().enumerate() {
+        let mut resolved = Vec::new();
+        for need in command.needs.iter().flatten() {
+            match names.get(need.as_str()) {
+                Some(&target) => resolved.push(target),
+                None => errors.push(CommandGraphError::UnknownNeed {
+                    index,
+                    need: need.clone(),
+                }),
+            }
+        }
+        needs.push(resolved);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Repeatedly settle commands whose needs are all settled; anything left is in a cycle
+    let mut settled = vec![false; commands.len()];
+    loop {
+        let ready: Vec<usize> = (0..commands.len())
+            .filter(|&index| !settled[index] && needs[index].iter().all(|&n| settled[n]))
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for index in ready {
+            settled[index] = true;
+        }
+    }
+    match settled.iter().position(|done| !done) {
+        Some(index) => Err(vec![CommandGraphError::Cycle { index }]),
+        None => Ok(needs),
+    }
+}
+
+/// Splits a command entry's `run` block into the commands to execute
+///
+/// In script mode the block is kept whole so it runs in a single shell, unless it only
+/// contains blank lines and comments. In lines mode every non-comment line is a command.
+///
+/// # Errors
+///
+/// Returns an error if line extraction fails in lines mode.
+pub fn split_run_commands(run: &str, mode: RunMode) -> Result<Vec<String>> {
+    match mode {
+        RunMode::Lines => extract_bash_commands(run),
+        RunMode::Script => {
+            let has_command = run.lines().any(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            });
+            if has_command {
+                Ok(vec![run.trim_end().to_string()])
+            } else {
+                Ok(Vec::new())
+            }
+        }
+    }
 }
 
 /// Extracts individual commands from a bash script string
@@ -2729,11 +5318,11 @@ userPromptSubmit:
         assert_eq!(cmd.run, ".claude/scripts/log.sh");
         assert_eq!(cmd.pattern, Some("deploy|release".to_string()));
         assert_eq!(cmd.case_insensitive, Some(true));
-        assert_eq!(cmd.show_command, Some(true));
-        assert_eq!(cmd.show_stdout, Some(true));
-        assert_eq!(cmd.show_stderr, Some(false));
-        assert_eq!(cmd.max_output_lines, Some(100));
-        assert_eq!(cmd.timeout, Some(30));
+        assert_eq!(cmd.launch.show_command, Some(true));
+        assert_eq!(cmd.launch.show_stdout, Some(true));
+        assert_eq!(cmd.launch.show_stderr, Some(false));
+        assert_eq!(cmd.launch.max_output_lines, Some(100));
+        assert_eq!(cmd.launch.timeout, Some(30));
     }
 
     // Test: Command with minimal options uses defaults
@@ -2752,11 +5341,11 @@ userPromptSubmit:
         assert_eq!(cmd.run, "echo received");
         assert_eq!(cmd.pattern, None); // Default: matches all
         assert_eq!(cmd.case_insensitive, None); // Default: false
-        assert_eq!(cmd.show_command, Some(true)); // Default: true
-        assert_eq!(cmd.show_stdout, None); // Default: false
-        assert_eq!(cmd.show_stderr, None); // Default: false
-        assert_eq!(cmd.max_output_lines, None); // Default: no limit
-        assert_eq!(cmd.timeout, None); // Default: no timeout
+        assert_eq!(cmd.launch.show_command, Some(true)); // Default: true
+        assert_eq!(cmd.launch.show_stdout, None); // Default: false
+        assert_eq!(cmd.launch.show_stderr, None); // Default: false
+        assert_eq!(cmd.launch.max_output_lines, None); // Default: no limit
+        assert_eq!(cmd.launch.timeout, None); // Default: no timeout
     }
 
     #[test]
@@ -1,10 +1,14 @@
-use crate::config::{parse_and_validate_config, suggest_similar_fields, ConclaudeConfig};
+use crate::config::{
+    collect_config_warnings, default_value_paths, parse_and_validate_config,
+    render_yaml_with_default_markers, resolved_config_value, sort_config_maps, suggest_fields_at_path, ConclaudeConfig,
+    ConfigParseError, ConfigValidationError, DiagnosticParser, FailureAction, OutputMode, Severity,
+};
 use std::path::Path;
 
 #[test]
-fn test_suggest_similar_fields_common_typo() {
+fn test_suggest_fields_at_path_common_typo() {
     // Test common typo: "showStdOut" should suggest "showStdout"
-    let suggestions = suggest_similar_fields("showStdOut", "commands");
+    let suggestions = suggest_fields_at_path("showStdOut", "stop.commands[0]");
     assert!(
         !suggestions.is_empty(),
         "Should suggest fields for common typo"
@@ -16,9 +20,9 @@ fn test_suggest_similar_fields_common_typo() {
 }
 
 #[test]
-fn test_suggest_similar_fields_case_insensitive() {
+fn test_suggest_fields_at_path_case_insensitive() {
     // Test case-insensitive matching: "INFINITE" should suggest "infinite"
-    let suggestions = suggest_similar_fields("INFINITE", "stop");
+    let suggestions = suggest_fields_at_path("INFINITE", "stop");
     assert!(
         !suggestions.is_empty(),
         "Should suggest fields ignoring case"
@@ -30,24 +34,24 @@ fn test_suggest_similar_fields_case_insensitive() {
 }
 
 #[test]
-fn test_suggest_similar_fields_distance_threshold() {
+fn test_suggest_fields_at_path_distance_threshold() {
     // Test that only suggestions within distance 3 are returned
     // "infinit" (distance 1) should be suggested
-    let suggestions = suggest_similar_fields("infinit", "stop");
+    let suggestions = suggest_fields_at_path("infinit", "stop");
     assert!(
         suggestions.contains(&"infinite".to_string()),
         "Should suggest 'infinite' for 'infinit' (distance 1)"
     );
 
     // "infinte" (distance 1, missing 'i') should be suggested
-    let suggestions = suggest_similar_fields("infinte", "stop");
+    let suggestions = suggest_fields_at_path("infinte", "stop");
     assert!(
         suggestions.contains(&"infinite".to_string()),
         "Should suggest 'infinite' for 'infinte' (distance 1)"
     );
 
     // "wxyz" has distance > 3 from all stop fields, should not suggest anything
-    let suggestions = suggest_similar_fields("wxyz", "stop");
+    let suggestions = suggest_fields_at_path("wxyz", "stop");
     assert!(
         suggestions.is_empty(),
         "Should not suggest anything for 'wxyz' (distance > 3 from all fields)"
@@ -55,15 +59,15 @@ fn test_suggest_similar_fields_distance_threshold() {
 }
 
 #[test]
-fn test_suggest_similar_fields_no_close_matches() {
+fn test_suggest_fields_at_path_no_close_matches() {
     // Test that empty results are returned when no close matches exist
-    let suggestions = suggest_similar_fields("completelywrongfield", "stop");
+    let suggestions = suggest_fields_at_path("completelywrongfield", "stop");
     assert!(
         suggestions.is_empty(),
         "Should return empty for field with no close matches"
     );
 
-    let suggestions = suggest_similar_fields("abcdefgh", "rules");
+    let suggestions = suggest_fields_at_path("abcdefgh", "rules");
     assert!(
         suggestions.is_empty(),
         "Should return empty when distance exceeds threshold"
@@ -71,10 +75,10 @@ fn test_suggest_similar_fields_no_close_matches() {
 }
 
 #[test]
-fn test_suggest_similar_fields_sorted_by_distance() {
+fn test_suggest_fields_at_path_sorted_by_distance() {
     // Test that suggestions are sorted by distance (closest first)
     // "messag" (distance 1 from "message") should come before anything with higher distance
-    let suggestions = suggest_similar_fields("messag", "commands");
+    let suggestions = suggest_fields_at_path("messag", "stop.commands[0]");
     if !suggestions.is_empty() {
         assert_eq!(
             suggestions[0], "message",
@@ -84,9 +88,9 @@ fn test_suggest_similar_fields_sorted_by_distance() {
 }
 
 #[test]
-fn test_suggest_similar_fields_max_three_suggestions() {
+fn test_suggest_fields_at_path_max_three_suggestions() {
     // Test that at most 3 suggestions are returned
-    let suggestions = suggest_similar_fields("sho", "commands");
+    let suggestions = suggest_fields_at_path("sho", "stop.commands[0]");
     assert!(
         suggestions.len() <= 3,
         "Should return at most 3 suggestions, got {}",
@@ -95,19 +99,19 @@ fn test_suggest_similar_fields_max_three_suggestions() {
 }
 
 #[test]
-fn test_suggest_similar_fields_invalid_section() {
-    // Test that empty results are returned for invalid section
-    let suggestions = suggest_similar_fields("infinite", "invalid_section");
+fn test_suggest_fields_at_path_invalid_path() {
+    // Test that empty results are returned for a path outside the configuration
+    let suggestions = suggest_fields_at_path("infinite", "invalid_section");
     assert!(
         suggestions.is_empty(),
-        "Should return empty for invalid section"
+        "Should return empty for invalid path"
     );
 }
 
 #[test]
-fn test_suggest_similar_fields_notifications_section() {
+fn test_suggest_fields_at_path_notifications_section() {
     // Test suggestions for notifications section
-    let suggestions = suggest_similar_fields("enable", "notifications");
+    let suggestions = suggest_fields_at_path("enable", "notifications");
     assert!(
         suggestions.contains(&"enabled".to_string()),
         "Should suggest 'enabled' for 'enable' in notifications section"
@@ -115,9 +119,9 @@ fn test_suggest_similar_fields_notifications_section() {
 }
 
 #[test]
-fn test_suggest_similar_fields_pretooluse_section() {
+fn test_suggest_fields_at_path_pretooluse_section() {
     // Test suggestions for preToolUse section with camelCase field
-    let suggestions = suggest_similar_fields("preventRootAddition", "preToolUse");
+    let suggestions = suggest_fields_at_path("preventRootAddition", "preToolUse");
     assert!(
         suggestions.contains(&"preventRootAdditions".to_string()),
         "Should suggest 'preventRootAdditions' for 'preventRootAddition'"
@@ -549,8 +553,8 @@ notifications:
 
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 2);
-    assert_eq!(config.stop.commands[0].timeout, Some(30));
-    assert_eq!(config.stop.commands[1].timeout, None);
+    assert_eq!(config.stop.commands[0].launch.timeout, Some(30));
+    assert_eq!(config.stop.commands[1].launch.timeout, None);
 }
 
 #[test]
@@ -643,7 +647,7 @@ notifications:
     let config = result.unwrap();
     let cmds = config.subagent_stop.commands.get("*").unwrap();
     assert_eq!(cmds.len(), 1);
-    assert_eq!(cmds[0].timeout, Some(60));
+    assert_eq!(cmds[0].launch.timeout, Some(60));
 }
 
 #[test]
@@ -674,7 +678,7 @@ notifications:
     );
 
     let config = result.unwrap();
-    assert_eq!(config.stop.commands[0].timeout, None);
+    assert_eq!(config.stop.commands[0].launch.timeout, None);
 }
 
 #[test]
@@ -1168,7 +1172,10 @@ notifications:
 
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 1);
-    assert_eq!(config.stop.commands[0].notify_per_command, Some(true));
+    assert_eq!(
+        config.stop.commands[0].launch.notify_per_command,
+        Some(true)
+    );
 }
 
 #[test]
@@ -1199,7 +1206,10 @@ notifications:
 
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 1);
-    assert_eq!(config.stop.commands[0].notify_per_command, Some(false));
+    assert_eq!(
+        config.stop.commands[0].launch.notify_per_command,
+        Some(false)
+    );
 }
 
 #[test]
@@ -1230,7 +1240,7 @@ notifications:
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 1);
     assert_eq!(
-        config.stop.commands[0].notify_per_command, None,
+        config.stop.commands[0].launch.notify_per_command, None,
         "notifyPerCommand should default to None when omitted"
     );
 }
@@ -1299,7 +1309,7 @@ notifications:
     let config = result.unwrap();
     let cmds = config.subagent_stop.commands.get("*").unwrap();
     assert_eq!(cmds.len(), 1);
-    assert_eq!(cmds[0].notify_per_command, Some(true));
+    assert_eq!(cmds[0].launch.notify_per_command, Some(true));
 }
 
 #[test]
@@ -1334,7 +1344,7 @@ notifications:
     let config = result.unwrap();
     let cmds = config.subagent_stop.commands.get("*").unwrap();
     assert_eq!(cmds.len(), 1);
-    assert_eq!(cmds[0].notify_per_command, Some(false));
+    assert_eq!(cmds[0].launch.notify_per_command, Some(false));
 }
 
 #[test]
@@ -1369,7 +1379,7 @@ notifications:
     let cmds = config.subagent_stop.commands.get("*").unwrap();
     assert_eq!(cmds.len(), 1);
     assert_eq!(
-        cmds[0].notify_per_command, None,
+        cmds[0].launch.notify_per_command, None,
         "notifyPerCommand should default to None when omitted"
     );
 }
@@ -1406,17 +1416,745 @@ notifications:
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 3);
     assert_eq!(
-        config.stop.commands[0].notify_per_command,
+        config.stop.commands[0].launch.notify_per_command,
         Some(true),
         "First command should have notifyPerCommand: true"
     );
     assert_eq!(
-        config.stop.commands[1].notify_per_command,
+        config.stop.commands[1].launch.notify_per_command,
         Some(false),
         "Second command should have notifyPerCommand: false"
     );
     assert_eq!(
-        config.stop.commands[2].notify_per_command, None,
+        config.stop.commands[2].launch.notify_per_command, None,
         "Third command should have notifyPerCommand: None (omitted)"
     );
 }
+
+// ========== Variable Interpolation Tests ==========
+
+#[test]
+fn test_interpolation_expands_environment_variables() {
+    std::env::set_var("CONCLAUDE_TEST_INTERP_TARGET", "release");
+    let yaml = r#"
+stop:
+  commands:
+    - run: "cargo build --profile ${CONCLAUDE_TEST_INTERP_TARGET}"
+      message: "Build (${CONCLAUDE_TEST_INTERP_TARGET}) failed"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(config.stop.commands[0].run, "cargo build --profile release");
+    assert_eq!(
+        config.stop.commands[0].message.as_deref(),
+        Some("Build (release) failed")
+    );
+}
+
+#[test]
+fn test_interpolation_uses_default_when_unset() {
+    std::env::remove_var("CONCLAUDE_TEST_INTERP_UNSET");
+    let yaml = r#"
+worktreeCreate:
+  command: "make worktree NAME=${CONCLAUDE_TEST_INTERP_UNSET:-scratch}"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(
+        config.worktree_create.command.as_deref(),
+        Some("make worktree NAME=scratch")
+    );
+}
+
+#[test]
+fn test_interpolation_builtin_config_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+    let yaml = r#"
+userPromptSubmit:
+  contextRules:
+    - pattern: "docs"
+      prompt: "Read ${CONFIG_DIR}/docs/STYLE.md first"
+"#;
+    let config = parse_and_validate_config(yaml, &config_path).unwrap();
+    let expected_dir = temp_dir.path().canonicalize().unwrap();
+
+    assert_eq!(
+        config.user_prompt_submit.context_rules[0].prompt,
+        format!("Read {}/docs/STYLE.md first", expected_dir.display())
+    );
+}
+
+#[test]
+fn test_interpolation_unset_required_variable_is_validation_error() {
+    std::env::remove_var("CONCLAUDE_TEST_INTERP_REQUIRED");
+    let yaml = r#"
+stop:
+  commands:
+    - run: "deploy --token ${CONCLAUDE_TEST_INTERP_REQUIRED:?}"
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("Unset variable"));
+    assert!(error.contains("stop.commands[0].run"));
+    assert!(error.contains("${CONCLAUDE_TEST_INTERP_REQUIRED:?}"));
+}
+
+#[test]
+fn test_interpolation_leaves_runtime_variables_for_the_shell() {
+    std::env::remove_var("CONCLAUDE_SESSION_ID");
+    let yaml = r#"
+stop:
+  commands:
+    - run: "echo ${CONCLAUDE_SESSION_ID}"
+      message: "Session ${CONCLAUDE_SESSION_ID} failed its checks"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(config.stop.commands[0].run, "echo ${CONCLAUDE_SESSION_ID}");
+    assert_eq!(
+        config.stop.commands[0].message.as_deref(),
+        Some("Session ${CONCLAUDE_SESSION_ID} failed its checks")
+    );
+}
+
+#[test]
+fn test_interpolation_escape_and_shell_expansions_pass_through() {
+    let yaml = r#"
+stop:
+  commands:
+    - run: "for f in *.txt; do echo $${f} ${f%.txt} $HOME; done"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(
+        config.stop.commands[0].run,
+        "for f in *.txt; do echo ${f} ${f%.txt} $HOME; done"
+    );
+}
+
+#[test]
+fn test_interpolation_ignores_non_interpolated_fields() {
+    std::env::remove_var("CONCLAUDE_TEST_INTERP_GLOB");
+    let yaml = r#"
+preToolUse:
+  uneditableFiles:
+    - "${CONCLAUDE_TEST_INTERP_GLOB}/*.lock"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(
+        config.pre_tool_use.uneditable_files[0].pattern(),
+        "${CONCLAUDE_TEST_INTERP_GLOB}/*.lock"
+    );
+}
+
+#[test]
+fn test_missing_config_defaults_to_allow() {
+    let config =
+        parse_and_validate_config("stop:\n  commands: []\n", Path::new(".conclaude.yaml")).unwrap();
+
+    assert_eq!(config.missing_config.mode, "allow");
+    assert!(!config.missing_config.session_start_hint);
+}
+
+#[test]
+fn test_missing_config_rejects_unknown_mode() {
+    let yaml = r#"
+missingConfig:
+  mode: deny
+"#;
+    let error = parse_and_validate_config(yaml, Path::new("config.yaml"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("missingConfig.mode"));
+    assert!(error.contains("Invalid value 'deny'"));
+}
+
+#[test]
+fn test_validation_reports_all_errors_with_positions() {
+    let yaml = r#"stop:
+  commands:
+    - run: "cargo test"
+      timeout: 0
+preToolUse:
+  uneditableFiles:
+    - "src/**.rs"
+  toolUsageValidation:
+    - tool: "Bash"
+      pattern: "*"
+      action: "deny"
+subagentStop:
+  commands:
+    "[coder":
+      - run: "npm run lint"
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .downcast::<ConfigValidationError>()
+        .unwrap();
+
+    let located: Vec<(&str, Option<usize>, Option<usize>)> = error
+        .issues
+        .iter()
+        .map(|issue| (issue.path.as_str(), issue.line, issue.column))
+        .collect();
+    assert_eq!(
+        located,
+        vec![
+            ("stop.commands[0].timeout", Some(4), Some(7)),
+            ("preToolUse.uneditableFiles[0]", Some(7), Some(5)),
+            (
+                "preToolUse.toolUsageValidation[0].action",
+                Some(11),
+                Some(7)
+            ),
+            ("subagentStop.commands[\"[coder\"]", Some(14), Some(5)),
+        ]
+    );
+
+    let message = error.to_string();
+    assert!(message.contains("4 problems"), "error: {message}");
+    assert!(message.contains(".conclaude.yaml:4:7"), "error: {message}");
+}
+
+#[test]
+fn test_validation_suggests_hook_names_for_notifications() {
+    let yaml = r#"
+notifications:
+  enabled: true
+  hooks: ["Stop", "preToolUse"]
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("notifications.hooks[1]"), "error: {error}");
+    assert!(error.contains("Did you mean: PreToolUse"), "error: {error}");
+    // Flow sequences are located at their enclosing key
+    assert!(error.contains(".conclaude.yaml:4:3"), "error: {error}");
+}
+
+#[test]
+fn test_config_warnings_report_rules_without_effect() {
+    let yaml = r#"
+permissionRequest:
+  default: deny
+  allow: ["Bash", "Read"]
+  deny: ["Bash*"]
+preToolUse:
+  toolUsageValidation:
+    - tool: "Write"
+      pattern: "*.md"
+      action: "block"
+    - tool: "Write"
+      pattern: "*.md"
+      action: "block"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    let warnings = collect_config_warnings(&config, None);
+
+    let found: Vec<(&str, &str)> = warnings.iter().map(|w| (w.code, w.path.as_str())).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("duplicate-rule", "preToolUse.toolUsageValidation[1]"),
+            ("shadowed-rule", "permissionRequest.allow[0]"),
+        ]
+    );
+    assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+}
+
+#[test]
+fn test_parse_error_carries_path_position_and_suggestions() {
+    let yaml = "notifications:\n  enabeld: true\n";
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap_err();
+    let parse_error = error.downcast_ref::<ConfigParseError>().unwrap();
+
+    assert_eq!(parse_error.issue.code, "unknown-field");
+    assert_eq!(parse_error.issue.path, "notifications.enabeld");
+    assert_eq!(parse_error.issue.line, Some(2));
+    assert!(parse_error
+        .issue
+        .suggestions
+        .contains(&"enabled".to_string()));
+    // The rendered message keeps the full guidance
+    assert!(error.to_string().contains("Did you mean one of these?"));
+}
+
+#[test]
+fn test_nested_typos_suggest_fields_at_their_depth() {
+    let cases = [
+        (
+            "stop:\n  commands:\n    - run: \"cargo test\"\n      showStdOut: true\n",
+            "stop.commands[0].showStdOut",
+            "showStdout",
+        ),
+        (
+            "userPromptSubmit:\n  slashCommands:\n    comands: {}\n",
+            "userPromptSubmit.slashCommands.comands",
+            "commands",
+        ),
+        (
+            "subagentStop:\n  commands:\n    coder:\n      - run: \"npm test\"\n        tiemout: 30\n",
+            "subagentStop.commands.coder[0].tiemout",
+            "timeout",
+        ),
+        (
+            "profiles:\n  ci:\n    stop:\n      infinit: true\n",
+            "profiles.ci.stop.infinit",
+            "infinite",
+        ),
+    ];
+
+    for (yaml, path, expected) in cases {
+        let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap_err();
+        let parse_error = error.downcast_ref::<ConfigParseError>().unwrap();
+
+        assert_eq!(parse_error.issue.path, path);
+        assert!(
+            parse_error
+                .issue
+                .suggestions
+                .contains(&expected.to_string()),
+            "expected {expected} for {path}, got {:?}",
+            parse_error.issue.suggestions
+        );
+        assert!(error.to_string().contains(&format!("   - {expected}")));
+    }
+}
+
+#[test]
+fn test_suggest_fields_at_path_walks_nested_sections() {
+    assert_eq!(
+        suggest_fields_at_path("maxOutputLine", "stop.commands[2]"),
+        vec!["maxOutputLines".to_string()]
+    );
+    assert_eq!(
+        suggest_fields_at_path("tol", "preToolUse.toolUsageValidation[0]"),
+        vec!["tool".to_string()]
+    );
+    // Unknown sections and scalar fields have no nested keys to suggest
+    assert!(suggest_fields_at_path("run", "nonexistent").is_empty());
+    assert!(suggest_fields_at_path("run", "stop.infinite").is_empty());
+}
+
+#[test]
+fn test_default_value_paths_marks_values_missing_from_files() {
+    let explicit: serde_yaml::Value =
+        serde_yaml::from_str("stop:\n  commands:\n    - run: \"cargo test\"\n").unwrap();
+    let config: ConclaudeConfig = serde_yaml::from_value(explicit.clone()).unwrap();
+    let effective = serde_yaml::to_value(&config).unwrap();
+
+    let defaults = default_value_paths(&effective, &explicit);
+    assert!(defaults.contains(&"stop.infinite".to_string()));
+    assert!(defaults.contains(&"stop.commands[0].timeout".to_string()));
+    assert!(defaults.contains(&"notifications".to_string()));
+    // Missing sections are reported once, not field by field
+    assert!(!defaults.contains(&"notifications.enabled".to_string()));
+    assert!(!defaults.contains(&"stop.commands[0].run".to_string()));
+
+    let rendered = render_yaml_with_default_markers(&effective, &explicit).unwrap();
+    assert!(rendered.contains("  infinite: false  # default\n"));
+    assert!(rendered.contains("  - run: cargo test\n"));
+    assert!(rendered.contains("notifications:  # default\n"));
+}
+
+#[test]
+fn test_resolved_config_fills_in_runtime_defaults() {
+    let yaml = r#"stop:
+  commands:
+    - run: "echo hi"
+subagentStop:
+  commands:
+    "*":
+      - run: "cargo test"
+        retries: 2
+userPromptSubmit:
+  contextRules:
+    - pattern: "deploy"
+      prompt: "Read the runbook"
+  commands:
+    - run: "echo prompt"
+setup:
+  commands:
+    "*":
+      - run: "npm install"
+"#;
+    let explicit: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    let resolved = resolved_config_value(&config).unwrap();
+    let rendered = render_yaml_with_default_markers(&resolved, &explicit).unwrap();
+
+    // Settings the hooks default at runtime never show up as unset
+    for key in [
+        "showCommand",
+        "showStdout",
+        "showStderr",
+        "outputMode",
+        "errorPatterns",
+        "notifyPerCommand",
+        "shell",
+        "mode",
+        "retries",
+        "retryDelay",
+        "gracePeriod",
+        "maxParallel",
+        "failFast",
+        "enabled",
+        "caseInsensitive",
+    ] {
+        assert!(
+            !rendered.contains(&format!(" {key}: null  # default")),
+            "{key} rendered as null:\n{rendered}"
+        );
+    }
+    assert!(rendered.contains("    shell: bash  # default\n"));
+    assert!(rendered.contains("    mode: script  # default\n"));
+    assert!(rendered.contains("    outputMode: head  # default\n"));
+    assert!(rendered.contains("    retryDelay: 1  # default\n"));
+    assert!(rendered.contains("    gracePeriod: 5  # default\n"));
+    assert!(rendered.contains("  failFast: true  # default\n"));
+    // Values set in the file are kept
+    assert!(rendered.contains("      retries: 2\n"));
+    // Settings without a default stay unset
+    assert!(rendered.contains("    timeout: null  # default\n"));
+}
+
+#[test]
+fn test_default_value_paths_quote_keys_with_separators() {
+    let explicit: serde_yaml::Value = serde_yaml::from_str(
+        "fileChanged:\n  commands:\n    \"*.rs\":\n      - run: \"cargo check\"\n",
+    )
+    .unwrap();
+    let config: ConclaudeConfig = serde_yaml::from_value(explicit.clone()).unwrap();
+    let effective = serde_yaml::to_value(&config).unwrap();
+
+    let defaults = default_value_paths(&effective, &explicit);
+    assert!(defaults.contains(&"fileChanged.commands[\"*.rs\"][0].timeout".to_string()));
+}
+
+#[test]
+fn test_sort_config_maps_orders_pattern_keys_only() {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(
+        "subagentStop:\n  commands:\n    tester:\n      - run: b\n        message: m\n    coder:\n      - run: a\nstop:\n  infinite: true\n",
+    )
+    .unwrap();
+    sort_config_maps(&mut value);
+
+    let rendered = serde_yaml::to_string(&value).unwrap();
+    // Pattern keys are sorted, struct fields and sections keep their order
+    assert!(rendered.find("coder:").unwrap() < rendered.find("tester:").unwrap());
+    assert!(rendered.find("subagentStop:").unwrap() < rendered.find("stop:\n").unwrap());
+    assert!(rendered.find("run: b").unwrap() < rendered.find("message: m").unwrap());
+}
+
+#[test]
+fn test_validation_reports_stop_command_needs_problems() {
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run lint"
+      name: lint
+    - run: "npm test"
+      name: lint
+    - run: "npm run build"
+      needs: [lnt]
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .downcast::<ConfigValidationError>()
+        .unwrap();
+
+    let found: Vec<(&str, &str)> = error
+        .issues
+        .iter()
+        .map(|issue| (issue.code, issue.path.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("duplicate-name", "stop.commands[1].name"),
+            ("unknown-reference", "stop.commands[2].needs"),
+        ]
+    );
+    assert_eq!(error.issues[1].suggestions, vec!["lint".to_string()]);
+}
+
+#[test]
+fn test_validation_reports_stop_command_needs_cycle() {
+    let yaml = r#"stop:
+  commands:
+    - run: "echo a"
+      name: a
+      needs: [b]
+    - run: "echo b"
+      name: b
+      needs: [a]
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("dependency cycle"), "error: {error}");
+}
+
+#[test]
+fn test_stop_parallel_config_and_max_parallel_range() {
+    let yaml = r#"stop:
+  parallel: true
+  maxParallel: 3
+  commands:
+    - run: "cargo clippy"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert_eq!(config.stop.concurrency(), 3);
+
+    let yaml = r#"stop:
+  parallel: true
+  maxParallel: 0
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("stop.maxParallel"), "error: {error}");
+}
+
+#[test]
+fn test_fail_fast_on_stop_and_subagent_stop() {
+    let yaml = r#"stop:
+  failFast: false
+  commands:
+    - run: "cargo clippy"
+subagentStop:
+  failFast: false
+  commands:
+    "*":
+      - run: "cargo test"
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert!(!config.stop.fail_fast);
+    assert!(!config.subagent_stop.fail_fast);
+
+    let config =
+        parse_and_validate_config("stop:\n  commands: []\n", Path::new(".conclaude.yaml")).unwrap();
+    assert!(config.stop.fail_fast);
+    assert!(config.subagent_stop.fail_fast);
+}
+
+#[test]
+fn test_command_retry_settings_and_ranges() {
+    let yaml = r#"stop:
+  commands:
+    - run: "docker compose run --rm integration"
+      retries: 3
+      retryDelay: 5
+      retryOn: [75, 137]
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    let command = &config.stop.commands[0];
+    assert_eq!(command.launch.retries, Some(3));
+    assert_eq!(command.launch.retry_delay, Some(5));
+    assert_eq!(command.launch.retry_on, Some(vec![75, 137]));
+
+    let yaml = r#"subagentStop:
+  commands:
+    "*":
+      - run: "npm test"
+        retries: 11
+        retryDelay: 301
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("subagentStop.commands[\"*\"][0].retries"),
+        "error: {error}"
+    );
+    assert!(
+        error.contains("subagentStop.commands[\"*\"][0].retryDelay"),
+        "error: {error}"
+    );
+}
+
+#[test]
+fn test_grace_period_range() {
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run e2e"
+      timeout: 600
+      gracePeriod: 30
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert_eq!(config.stop.commands[0].launch.grace_period, Some(30));
+
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run e2e"
+      gracePeriod: 301
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("stop.commands[0].gracePeriod"),
+        "error: {error}"
+    );
+}
+
+#[test]
+fn test_output_mode_parsing() {
+    let yaml = r#"stop:
+  commands:
+    - run: "cargo test"
+      maxOutputLines: 50
+      outputMode: errors
+      errorPatterns: ["^test .* FAILED$", "panicked at"]
+    - run: "cargo build"
+      outputMode: headTail
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert_eq!(
+        config.stop.commands[0].launch.output_mode,
+        Some(OutputMode::Errors)
+    );
+    assert_eq!(
+        config.stop.commands[0].launch.error_patterns.as_deref(),
+        Some(&["^test .* FAILED$".to_string(), "panicked at".to_string()][..])
+    );
+    assert_eq!(
+        config.stop.commands[1].launch.output_mode,
+        Some(OutputMode::HeadTail)
+    );
+
+    let yaml = r#"stop:
+  commands:
+    - run: "cargo test"
+      outputMode: middle
+"#;
+    assert!(parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).is_err());
+}
+
+#[test]
+fn test_invalid_error_pattern() {
+    let yaml = r#"subagentStop:
+  commands:
+    "*":
+      - run: "npm test"
+        outputMode: errors
+        errorPatterns: ["ok", "FAIL ("]
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("subagentStop.commands[\"*\"][0].errorPatterns[1]"),
+        "error: {error}"
+    );
+}
+
+#[test]
+fn test_diagnostic_parser_parsing() {
+    let yaml = r#"stop:
+  commands:
+    - run: "cargo clippy --message-format=json"
+      parser: cargo-json
+subagentStop:
+  commands:
+    "*":
+      - run: "npx eslint --format json ."
+        parser: eslint-json
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert_eq!(
+        config.stop.commands[0].parser,
+        Some(DiagnosticParser::CargoJson)
+    );
+    assert_eq!(
+        config.subagent_stop.commands["*"][0].parser,
+        Some(DiagnosticParser::EslintJson)
+    );
+
+    let yaml = r#"stop:
+  commands:
+    - run: "pytest"
+      parser: pytest
+"#;
+    assert!(parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).is_err());
+}
+
+#[test]
+fn test_fix_command_parsing() {
+    let yaml = r#"stop:
+  commands:
+    - run: "cargo fmt --check"
+      fix: "cargo fmt"
+subagentStop:
+  commands:
+    "*":
+      - run: "npx eslint ."
+        fix: "npx eslint --fix ."
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    assert_eq!(config.stop.commands[0].fix.as_deref(), Some("cargo fmt"));
+    assert_eq!(
+        config.subagent_stop.commands["*"][0].fix.as_deref(),
+        Some("npx eslint --fix .")
+    );
+
+    // Only stop checks can be repaired
+    let yaml = r#"setup:
+  commands:
+    "*":
+      - run: "npm ci"
+        fix: "rm -rf node_modules"
+"#;
+    assert!(parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).is_err());
+}
+
+#[test]
+fn test_failure_policy_parsing_and_exit_code_range() {
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run lint"
+      onFailure: warn
+      exitCodes:
+        2: block
+teammateIdle:
+  commands:
+    "*":
+      - run: "./check-idle.sh"
+        exitCodes: {1: context, 3: ignore}
+"#;
+    let config = parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).unwrap();
+    let lint = &config.stop.commands[0];
+    assert_eq!(lint.launch.on_failure, Some(FailureAction::Warn));
+    assert_eq!(
+        lint.launch.exit_codes.as_ref().unwrap().get(&2),
+        Some(&FailureAction::Block)
+    );
+    let idle = &config.teammate_idle.commands["*"][0];
+    assert_eq!(idle.launch.exit_codes.as_ref().unwrap().len(), 2);
+
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run lint"
+      exitCodes:
+        0: warn
+"#;
+    let error = parse_and_validate_config(yaml, Path::new(".conclaude.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("stop.commands[0].exitCodes") && error.contains("Exit code 0"),
+        "error: {error}"
+    );
+
+    let yaml = r#"stop:
+  commands:
+    - run: "npm run lint"
+      onFailure: fail
+"#;
+    assert!(parse_and_validate_config(yaml, Path::new(".conclaude.yaml")).is_err());
+}
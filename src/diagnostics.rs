@@ -0,0 +1,313 @@
+//! Structured diagnostics extracted from toolchain output.
+//!
+//! A stop command with a `parser:` has its output turned into a deduplicated list of
+//! `file:line:message` diagnostics, grouped by file, which replaces the raw stdout/stderr
+//! sections in the block message. Output a parser does not recognise yields no diagnostics,
+//! and the caller falls back to the raw sections.
+
+use crate::config::DiagnosticParser;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Most diagnostics listed in a block message; the rest are counted
+pub const MAX_DIAGNOSTICS: usize = 50;
+
+/// A single problem reported by a tool
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The tool's message, including its severity and code where it reports them
+    pub message: String,
+}
+
+/// Extracts the diagnostics in `output` with `parser`, in order and without duplicates
+#[must_use]
+pub fn parse_diagnostics(parser: DiagnosticParser, output: &str) -> Vec<Diagnostic> {
+    let diagnostics = match parser {
+        DiagnosticParser::CargoJson => parse_cargo_json(output),
+        DiagnosticParser::Tsc => parse_tsc(output),
+        DiagnosticParser::EslintJson => parse_eslint_json(output),
+        DiagnosticParser::JunitXml => parse_junit_xml(output),
+        DiagnosticParser::GenericFileLine => parse_generic_file_line(output),
+    };
+    let mut seen = HashSet::new();
+    diagnostics
+        .into_iter()
+        .filter(|diagnostic| seen.insert(diagnostic.clone()))
+        .collect()
+}
+
+/// Formats diagnostics grouped by file, in order of first appearance, listing at most
+/// `max` of them
+///
+/// ```text
+/// 3 diagnostics in 2 files:
+/// src/app.ts
+///   12:5: error TS2322: Type 'string' is not assignable to type 'number'.
+/// ```
+#[must_use]
+pub fn format_diagnostics(diagnostics: &[Diagnostic], max: usize) -> String {
+    let mut files: Vec<(&str, Vec<&Diagnostic>)> = Vec::new();
+    for diagnostic in diagnostics.iter().take(max) {
+        match files.iter_mut().find(|(file, _)| *file == diagnostic.file) {
+            Some((_, group)) => group.push(diagnostic),
+            None => files.push((&diagnostic.file, vec![diagnostic])),
+        }
+    }
+
+    let file_count = diagnostics
+        .iter()
+        .map(|diagnostic| &diagnostic.file)
+        .collect::<HashSet<_>>()
+        .len();
+    let mut formatted = format!(
+        "{} in {}:",
+        plural(diagnostics.len(), "diagnostic"),
+        plural(file_count, "file")
+    );
+    for (file, group) in files {
+        formatted.push_str(&format!("\n{file}"));
+        for diagnostic in group {
+            let location = match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => format!("{line}:{column}: "),
+                (Some(line), None) => format!("{line}: "),
+                _ => String::new(),
+            };
+            formatted.push_str(&format!("\n  {location}{}", diagnostic.message));
+        }
+    }
+    if diagnostics.len() > max {
+        formatted.push_str(&format!(
+            "\n... and {}",
+            plural(diagnostics.len() - max, "more diagnostic")
+        ));
+    }
+    formatted
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Errors and warnings from `cargo build|check|clippy --message-format=json`
+fn parse_cargo_json(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line.trim()).ok())
+        .filter(|value| value["reason"] == "compiler-message")
+        .filter_map(|value| {
+            let message = &value["message"];
+            let level = message["level"].as_str()?;
+            if level != "error" && level != "warning" {
+                return None;
+            }
+            let span = message["spans"]
+                .as_array()?
+                .iter()
+                .find(|span| span["is_primary"] == true)?;
+            let text = message["message"].as_str()?;
+            let message = match message["code"]["code"].as_str() {
+                Some(code) => format!("{level}[{code}]: {text}"),
+                None => format!("{level}: {text}"),
+            };
+            Some(Diagnostic {
+                file: span["file_name"].as_str()?.to_string(),
+                line: json_u32(&span["line_start"]),
+                column: json_u32(&span["column_start"]),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// `file(line,col): error TS1234: ...` and the `--pretty` form `file:line:col - error TS1234: ...`
+fn parse_tsc(output: &str) -> Vec<Diagnostic> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^(?P<file>\S.*?)(?:\((?P<line>\d+),(?P<col>\d+)\):|:(?P<pline>\d+):(?P<pcol>\d+) -) (?P<msg>(?:error|warning) TS\d+: .*)$",
+        )
+        .expect("tsc pattern is valid")
+    });
+    let output = strip_ansi(output);
+    output
+        .lines()
+        .filter_map(|line| pattern.captures(line.trim_end()))
+        .map(|caps| Diagnostic {
+            file: caps["file"].to_string(),
+            line: caps
+                .name("line")
+                .or_else(|| caps.name("pline"))
+                .and_then(|m| m.as_str().parse().ok()),
+            column: caps
+                .name("col")
+                .or_else(|| caps.name("pcol"))
+                .and_then(|m| m.as_str().parse().ok()),
+            message: caps["msg"].to_string(),
+        })
+        .collect()
+}
+
+/// The result array of `eslint --format json`
+fn parse_eslint_json(output: &str) -> Vec<Diagnostic> {
+    let results = serde_json::from_str::<Value>(output.trim())
+        .ok()
+        .or_else(|| {
+            output
+                .lines()
+                .filter(|line| line.trim_start().starts_with('['))
+                .find_map(|line| serde_json::from_str::<Value>(line.trim()).ok())
+        });
+    let Some(Value::Array(results)) = results else {
+        return Vec::new();
+    };
+
+    let mut diagnostics = Vec::new();
+    for result in &results {
+        let Some(file) = result["filePath"].as_str() else {
+            continue;
+        };
+        for message in result["messages"].as_array().into_iter().flatten() {
+            let Some(text) = message["message"].as_str() else {
+                continue;
+            };
+            let level = if message["severity"] == 2 {
+                "error"
+            } else {
+                "warning"
+            };
+            let message_text = match message["ruleId"].as_str() {
+                Some(rule) => format!("{level}: {text} ({rule})"),
+                None => format!("{level}: {text}"),
+            };
+            diagnostics.push(Diagnostic {
+                file: file.to_string(),
+                line: json_u32(&message["line"]),
+                column: json_u32(&message["column"]),
+                message: message_text,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Failed and errored `<testcase>` elements of a JUnit XML report, as written by pytest,
+/// jest-junit, cargo-nextest and most other test runners, either to the command's output
+/// or to its `reportFile`
+fn parse_junit_xml(output: &str) -> Vec<Diagnostic> {
+    static TESTCASE: OnceLock<Regex> = OnceLock::new();
+    static FAILURE: OnceLock<Regex> = OnceLock::new();
+    let testcase = TESTCASE.get_or_init(|| {
+        Regex::new(r"(?s)<testcase\b(?P<attrs>[^>]*?)(?:/>|>(?P<body>.*?)</testcase>)")
+            .expect("testcase pattern is valid")
+    });
+    let failure = FAILURE.get_or_init(|| {
+        Regex::new(
+            r"(?s)<(?:failure|error)\b(?P<attrs>[^>]*?)(?:/>|>(?P<text>.*?)</(?:failure|error)>)",
+        )
+        .expect("failure pattern is valid")
+    });
+
+    testcase
+        .captures_iter(output)
+        .filter_map(|case| {
+            let failed = failure.captures(case.name("body")?.as_str())?;
+            let mut attrs = xml_attrs(&case["attrs"]);
+            let name = attrs.remove("name").unwrap_or_default();
+            let reason = xml_attrs(&failed["attrs"])
+                .remove("message")
+                .filter(|message| !message.trim().is_empty())
+                .or_else(|| {
+                    failed.name("text").and_then(|text| {
+                        xml_unescape(text.as_str())
+                            .lines()
+                            .map(str::trim)
+                            .find(|line| !line.is_empty())
+                            .map(str::to_string)
+                    })
+                });
+            let message = match reason {
+                Some(reason) => format!("{name}: {}", reason.trim()),
+                None => format!("{name}: failed"),
+            };
+            let line = attrs.get("line").and_then(|line| line.parse().ok());
+            Some(Diagnostic {
+                file: attrs
+                    .remove("file")
+                    .or_else(|| attrs.remove("classname"))
+                    .unwrap_or_else(|| "(unknown)".to_string()),
+                line,
+                column: None,
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Lines of the form `path:line[:col]: message`, as printed by gcc, go vet, mypy, flake8
+/// and many other tools. The path must contain a `.` or `/`.
+fn parse_generic_file_line(output: &str) -> Vec<Diagnostic> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?P<file>[^\s:]*[./][^\s:]*):(?P<line>\d+)(?::(?P<col>\d+))?:?\s+(?P<msg>\S.*)$",
+        )
+        .expect("file:line pattern is valid")
+    });
+    let output = strip_ansi(output);
+    output
+        .lines()
+        .filter_map(|line| pattern.captures(line.trim_end()))
+        .map(|caps| Diagnostic {
+            file: caps["file"].to_string(),
+            line: caps["line"].parse().ok(),
+            column: caps.name("col").and_then(|m| m.as_str().parse().ok()),
+            message: caps["msg"].to_string(),
+        })
+        .collect()
+}
+
+fn json_u32(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|n| u32::try_from(n).ok())
+}
+
+/// Removes terminal color codes, which `--pretty` and colored output wrap around locations
+fn strip_ansi(output: &str) -> std::borrow::Cow<'_, str> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").expect("ANSI pattern is valid"))
+        .replace_all(output, "")
+}
+
+/// The unescaped attribute values of an XML start tag's attribute text, by name
+fn xml_attrs(attrs: &str) -> HashMap<&str, String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r#"(?P<name>[\w:.-]+)\s*=\s*(?:"(?P<double>[^"]*)"|'(?P<single>[^']*)')"#)
+            .expect("attribute pattern is valid")
+    });
+    pattern
+        .captures_iter(attrs)
+        .filter_map(|caps| {
+            let value = caps.name("double").or_else(|| caps.name("single"))?;
+            Some((caps.name("name")?.as_str(), xml_unescape(value.as_str())))
+        })
+        .collect()
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
@@ -1,11 +1,16 @@
 use crate::config::{
-    extract_bash_commands, load_conclaude_config, ConclaudeConfig, ConfigChangeConfig,
-    CwdChangedConfig, ElicitationConfig, ElicitationResultConfig, FileChangedConfig,
-    InstructionsLoadedConfig, MessageDisplayConfig, PermissionDeniedConfig, PostCompactConfig,
-    PostToolBatchConfig, SetupConfig, SkillStartConfig, SlashCommandConfig, SubagentStopConfig,
+    find_nearest_config, global_config_path, load_global_only_config, load_layered_config,
+    resolve_command_needs, search_config, split_run_commands, CommandShell, ConclaudeConfig,
+    ConfigChangeConfig, ConfigFingerprint, ConfigNotFound, ConfigStore, CwdChangedConfig,
+    DiagnosticParser, ElicitationConfig, ElicitationResultConfig, FailureAction, FileChangedConfig,
+    InstructionsLoadedConfig, LaunchOptions, MessageDisplayConfig, OutputMode,
+    PermissionDeniedConfig, PostCompactConfig, PostToolBatchConfig, RunMode, SearchBoundary,
+    SetupConfig, SkillStartConfig, SlashCommandConfig, StopCommand, StopConfig, SubagentStopConfig,
     TaskCompletedConfig, TaskCreatedConfig, TeammateIdleConfig, UserPromptExpansionConfig,
-    UserPromptSubmitCommand,
+    UserPromptSubmitCommand, DEFAULT_ERROR_PATTERN, DEFAULT_GRACE_PERIOD_SECS,
+    LOCAL_CONFIG_FILE_NAMES,
 };
+use crate::diagnostics::{format_diagnostics, parse_diagnostics, MAX_DIAGNOSTICS};
 use crate::gitignore::{find_git_root, is_path_git_ignored};
 use crate::types::{
     validate_base_payload, validate_cwd_changed_payload, validate_elicitation_payload,
@@ -23,18 +28,23 @@ use crate::types::{
     PostToolUsePayload, PreCompactPayload, PreToolUsePayload, SessionEndPayload,
     SessionStartPayload, SetupPayload, StopFailurePayload, StopPayload, SubagentStartPayload,
     SubagentStopPayload, TaskCompletedPayload, TaskCreatedPayload, TeammateIdlePayload,
-    UserPromptExpansionPayload, UserPromptSubmitPayload, WorktreeCreatePayload, WorktreeRemovePayload,
+    UserPromptExpansionPayload, UserPromptSubmitPayload, WorktreeCreatePayload,
+    WorktreeRemovePayload,
 };
 use anyhow::{Context, Result};
 use glob::Pattern;
 use notify_rust::{Notification, Urgency};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 use tokio::process::Command as TokioCommand;
 use tokio::time::{timeout, Duration};
 
@@ -81,7 +91,70 @@ pub fn read_agent_from_session_file(session_id: &str) -> String {
     }
 }
 
+/// Tools whose `file_path` (or `notebook_path`) input is modified on disk
+const FILE_MODIFYING_TOOLS: [&str; 4] = ["Write", "Edit", "MultiEdit", "NotebookEdit"];
+
+/// Directory for per-session state shared between hook processes
+///
+/// Lives in the user's state directory (`$XDG_STATE_HOME/conclaude` on Linux), falling back to
+/// the cache directory, so other users cannot read or plant the files in it.
+fn session_state_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("conclaude")
+}
+
+/// Creates [`session_state_dir`], accessible only to the current user
+fn create_session_state_dir() -> std::io::Result<PathBuf> {
+    let dir = session_state_dir();
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Get the path to the file recording which files a session has edited.
+#[must_use]
+pub fn get_edited_files_path(session_id: &str) -> PathBuf {
+    session_state_dir().join(format!("edits-{session_id}.log"))
+}
+
+/// Record a file edited during PostToolUse so Stop can find the package configs it touched.
+///
+/// Each path is appended as one line in a single write, so hooks running in parallel for
+/// a batch of edits do not overwrite each other's entries.
+///
+/// # Errors
+///
+/// Returns an error if the edits file cannot be written.
+pub fn record_edited_file(session_id: &str, file_path: &Path) -> std::io::Result<()> {
+    create_session_state_dir()?;
+    let mut options = fs::OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(get_edited_files_path(session_id))?;
+    std::io::Write::write_all(&mut file, format!("{}\n", file_path.display()).as_bytes())
+}
+
+/// Read the files recorded as edited for a session, in the order first recorded.
+/// Returns an empty list if nothing has been recorded yet.
+#[must_use]
+pub fn read_edited_files(session_id: &str) -> Vec<PathBuf> {
+    let content = fs::read_to_string(get_edited_files_path(session_id)).unwrap_or_default();
+    let mut seen = HashSet::new();
+    content
+        .lines()
+        .filter(|line| !line.is_empty() && seen.insert(*line))
+        .map(PathBuf::from)
+        .collect()
+}
+
 /// Represents a stop command with its configuration
+#[derive(Clone)]
 pub(crate) struct StopCommandConfig {
     pub(crate) command: String,
     pub(crate) message: Option<String>,
@@ -91,6 +164,14 @@ pub(crate) struct StopCommandConfig {
     pub(crate) timeout: Option<u64>,
     pub(crate) show_command: bool,
     pub(crate) notify_per_command: bool,
+    pub(crate) launch: CommandLaunch,
+    pub(crate) parser: Option<DiagnosticParser>,
+    /// File the `parser` reads instead of the output, relative to the working directory
+    pub(crate) report_file: Option<String>,
+    /// Command run to repair a failure before the command is checked again
+    pub(crate) fix: Option<String>,
+    /// Indices of the commands in the same list that must succeed before this one starts
+    pub(crate) needs: Vec<usize>,
 }
 
 /// Represents a subagent stop command with its configuration
@@ -103,6 +184,12 @@ pub(crate) struct SubagentStopCommandConfig {
     pub(crate) timeout: Option<u64>,
     pub(crate) show_command: bool,
     pub(crate) notify_per_command: bool,
+    pub(crate) launch: CommandLaunch,
+    pub(crate) parser: Option<DiagnosticParser>,
+    /// File the `parser` reads instead of the output, relative to the working directory
+    pub(crate) report_file: Option<String>,
+    /// Command run to repair a failure before the command is checked again
+    pub(crate) fix: Option<String>,
 }
 
 /// Represents a user prompt submit command with its configuration
@@ -114,6 +201,7 @@ pub(crate) struct UserPromptSubmitCommandConfig {
     pub(crate) timeout: Option<u64>,
     pub(crate) show_command: bool,
     pub(crate) notify_per_command: bool,
+    pub(crate) launch: CommandLaunch,
 }
 
 /// Represents a slash command entry with its configuration
@@ -125,6 +213,7 @@ pub(crate) struct SlashCommandEntryConfig {
     pub(crate) timeout: Option<u64>,
     pub(crate) show_command: bool,
     pub(crate) notify_per_command: bool,
+    pub(crate) launch: CommandLaunch,
 }
 
 /// Represents a skill start command with its configuration
@@ -136,6 +225,760 @@ pub(crate) struct SkillStartCommandConfig {
     pub(crate) timeout: Option<u64>,
     pub(crate) show_command: bool,
     pub(crate) notify_per_command: bool,
+    pub(crate) launch: CommandLaunch,
+}
+
+impl StopCommandConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: self.message.as_deref(),
+            parser: self.parser,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
+}
+
+impl SubagentStopCommandConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: self.message.as_deref(),
+            parser: self.parser,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
+}
+
+impl UserPromptSubmitCommandConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: None,
+            parser: None,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
+}
+
+impl SlashCommandEntryConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: None,
+            parser: None,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
+}
+
+impl SkillStartCommandConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: None,
+            parser: None,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
+}
+
+/// How a configured command is launched: its shell, run mode, working directory and extra environment
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CommandLaunch {
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) env_file: Option<String>,
+    pub(crate) cwd: Option<String>,
+    pub(crate) shell: CommandShell,
+    pub(crate) mode: RunMode,
+    pub(crate) retry: CommandRetry,
+    /// Seconds between SIGTERM and SIGKILL when the command times out; see [`DEFAULT_GRACE_PERIOD_SECS`]
+    pub(crate) grace_period: Option<u64>,
+    /// Which lines of the command's output are shown when it exceeds `maxOutputLines`
+    pub(crate) output: OutputExcerpt,
+    /// What a failure does to the hook, from `onFailure` and `exitCodes`
+    pub(crate) failure: FailurePolicy,
+}
+
+impl CommandLaunch {
+    /// Builds the launch settings from a command entry's shared options
+    ///
+    /// `errorPatterns` that fail to compile are skipped; config validation reports them.
+    pub(crate) fn from_options(options: &LaunchOptions) -> Self {
+        Self {
+            env: options.env.clone().unwrap_or_default(),
+            env_file: options.env_file.clone(),
+            cwd: options.cwd.clone(),
+            shell: options.shell(),
+            mode: options.mode(),
+            retry: CommandRetry {
+                retries: options.retries(),
+                delay: Duration::from_secs(options.retry_delay()),
+                retry_on: options.retry_on.clone(),
+            },
+            grace_period: Some(options.grace_period()),
+            output: OutputExcerpt {
+                mode: options.output_mode(),
+                error_patterns: options.error_patterns.as_ref().map(|patterns| {
+                    patterns
+                        .iter()
+                        .filter_map(|pattern| regex::Regex::new(pattern).ok())
+                        .collect()
+                }),
+            },
+            failure: FailurePolicy {
+                on_failure: options.on_failure,
+                exit_codes: options.exit_codes.clone().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// The directory the command runs in: `cwd` resolved against the config directory
+    pub(crate) fn working_dir(&self, config_dir: &Path) -> PathBuf {
+        self.cwd
+            .as_ref()
+            .map_or_else(|| config_dir.to_path_buf(), |cwd| config_dir.join(cwd))
+    }
+}
+
+/// A file a command writes its report to, as it was before the command ran
+struct ReportFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ReportFile {
+    /// Notes the modification time of `file`, resolved against the command's working directory
+    fn before_run(file: &str, launch: &CommandLaunch, config_dir: &Path) -> Self {
+        let path = launch.working_dir(config_dir).join(file);
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    /// The report's content when the command wrote it; a report left over from an earlier
+    /// run is ignored
+    fn read_new(&self) -> Option<String> {
+        let modified = modified_time(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        fs::read_to_string(&self.path).ok()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// How often a failing command is retried and how long to wait in between
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CommandRetry {
+    /// Retries after the first attempt
+    pub(crate) retries: u32,
+    /// Delay before the first retry, doubled after every further attempt
+    pub(crate) delay: Duration,
+    /// Exit codes that are retried; any failure, including a timeout, when unset
+    pub(crate) retry_on: Option<Vec<i32>>,
+}
+
+impl CommandRetry {
+    /// Whether a failed attempt should be retried; `exit_code` is `None` after a timeout
+    fn should_retry(&self, attempt: u32, exit_code: Option<i32>) -> bool {
+        attempt <= self.retries
+            && match (&self.retry_on, exit_code) {
+                (None, _) => true,
+                (Some(codes), Some(code)) => codes.contains(&code),
+                (Some(_), None) => false,
+            }
+    }
+
+    /// Delay after failed attempt number `attempt`, counting from 1
+    pub(crate) fn delay_after(&self, attempt: u32) -> Duration {
+        self.delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// What a failing command does to its hook, from `onFailure` and `exitCodes`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FailurePolicy {
+    pub(crate) on_failure: Option<FailureAction>,
+    pub(crate) exit_codes: BTreeMap<i32, FailureAction>,
+}
+
+impl FailurePolicy {
+    /// The action for a failure that exited with `exit_code`, or that timed out or could not
+    /// run when it is `None`
+    ///
+    /// An `exitCodes` entry wins over `onFailure`, which wins over `default`, the hook's
+    /// own behaviour for that failure.
+    pub(crate) fn action(&self, exit_code: Option<i32>, default: FailureAction) -> FailureAction {
+        exit_code
+            .and_then(|code| self.exit_codes.get(&code).copied())
+            .or(self.on_failure)
+            .unwrap_or(default)
+    }
+}
+
+/// Which lines of long command output are kept, from `outputMode` and `errorPatterns`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OutputExcerpt {
+    pub(crate) mode: OutputMode,
+    /// Lines `OutputMode::Errors` keeps context around; [`default_error_pattern`] when unset
+    pub(crate) error_patterns: Option<Vec<regex::Regex>>,
+}
+
+/// Lines kept before and after each matching line in `OutputMode::Errors`
+const ERROR_CONTEXT_LINES: usize = 3;
+
+/// Matches the lines compilers and test runners use to report problems
+fn default_error_pattern() -> &'static regex::Regex {
+    static PATTERN: OnceLock<regex::Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        regex::Regex::new(DEFAULT_ERROR_PATTERN).expect("default error pattern is valid")
+    })
+}
+
+impl OutputExcerpt {
+    /// Whether `line` matches one of the error patterns
+    fn is_error_line(&self, line: &str) -> bool {
+        match &self.error_patterns {
+            Some(patterns) => patterns.iter().any(|pattern| pattern.is_match(line)),
+            None => default_error_pattern().is_match(line),
+        }
+    }
+
+    /// The line ranges kept from `lines`, in order and without overlaps, at most `max_lines` long in total
+    #[allow(clippy::single_range_in_vec_init)]
+    fn kept_ranges(&self, lines: &[&str], max_lines: usize) -> Vec<std::ops::Range<usize>> {
+        let total = lines.len();
+        let tail = |count: usize| vec![total - count..total];
+        match self.mode {
+            OutputMode::Head => vec![0..max_lines],
+            OutputMode::Tail => tail(max_lines),
+            OutputMode::HeadTail => {
+                let head = max_lines.div_ceil(2);
+                vec![0..head, total - (max_lines - head)..total]
+            }
+            OutputMode::Errors => {
+                let mut windows: Vec<std::ops::Range<usize>> = Vec::new();
+                for (index, line) in lines.iter().enumerate() {
+                    if !self.is_error_line(line) {
+                        continue;
+                    }
+                    let start = index.saturating_sub(ERROR_CONTEXT_LINES);
+                    let end = (index + ERROR_CONTEXT_LINES + 1).min(total);
+                    match windows.last_mut() {
+                        Some(last) if start <= last.end => last.end = end,
+                        _ => windows.push(start..end),
+                    }
+                }
+                if windows.is_empty() {
+                    return tail(max_lines);
+                }
+                let mut remaining = max_lines;
+                let mut kept = Vec::new();
+                for window in windows {
+                    if remaining == 0 {
+                        break;
+                    }
+                    let end = window.end.min(window.start + remaining);
+                    remaining -= end - window.start;
+                    kept.push(window.start..end);
+                }
+                kept
+            }
+        }
+    }
+}
+
+/// How a command ended on its last attempt
+pub(crate) enum CommandOutcome {
+    /// The command exited with this output
+    Exited(std::process::Output),
+    /// The command ran longer than its timeout and was terminated; holds the output
+    /// written before then
+    TimedOut { stdout: Vec<u8>, stderr: Vec<u8> },
+}
+
+/// The result of running a command with its retry policy
+pub(crate) struct CommandRun {
+    pub(crate) outcome: CommandOutcome,
+    /// Number of attempts made, including the first
+    pub(crate) attempts: u32,
+}
+
+impl CommandRun {
+    /// `" after N attempts"` when the command was retried, for failure and success reports
+    pub(crate) fn attempts_suffix(&self) -> String {
+        if self.attempts > 1 {
+            format!(" after {} attempts", self.attempts)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Whether the last attempt exited successfully
+    pub(crate) fn succeeded(&self) -> bool {
+        matches!(&self.outcome, CommandOutcome::Exited(output) if output.status.success())
+    }
+
+    /// Exit code of the last attempt; `None` when it timed out
+    pub(crate) fn exit_code(&self) -> Option<i32> {
+        match &self.outcome {
+            CommandOutcome::Exited(output) => Some(output.status.code().unwrap_or(1)),
+            CommandOutcome::TimedOut { .. } => None,
+        }
+    }
+
+    /// Stdout and stderr of the last attempt, up to the timeout when it timed out
+    pub(crate) fn output(&self) -> (String, String) {
+        let (stdout, stderr) = match &self.outcome {
+            CommandOutcome::Exited(output) => (&output.stdout, &output.stderr),
+            CommandOutcome::TimedOut { stdout, stderr } => (stdout, stderr),
+        };
+        (
+            String::from_utf8_lossy(stdout).into_owned(),
+            String::from_utf8_lossy(stderr).into_owned(),
+        )
+    }
+}
+
+/// Runs a command until it succeeds or its retries are used up
+///
+/// Each attempt is spawned with [`spawn_command`] and limited to `timeout_secs`. A failed
+/// attempt is retried as long as retries remain and its exit code is listed in `retryOn`;
+/// without `retryOn` every failure is retried, timeouts included. The wait before a retry
+/// starts at `retryDelay` and doubles after each attempt. `block_exit_code` is the exit
+/// code a hook treats as a deliberate block, which is never retried. When `notify_hook` is
+/// set, a notification is sent for every retry.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be spawned or waited on. These are not retried.
+pub(crate) async fn run_with_retries(
+    command: &str,
+    launch: &CommandLaunch,
+    timeout_secs: Option<u64>,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+    block_exit_code: Option<i32>,
+    notify_hook: Option<&str>,
+) -> Result<CommandRun> {
+    let retry = &launch.retry;
+    let total = retry.retries + 1;
+    let mut attempt = 1;
+    loop {
+        let child = spawn_command(command, launch, env_vars, config_dir)
+            .with_context(|| format!("Failed to spawn command: {command}"))?;
+        let grace_period = launch.grace_period.unwrap_or(DEFAULT_GRACE_PERIOD_SECS);
+        let outcome = wait_for_command(child, timeout_secs, Duration::from_secs(grace_period))
+            .await
+            .with_context(|| format!("Failed to wait for command: {command}"))?;
+
+        let (exit_code, reason) = match &outcome {
+            CommandOutcome::Exited(output) if output.status.success() => {
+                return Ok(CommandRun {
+                    outcome,
+                    attempts: attempt,
+                });
+            }
+            CommandOutcome::Exited(output) => {
+                let code = output.status.code();
+                let reason = format!("exit code {}", code.unwrap_or(1));
+                (code, reason)
+            }
+            CommandOutcome::TimedOut { .. } => (
+                None,
+                format!(
+                    "timed out after {} seconds",
+                    timeout_secs.unwrap_or_default()
+                ),
+            ),
+        };
+        if (exit_code.is_some() && exit_code == block_exit_code)
+            || !retry.should_retry(attempt, exit_code)
+        {
+            return Ok(CommandRun {
+                outcome,
+                attempts: attempt,
+            });
+        }
+
+        let delay = retry.delay_after(attempt);
+        eprintln!(
+            "Attempt {attempt}/{total} failed ({reason}), retrying in {}s",
+            delay.as_secs()
+        );
+        if let Some(hook) = notify_hook {
+            send_notification(
+                hook,
+                "running",
+                Some(&format!(
+                    "Retrying ({reason}), attempt {}/{total}",
+                    attempt + 1
+                )),
+            );
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// How long to keep reading a terminated command's pipes, which a child that left its
+/// process group may still hold open
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Reads a child's stdout or stderr in the background, keeping what was read if the
+/// command is terminated
+struct OutputCollector {
+    buffer: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl OutputCollector {
+    fn start(pipe: Option<impl tokio::io::AsyncRead + Unpin + Send + 'static>) -> Self {
+        let buffer = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = buffer.clone();
+        let task = tokio::spawn(async move {
+            use tokio::io::AsyncReadExt;
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut chunk = [0u8; 8192];
+            while let Ok(read) = pipe.read(&mut chunk).await {
+                if read == 0 {
+                    break;
+                }
+                sink.lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner)
+                    .extend_from_slice(&chunk[..read]);
+            }
+        });
+        Self { buffer, task }
+    }
+
+    /// Returns the collected output once the pipe is closed, or after `limit`
+    async fn finish(self, limit: Option<Duration>) -> Vec<u8> {
+        let Self { buffer, mut task } = self;
+        match limit {
+            Some(limit) => {
+                if timeout(limit, &mut task).await.is_err() {
+                    task.abort();
+                }
+            }
+            None => {
+                let _ = task.await;
+            }
+        }
+        let mut buffer = buffer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        std::mem::take(&mut *buffer)
+    }
+}
+
+/// Waits for a spawned command, collecting its output as it is written
+///
+/// When `timeout_secs` elapses the command is stopped with [`terminate_process_group`], and
+/// the output written up to that point is returned with [`CommandOutcome::TimedOut`].
+///
+/// # Errors
+///
+/// Returns an error if waiting on the process fails.
+pub(crate) async fn wait_for_command(
+    mut child: tokio::process::Child,
+    timeout_secs: Option<u64>,
+    grace_period: Duration,
+) -> Result<CommandOutcome> {
+    let stdout = OutputCollector::start(child.stdout.take());
+    let stderr = OutputCollector::start(child.stderr.take());
+
+    let status = match timeout_secs {
+        Some(secs) => match timeout(Duration::from_secs(secs), child.wait()).await {
+            Ok(status) => Some(status?),
+            Err(_) => {
+                terminate_process_group(&mut child, grace_period).await;
+                None
+            }
+        },
+        None => Some(child.wait().await?),
+    };
+
+    Ok(match status {
+        Some(status) => CommandOutcome::Exited(std::process::Output {
+            status,
+            stdout: stdout.finish(None).await,
+            stderr: stderr.finish(None).await,
+        }),
+        None => CommandOutcome::TimedOut {
+            stdout: stdout.finish(Some(OUTPUT_DRAIN_TIMEOUT)).await,
+            stderr: stderr.finish(Some(OUTPUT_DRAIN_TIMEOUT)).await,
+        },
+    })
+}
+
+/// Stops a timed-out command and everything it started
+///
+/// The command's process group gets SIGTERM, and SIGKILL if any of its processes are still
+/// running after `grace_period`. Without process groups only the command itself is killed.
+#[cfg(unix)]
+async fn terminate_process_group(child: &mut tokio::process::Child, grace_period: Duration) {
+    let Some(pgid) = child.id().and_then(|pid| libc::pid_t::try_from(pid).ok()) else {
+        return;
+    };
+    // SAFETY: `killpg` only sends a signal; `pgid` is the group `spawn_command` created for
+    // this child, which cannot be reused while the unreaped leader keeps the id taken.
+    unsafe { libc::killpg(pgid, libc::SIGTERM) };
+
+    let deadline = tokio::time::Instant::now() + grace_period;
+    loop {
+        // Reap the leader as soon as it exits, so only live members keep the group alive
+        let _ = child.try_wait();
+        // SAFETY: signal 0 only checks whether any process in the group still exists
+        if unsafe { libc::killpg(pgid, 0) } != 0 {
+            break;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            eprintln!(
+                "Command did not exit within {}s of SIGTERM, sending SIGKILL",
+                grace_period.as_secs()
+            );
+            // SAFETY: as above
+            unsafe { libc::killpg(pgid, libc::SIGKILL) };
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let _ = child.wait().await;
+}
+
+#[cfg(not(unix))]
+async fn terminate_process_group(child: &mut tokio::process::Child, _grace_period: Duration) {
+    let _ = child.kill().await;
+}
+
+/// Marker written to stderr by the ERR trap of script-mode bash commands
+const SCRIPT_FAILURE_MARKER: &str = "conclaude: script failed at line ";
+
+/// Wraps a script-mode command so its shell stops at the first failing line
+///
+/// Bash and zsh get `set -euo pipefail`; POSIX `sh` has no `pipefail` and gets `set -eu`.
+/// Bash additionally traps `ERR` to report the failing line, numbered from the first line
+/// of `run`, which [`script_failed_line`] reads back from stderr.
+fn script_with_strict_mode(script: &str, shell: CommandShell) -> String {
+    let preamble = match shell {
+        CommandShell::Bash => format!(
+            "set -Eeuo pipefail; trap 'echo \"{SCRIPT_FAILURE_MARKER}$((LINENO - 1)): $BASH_COMMAND\" >&2' ERR"
+        ),
+        CommandShell::Zsh => "set -euo pipefail".to_string(),
+        CommandShell::Sh | CommandShell::None => "set -eu".to_string(),
+    };
+    format!("{preamble}\n{script}")
+}
+
+/// Finds the line a script-mode command failed at
+///
+/// Returns the line number within `script` and that line's source, taken from the first
+/// failure marker in `stderr`. The trap's own command text is used when the line number
+/// falls outside the script.
+pub(crate) fn script_failed_line(script: &str, stderr: &str) -> Option<(usize, String)> {
+    stderr.lines().find_map(|line| {
+        let (number, command) = line.strip_prefix(SCRIPT_FAILURE_MARKER)?.split_once(": ")?;
+        let number: usize = number.parse().ok()?;
+        let source = number
+            .checked_sub(1)
+            .and_then(|index| script.lines().nth(index))
+            .map_or(command, str::trim);
+        Some((number, source.to_string()))
+    })
+}
+
+/// Removes the failure markers of [`script_with_strict_mode`] from a command's stderr, so
+/// only what the command itself wrote is shown
+pub(crate) fn strip_failure_markers(stderr: &str) -> String {
+    stderr
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with(SCRIPT_FAILURE_MARKER))
+        .collect()
+}
+
+/// Spawns a configured command with piped output
+///
+/// The command runs through its shell with `-c`, or is split into arguments and executed
+/// directly when the shell is `none`. In script mode the shell stops at the first failing
+/// line, and a `none` shell only accepts a single line. It starts in `cwd` resolved against
+/// the config directory, as the leader of its own process group on Unix. Variables are
+/// applied in order: the hook's `env_vars`, then `envFile`, then `env`, so the entry's own
+/// settings take precedence.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be split into arguments, the working directory
+/// or env file cannot be used, or the process fails to start.
+pub(crate) fn spawn_command(
+    command: &str,
+    launch: &CommandLaunch,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Result<tokio::process::Child> {
+    let mut process = match launch.shell.program() {
+        Some(shell) => {
+            let mut process = TokioCommand::new(shell);
+            match launch.mode {
+                RunMode::Script => process
+                    .arg("-c")
+                    .arg(script_with_strict_mode(command, launch.shell)),
+                RunMode::Lines => process.arg("-c").arg(command),
+            };
+            process
+        }
+        None => {
+            if command.trim().lines().count() > 1 {
+                anyhow::bail!(
+                    "shell: none runs a single command; use mode: lines to run each line separately"
+                );
+            }
+            let argv = shlex::split(command)
+                .with_context(|| format!("Unbalanced quotes in command: {command}"))?;
+            let (program, args) = argv
+                .split_first()
+                .context("Command is empty; nothing to execute with shell: none")?;
+            let mut process = TokioCommand::new(program);
+            process.args(args);
+            process
+        }
+    };
+
+    let working_dir = launch.working_dir(config_dir);
+    if launch.cwd.is_some() && !working_dir.is_dir() {
+        anyhow::bail!(
+            "Command working directory does not exist: {}",
+            working_dir.display()
+        );
+    }
+
+    process
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .current_dir(working_dir)
+        .envs(env_vars);
+    if let Some(env_file) = &launch.env_file {
+        process.envs(read_env_file(&config_dir.join(env_file))?);
+    }
+    process.envs(&launch.env);
+
+    // Own process group, so a timeout can signal the command's children too
+    #[cfg(unix)]
+    process.process_group(0);
+    process.kill_on_drop(true);
+
+    Ok(process.spawn()?)
+}
+
+/// Reads `KEY=VALUE` lines from a dotenv file
+///
+/// Blank lines and `#` comments are skipped, an `export ` prefix is allowed, and values may
+/// be wrapped in single or double quotes. Double-quoted values understand `\n`, `\"` and
+/// `\\`; unquoted values end at ` #`.
+pub(crate) fn read_env_file(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read env file: {}", path.display()))?;
+    let mut vars = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            anyhow::bail!(
+                "{}:{}: expected KEY=VALUE, found '{}'",
+                path.display(),
+                number + 1,
+                line
+            );
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            anyhow::bail!(
+                "{}:{}: invalid variable name '{}'",
+                path.display(),
+                number + 1,
+                key
+            );
+        }
+        vars.push((key.to_string(), parse_env_value(value.trim())));
+    }
+    Ok(vars)
+}
+
+/// Unquotes a dotenv value
+fn parse_env_value(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.to_string();
+    }
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        let mut unescaped = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            }
+        }
+        return unescaped;
+    }
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end().to_string(),
+        None => value.to_string(),
+    }
 }
 
 /// Result of detecting a slash command in prompt text
@@ -179,8 +1022,8 @@ pub(crate) fn detect_slash_command(prompt: &str) -> Option<SlashCommandDetection
     None
 }
 
-/// Cached configuration instance to avoid repeated loads
-static CACHED_CONFIG: OnceLock<(ConclaudeConfig, std::path::PathBuf)> = OnceLock::new();
+/// Configuration found from the current directory, reloaded when its files change
+static CONFIG_STORE: ConfigStore = ConfigStore::new();
 
 /// Determine if a hook is a system event hook
 ///
@@ -226,18 +1069,222 @@ pub(crate) fn is_system_event_hook(hook_name: &str) -> bool {
 
 /// Load configuration with caching to avoid repeated file system operations
 ///
+/// The cached configuration is reloaded when any file it was built from changes. An edit
+/// that fails to load leaves the last good configuration in effect, see
+/// [`keep_last_good_config`].
+///
 /// # Errors
 ///
-/// Returns an error if the configuration file cannot be loaded or parsed.
+/// Returns an error if the configuration file cannot be loaded or parsed and there is no
+/// earlier configuration to fall back to.
 async fn get_config() -> Result<&'static (ConclaudeConfig, std::path::PathBuf)> {
-    if let Some(config) = CACHED_CONFIG.get() {
-        Ok(config)
-    } else {
-        let config = load_conclaude_config(None).await?;
-        Ok(CACHED_CONFIG.get_or_init(|| config))
+    if let Some(config) = CONFIG_STORE.fresh() {
+        return Ok(config);
+    }
+
+    match load_layered_config(None).await {
+        Ok(layered) => {
+            let fingerprint = ConfigFingerprint::capture(&layered.watched_files());
+            Ok(CONFIG_STORE.replace((layered.config, layered.config_path), fingerprint))
+        }
+        Err(e) if e.downcast_ref::<ConfigNotFound>().is_some() => {
+            let config = missing_config_fallback(e)?;
+            // Watch every location the search checked, so creating a config is noticed
+            let mut watched = search_config(None)?.paths;
+            watched.extend(global_config_path());
+            Ok(CONFIG_STORE.replace(config, ConfigFingerprint::capture(&watched)))
+        }
+        Err(e) => keep_last_good_config(&CONFIG_STORE, e),
     }
 }
 
+/// Fall back to the last good configuration after `error` prevented a reload
+///
+/// Only a configuration this process loaded before is kept; nothing is shared between
+/// hook processes. A warning with the error is printed when it is used.
+///
+/// # Errors
+///
+/// Returns `error` when this process has no earlier configuration to fall back to.
+fn keep_last_good_config(
+    store: &ConfigStore,
+    error: anyhow::Error,
+) -> Result<&'static (ConclaudeConfig, PathBuf)> {
+    let Some(config) = store.keep(&error) else {
+        return Err(error);
+    };
+    eprintln!(
+        "Warning: the configuration could not be reloaded; keeping the last good \
+         configuration from {}.\n\n{error}",
+        config.1.display()
+    );
+    Ok(config)
+}
+
+/// Reload the configuration when a hook event reports that one of its files changed
+///
+/// Returns context describing the problem when the edited configuration was rejected
+/// and the last good configuration stays in effect.
+async fn reload_if_config_changed(file_path: &str) -> Option<String> {
+    let path = Path::new(file_path);
+    let is_config_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            matches!(name, ".conclaude.yaml" | ".conclaude.yml")
+                || LOCAL_CONFIG_FILE_NAMES.contains(&name)
+        });
+    if !is_config_file && !CONFIG_STORE.watches(path) {
+        return None;
+    }
+
+    CONFIG_STORE.invalidate();
+    get_config().await.ok()?;
+    CONFIG_STORE.rejected_change().map(|error| {
+        format!(
+            "The conclaude configuration change to {file_path} was rejected and the previous \
+             configuration is still in effect:\n\n{error}"
+        )
+    })
+}
+
+/// Set when no project configuration exists and hooks run with the global-only fallback
+static CONFIG_MISSING: AtomicBool = AtomicBool::new(false);
+
+/// Session of the hook payload this process is handling, once it has been read
+static SESSION_ID: OnceLock<String> = OnceLock::new();
+
+/// Context added at session start when `missingConfig.sessionStartHint` is enabled
+const MISSING_CONFIG_HINT: &str = "conclaude hooks are installed, but this project has no \
+    .conclaude.yaml, so no guardrails are active. Run `conclaude init` to create one.";
+
+/// Build the configuration used when the project has no configuration file
+///
+/// With `missingConfig.mode: allow` (the default) this is the global configuration on its
+/// own, see [`load_global_only_config`]; without one every operation is allowed. With
+/// `block` the not-found error is returned unchanged, and [`handle_hook_result`] turns it
+/// into a blocking result.
+///
+/// # Errors
+///
+/// Returns the not-found error in block mode, or an error if the global configuration
+/// cannot be loaded.
+fn missing_config_fallback(not_found: anyhow::Error) -> Result<(ConclaudeConfig, PathBuf)> {
+    let config = load_global_only_config()?;
+    if config.missing_config.mode.eq_ignore_ascii_case("block") {
+        return Err(not_found);
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    // Every tool call runs a hook, so the warning is only printed once per session
+    if SESSION_ID
+        .get()
+        .is_none_or(|session_id| claim_session_marker(session_id, "missing-config-warning"))
+    {
+        eprintln!(
+            "No conclaude configuration found from {}; only the global configuration applies. \
+             Set missingConfig.mode: block in the global configuration to fail closed instead.",
+            cwd.display()
+        );
+    }
+    CONFIG_MISSING.store(true, Ordering::Relaxed);
+
+    Ok((config, cwd.join(".conclaude.yaml")))
+}
+
+/// Claim a one-time marker, such as the missing configuration hint, for a session.
+/// Returns `true` only the first time it is called for a given session and marker.
+fn claim_session_marker(session_id: &str, marker: &str) -> bool {
+    let Ok(dir) = create_session_state_dir() else {
+        return true;
+    };
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dir.join(format!("{marker}-{session_id}")))
+        .is_ok()
+}
+
+/// Stores for the package configurations loaded by this process, keyed by config file path
+///
+/// Stores are leaked so they can live next to [`CONFIG_STORE`]; there is one per package
+/// config that a hook touches.
+static PACKAGE_CONFIGS: OnceLock<Mutex<HashMap<PathBuf, &'static ConfigStore>>> = OnceLock::new();
+
+/// Check whether two paths refer to the same file, comparing canonical forms when possible
+fn is_same_path(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (a.canonicalize(), b.canonicalize()),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// Load the configuration stored at `config_file`, reusing earlier loads in this process
+/// until one of its files changes
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be loaded or parsed and there is no
+/// earlier configuration to fall back to.
+async fn get_package_config(config_file: &Path) -> Result<&'static (ConclaudeConfig, PathBuf)> {
+    let primary_path = match search_config(None).map(|search| search.boundary) {
+        Ok(SearchBoundary::ConfigFound(path)) => Some(path),
+        _ => None,
+    };
+    if primary_path.is_some_and(|primary| is_same_path(&primary, config_file)) {
+        return get_config().await;
+    }
+
+    let store: &'static ConfigStore = PACKAGE_CONFIGS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("package config cache poisoned")
+        .entry(config_file.to_path_buf())
+        .or_insert_with(|| Box::leak(Box::new(ConfigStore::new())));
+    if let Some(config) = store.fresh() {
+        return Ok(config);
+    }
+
+    match load_layered_config(config_file.parent()).await {
+        Ok(layered) => {
+            let fingerprint = ConfigFingerprint::capture(&layered.watched_files());
+            Ok(store.replace((layered.config, layered.config_path), fingerprint))
+        }
+        Err(e) => keep_last_good_config(store, e),
+    }
+}
+
+/// Load the configuration that owns a file
+///
+/// Resolves the `.conclaude.yaml` nearest to the file rather than to the current directory,
+/// so in a monorepo each package's rules apply to the files inside it. Falls back to the
+/// configuration found from the current directory when no config encloses the file.
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be loaded or parsed.
+async fn get_config_for_path(file_path: &Path) -> Result<&'static (ConclaudeConfig, PathBuf)> {
+    match file_path.parent().and_then(find_nearest_config) {
+        Some(config_file) => get_package_config(&config_file).await,
+        None => get_config().await,
+    }
+}
+
+/// Express a file path relative to the directory of the configuration that owns it
+///
+/// Falls back to the path relative to `cwd`, then to the path as given, when the file
+/// lies outside the config directory.
+fn path_relative_to_config(resolved_path: &Path, config_path: &Path, cwd: &Path) -> String {
+    let config_dir = cwd.join(get_config_dir(config_path));
+    resolved_path
+        .strip_prefix(&config_dir)
+        .or_else(|_| resolved_path.strip_prefix(cwd))
+        .unwrap_or(resolved_path)
+        .to_string_lossy()
+        .to_string()
+}
+
 /// Extract the directory containing the config file
 ///
 /// Normalizes empty parent paths (when config is in CWD) to "." for consistent
@@ -366,6 +1413,13 @@ where
     let payload: T =
         serde_json::from_str(&buffer).context("Failed to parse JSON payload from stdin")?;
 
+    if let Some(session_id) = serde_json::from_str::<Value>(&buffer)
+        .ok()
+        .and_then(|json| json.get("session_id")?.as_str().map(str::to_string))
+    {
+        let _ = SESSION_ID.set(session_id);
+    }
+
     Ok(payload)
 }
 
@@ -401,6 +1455,10 @@ where
         }
         Err(error) => {
             eprintln!("{error}");
+            // `missingConfig.mode: block` fails closed when there is no configuration
+            if error.downcast_ref::<ConfigNotFound>().is_some() {
+                std::process::exit(2);
+            }
             std::process::exit(1);
         }
     }
@@ -446,9 +1504,7 @@ pub async fn handle_pre_tool_use() -> Result<HookResult> {
         return Ok(result);
     }
 
-    let file_modifying_tools = ["Write", "Edit", "MultiEdit", "NotebookEdit"];
-
-    if file_modifying_tools.contains(&payload.tool_name.as_str()) {
+    if FILE_MODIFYING_TOOLS.contains(&payload.tool_name.as_str()) {
         // Check if file is git-ignored and should not be modified
         if let Some(result) = check_git_ignored_file(&payload).await? {
             send_notification(
@@ -593,8 +1649,6 @@ pub async fn handle_permission_request() -> Result<HookResult> {
 ///
 /// Returns an error if configuration loading fails, directory access fails, or glob pattern processing fails.
 async fn check_file_validation_rules(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
-    let (config, config_path) = get_config().await?;
-
     // Extract file path from tool input
     let file_path = extract_file_path(&payload.tool_input);
     let Some(file_path) = file_path else {
@@ -603,7 +1657,11 @@ async fn check_file_validation_rules(payload: &PreToolUsePayload) -> Result<Opti
 
     let cwd = std::env::current_dir().context("Failed to get current working directory")?;
     let resolved_path = cwd.join(&file_path);
-    let relative_path = resolved_path
+    let (config, config_path) = get_config_for_path(&resolved_path).await?;
+
+    // Patterns are relative to the directory of the config that owns the file
+    let relative_path = path_relative_to_config(&resolved_path, config_path, &cwd);
+    let cwd_relative_path = resolved_path
         .strip_prefix(&cwd)
         .unwrap_or(resolved_path.as_path())
         .to_string_lossy()
@@ -614,7 +1672,7 @@ async fn check_file_validation_rules(payload: &PreToolUsePayload) -> Result<Opti
     // but prevents creation of new files at root
     if config.pre_tool_use.prevent_root_additions
         && payload.tool_name == "Write"
-        && is_root_addition(&file_path, &relative_path, config_path)
+        && is_root_addition(&file_path, &cwd_relative_path, config_path)
         && !resolved_path.exists()
     {
         // Use custom message if configured, otherwise use default
@@ -835,6 +1893,22 @@ pub async fn handle_post_tool_use() -> Result<HookResult> {
         payload.base.session_id, payload.tool_name
     );
 
+    // Remember edited files so Stop can run the checks of every package they belong to
+    if FILE_MODIFYING_TOOLS.contains(&payload.tool_name.as_str()) {
+        if let Some(file_path) = extract_file_path(&payload.tool_input) {
+            let resolved_path = std::env::current_dir()
+                .context("Failed to get current working directory")?
+                .join(file_path);
+            if let Err(e) = record_edited_file(&payload.base.session_id, &resolved_path) {
+                eprintln!(
+                    "Failed to record edited file {}: {}",
+                    resolved_path.display(),
+                    e
+                );
+            }
+        }
+    }
+
     // Send notification for post tool use completion
     send_notification(
         "PostToolUse",
@@ -913,7 +1987,7 @@ pub fn expand_file_references(prompt: &str, config_dir: &Path) -> String {
 pub fn compile_rule_pattern(rule: &crate::config::ContextInjectionRule) -> Option<regex::Regex> {
     use regex::RegexBuilder;
 
-    let pattern = if rule.case_insensitive.unwrap_or(false) {
+    let pattern = if rule.case_insensitive() {
         format!("(?i){}", rule.pattern)
     } else {
         rule.pattern.clone()
@@ -940,7 +2014,7 @@ fn compile_command_pattern(command: &UserPromptSubmitCommand) -> Option<regex::R
         return None; // No pattern means match all prompts
     };
 
-    let full_pattern = if command.case_insensitive.unwrap_or(false) {
+    let full_pattern = if command.case_insensitive() {
         format!("(?i){}", pattern)
     } else {
         pattern.clone()
@@ -1021,13 +2095,13 @@ pub(crate) fn collect_user_prompt_submit_commands(
         }
 
         // Extract and add commands
-        let extracted = extract_bash_commands(&cmd_config.run)?;
-        let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-        let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-        let show_command = cmd_config.show_command.unwrap_or(true);
-        let max_output_lines = cmd_config.max_output_lines;
-        let timeout = cmd_config.timeout;
-        let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
+        let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
+        let show_stdout = cmd_config.launch.show_stdout();
+        let show_stderr = cmd_config.launch.show_stderr();
+        let show_command = cmd_config.launch.show_command();
+        let max_output_lines = cmd_config.launch.max_output_lines;
+        let timeout = cmd_config.launch.timeout;
+        let notify_per_command = cmd_config.launch.notify_per_command();
 
         for cmd in extracted {
             result.push(UserPromptSubmitCommandConfig {
@@ -1038,6 +2112,7 @@ pub(crate) fn collect_user_prompt_submit_commands(
                 timeout,
                 show_command,
                 notify_per_command,
+                launch: CommandLaunch::from_options(&cmd_config.launch),
             });
         }
     }
@@ -1047,8 +2122,9 @@ pub(crate) fn collect_user_prompt_submit_commands(
 
 /// Execute user prompt submit hook commands with environment variables
 ///
-/// Commands are observational (read-only) and cannot block prompt processing.
-/// Failures are logged but do not affect the hook result.
+/// Failures are logged and do not block prompt processing, unless a command's `onFailure`
+/// or `exitCodes` says otherwise: the block message of the first blocking failure is
+/// returned, and warnings and context go to `feedback`.
 ///
 /// # Errors
 ///
@@ -1058,9 +2134,10 @@ async fn execute_user_prompt_submit_commands(
     commands: &[UserPromptSubmitCommandConfig],
     env_vars: &HashMap<String, String>,
     config_dir: &Path,
-) -> Result<()> {
+    feedback: &mut CommandFeedback,
+) -> Result<Option<String>> {
     if commands.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
     println!(
@@ -1078,256 +2155,63 @@ async fn execute_user_prompt_submit_commands(
             );
         } else {
             println!(
-                "Executing user prompt submit command {}/{}",
-                index + 1,
-                commands.len()
-            );
-        }
-
-        // Send start notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification("UserPromptSubmit", "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env_vars)
-            .current_dir(config_dir)
-            .spawn();
-
-        let child = match child {
-            Ok(c) => c,
-            Err(e) => {
-                // Log error but continue to next command
-                if cmd_config.show_command {
-                    eprintln!(
-                        "Failed to spawn user prompt submit command '{}': {}",
-                        cmd_config.command, e
-                    );
-                } else {
-                    eprintln!("Failed to spawn user prompt submit command: {}", e);
-                }
-
-                // Send failure notification if per-command notifications are enabled
-                if cmd_config.notify_per_command {
-                    let context_msg = if cmd_config.show_command {
-                        format!("Failed to spawn command: {}", cmd_config.command)
-                    } else {
-                        "Failed to spawn command".to_string()
-                    };
-                    send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                }
-
-                continue;
-            }
-        };
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(result) => match result {
-                    Ok(o) => o,
-                    Err(e) => {
-                        if cmd_config.show_command {
-                            eprintln!(
-                                "Failed to wait for user prompt submit command '{}': {}",
-                                cmd_config.command, e
-                            );
-                        } else {
-                            eprintln!("Failed to wait for user prompt submit command: {}", e);
-                        }
-
-                        // Send failure notification if per-command notifications are enabled
-                        if cmd_config.notify_per_command {
-                            let context_msg = if cmd_config.show_command {
-                                format!("Command failed to wait: {}", cmd_config.command)
-                            } else {
-                                "Command failed to wait".to_string()
-                            };
-                            send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                        }
-
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    // Timeout occurred - log and continue
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "User prompt submit command timed out after {} seconds: {}",
-                            timeout_secs, cmd_config.command
-                        );
-                    } else {
-                        eprintln!(
-                            "User prompt submit command timed out after {} seconds",
-                            timeout_secs
-                        );
-                    }
-
-                    // Send failure notification if per-command notifications are enabled
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command timed out: {}", cmd_config.command)
-                        } else {
-                            "Command timed out".to_string()
-                        };
-                        send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        } else {
-            match child.wait_with_output().await {
-                Ok(o) => o,
-                Err(e) => {
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Failed to wait for user prompt submit command '{}': {}",
-                            cmd_config.command, e
-                        );
-                    } else {
-                        eprintln!("Failed to wait for user prompt submit command: {}", e);
-                    }
-
-                    // Send failure notification if per-command notifications are enabled
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command failed to wait: {}", cmd_config.command)
-                        } else {
-                            "Command failed to wait".to_string()
-                        };
-                        send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
-
-            // Log failure information - respect showCommand flag
-            let mut diagnostic = if cmd_config.show_command {
-                format!(
-                    "User prompt submit command failed:\n  Command: {}\n  Status: Failed (exit code: {})",
-                    cmd_config.command, exit_code
-                )
-            } else {
-                format!(
-                    "User prompt submit command failed:\n  Status: Failed (exit code: {})",
-                    exit_code
-                )
-            };
-
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                let stdout_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stdout.trim().to_string()
-                };
-                let stdout_display = stdout_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stdout:\n{}", stdout_display));
-            }
-
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                let stderr_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stderr.trim().to_string()
-                };
-                let stderr_display = stderr_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stderr:\n{}", stderr_display));
-            }
-
-            eprintln!("{}", diagnostic);
-
-            // Send failure notification if per-command notifications are enabled
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!("Command failed: {}", cmd_config.command)
-                } else {
-                    "Command failed".to_string()
-                };
-                send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-            }
-
-            // Continue to next command (graceful failure handling)
-            continue;
+                "Executing user prompt submit command {}/{}",
+                index + 1,
+                commands.len()
+            );
         }
 
-        // Successful command - show output if configured
-        if cmd_config.show_stdout && !stdout.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
+        let report = cmd_config.report();
+        report.notify("UserPromptSubmit", "running", "Running");
+
+        let run = match run_with_retries(
+            &cmd_config.command,
+            &cmd_config.launch,
+            cmd_config.timeout,
+            env_vars,
+            config_dir,
+            None,
+            cmd_config.notify_per_command.then_some("UserPromptSubmit"),
+        )
+        .await
+        {
+            Ok(run) => run,
+            Err(e) => {
+                // Log error but continue to next command
+                if cmd_config.show_command {
+                    eprintln!("Failed to run user prompt submit command: {e:#}");
                 } else {
-                    truncated
+                    eprintln!(
+                        "Failed to run user prompt submit command: {}",
+                        e.root_cause()
+                    );
                 }
-            } else {
-                stdout.to_string()
-            };
-            println!("Stdout: {}", output_to_show);
-        }
 
-        if cmd_config.show_stderr && !stderr.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
-                } else {
-                    truncated
-                }
-            } else {
-                stderr.to_string()
-            };
-            eprintln!("Stderr: {}", output_to_show);
+                report.notify("UserPromptSubmit", "failure", "Failed to run command");
+
+                continue;
+            }
+        };
+        report.notify_result("UserPromptSubmit", &run);
+        if run.succeeded() {
+            report.log_output(&run);
+            continue;
         }
 
-        // Send success notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Command completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification("UserPromptSubmit", "success", Some(&context_msg));
+        // Failures are logged and skipped unless the command's policy says otherwise
+        let (log, failure) = describe_failure("User prompt submit", report, &run);
+        eprintln!("{log}");
+        let action = cmd_config
+            .launch
+            .failure
+            .action(run.exit_code(), FailureAction::Ignore);
+        if let Some(failure) = feedback.route(action, failure) {
+            return Ok(Some(failure.block_message()));
         }
     }
 
     println!("All user prompt submit hook commands completed");
-    Ok(())
+    Ok(None)
 }
 
 /// Handles `UserPromptSubmit` hook events when users submit input to Claude.
@@ -1338,8 +2222,8 @@ async fn execute_user_prompt_submit_commands(
 /// 3. Executing matching commands (after contextRules processing)
 /// 4. Returning the hook result with any injected context
 ///
-/// Commands are observational (read-only) and cannot block prompt processing.
-/// Command failures are logged but do not affect the hook result.
+/// Command failures are logged and do not block prompt processing unless a command's
+/// `onFailure` or `exitCodes` says so.
 ///
 /// # Errors
 ///
@@ -1375,7 +2259,7 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
         if !prompt.is_empty() {
             for rule in &config.user_prompt_submit.context_rules {
                 // Skip disabled rules
-                if rule.enabled == Some(false) {
+                if !rule.enabled() {
                     continue;
                 }
 
@@ -1409,7 +2293,7 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
     };
 
     // Execute commands after contextRules processing
-    // Commands are observational and cannot block prompt processing
+    let mut feedback = CommandFeedback::default();
     if !config.user_prompt_submit.commands.is_empty() {
         // Only execute commands if prompt is present and non-empty
         if let Some(ref prompt) = payload.prompt {
@@ -1425,11 +2309,18 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
                     let env_vars = build_user_prompt_submit_env_vars(&payload, config_dir);
 
                     // Execute commands (graceful failure handling)
-                    if let Err(e) =
-                        execute_user_prompt_submit_commands(&commands, &env_vars, config_dir).await
+                    match execute_user_prompt_submit_commands(
+                        &commands,
+                        &env_vars,
+                        config_dir,
+                        &mut feedback,
+                    )
+                    .await
                     {
+                        Ok(Some(message)) => return Ok(HookResult::blocked(message)),
+                        Ok(None) => {}
                         // Log error but don't block the hook result
-                        eprintln!("Error executing user prompt submit commands: {}", e);
+                        Err(e) => eprintln!("Error executing user prompt submit commands: {}", e),
                     }
                 }
             }
@@ -1467,8 +2358,13 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
                                 build_slash_command_env_vars(&payload, config_dir, &detection);
 
                             // Execute slash command hooks (can block on exit code 2)
-                            match execute_slash_command_hooks(&commands, &env_vars, config_dir)
-                                .await
+                            match execute_slash_command_hooks(
+                                &commands,
+                                &env_vars,
+                                config_dir,
+                                &mut feedback,
+                            )
+                            .await
                             {
                                 Ok(blocked) => {
                                     if let Some(message) = blocked {
@@ -1496,12 +2392,13 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
                 matching_contexts.len()
             )),
         );
-        return Ok(HookResult::with_context(context));
+        feedback.context.insert(0, context);
+        return Ok(feedback.into_result());
     }
 
     // Send notification for user prompt submission (no context injection)
     send_notification("UserPromptSubmit", "success", Some("User input received"));
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `SessionStart` hook events when a new Claude session begins.
@@ -1509,7 +2406,6 @@ pub async fn handle_user_prompt_submit() -> Result<HookResult> {
 /// # Errors
 ///
 /// Returns an error if payload validation fails or configuration loading fails.
-#[allow(clippy::unused_async)]
 pub async fn handle_session_start() -> Result<HookResult> {
     let payload: SessionStartPayload = read_payload_from_stdin()?;
 
@@ -1538,6 +2434,15 @@ pub async fn handle_session_start() -> Result<HookResult> {
         "success",
         Some(&format!("Session started from {}", payload.source)),
     );
+
+    // Tell Claude once per session that this project is not using conclaude yet
+    let (config, _config_path) = get_config().await?;
+    if CONFIG_MISSING.load(Ordering::Relaxed)
+        && config.missing_config.session_start_hint
+        && claim_session_marker(&payload.base.session_id, "missing-config-hint")
+    {
+        return Ok(HookResult::with_context(MISSING_CONFIG_HINT));
+    }
     Ok(HookResult::success())
 }
 
@@ -1572,23 +2477,362 @@ pub async fn handle_session_end() -> Result<HookResult> {
     Ok(HookResult::success())
 }
 
-/// Truncate output to a maximum number of lines
+/// A failed hook command, with what Claude needs to fix it
+#[derive(Debug, Clone)]
+pub(crate) struct CommandFailure {
+    /// What went wrong: the exit code or timeout, and the command when it is shown
+    pub(crate) summary: String,
+    /// The command's custom `message`
+    pub(crate) message: Option<String>,
+    /// Failing script line and the stdout/stderr sections the command is configured to show
+    pub(crate) details: String,
+    /// The command's exit code; `None` when it timed out or could not be run
+    pub(crate) exit_code: Option<i32>,
+}
+
+impl CommandFailure {
+    /// Describes a command that exited with `exit_code`, or timed out after `timeout`
+    /// seconds when it is `None`
+    pub(crate) fn new(
+        command: &str,
+        show_command: bool,
+        message: Option<&str>,
+        exit_code: Option<i32>,
+        timeout: Option<u64>,
+        attempts: &str,
+        details: String,
+    ) -> Self {
+        let what = match exit_code {
+            Some(code) => format!("Command failed with exit code {code}{attempts}"),
+            None => format!(
+                "Command timed out after {} seconds{attempts}",
+                timeout.unwrap_or_default()
+            ),
+        };
+        Self {
+            summary: if show_command {
+                format!("{what}: {command}")
+            } else {
+                what
+            },
+            message: message.map(str::to_string),
+            details,
+            exit_code,
+        }
+    }
+
+    /// The custom message, or the summary when there is none
+    pub(crate) fn headline(&self) -> &str {
+        self.message.as_deref().unwrap_or(&self.summary)
+    }
+
+    /// The message used when this failure alone blocks the hook
+    pub(crate) fn block_message(&self) -> String {
+        format!("{}{}", self.headline(), self.details)
+    }
+}
+
+/// Failures a hook reports without blocking, from commands whose failure action is
+/// `warn` or `context`
+#[derive(Debug, Default)]
+pub(crate) struct CommandFeedback {
+    /// Headlines shown to the user
+    pub(crate) warnings: Vec<String>,
+    /// Failure messages, with their shown output, passed to Claude
+    pub(crate) context: Vec<String>,
+}
+
+impl CommandFeedback {
+    /// Records `failure` according to `action`, handing it back when the action is `block`
+    ///
+    /// Ignored failures are dropped; the executors have already logged them.
+    pub(crate) fn route(
+        &mut self,
+        action: FailureAction,
+        failure: CommandFailure,
+    ) -> Option<CommandFailure> {
+        match action {
+            FailureAction::Block => return Some(failure),
+            FailureAction::Warn => self.warnings.push(failure.headline().to_string()),
+            FailureAction::Context => self.context.push(failure.block_message()),
+            FailureAction::Ignore => {}
+        }
+        None
+    }
+
+    /// The result of a hook that was not blocked: a success carrying the warnings as its
+    /// message and the context as additional context
+    pub(crate) fn into_result(self) -> HookResult {
+        let mut result = HookResult::success();
+        if !self.warnings.is_empty() {
+            result.message = Some(self.warnings.join("\n"));
+        }
+        if !self.context.is_empty() {
+            result.system_prompt = Some(self.context.join("\n\n"));
+        }
+        result
+    }
+}
+
+/// The settings of a configured command that decide how its result is reported
+#[derive(Clone, Copy)]
+pub(crate) struct CommandReport<'a> {
+    pub(crate) command: &'a str,
+    pub(crate) message: Option<&'a str>,
+    pub(crate) parser: Option<DiagnosticParser>,
+    /// Text the parser reads instead of the command's output, from its report file
+    pub(crate) parser_input: Option<&'a str>,
+    pub(crate) show_command: bool,
+    pub(crate) show_stdout: bool,
+    pub(crate) show_stderr: bool,
+    pub(crate) max_output_lines: Option<u32>,
+    pub(crate) timeout: Option<u64>,
+    pub(crate) notify_per_command: bool,
+    pub(crate) output: &'a OutputExcerpt,
+}
+
+impl CommandReport<'_> {
+    /// `what`, followed by the command when it is shown
+    fn labelled(&self, what: &str) -> String {
+        if self.show_command {
+            format!("{what}: {}", self.command)
+        } else {
+            what.to_string()
+        }
+    }
+
+    /// Sends a `status` notification for the command when `notifyPerCommand` is set
+    fn notify(&self, hook_name: &str, status: &str, what: &str) {
+        if self.notify_per_command {
+            send_notification(hook_name, status, Some(&self.labelled(what)));
+        }
+    }
+
+    /// Sends the completion notification for a finished `run`
+    fn notify_result(&self, hook_name: &str, run: &CommandRun) {
+        let attempts = run.attempts_suffix();
+        if run.succeeded() {
+            self.notify(
+                hook_name,
+                "success",
+                &format!("Command completed{attempts}"),
+            );
+        } else if run.exit_code().is_some() {
+            self.notify(hook_name, "failure", &format!("Command failed{attempts}"));
+        } else {
+            self.notify(
+                hook_name,
+                "failure",
+                &format!("Command timed out{attempts}"),
+            );
+        }
+    }
+
+    /// Logs the output of a successful `run` that the command is configured to show
+    fn log_output(&self, run: &CommandRun) {
+        let (stdout, stderr) = run.output();
+        let shown = |output: &str| match self.max_output_lines {
+            Some(max_lines) => excerpt_output(output, max_lines, self.output),
+            None => output.to_string(),
+        };
+        if self.show_stdout && !stdout.trim().is_empty() {
+            println!("Stdout: {}", shown(&stdout));
+        }
+        if self.show_stderr && !stderr.trim().is_empty() {
+            eprintln!("Stderr: {}", shown(&stderr));
+        }
+    }
+}
+
+/// Describes a failed `run` of a `kind` command, returning the text to log and the failure
+///
+/// The log text gives the command, exit code or timeout, failing script line and the
+/// shown output indented below them. The failure's details hold the failing line and the
+/// parser's diagnostics, or the shown output when there are none. A timed-out run reports
+/// the output written before it was stopped.
+pub(crate) fn describe_failure(
+    kind: &str,
+    cmd: CommandReport<'_>,
+    run: &CommandRun,
+) -> (String, CommandFailure) {
+    let attempts = run.attempts_suffix();
+    let exit_code = run.exit_code();
+    let (stdout, stderr) = run.output();
+    let failed_line = script_failed_line(cmd.command, &stderr);
+    let stderr = strip_failure_markers(&stderr);
+
+    let mut log = format!("{kind} command failed:");
+    if cmd.show_command {
+        log.push_str(&format!("\n  Command: {}", cmd.command));
+    }
+    match exit_code {
+        Some(code) => log.push_str(&format!("\n  Status: Failed (exit code: {code}){attempts}")),
+        None => log.push_str(&format!(
+            "\n  Status: Timed out after {} seconds{attempts}",
+            cmd.timeout.unwrap_or_default()
+        )),
+    }
+    if let Some((line, command)) = &failed_line {
+        log.push_str(&format!("\n  Failed at line {line}: {command}"));
+    }
+    for (label, output, show) in [
+        ("Stdout", &stdout, cmd.show_stdout),
+        ("Stderr", &stderr, cmd.show_stderr),
+    ] {
+        if !show || output.trim().is_empty() {
+            continue;
+        }
+        let content = match cmd.max_output_lines {
+            Some(max_lines) => excerpt_output(output, max_lines, cmd.output),
+            None => output.trim().to_string(),
+        };
+        let indented = content
+            .lines()
+            .map(|line| format!("    {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        log.push_str(&format!("\n  {label}:\n{indented}"));
+    }
+    if let Some(message) = cmd.message {
+        log.push_str(&format!("\n  Message: {message}"));
+    }
+
+    let failed_line_section = failed_line
+        .map(|(line, command)| format!("\nFailed at line {line}: {command}"))
+        .unwrap_or_default();
+    let parser_input = cmd
+        .parser_input
+        .map_or_else(|| format!("{stdout}\n{stderr}"), str::to_string);
+    let output_details = diagnostics_section(cmd.parser, &parser_input).unwrap_or_else(|| {
+        let stdout_section = output_section(
+            "Stdout",
+            &stdout,
+            cmd.show_stdout,
+            cmd.max_output_lines,
+            cmd.output,
+        );
+        let stderr_section = output_section(
+            "Stderr",
+            &stderr,
+            cmd.show_stderr,
+            cmd.max_output_lines,
+            cmd.output,
+        );
+        format!("{stdout_section}{stderr_section}")
+    });
+    let failure = CommandFailure::new(
+        cmd.command,
+        cmd.show_command,
+        cmd.message,
+        exit_code,
+        cmd.timeout,
+        &attempts,
+        format!("{failed_line_section}{output_details}"),
+    );
+    (log, failure)
+}
+
+/// Formats a `Stdout:`/`Stderr:` section for a failure message, excerpted to `max_output_lines`
+///
+/// Returns an empty string when the stream is hidden or empty.
+pub(crate) fn output_section(
+    label: &str,
+    output: &str,
+    show: bool,
+    max_output_lines: Option<u32>,
+    excerpt: &OutputExcerpt,
+) -> String {
+    if !show || output.is_empty() {
+        return String::new();
+    }
+    match max_output_lines {
+        Some(max_lines) => format!("\n{label}: {}", excerpt_output(output, max_lines, excerpt)),
+        None => format!("\n{label}: {output}"),
+    }
+}
+
+/// The diagnostics `parser` finds in a failed command's output or report, formatted for
+/// the block message
+///
+/// Returns `None` without a parser or when it recognises nothing, so the caller can show
+/// the raw output instead.
+fn diagnostics_section(parser: Option<DiagnosticParser>, output: &str) -> Option<String> {
+    let diagnostics = parse_diagnostics(parser?, output);
+    if diagnostics.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "\n{}",
+        format_diagnostics(&diagnostics, MAX_DIAGNOSTICS)
+    ))
+}
+
+/// Combines the failures of a run-all execution into one block message
+///
+/// Failures are numbered in config order, each with its summary, custom message and
+/// output. Commands that never started because a command they need failed are listed last.
+pub(crate) fn failure_report(
+    kind: &str,
+    total: usize,
+    failures: &[CommandFailure],
+    skipped: &[String],
+) -> String {
+    let mut report = format!("{} of {total} {kind} commands failed:", failures.len());
+    for (number, failure) in failures.iter().enumerate() {
+        report.push_str(&format!("\n\n[{}] {}", number + 1, failure.summary));
+        if let Some(message) = &failure.message {
+            report.push_str(&format!("\n{message}"));
+        }
+        report.push_str(&failure.details);
+    }
+    if !skipped.is_empty() {
+        report.push_str("\n\nSkipped because a command they need failed:");
+        for command in skipped {
+            report.push_str(&format!("\n  - {command}"));
+        }
+    }
+    report
+}
+
+/// Limits output to `max_lines` lines, chosen by the excerpt's output mode
 ///
-/// Returns a tuple of (truncated_output, is_truncated, omitted_line_count)
-pub(crate) fn truncate_output(output: &str, max_lines: u32) -> (String, bool, usize) {
+/// Every dropped region is replaced by a marker naming its lines, such as
+/// `... (40 lines omitted: lines 11-50)`. Output within the limit is returned unchanged.
+pub(crate) fn excerpt_output(output: &str, max_lines: u32, excerpt: &OutputExcerpt) -> String {
     let lines: Vec<&str> = output.lines().collect();
-    let total_lines = lines.len();
-    let max_lines_usize = max_lines as usize;
+    let max_lines = max_lines as usize;
+    if lines.len() <= max_lines {
+        return output.to_string();
+    }
 
-    if total_lines <= max_lines_usize {
-        // No truncation needed
-        (output.to_string(), false, 0)
+    let mut excerpted: Vec<String> = Vec::new();
+    let mut next = 0;
+    for range in excerpt.kept_ranges(&lines, max_lines) {
+        if range.is_empty() {
+            continue;
+        }
+        if range.start > next {
+            excerpted.push(omitted_marker(next, range.start));
+        }
+        excerpted.extend(lines[range.clone()].iter().map(|line| (*line).to_string()));
+        next = range.end;
+    }
+    if next < lines.len() {
+        excerpted.push(omitted_marker(next, lines.len()));
+    }
+    excerpted.join("\n")
+}
+
+/// Marker for the dropped lines `start..end`, numbered from 1
+fn omitted_marker(start: usize, end: usize) -> String {
+    if end - start == 1 {
+        format!("... (1 line omitted: line {end})")
     } else {
-        // Take first N lines and calculate omitted count
-        let truncated_lines = &lines[..max_lines_usize];
-        let omitted_count = total_lines - max_lines_usize;
-        let truncated = truncated_lines.join("\n");
-        (truncated, true, omitted_count)
+        format!(
+            "... ({} lines omitted: lines {}-{end})",
+            end - start,
+            start + 1
+        )
     }
 }
 
@@ -1596,28 +2840,72 @@ pub(crate) fn truncate_output(output: &str, max_lines: u32) -> (String, bool, us
 ///
 /// # Errors
 ///
-/// Returns an error if bash command extraction fails.
+/// Returns an error if bash command extraction fails or the `needs` graph is invalid.
 pub(crate) fn collect_stop_commands(config: &ConclaudeConfig) -> Result<Vec<StopCommandConfig>> {
-    let mut commands = Vec::new();
+    collect_stop_command_entries(&config.stop.commands)
+}
 
-    // Add structured commands with messages and output control
-    for cmd_config in &config.stop.commands {
-        let extracted = extract_bash_commands(&cmd_config.run)?;
-        let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-        let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-        let show_command = cmd_config.show_command.unwrap_or(true);
-        let max_output_lines = cmd_config.max_output_lines;
-        let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
-        for cmd in extracted {
+/// Expands stop command entries into the commands to run and their dependencies
+///
+/// The lines of an entry in lines mode run one after another; the first waits for the
+/// entries named in `needs`. An entry without commands passes its own needs on to the
+/// entries that need it.
+///
+/// # Errors
+///
+/// Returns an error if bash command extraction fails or the `needs` graph is invalid.
+fn collect_stop_command_entries(entries: &[StopCommand]) -> Result<Vec<StopCommandConfig>> {
+    let entry_needs = resolve_command_needs(entries).map_err(|errors| {
+        let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        anyhow::anyhow!("Invalid stop command dependencies: {}", details.join("; "))
+    })?;
+
+    let mut extracted = Vec::with_capacity(entries.len());
+    for cmd_config in entries {
+        extracted.push(split_run_commands(
+            &cmd_config.run,
+            cmd_config.launch.mode(),
+        )?);
+    }
+
+    // Index of the command that finishes each entry, once flattened
+    let mut entry_last = Vec::with_capacity(entries.len());
+    let mut next_index = 0;
+    for lines in &extracted {
+        next_index += lines.len();
+        entry_last.push(next_index.checked_sub(1).filter(|_| !lines.is_empty()));
+    }
+
+    let mut commands = Vec::new();
+    for ((entry, cmd_config), lines) in entries.iter().enumerate().zip(extracted) {
+        let show_stdout = cmd_config.launch.show_stdout();
+        let show_stderr = cmd_config.launch.show_stderr();
+        let show_command = cmd_config.launch.show_command();
+        let max_output_lines = cmd_config.launch.max_output_lines;
+        let notify_per_command = cmd_config.launch.notify_per_command();
+        for (line, cmd) in lines.into_iter().enumerate() {
+            let needs = if line == 0 {
+                entry_needs[entry]
+                    .iter()
+                    .flat_map(|&need| entry_completion(need, &entry_last, &entry_needs))
+                    .collect()
+            } else {
+                vec![commands.len() - 1]
+            };
             commands.push(StopCommandConfig {
                 command: cmd,
                 message: cmd_config.message.clone(),
                 show_stdout,
                 show_stderr,
                 max_output_lines,
-                timeout: cmd_config.timeout,
+                timeout: cmd_config.launch.timeout,
                 show_command,
                 notify_per_command,
+                launch: CommandLaunch::from_options(&cmd_config.launch),
+                parser: cmd_config.parser,
+                report_file: cmd_config.report_file.clone(),
+                fix: cmd_config.fix.clone(),
+                needs,
             });
         }
     }
@@ -1625,219 +2913,608 @@ pub(crate) fn collect_stop_commands(config: &ConclaudeConfig) -> Result<Vec<Stop
     Ok(commands)
 }
 
+/// Indices of the commands whose success means `entry` has finished
+///
+/// That is the entry's last command, or for an entry without commands, the commands that
+/// finish the entries it needs.
+fn entry_completion(
+    entry: usize,
+    entry_last: &[Option<usize>],
+    entry_needs: &[Vec<usize>],
+) -> Vec<usize> {
+    match entry_last[entry] {
+        Some(index) => vec![index],
+        None => entry_needs[entry]
+            .iter()
+            .flat_map(|&need| entry_completion(need, entry_last, entry_needs))
+            .collect(),
+    }
+}
+
+/// How a list of stop commands is scheduled
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StopRunOptions {
+    /// Maximum number of commands running at once
+    pub(crate) concurrency: usize,
+    /// Stop launching commands after the first failure
+    pub(crate) fail_fast: bool,
+}
+
+impl Default for StopRunOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            fail_fast: true,
+        }
+    }
+}
+
+impl StopRunOptions {
+    /// Scheduling options from a config's `stop` section
+    pub(crate) fn from_config(stop: &StopConfig) -> Self {
+        Self {
+            concurrency: stop.concurrency(),
+            fail_fast: stop.fail_fast,
+        }
+    }
+}
+
+/// Build environment variables for stop command execution
+///
+/// Besides the session context shared by every hook, commands receive the last assistant
+/// message, whether a stop hook is already active, and the in-flight background tasks and
+/// session crons as JSON arrays, so scripts can decide without parsing the transcript.
+#[must_use]
+pub(crate) fn build_stop_env_vars(
+    payload: &StopPayload,
+    config_dir: &Path,
+) -> HashMap<String, String> {
+    let mut env_vars = HashMap::new();
+    env_vars.insert(
+        "CONCLAUDE_STOP_HOOK_ACTIVE".to_string(),
+        payload.stop_hook_active.to_string(),
+    );
+    env_vars.insert(
+        "CONCLAUDE_LAST_ASSISTANT_MESSAGE".to_string(),
+        payload.last_assistant_message.clone().unwrap_or_default(),
+    );
+    env_vars.insert(
+        "CONCLAUDE_BACKGROUND_TASKS".to_string(),
+        json_array(payload.background_tasks.as_deref()),
+    );
+    env_vars.insert(
+        "CONCLAUDE_SESSION_CRONS".to_string(),
+        json_array(payload.session_crons.as_deref()),
+    );
+    insert_base_env_vars(&mut env_vars, &payload.base, payload, "Stop", config_dir);
+    env_vars
+}
+
+/// Build environment variables for stop failure command execution
+///
+/// Adds the API error code and its details to the session context shared by every hook.
+#[must_use]
+pub(crate) fn build_stop_failure_env_vars(
+    payload: &StopFailurePayload,
+    config_dir: &Path,
+) -> HashMap<String, String> {
+    let mut env_vars = HashMap::new();
+    env_vars.insert(
+        "CONCLAUDE_STOP_HOOK_ACTIVE".to_string(),
+        payload.stop_hook_active.to_string(),
+    );
+    env_vars.insert(
+        "CONCLAUDE_LAST_ASSISTANT_MESSAGE".to_string(),
+        payload.last_assistant_message.clone().unwrap_or_default(),
+    );
+    env_vars.insert("CONCLAUDE_STOP_ERROR".to_string(), payload.error.clone());
+    env_vars.insert(
+        "CONCLAUDE_STOP_ERROR_DETAILS".to_string(),
+        payload.error_details.clone().unwrap_or_default(),
+    );
+    insert_base_env_vars(
+        &mut env_vars,
+        &payload.base,
+        payload,
+        "StopFailure",
+        config_dir,
+    );
+    env_vars
+}
+
+/// Serializes an optional payload list as a JSON array, empty when absent
+fn json_array<T: serde::Serialize>(items: Option<&[T]>) -> String {
+    serde_json::to_string(items.unwrap_or_default()).unwrap_or_else(|_| "[]".to_string())
+}
+
 /// Execute stop hook commands
 ///
+/// Every command receives `env_vars`, with `CONCLAUDE_CONFIG_DIR` pointing at `config_dir`.
+/// Up to `options.concurrency` commands run at once, each starting in config order as soon
+/// as the commands in its `needs` have succeeded. With `fail_fast`, no further commands
+/// start after a failure; those already running finish, and every failure is reported in
+/// config order. Without it, every command whose needs succeeded runs and the hook is
+/// blocked with one report listing all failures and the commands skipped because of them.
+/// A failure whose action is `warn`, `context` or `ignore` does not block: it goes to
+/// `feedback` and counts as a success for the commands that need it.
+/// A command with a `fix` that fails has its fix run and is then run again; when it passes
+/// the second time, a report of the files the fix modified goes to `feedback`. Fixes run
+/// one at a time once no other command is running, and no command starts while a fix is
+/// waiting, so the files a fix modified are its own. With `fail_fast`, fixes still waiting
+/// when another failure blocks the hook are not run and their checks count as failed.
+///
 /// # Errors
 ///
 /// Returns an error if command execution fails or process spawning fails.
-async fn execute_stop_commands(
+pub(crate) async fn execute_stop_commands(
     commands: &[StopCommandConfig],
+    env_vars: &HashMap<String, String>,
     config_dir: &Path,
+    options: StopRunOptions,
+    feedback: &mut CommandFeedback,
 ) -> Result<Option<HookResult>> {
-    println!("Executing {} stop hook commands", commands.len());
+    let concurrency = options.concurrency.max(1);
+    if concurrency > 1 {
+        println!(
+            "Executing {} stop hook commands (up to {} in parallel)",
+            commands.len(),
+            concurrency
+        );
+    } else {
+        println!("Executing {} stop hook commands", commands.len());
+    }
 
-    for (index, cmd_config) in commands.iter().enumerate() {
-        if cmd_config.show_command {
-            println!(
-                "Executing command {}/{}: {}",
-                index + 1,
-                commands.len(),
-                cmd_config.command
-            );
-        } else {
-            println!("Executing command {}/{}", index + 1, commands.len());
+    let mut env_vars = env_vars.clone();
+    env_vars.insert(
+        "CONCLAUDE_CONFIG_DIR".to_string(),
+        config_dir.to_string_lossy().to_string(),
+    );
+    let total = commands.len();
+    let mut started = vec![false; total];
+    let mut succeeded = vec![false; total];
+    let mut failures: Vec<(usize, CommandFailure)> = Vec::new();
+    let mut tolerated: Vec<(usize, FailureAction, CommandFailure)> = Vec::new();
+    let mut fixed: Vec<(usize, String)> = Vec::new();
+    let mut pending_fixes: Vec<(usize, CommandFailure)> = Vec::new();
+    let mut running = tokio::task::JoinSet::new();
+
+    loop {
+        if pending_fixes.is_empty() && (failures.is_empty() || !options.fail_fast) {
+            for (index, cmd_config) in commands.iter().enumerate() {
+                if running.len() >= concurrency {
+                    break;
+                }
+                if started[index] || !cmd_config.needs.iter().all(|&need| succeeded[need]) {
+                    continue;
+                }
+                started[index] = true;
+                let cmd_config = cmd_config.clone();
+                let env_vars = env_vars.clone();
+                let config_dir = config_dir.to_path_buf();
+                running.spawn(async move {
+                    let failure =
+                        run_stop_check(&cmd_config, index, total, &env_vars, &config_dir).await;
+                    (index, failure)
+                });
+            }
         }
 
-        // Send start notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification("Stop", "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .current_dir(config_dir)
-            .env(
-                "CONCLAUDE_CONFIG_DIR",
-                config_dir.to_string_lossy().to_string(),
-            )
-            .spawn()
-            .with_context(|| format!("Failed to spawn command: {}", cmd_config.command))?;
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(result) => result.with_context(|| {
-                    format!("Failed to wait for command: {}", cmd_config.command)
-                })?,
-                Err(_) => {
-                    // Timeout occurred - return blocked result
-                    let error_msg = format!(
-                        "Command timed out after {} seconds: {}",
-                        timeout_secs, cmd_config.command
-                    );
-                    eprintln!("{}", error_msg);
-
-                    // Send failure notification if per-command notifications are enabled
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command timed out: {}", cmd_config.command)
-                        } else {
-                            "Command timed out".to_string()
-                        };
-                        send_notification("Stop", "failure", Some(&context_msg));
+        let mut finished = Vec::new();
+        match running.join_next().await {
+            Some(joined) => {
+                let (index, failure) = joined.context("Stop command task panicked")?;
+                match failure? {
+                    None => finished.push((index, CheckOutcome::Passed)),
+                    Some(failure) if commands[index].fix.is_some() => {
+                        pending_fixes.push((index, failure));
                     }
+                    Some(failure) => finished.push((index, CheckOutcome::Failed(failure))),
+                }
+            }
+            None if !pending_fixes.is_empty() => {
+                pending_fixes.sort_by_key(|(index, _)| *index);
+                if options.fail_fast && !failures.is_empty() {
+                    // The hook blocks already, so the checks fail without their fixes
+                    finished.extend(
+                        pending_fixes
+                            .drain(..)
+                            .map(|(index, failure)| (index, CheckOutcome::Failed(failure))),
+                    );
+                } else {
+                    let (index, failure) = pending_fixes.remove(0);
+                    let outcome = fix_stop_command(
+                        &commands[index],
+                        index,
+                        total,
+                        failure,
+                        &env_vars,
+                        config_dir,
+                    )
+                    .await?;
+                    finished.push((index, outcome));
+                }
+            }
+            None => break,
+        }
 
-                    let message = cmd_config.message.as_deref().unwrap_or(&error_msg);
-                    return Ok(Some(HookResult::blocked(message)));
+        for (index, outcome) in finished {
+            match outcome {
+                CheckOutcome::Failed(failure) => {
+                    let launch = &commands[index].launch;
+                    match launch
+                        .failure
+                        .action(failure.exit_code, FailureAction::Block)
+                    {
+                        FailureAction::Block => failures.push((index, failure)),
+                        action => {
+                            tolerated.push((index, action, failure));
+                            succeeded[index] = true;
+                        }
+                    }
+                }
+                CheckOutcome::Fixed(report) => {
+                    fixed.push((index, report));
+                    succeeded[index] = true;
                 }
+                CheckOutcome::Passed => succeeded[index] = true,
             }
-        } else {
-            child
-                .wait_with_output()
-                .await
-                .with_context(|| format!("Failed to wait for command: {}", cmd_config.command))?
-        };
+        }
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+    fixed.sort_by_key(|(index, _)| *index);
+    feedback
+        .context
+        .extend(fixed.into_iter().map(|(_, report)| report));
+    tolerated.sort_by_key(|(index, _, _)| *index);
+    for (_, action, failure) in tolerated {
+        feedback.route(action, failure);
+    }
 
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
+    if failures.is_empty() {
+        println!("All stop hook commands completed successfully");
+        return Ok(None);
+    }
 
-            // Log detailed failure information with command and outputs appended
-            // Respect showCommand, showStdout and showStderr flags when logging to console
-            // Build diagnostic output dynamically to omit sections when flags are false
-            let mut diagnostic = if cmd_config.show_command {
-                format!(
-                    "Stop command failed:\n  Command: {}\n  Status: Failed (exit code: {})",
-                    cmd_config.command, exit_code
-                )
-            } else {
-                format!(
-                    "Stop command failed:\n  Status: Failed (exit code: {})",
-                    exit_code
-                )
-            };
+    failures.sort_by_key(|(index, _)| *index);
+    let failures: Vec<CommandFailure> = failures.into_iter().map(|(_, f)| f).collect();
+    let message = if options.fail_fast {
+        failures
+            .iter()
+            .map(CommandFailure::block_message)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        let skipped: Vec<String> = commands
+            .iter()
+            .zip(&started)
+            .filter(|(_, &started)| !started)
+            .map(|(cmd_config, _)| cmd_config.command.clone())
+            .collect();
+        failure_report("stop", total, &failures, &skipped)
+    };
+    Ok(Some(HookResult::blocked(message)))
+}
+
+/// How a check command with an optional `fix` ended
+enum CheckOutcome {
+    /// The command succeeded on its first run
+    Passed,
+    /// The command failed, its fix ran and the command then succeeded; holds the report
+    /// for Claude
+    Fixed(String),
+    /// The command failed, after its fix when it has one
+    Failed(CommandFailure),
+}
+
+/// Runs the `fix` of a failed command and describes what it did
+///
+/// The fix uses the command's launch settings without its retries. The files it modified
+/// are found by comparing `git status` of the repository before and after it runs, which
+/// also catches further edits to files that were already changed.
+async fn run_fix(
+    fix: &str,
+    launch: &CommandLaunch,
+    timeout_secs: Option<u64>,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+    show_command: bool,
+) -> String {
+    let label = if show_command {
+        format!("Fix `{fix}`")
+    } else {
+        "Fix".to_string()
+    };
+    if show_command {
+        println!("Running fix: {fix}");
+    } else {
+        println!("Running fix");
+    }
+    let working_dir = launch.working_dir(config_dir);
+    let before = dirty_file_hashes(&working_dir).await;
 
-            // Only include Stdout section if showStdout is true
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                let stdout_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stdout.trim().to_string()
-                };
-                let stdout_display = stdout_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stdout:\n{}", stdout_display));
-            }
+    let launch = CommandLaunch {
+        retry: CommandRetry::default(),
+        ..launch.clone()
+    };
+    let status = match run_with_retries(
+        fix,
+        &launch,
+        timeout_secs,
+        env_vars,
+        config_dir,
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(run) => match run.outcome {
+            CommandOutcome::Exited(output) if output.status.success() => None,
+            CommandOutcome::Exited(output) => Some(format!(
+                "exited with code {}",
+                output.status.code().unwrap_or(1)
+            )),
+            CommandOutcome::TimedOut { .. } => Some(format!(
+                "timed out after {} seconds",
+                timeout_secs.unwrap_or_default()
+            )),
+        },
+        Err(e) => return format!("{label} could not run: {}", e.root_cause()),
+    };
 
-            // Only include Stderr section if showStderr is true
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                let stderr_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stderr.trim().to_string()
-                };
-                let stderr_display = stderr_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stderr:\n{}", stderr_display));
+    let changes = match (before, dirty_file_hashes(&working_dir).await) {
+        (Some(before), Some(after)) => {
+            let files = modified_files(&before, &after);
+            match files.len() {
+                0 => "modified no files".to_string(),
+                1 => format!("modified 1 file:\n  {}", files[0]),
+                count => format!("modified {count} files:\n  {}", files.join("\n  ")),
             }
+        }
+        _ => "ran outside a git repository, so the files it modified are unknown".to_string(),
+    };
+    match status {
+        Some(status) => format!("{label} {status} and {changes}"),
+        None => format!("{label} {changes}"),
+    }
+}
 
-            eprintln!("{}", diagnostic);
+/// The context given to Claude when a failing command passed after its fix ran
+fn fixed_report(hook: &str, command: &str, show_command: bool, fix_report: &str) -> String {
+    let check = if show_command {
+        format!(": {command}")
+    } else {
+        String::new()
+    };
+    format!(
+        "A failing {hook} command passed after its fix ran{check}\n{fix_report}\n\
+         These files changed on disk; re-read them before editing them again."
+    )
+}
 
-            let stdout_section = if cmd_config.show_stdout && !stdout.is_empty() {
-                if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                    if is_truncated {
-                        format!("\nStdout: {}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        format!("\nStdout: {}", truncated)
-                    }
-                } else {
-                    format!("\nStdout: {}", stdout)
-                }
-            } else {
-                String::new()
-            };
+/// Content hashes of the files `git status` lists as changed or untracked, keyed by path
+/// relative to the repository root; a deleted file has no hash
+///
+/// Returns `None` outside a git repository or when git cannot be run.
+async fn dirty_file_hashes(dir: &Path) -> Option<HashMap<String, Option<u64>>> {
+    let root = find_git_root(dir)?;
+    let output = TokioCommand::new("git")
+        .args(["status", "--porcelain", "-z", "--untracked-files=all"])
+        .current_dir(&root)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
 
-            let stderr_section = if cmd_config.show_stderr && !stderr.is_empty() {
-                if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                    if is_truncated {
-                        format!("\nStderr: {}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        format!("\nStderr: {}", truncated)
-                    }
-                } else {
-                    format!("\nStderr: {}", stderr)
-                }
-            } else {
-                String::new()
-            };
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let mut entries = listing.split('\0');
+    let mut hashes = HashMap::new();
+    while let Some(entry) = entries.next() {
+        // `XY path`, followed by the original path for renames and copies
+        let Some(path) = entry.get(3..) else {
+            continue;
+        };
+        if entry.starts_with(['R', 'C']) {
+            entries.next();
+        }
+        hashes.insert(path.to_string(), file_hash(&root.join(path)));
+    }
+    Some(hashes)
+}
 
-            let error_message = if let Some(custom_msg) = &cmd_config.message {
-                format!("{custom_msg}{stdout_section}{stderr_section}")
-            } else if cmd_config.show_command {
-                format!(
-                    "Command failed with exit code {exit_code}: {}{stdout_section}{stderr_section}",
-                    cmd_config.command
-                )
-            } else {
-                format!("Command failed with exit code {exit_code}{stdout_section}{stderr_section}")
-            };
+/// A file's content hash, with the modification time and size it was taken at
+struct HashedFile {
+    modified: SystemTime,
+    len: u64,
+    hash: u64,
+}
 
-            // Send failure notification if per-command notifications are enabled
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!("Command failed: {}", cmd_config.command)
-                } else {
-                    "Command failed".to_string()
-                };
-                send_notification("Stop", "failure", Some(&context_msg));
+/// Content hashes of the files hashed so far, by path
+static FILE_HASHES: OnceLock<Mutex<HashMap<PathBuf, HashedFile>>> = OnceLock::new();
+
+/// Content hash of the file at `path`, or `None` when it cannot be read
+///
+/// The file is only read again when its modification time or size changed since it was
+/// last hashed, so a snapshot after a fix rehashes just the files something wrote to.
+fn file_hash(path: &Path) -> Option<u64> {
+    let metadata = fs::metadata(path).ok()?;
+    let stamp = metadata
+        .modified()
+        .ok()
+        .map(|modified| (modified, metadata.len()));
+    let cache = FILE_HASHES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let (Some(stamp), Ok(cache)) = (stamp, cache.lock()) {
+        if let Some(hashed) = cache.get(path) {
+            if (hashed.modified, hashed.len) == stamp {
+                return Some(hashed.hash);
             }
-
-            return Ok(Some(HookResult::blocked(error_message)));
         }
+    }
 
-        // Send success notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Command completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification("Stop", "success", Some(&context_msg));
-        }
+    let content = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    let hash = hasher.finish();
+    if let (Some((modified, len)), Ok(mut cache)) = (stamp, cache.lock()) {
+        cache.insert(
+            path.to_path_buf(),
+            HashedFile {
+                modified,
+                len,
+                hash,
+            },
+        );
+    }
+    Some(hash)
+}
+
+/// Paths whose content differs between two [`dirty_file_hashes`] snapshots, sorted
+fn modified_files(
+    before: &HashMap<String, Option<u64>>,
+    after: &HashMap<String, Option<u64>>,
+) -> Vec<String> {
+    let mut files: Vec<String> = after
+        .iter()
+        .filter(|(path, hash)| before.get(*path) != Some(hash))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    files.sort();
+    files
+}
+
+/// Runs the `fix` of a failed stop command, then the command again
+///
+/// A command without a fix keeps its `failure`.
+///
+/// # Errors
+///
+/// Returns an error if process spawning or waiting fails.
+async fn fix_stop_command(
+    cmd_config: &StopCommandConfig,
+    index: usize,
+    total: usize,
+    failure: CommandFailure,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Result<CheckOutcome> {
+    let Some(fix) = &cmd_config.fix else {
+        return Ok(CheckOutcome::Failed(failure));
+    };
+
+    let fix_report = run_fix(
+        fix,
+        &cmd_config.launch,
+        cmd_config.timeout,
+        env_vars,
+        config_dir,
+        cmd_config.show_command,
+    )
+    .await;
+    Ok(
+        match run_stop_check(cmd_config, index, total, env_vars, config_dir).await? {
+            None => CheckOutcome::Fixed(fixed_report(
+                "stop",
+                &cmd_config.command,
+                cmd_config.show_command,
+                &fix_report,
+            )),
+            Some(mut failure) => {
+                failure.details.push_str(&format!("\n{fix_report}"));
+                CheckOutcome::Failed(failure)
+            }
+        },
+    )
+}
 
+/// Runs a single stop command and returns its failure, or `None` on success
+///
+/// # Errors
+///
+/// Returns an error if process spawning or waiting fails.
+async fn run_stop_check(
+    cmd_config: &StopCommandConfig,
+    index: usize,
+    total: usize,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Result<Option<CommandFailure>> {
+    if cmd_config.show_command {
+        println!(
+            "Executing command {}/{}: {}",
+            index + 1,
+            total,
+            cmd_config.command
+        );
+    } else {
+        println!("Executing command {}/{}", index + 1, total);
+    }
+
+    let report = cmd_config.report();
+    report.notify("Stop", "running", "Running");
+    let report_file = cmd_config
+        .report_file
+        .as_deref()
+        .map(|file| ReportFile::before_run(file, &cmd_config.launch, config_dir));
+
+    let run = run_with_retries(
+        &cmd_config.command,
+        &cmd_config.launch,
+        cmd_config.timeout,
+        env_vars,
+        config_dir,
+        None,
+        cmd_config.notify_per_command.then_some("Stop"),
+    )
+    .await?;
+    report.notify_result("Stop", &run);
+    if run.succeeded() {
         // Successful individual commands produce no output
+        return Ok(None);
     }
 
-    println!("All stop hook commands completed successfully");
-    Ok(None)
+    let parser_input = report_file.and_then(|file| file.read_new());
+    let report = CommandReport {
+        parser_input: parser_input.as_deref(),
+        ..report
+    };
+    let (log, failure) = describe_failure("Stop", report, &run);
+    eprintln!("{log}");
+    Ok(Some(failure))
+}
+
+/// Find the package configurations owning files edited during a session
+///
+/// The configuration at `primary_config_path` is excluded since its commands always run.
+/// Results are ordered by config path so stop commands run in a stable order.
+///
+/// # Errors
+///
+/// Returns an error if a package configuration cannot be loaded or parsed.
+async fn edited_package_configs(
+    session_id: &str,
+    primary_config_path: &Path,
+) -> Result<Vec<&'static (ConclaudeConfig, PathBuf)>> {
+    let mut config_files: Vec<PathBuf> = read_edited_files(session_id)
+        .iter()
+        .filter_map(|file| file.parent().and_then(find_nearest_config))
+        .filter(|config_file| !is_same_path(config_file, primary_config_path))
+        .collect();
+    config_files.sort();
+    config_files.dedup();
+
+    let mut configs = Vec::with_capacity(config_files.len());
+    for config_file in &config_files {
+        configs.push(get_package_config(config_file).await?);
+    }
+    Ok(configs)
 }
 
 /// Handles `Stop` hook events when a Claude session is terminating.
@@ -1874,25 +3551,61 @@ pub async fn handle_stop() -> Result<HookResult> {
         None
     };
 
-    // Extract and execute commands from config.stop.commands
-    let commands_with_messages = collect_stop_commands(config)?;
+    // Extract commands from config.stop.commands, followed by the stop commands of every
+    // package config whose subtree was edited during this session
+    let mut stop_targets = vec![(
+        collect_stop_commands(config)?,
+        config_dir.to_path_buf(),
+        StopRunOptions::from_config(&config.stop),
+    )];
+    for (package_config, package_config_path) in
+        edited_package_configs(&payload.base.session_id, config_path).await?
+    {
+        let package_commands = collect_stop_commands(package_config)?;
+        if !package_commands.is_empty() {
+            println!(
+                "Running stop commands for edited package: {}",
+                package_config_path.display()
+            );
+            stop_targets.push((
+                package_commands,
+                get_config_dir(package_config_path).to_path_buf(),
+                StopRunOptions::from_config(&package_config.stop),
+            ));
+        }
+    }
 
     // Execute commands
-    if let Some(result) = execute_stop_commands(&commands_with_messages, config_dir).await? {
-        // Send notification for blocked/failed stop hook
-        send_notification(
-            "Stop",
-            "failure",
-            Some(
-                &result
-                    .message
-                    .clone()
-                    .unwrap_or_else(|| "Hook blocked".to_string()),
-            ),
-        );
-        return Ok(result);
+    let env_vars = build_stop_env_vars(&payload, config_dir);
+    let mut feedback = CommandFeedback::default();
+    for (commands_with_messages, commands_dir, options) in &stop_targets {
+        if let Some(result) = execute_stop_commands(
+            commands_with_messages,
+            &env_vars,
+            commands_dir,
+            *options,
+            &mut feedback,
+        )
+        .await?
+        {
+            // Send notification for blocked/failed stop hook
+            send_notification(
+                "Stop",
+                "failure",
+                Some(
+                    &result
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| "Hook blocked".to_string()),
+                ),
+            );
+            return Ok(result);
+        }
     }
 
+    // Every edited package passed its checks, so later stops start from a clean slate
+    let _ = fs::remove_file(get_edited_files_path(&payload.base.session_id));
+
     // Check root additions if enabled
     if let Some(snapshot) = root_snapshot {
         if let Some(result) = check_root_additions(&snapshot)? {
@@ -1931,37 +3644,14 @@ pub async fn handle_stop() -> Result<HookResult> {
 
     // Send notification for successful stop hook completion
     send_notification("Stop", "success", None);
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Collect stop failure commands from config into `StopCommandConfig` list.
 pub(crate) fn collect_stop_failure_commands(
     config: &ConclaudeConfig,
 ) -> Result<Vec<StopCommandConfig>> {
-    let mut commands = Vec::new();
-
-    for cmd_config in &config.stop_failure.commands {
-        let extracted = extract_bash_commands(&cmd_config.run)?;
-        let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-        let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-        let show_command = cmd_config.show_command.unwrap_or(true);
-        let max_output_lines = cmd_config.max_output_lines;
-        let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
-        for cmd in extracted {
-            commands.push(StopCommandConfig {
-                command: cmd,
-                message: cmd_config.message.clone(),
-                show_stdout,
-                show_stderr,
-                max_output_lines,
-                timeout: cmd_config.timeout,
-                show_command,
-                notify_per_command,
-            });
-        }
-    }
-
-    Ok(commands)
+    collect_stop_command_entries(&config.stop_failure.commands)
 }
 
 /// Handles `StopFailure` hook events when a turn ends due to an API error.
@@ -1982,9 +3672,6 @@ pub async fn handle_stop_failure() -> Result<HookResult> {
         std::env::set_var("CONCLAUDE_AGENT_NAME", name);
     }
 
-    // Set error-specific environment variables
-    std::env::set_var("CONCLAUDE_STOP_ERROR", &payload.error);
-
     println!(
         "Processing StopFailure hook: session_id={}, error={}",
         payload.base.session_id, payload.error
@@ -1996,7 +3683,17 @@ pub async fn handle_stop_failure() -> Result<HookResult> {
     // Collect and execute commands from config.stop_failure.commands
     let commands_with_messages = collect_stop_failure_commands(config)?;
 
-    if let Some(result) = execute_stop_commands(&commands_with_messages, config_dir).await? {
+    let env_vars = build_stop_failure_env_vars(&payload, config_dir);
+    let mut feedback = CommandFeedback::default();
+    if let Some(result) = execute_stop_commands(
+        &commands_with_messages,
+        &env_vars,
+        config_dir,
+        StopRunOptions::default(),
+        &mut feedback,
+    )
+    .await?
+    {
         send_notification(
             "StopFailure",
             "failure",
@@ -2016,7 +3713,7 @@ pub async fn handle_stop_failure() -> Result<HookResult> {
         Some(&format!("API error: {}", payload.error)),
     );
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `SubagentStart` hook events when Claude subagents begin execution.
@@ -2054,6 +3751,7 @@ pub async fn handle_subagent_start() -> Result<HookResult> {
     );
 
     // Load configuration and execute skill start commands if configured
+    let mut feedback = CommandFeedback::default();
     let config_result = get_config().await;
     if let Ok((config, config_path)) = config_result {
         let config_dir = get_config_dir(config_path);
@@ -2080,8 +3778,13 @@ pub async fn handle_subagent_start() -> Result<HookResult> {
                     let env_vars = build_skill_start_env_vars(&payload, config_dir);
 
                     // Execute skill start commands
-                    if let Err(e) =
-                        execute_skill_start_commands(&commands, &env_vars, config_dir).await
+                    if let Err(e) = execute_skill_start_commands(
+                        &commands,
+                        &env_vars,
+                        config_dir,
+                        &mut feedback,
+                    )
+                    .await
                     {
                         eprintln!("Error executing skill start commands: {}", e);
                     }
@@ -2096,7 +3799,7 @@ pub async fn handle_subagent_start() -> Result<HookResult> {
         "success",
         Some(&format!("Subagent '{}' started", payload.agent_id)),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Match agent_id against configured patterns in SubagentStopConfig
@@ -2221,12 +3924,12 @@ pub(crate) fn collect_subagent_stop_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
-                let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-                let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-                let show_command = cmd_config.show_command.unwrap_or(true);
-                let max_output_lines = cmd_config.max_output_lines;
-                let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
+                let show_stdout = cmd_config.launch.show_stdout();
+                let show_stderr = cmd_config.launch.show_stderr();
+                let show_command = cmd_config.launch.show_command();
+                let max_output_lines = cmd_config.launch.max_output_lines;
+                let notify_per_command = cmd_config.launch.notify_per_command();
 
                 for cmd in extracted {
                     commands.push(SubagentStopCommandConfig {
@@ -2235,9 +3938,13 @@ pub(crate) fn collect_subagent_stop_commands(
                         show_stdout,
                         show_stderr,
                         max_output_lines,
-                        timeout: cmd_config.timeout,
+                        timeout: cmd_config.launch.timeout,
                         show_command,
                         notify_per_command,
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
+                        parser: cmd_config.parser,
+                        report_file: cmd_config.report_file.clone(),
+                        fix: cmd_config.fix.clone(),
                     });
                 }
             }
@@ -2249,293 +3956,183 @@ pub(crate) fn collect_subagent_stop_commands(
 
 /// Execute subagent stop hook commands with environment variables
 ///
+/// Failed commands are logged, and those whose failure action is `block` are returned in
+/// order; warnings and context go to `feedback`. Failures are ignored unless a command's
+/// `onFailure` or `exitCodes` says otherwise. With `fail_fast`, execution stops at the
+/// first blocking failure; otherwise every command runs.
+///
 /// # Errors
 ///
-/// Returns an error if command spawning fails. Individual command failures are logged
-/// but do not stop subsequent command execution.
-async fn execute_subagent_stop_commands(
-    commands: &[SubagentStopCommandConfig],
-    env_vars: &HashMap<String, String>,
-    config_dir: &Path,
-) -> Result<()> {
-    if commands.is_empty() {
-        return Ok(());
-    }
-
-    println!("Executing {} subagent stop hook commands", commands.len());
-
-    for (index, cmd_config) in commands.iter().enumerate() {
-        if cmd_config.show_command {
-            println!(
-                "Executing subagent stop command {}/{}: {}",
-                index + 1,
-                commands.len(),
-                cmd_config.command
-            );
-        } else {
-            println!(
-                "Executing subagent stop command {}/{}",
-                index + 1,
-                commands.len()
-            );
-        }
-
-        // Send start notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification("SubagentStop", "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env_vars)
-            .current_dir(config_dir)
-            .spawn();
-
-        let child = match child {
-            Ok(c) => c,
-            Err(e) => {
-                // Log error but continue to next command
-                if cmd_config.show_command {
-                    eprintln!(
-                        "Failed to spawn subagent stop command '{}': {}",
-                        cmd_config.command, e
-                    );
-                } else {
-                    eprintln!("Failed to spawn subagent stop command: {}", e);
-                }
-
-                // Send failure notification if per-command notifications are enabled
-                if cmd_config.notify_per_command {
-                    let context_msg = if cmd_config.show_command {
-                        format!("Failed to spawn command: {}", cmd_config.command)
-                    } else {
-                        "Failed to spawn command".to_string()
-                    };
-                    send_notification("SubagentStop", "failure", Some(&context_msg));
-                }
-
-                continue;
-            }
-        };
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(result) => match result {
-                    Ok(o) => o,
-                    Err(e) => {
-                        // Log error but continue to next command
-                        if cmd_config.show_command {
-                            eprintln!(
-                                "Failed to wait for subagent stop command '{}': {}",
-                                cmd_config.command, e
-                            );
-                        } else {
-                            eprintln!("Failed to wait for subagent stop command: {}", e);
-                        }
-
-                        // Send failure notification if per-command notifications are enabled
-                        if cmd_config.notify_per_command {
-                            let context_msg = if cmd_config.show_command {
-                                format!("Command failed to wait: {}", cmd_config.command)
-                            } else {
-                                "Command failed to wait".to_string()
-                            };
-                            send_notification("SubagentStop", "failure", Some(&context_msg));
-                        }
-
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    // Timeout occurred - log and continue
-                    // Note: child is consumed by wait_with_output, so we can't kill it here
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Subagent stop command timed out after {} seconds: {}",
-                            timeout_secs, cmd_config.command
-                        );
-                    } else {
-                        eprintln!(
-                            "Subagent stop command timed out after {} seconds",
-                            timeout_secs
-                        );
-                    }
-                    if let Some(custom_msg) = &cmd_config.message {
-                        eprintln!("Message: {}", custom_msg);
-                    }
-
-                    // Send failure notification if per-command notifications are enabled
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command timed out: {}", cmd_config.command)
-                        } else {
-                            "Command timed out".to_string()
-                        };
-                        send_notification("SubagentStop", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        } else {
-            match child.wait_with_output().await {
-                Ok(o) => o,
-                Err(e) => {
-                    // Log error but continue to next command
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Failed to wait for subagent stop command '{}': {}",
-                            cmd_config.command, e
-                        );
-                    } else {
-                        eprintln!("Failed to wait for subagent stop command: {}", e);
-                    }
-
-                    // Send failure notification if per-command notifications are enabled
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command failed to wait: {}", cmd_config.command)
-                        } else {
-                            "Command failed to wait".to_string()
-                        };
-                        send_notification("SubagentStop", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
-
-            // Log failure information
-            // Respect showCommand flag when displaying command
-            let mut diagnostic = if cmd_config.show_command {
-                format!(
-                    "Subagent stop command failed:\n  Command: {}\n  Status: Failed (exit code: {})",
-                    cmd_config.command, exit_code
-                )
-            } else {
-                format!(
-                    "Subagent stop command failed:\n  Status: Failed (exit code: {})",
-                    exit_code
-                )
-            };
-
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                let stdout_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stdout.trim().to_string()
-                };
-                let stdout_display = stdout_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stdout:\n{}", stdout_display));
-            }
-
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                let stderr_content = if let Some(max_lines) = cmd_config.max_output_lines {
-                    let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                    if is_truncated {
-                        format!("{}\n... ({} lines omitted)", truncated, omitted)
-                    } else {
-                        truncated
-                    }
-                } else {
-                    stderr.trim().to_string()
-                };
-                let stderr_display = stderr_content
-                    .lines()
-                    .map(|line| format!("    {}", line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                diagnostic.push_str(&format!("\n  Stderr:\n{}", stderr_display));
-            }
+/// Individual command failures, including commands that cannot be spawned, are returned
+/// as failures rather than errors.
+pub(crate) async fn execute_subagent_stop_commands(
+    commands: &[SubagentStopCommandConfig],
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+    fail_fast: bool,
+    feedback: &mut CommandFeedback,
+) -> Result<Vec<CommandFailure>> {
+    let mut failures = Vec::new();
+    if commands.is_empty() {
+        return Ok(failures);
+    }
 
-            eprintln!("{}", diagnostic);
+    println!("Executing {} subagent stop hook commands", commands.len());
 
-            // If there's a custom message, print it
-            if let Some(custom_msg) = &cmd_config.message {
-                eprintln!("Message: {}", custom_msg);
+    for (index, cmd_config) in commands.iter().enumerate() {
+        let failure = match run_subagent_stop_command(
+            cmd_config,
+            index,
+            commands.len(),
+            env_vars,
+            config_dir,
+        )
+        .await
+        {
+            CheckOutcome::Passed => continue,
+            CheckOutcome::Fixed(report) => {
+                feedback.context.push(report);
+                continue;
             }
-
-            // Send failure notification if per-command notifications are enabled
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!("Command failed: {}", cmd_config.command)
-                } else {
-                    "Command failed".to_string()
-                };
-                send_notification("SubagentStop", "failure", Some(&context_msg));
+            CheckOutcome::Failed(failure) => failure,
+        };
+        let action = cmd_config
+            .launch
+            .failure
+            .action(failure.exit_code, FailureAction::Ignore);
+        if let Some(failure) = feedback.route(action, failure) {
+            failures.push(failure);
+            if fail_fast {
+                break;
             }
-
-            // Continue to next command (graceful failure handling)
-            continue;
         }
+    }
 
-        // Successful command - show output if configured
-        if cmd_config.show_stdout && !stdout.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
-                } else {
-                    truncated
-                }
+    println!("All subagent stop hook commands completed");
+    Ok(failures)
+}
+
+/// Runs a single subagent stop command and returns its failure, or `None` on success
+///
+/// A command that cannot be spawned is reported as a failure.
+async fn run_subagent_stop_check(
+    cmd_config: &SubagentStopCommandConfig,
+    index: usize,
+    total: usize,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> Option<CommandFailure> {
+    if cmd_config.show_command {
+        println!(
+            "Executing subagent stop command {}/{}: {}",
+            index + 1,
+            total,
+            cmd_config.command
+        );
+    } else {
+        println!("Executing subagent stop command {}/{}", index + 1, total);
+    }
+
+    let report = cmd_config.report();
+    report.notify("SubagentStop", "running", "Running");
+    let report_file = cmd_config
+        .report_file
+        .as_deref()
+        .map(|file| ReportFile::before_run(file, &cmd_config.launch, config_dir));
+
+    let run = match run_with_retries(
+        &cmd_config.command,
+        &cmd_config.launch,
+        cmd_config.timeout,
+        env_vars,
+        config_dir,
+        None,
+        cmd_config.notify_per_command.then_some("SubagentStop"),
+    )
+    .await
+    {
+        Ok(run) => run,
+        Err(e) => {
+            // Log the error and report it as a failure
+            if cmd_config.show_command {
+                eprintln!("Failed to run subagent stop command: {e:#}");
             } else {
-                stdout.to_string()
-            };
-            println!("Stdout: {}", output_to_show);
-        }
+                eprintln!("Failed to run subagent stop command: {}", e.root_cause());
+            }
+
+            report.notify("SubagentStop", "failure", "Failed to run command");
 
-        if cmd_config.show_stderr && !stderr.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
+            let failure = CommandFailure {
+                summary: if cmd_config.show_command {
+                    format!(
+                        "Failed to run command: {}: {}",
+                        cmd_config.command,
+                        e.root_cause()
+                    )
                 } else {
-                    truncated
-                }
-            } else {
-                stderr.to_string()
+                    format!("Failed to run command: {}", e.root_cause())
+                },
+                message: cmd_config.message.clone(),
+                details: String::new(),
+                exit_code: None,
             };
-            eprintln!("Stderr: {}", output_to_show);
+            return Some(failure);
         }
+    };
+    report.notify_result("SubagentStop", &run);
+    if run.succeeded() {
+        report.log_output(&run);
+        return None;
+    }
 
-        // Send success notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Command completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification("SubagentStop", "success", Some(&context_msg));
+    let parser_input = report_file.and_then(|file| file.read_new());
+    let report = CommandReport {
+        parser_input: parser_input.as_deref(),
+        ..report
+    };
+    let (log, failure) = describe_failure("Subagent stop", report, &run);
+    eprintln!("{log}");
+    Some(failure)
+}
+
+/// Runs a subagent stop command, and when it fails, its `fix` and the command again
+async fn run_subagent_stop_command(
+    cmd_config: &SubagentStopCommandConfig,
+    index: usize,
+    total: usize,
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+) -> CheckOutcome {
+    let Some(failure) =
+        run_subagent_stop_check(cmd_config, index, total, env_vars, config_dir).await
+    else {
+        return CheckOutcome::Passed;
+    };
+    let Some(fix) = &cmd_config.fix else {
+        return CheckOutcome::Failed(failure);
+    };
+
+    let fix_report = run_fix(
+        fix,
+        &cmd_config.launch,
+        cmd_config.timeout,
+        env_vars,
+        config_dir,
+        cmd_config.show_command,
+    )
+    .await;
+    match run_subagent_stop_check(cmd_config, index, total, env_vars, config_dir).await {
+        None => CheckOutcome::Fixed(fixed_report(
+            "subagent stop",
+            &cmd_config.command,
+            cmd_config.show_command,
+            &fix_report,
+        )),
+        Some(mut failure) => {
+            failure.details.push_str(&format!("\n{fix_report}"));
+            CheckOutcome::Failed(failure)
         }
     }
-
-    println!("All subagent stop hook commands completed");
-    Ok(())
 }
 
 /// Match skill name against configured patterns in SkillStartConfig
@@ -2652,12 +4249,12 @@ pub(crate) fn collect_skill_start_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
-                let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-                let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-                let show_command = cmd_config.show_command.unwrap_or(true);
-                let max_output_lines = cmd_config.max_output_lines;
-                let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
+                let show_stdout = cmd_config.launch.show_stdout();
+                let show_stderr = cmd_config.launch.show_stderr();
+                let show_command = cmd_config.launch.show_command();
+                let max_output_lines = cmd_config.launch.max_output_lines;
+                let notify_per_command = cmd_config.launch.notify_per_command();
 
                 for cmd in extracted {
                     commands.push(SkillStartCommandConfig {
@@ -2665,9 +4262,10 @@ pub(crate) fn collect_skill_start_commands(
                         show_stdout,
                         show_stderr,
                         max_output_lines,
-                        timeout: cmd_config.timeout,
+                        timeout: cmd_config.launch.timeout,
                         show_command,
                         notify_per_command,
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -2679,6 +4277,9 @@ pub(crate) fn collect_skill_start_commands(
 
 /// Execute skill start hook commands with environment variables
 ///
+/// `SubagentStart` cannot be blocked, so failures are only logged, except that those whose
+/// action is `warn` or `context` also go to `feedback`.
+///
 /// # Errors
 ///
 /// Returns an error if command spawning fails. Individual command failures are logged
@@ -2687,6 +4288,7 @@ async fn execute_skill_start_commands(
     commands: &[SkillStartCommandConfig],
     env_vars: &HashMap<String, String>,
     config_dir: &Path,
+    feedback: &mut CommandFeedback,
 ) -> Result<()> {
     if commands.is_empty() {
         return Ok(());
@@ -2710,204 +4312,47 @@ async fn execute_skill_start_commands(
             );
         }
 
-        // Send start notification if per-command notifications are enabled
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification("SubagentStart", "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env_vars)
-            .current_dir(config_dir)
-            .spawn();
-
-        let child = match child {
-            Ok(c) => c,
+        let report = cmd_config.report();
+        report.notify("SubagentStart", "running", "Running");
+
+        let run = match run_with_retries(
+            &cmd_config.command,
+            &cmd_config.launch,
+            cmd_config.timeout,
+            env_vars,
+            config_dir,
+            None,
+            cmd_config.notify_per_command.then_some("SubagentStart"),
+        )
+        .await
+        {
+            Ok(run) => run,
             Err(e) => {
                 // Log error but continue to next command
                 if cmd_config.show_command {
-                    eprintln!(
-                        "Failed to spawn skill start command '{}': {}",
-                        cmd_config.command, e
-                    );
+                    eprintln!("Failed to run skill start command: {e:#}");
                 } else {
-                    eprintln!("Failed to spawn skill start command: {}", e);
+                    eprintln!("Failed to run skill start command: {}", e.root_cause());
                 }
 
-                if cmd_config.notify_per_command {
-                    let context_msg = if cmd_config.show_command {
-                        format!("Failed to spawn command: {}", cmd_config.command)
-                    } else {
-                        "Failed to spawn command".to_string()
-                    };
-                    send_notification("SubagentStart", "failure", Some(&context_msg));
-                }
+                report.notify("SubagentStart", "failure", "Failed to run command");
 
                 continue;
             }
         };
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(result) => match result {
-                    Ok(o) => o,
-                    Err(e) => {
-                        if cmd_config.show_command {
-                            eprintln!(
-                                "Failed to wait for skill start command '{}': {}",
-                                cmd_config.command, e
-                            );
-                        } else {
-                            eprintln!("Failed to wait for skill start command: {}", e);
-                        }
-
-                        if cmd_config.notify_per_command {
-                            let context_msg = if cmd_config.show_command {
-                                format!("Command failed to wait: {}", cmd_config.command)
-                            } else {
-                                "Command failed to wait".to_string()
-                            };
-                            send_notification("SubagentStart", "failure", Some(&context_msg));
-                        }
-
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Skill start command timed out after {} seconds: {}",
-                            timeout_secs, cmd_config.command
-                        );
-                    } else {
-                        eprintln!(
-                            "Skill start command timed out after {} seconds",
-                            timeout_secs
-                        );
-                    }
-
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command timed out: {}", cmd_config.command)
-                        } else {
-                            "Command timed out".to_string()
-                        };
-                        send_notification("SubagentStart", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        } else {
-            match child.wait_with_output().await {
-                Ok(o) => o,
-                Err(e) => {
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Failed to wait for skill start command '{}': {}",
-                            cmd_config.command, e
-                        );
-                    } else {
-                        eprintln!("Failed to wait for skill start command: {}", e);
-                    }
-
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command failed to wait: {}", cmd_config.command)
-                        } else {
-                            "Command failed to wait".to_string()
-                        };
-                        send_notification("SubagentStart", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
-
-            if cmd_config.show_command {
-                eprintln!(
-                    "Skill start command failed (exit code: {}): {}",
-                    exit_code, cmd_config.command
-                );
-            } else {
-                eprintln!("Skill start command failed (exit code: {})", exit_code);
-            }
-
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                eprintln!("Stdout: {}", stdout.trim());
-            }
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                eprintln!("Stderr: {}", stderr.trim());
-            }
-
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!(
-                        "Command failed (exit code: {}): {}",
-                        exit_code, cmd_config.command
-                    )
-                } else {
-                    format!("Command failed (exit code: {})", exit_code)
-                };
-                send_notification("SubagentStart", "failure", Some(&context_msg));
-            }
-
+        report.notify_result("SubagentStart", &run);
+        if run.succeeded() {
+            report.log_output(&run);
             continue;
         }
 
-        // Successful command - show output if configured
-        if cmd_config.show_stdout && !stdout.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
-                } else {
-                    truncated
-                }
-            } else {
-                stdout.to_string()
-            };
-            println!("Stdout: {}", output_to_show);
-        }
-
-        if cmd_config.show_stderr && !stderr.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
-                } else {
-                    truncated
-                }
-            } else {
-                stderr.to_string()
-            };
-            eprintln!("Stderr: {}", output_to_show);
-        }
-
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Command completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification("SubagentStart", "success", Some(&context_msg));
-        }
+        let (log, failure) = describe_failure("Skill start", report, &run);
+        eprintln!("{log}");
+        let action = cmd_config
+            .launch
+            .failure
+            .action(run.exit_code(), FailureAction::Ignore);
+        feedback.route(action, failure);
     }
 
     println!("All skill start hook commands completed");
@@ -2966,6 +4411,7 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
     // Load configuration
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     // Check if subagentStop commands are configured
     if !config.subagent_stop.commands.is_empty() {
@@ -2986,8 +4432,29 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
                 // Build environment variables
                 let env_vars = build_subagent_env_vars(&payload, config_dir, agent_name.as_deref());
 
-                // Execute commands (graceful failure handling)
-                execute_subagent_stop_commands(&commands, &env_vars, config_dir).await?;
+                let fail_fast = config.subagent_stop.fail_fast;
+                let failures = execute_subagent_stop_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    fail_fast,
+                    &mut feedback,
+                )
+                .await?;
+
+                if let Some(first) = failures.first() {
+                    let message = if fail_fast {
+                        first.block_message()
+                    } else {
+                        failure_report("subagent stop", commands.len(), &failures, &[])
+                    };
+                    send_notification(
+                        "SubagentStop",
+                        "failure",
+                        Some(&format!("Subagent '{}' blocked", payload.agent_id)),
+                    );
+                    return Ok(HookResult::blocked(message));
+                }
             }
         } else {
             println!(
@@ -3004,7 +4471,7 @@ pub async fn handle_subagent_stop() -> Result<HookResult> {
         Some(&format!("Subagent '{}' completed", payload.agent_id)),
     );
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Match slash command against configured patterns in SlashCommandConfig
@@ -3104,289 +4571,121 @@ pub(crate) fn collect_slash_command_entries(
 
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
-            for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
-                let show_stdout = cmd_config.show_stdout.unwrap_or(false);
-                let show_stderr = cmd_config.show_stderr.unwrap_or(false);
-                let show_command = cmd_config.show_command.unwrap_or(true);
-                let max_output_lines = cmd_config.max_output_lines;
-                let notify_per_command = cmd_config.notify_per_command.unwrap_or(false);
-
-                for cmd in extracted {
-                    commands.push(SlashCommandEntryConfig {
-                        command: cmd,
-                        show_stdout,
-                        show_stderr,
-                        max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command,
-                        notify_per_command,
-                    });
-                }
-            }
-        }
-    }
-
-    Ok(commands)
-}
-
-/// Execute slash command hooks with environment variables
-///
-/// Returns Ok(Some(message)) if a command blocked (exit code 2),
-/// Ok(None) if all commands succeeded, or Err on execution failure.
-async fn execute_slash_command_hooks(
-    commands: &[SlashCommandEntryConfig],
-    env_vars: &HashMap<String, String>,
-    config_dir: &Path,
-) -> Result<Option<String>> {
-    if commands.is_empty() {
-        return Ok(None);
-    }
-
-    println!("Executing {} slash command hook(s)", commands.len());
-
-    for (index, cmd_config) in commands.iter().enumerate() {
-        if cmd_config.show_command {
-            println!(
-                "Executing slash command hook {}/{}: {}",
-                index + 1,
-                commands.len(),
-                cmd_config.command
-            );
-        } else {
-            println!(
-                "Executing slash command hook {}/{}",
-                index + 1,
-                commands.len()
-            );
-        }
-
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification("UserPromptSubmit", "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env_vars)
-            .current_dir(config_dir)
-            .spawn();
-
-        let child = match child {
-            Ok(c) => c,
-            Err(e) => {
-                if cmd_config.show_command {
-                    eprintln!(
-                        "Failed to spawn slash command hook '{}': {}",
-                        cmd_config.command, e
-                    );
-                } else {
-                    eprintln!("Failed to spawn slash command hook: {}", e);
-                }
-
-                if cmd_config.notify_per_command {
-                    let context_msg = if cmd_config.show_command {
-                        format!("Failed to spawn: {}", cmd_config.command)
-                    } else {
-                        "Failed to spawn command".to_string()
-                    };
-                    send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                }
-
-                continue;
-            }
-        };
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(result) => match result {
-                    Ok(o) => o,
-                    Err(e) => {
-                        if cmd_config.show_command {
-                            eprintln!(
-                                "Failed to wait for slash command hook '{}': {}",
-                                cmd_config.command, e
-                            );
-                        } else {
-                            eprintln!("Failed to wait for slash command hook: {}", e);
-                        }
-
-                        if cmd_config.notify_per_command {
-                            let context_msg = if cmd_config.show_command {
-                                format!("Failed to wait: {}", cmd_config.command)
-                            } else {
-                                "Failed to wait for command".to_string()
-                            };
-                            send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                        }
-
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Slash command hook timed out after {} seconds: {}",
-                            timeout_secs, cmd_config.command
-                        );
-                    } else {
-                        eprintln!(
-                            "Slash command hook timed out after {} seconds",
-                            timeout_secs
-                        );
-                    }
-
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Command timed out: {}", cmd_config.command)
-                        } else {
-                            "Command timed out".to_string()
-                        };
-                        send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        } else {
-            match child.wait_with_output().await {
-                Ok(o) => o,
-                Err(e) => {
-                    if cmd_config.show_command {
-                        eprintln!(
-                            "Failed to wait for slash command hook '{}': {}",
-                            cmd_config.command, e
-                        );
-                    } else {
-                        eprintln!("Failed to wait for slash command hook: {}", e);
-                    }
-
-                    if cmd_config.notify_per_command {
-                        let context_msg = if cmd_config.show_command {
-                            format!("Failed to wait: {}", cmd_config.command)
-                        } else {
-                            "Failed to wait for command".to_string()
-                        };
-                        send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-                    }
-
-                    continue;
-                }
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        let exit_code = output.status.code().unwrap_or(1);
-
-        // Exit code 2 means block the operation
-        if exit_code == 2 {
-            let message = if cmd_config.show_command {
-                format!(
-                    "Slash command blocked by hook (exit code: {}): {}",
-                    exit_code, cmd_config.command
-                )
-            } else {
-                format!("Slash command blocked by hook (exit code: {})", exit_code)
-            };
-
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                eprintln!("Stdout: {}", stdout.trim());
-            }
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                eprintln!("Stderr: {}", stderr.trim());
-            }
-
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!("Command blocked: {}", cmd_config.command)
-                } else {
-                    "Command blocked".to_string()
-                };
-                send_notification("UserPromptSubmit", "blocked", Some(&context_msg));
-            }
+            for cmd_config in cmd_list {
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
+                let show_stdout = cmd_config.launch.show_stdout();
+                let show_stderr = cmd_config.launch.show_stderr();
+                let show_command = cmd_config.launch.show_command();
+                let max_output_lines = cmd_config.launch.max_output_lines;
+                let notify_per_command = cmd_config.launch.notify_per_command();
 
-            return Ok(Some(message));
+                for cmd in extracted {
+                    commands.push(SlashCommandEntryConfig {
+                        command: cmd,
+                        show_stdout,
+                        show_stderr,
+                        max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command,
+                        notify_per_command,
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
+                    });
+                }
+            }
         }
+    }
 
-        if !output.status.success() {
-            if cmd_config.show_command {
-                eprintln!(
-                    "Slash command hook failed (exit code: {}): {}",
-                    exit_code, cmd_config.command
-                );
-            } else {
-                eprintln!("Slash command hook failed (exit code: {})", exit_code);
-            }
+    Ok(commands)
+}
 
-            if cmd_config.show_stdout && !stdout.trim().is_empty() {
-                eprintln!("Stdout: {}", stdout.trim());
-            }
-            if cmd_config.show_stderr && !stderr.trim().is_empty() {
-                eprintln!("Stderr: {}", stderr.trim());
-            }
+/// Execute slash command hooks with environment variables
+///
+/// Returns Ok(Some(message)) if a command blocked (by default, exit code 2),
+/// Ok(None) otherwise, or Err on execution failure. Failures whose action is `warn` or
+/// `context` go to `feedback`.
+async fn execute_slash_command_hooks(
+    commands: &[SlashCommandEntryConfig],
+    env_vars: &HashMap<String, String>,
+    config_dir: &Path,
+    feedback: &mut CommandFeedback,
+) -> Result<Option<String>> {
+    if commands.is_empty() {
+        return Ok(None);
+    }
 
-            if cmd_config.notify_per_command {
-                let context_msg = if cmd_config.show_command {
-                    format!(
-                        "Command failed (exit code: {}): {}",
-                        exit_code, cmd_config.command
-                    )
-                } else {
-                    format!("Command failed (exit code: {})", exit_code)
-                };
-                send_notification("UserPromptSubmit", "failure", Some(&context_msg));
-            }
+    println!("Executing {} slash command hook(s)", commands.len());
 
-            continue;
+    for (index, cmd_config) in commands.iter().enumerate() {
+        if cmd_config.show_command {
+            println!(
+                "Executing slash command hook {}/{}: {}",
+                index + 1,
+                commands.len(),
+                cmd_config.command
+            );
+        } else {
+            println!(
+                "Executing slash command hook {}/{}",
+                index + 1,
+                commands.len()
+            );
         }
 
-        // Successful command - show output if configured
-        if cmd_config.show_stdout && !stdout.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stdout, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
+        let report = cmd_config.report();
+        report.notify("UserPromptSubmit", "running", "Running");
+
+        let run = match run_with_retries(
+            &cmd_config.command,
+            &cmd_config.launch,
+            cmd_config.timeout,
+            env_vars,
+            config_dir,
+            Some(2),
+            cmd_config.notify_per_command.then_some("UserPromptSubmit"),
+        )
+        .await
+        {
+            Ok(run) => run,
+            Err(e) => {
+                if cmd_config.show_command {
+                    eprintln!("Failed to run slash command hook: {e:#}");
                 } else {
-                    truncated
+                    eprintln!("Failed to run slash command hook: {}", e.root_cause());
                 }
-            } else {
-                stdout.to_string()
-            };
-            println!("Stdout: {}", output_to_show);
-        }
 
-        if cmd_config.show_stderr && !stderr.trim().is_empty() {
-            let output_to_show = if let Some(max_lines) = cmd_config.max_output_lines {
-                let (truncated, is_truncated, omitted) = truncate_output(&stderr, max_lines);
-                if is_truncated {
-                    format!("{}\n... ({} lines omitted)", truncated, omitted)
-                } else {
-                    truncated
-                }
-            } else {
-                stderr.to_string()
-            };
-            eprintln!("Stderr: {}", output_to_show);
-        }
+                report.notify("UserPromptSubmit", "failure", "Failed to run command");
 
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Command completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification("UserPromptSubmit", "success", Some(&context_msg));
+                continue;
+            }
+        };
+        if run.succeeded() {
+            report.notify_result("UserPromptSubmit", &run);
+            report.log_output(&run);
+            continue;
         }
+
+        let (log, failure) = describe_failure("Slash command", report, &run);
+        eprintln!("{log}");
+
+        // Exit code 2 means block the operation unless the command's policy says otherwise
+        let exit_code = run.exit_code();
+        let default_action = if exit_code == Some(2) {
+            FailureAction::Block
+        } else {
+            FailureAction::Ignore
+        };
+        let action = cmd_config.launch.failure.action(exit_code, default_action);
+        let Some(failure) = feedback.route(action, failure) else {
+            report.notify_result("UserPromptSubmit", &run);
+            continue;
+        };
+        let Some(exit_code) = exit_code else {
+            report.notify_result("UserPromptSubmit", &run);
+            return Ok(Some(failure.block_message()));
+        };
+        report.notify("UserPromptSubmit", "blocked", "Command blocked");
+        let message = format!("Slash command blocked by hook (exit code: {exit_code})");
+        return Ok(Some(report.labelled(&message)));
     }
 
     println!("All slash command hooks completed");
@@ -3432,7 +4731,13 @@ pub async fn handle_pre_compact() -> Result<HookResult> {
 ///
 /// Returns an error if configuration loading fails or glob pattern creation fails.
 async fn check_tool_usage_rules(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
-    let (config, _config_path) = get_config().await?;
+    // File-based tools are checked against the config that owns the target file
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let file_path = extract_file_path(&payload.tool_input);
+    let (config, config_path) = match &file_path {
+        Some(path) => get_config_for_path(&cwd.join(path)).await?,
+        None => get_config().await?,
+    };
 
     // Detect current agent context from environment variable (set by CLI --agent flag)
     let current_agent = std::env::var(AGENT_ENV_VAR).unwrap_or_else(|_| "main".to_string());
@@ -3445,10 +4750,13 @@ async fn check_tool_usage_rules(payload: &PreToolUsePayload) -> Result<Option<Ho
                 continue;
             }
             // Check if this is a Bash command with a commandPattern rule
-            if payload.tool_name == "Bash" && rule.command_pattern.is_some() {
+            if let Some(pattern) = rule
+                .command_pattern
+                .as_ref()
+                .filter(|_| payload.tool_name == "Bash")
+            {
                 // Extract the command
                 if let Some(command) = extract_bash_command(&payload.tool_input) {
-                    let pattern = rule.command_pattern.as_ref().unwrap();
                     let mode = rule.match_mode.as_deref().unwrap_or("full");
 
                     // Perform pattern matching based on mode
@@ -3491,9 +4799,12 @@ async fn check_tool_usage_rules(payload: &PreToolUsePayload) -> Result<Option<Ho
                 continue;
             }
 
-            // Extract file path if available
-            if let Some(file_path) = extract_file_path(&payload.tool_input) {
-                let matches = Pattern::new(&rule.pattern)?.matches(&file_path);
+            // Match the file path as given and relative to the owning config directory
+            if let Some(file_path) = &file_path {
+                let relative_path =
+                    path_relative_to_config(&cwd.join(file_path), config_path, &cwd);
+                let glob = Pattern::new(&rule.pattern)?;
+                let matches = glob.matches(file_path) || glob.matches(&relative_path);
 
                 if (rule.action == "block" && matches) || (rule.action == "allow" && !matches) {
                     let message = rule.message.clone().unwrap_or_else(|| {
@@ -3524,19 +4835,22 @@ async fn check_tool_usage_rules(payload: &PreToolUsePayload) -> Result<Option<Ho
 ///
 /// Returns an error if configuration loading fails or gitignore check fails.
 async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<HookResult>> {
-    let (config, config_path) = get_config().await?;
-
-    // Only check if the feature is enabled
-    if !config.pre_tool_use.prevent_update_git_ignored {
-        return Ok(None);
-    }
-
     // Extract file path from tool input
     let file_path = extract_file_path(&payload.tool_input);
     let Some(file_path) = file_path else {
         return Ok(None);
     };
 
+    // Resolve the file path to check
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let resolved_path = cwd.join(&file_path);
+    let (config, config_path) = get_config_for_path(&resolved_path).await?;
+
+    // Only check if the feature is enabled
+    if !config.pre_tool_use.prevent_update_git_ignored {
+        return Ok(None);
+    }
+
     // Find the actual git repository root by walking up from config path
     // This is more reliable than just using config path's parent
     let config_dir = get_config_dir(config_path);
@@ -3548,10 +4862,6 @@ async fn check_git_ignored_file(payload: &PreToolUsePayload) -> Result<Option<Ho
         }
     };
 
-    // Resolve the file path to check
-    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
-    let resolved_path = cwd.join(&file_path);
-
     // Check if the file is git-ignored
     let (is_ignored, pattern) = is_path_git_ignored(&resolved_path, &repo_root)?;
 
@@ -3644,6 +4954,26 @@ pub(crate) struct GenericCommandConfig {
     pub timeout: Option<u64>,
     pub show_command: bool,
     pub notify_per_command: bool,
+    pub launch: CommandLaunch,
+}
+
+impl GenericCommandConfig {
+    /// How the command's result is reported
+    pub(crate) fn report(&self) -> CommandReport<'_> {
+        CommandReport {
+            command: &self.command,
+            message: self.message.as_deref(),
+            parser: None,
+            parser_input: None,
+            show_command: self.show_command,
+            show_stdout: self.show_stdout,
+            show_stderr: self.show_stderr,
+            max_output_lines: self.max_output_lines,
+            timeout: self.timeout,
+            notify_per_command: self.notify_per_command,
+            output: &self.launch.output,
+        }
+    }
 }
 
 /// Generic pattern matching function that works with any HashMap<String, Vec<T>> config.
@@ -3661,9 +4991,8 @@ pub(crate) fn match_generic_patterns<'a, T>(
             continue;
         }
 
-        let pattern = Pattern::new(pattern_str).with_context(|| {
-            format!("Invalid glob pattern in config: {}", pattern_str)
-        })?;
+        let pattern = Pattern::new(pattern_str)
+            .with_context(|| format!("Invalid glob pattern in config: {}", pattern_str))?;
 
         if pattern.matches(value) {
             other_matches.push(pattern_str.as_str());
@@ -3686,17 +5015,18 @@ fn collect_teammate_idle_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
                 for cmd in extracted {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -3714,17 +5044,18 @@ fn collect_task_completed_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
                 for cmd in extracted {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -3742,17 +5073,18 @@ fn collect_config_change_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
                 for cmd in extracted {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -3870,12 +5202,17 @@ fn build_config_change_env_vars(
     env_vars
 }
 
-/// Execute commands for pattern-based hooks. Returns Some(blocked) if exit code 2.
-async fn execute_generic_commands(
+/// Execute commands for pattern-based hooks
+///
+/// Returns `Some(blocked)` for the first failure whose action is `block`, by default exit
+/// code 2. Other failures are logged, and those whose action is `warn` or `context` go to
+/// `feedback`.
+pub(crate) async fn execute_generic_commands(
     commands: &[GenericCommandConfig],
     env_vars: &HashMap<String, String>,
     config_dir: &Path,
     hook_name: &str,
+    feedback: &mut CommandFeedback,
 ) -> Result<Option<HookResult>> {
     if commands.is_empty() {
         return Ok(None);
@@ -3901,127 +5238,62 @@ async fn execute_generic_commands(
             );
         }
 
-        if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Running: {}", cmd_config.command)
-            } else {
-                "Running command".to_string()
-            };
-            send_notification(hook_name, "running", Some(&context_msg));
-        }
-
-        let child = TokioCommand::new("bash")
-            .arg("-c")
-            .arg(&cmd_config.command)
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env_vars)
-            .current_dir(config_dir)
-            .spawn();
-
-        let child = match child {
-            Ok(c) => c,
+        let report = cmd_config.report();
+        report.notify(hook_name, "running", "Running");
+
+        let run = match run_with_retries(
+            &cmd_config.command,
+            &cmd_config.launch,
+            cmd_config.timeout,
+            env_vars,
+            config_dir,
+            Some(2),
+            cmd_config.notify_per_command.then_some(hook_name),
+        )
+        .await
+        {
+            Ok(run) => run,
             Err(e) => {
-                eprintln!("Failed to spawn {} command: {}", hook_name, e);
+                eprintln!("Failed to run {} command: {:#}", hook_name, e);
                 continue;
             }
         };
-
-        let output = if let Some(timeout_secs) = cmd_config.timeout {
-            match timeout(Duration::from_secs(timeout_secs), child.wait_with_output()).await {
-                Ok(Ok(o)) => o,
-                Ok(Err(e)) => {
-                    eprintln!("Failed to wait for {} command: {}", hook_name, e);
-                    continue;
-                }
-                Err(_) => {
-                    eprintln!(
-                        "{} command timed out after {} seconds",
-                        hook_name, timeout_secs
-                    );
-                    continue;
-                }
-            }
-        } else {
-            match child.wait_with_output().await {
-                Ok(o) => o,
-                Err(e) => {
-                    eprintln!("Failed to wait for {} command: {}", hook_name, e);
-                    continue;
-                }
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        // Show output if configured
-        if cmd_config.show_stdout && !stdout.is_empty() {
-            let (truncated, is_truncated, omitted) =
-                if let Some(max_lines) = cmd_config.max_output_lines {
-                    truncate_output(&stdout, max_lines)
-                } else {
-                    (stdout.to_string(), false, 0)
-                };
-            println!("[stdout] {}", truncated);
-            if is_truncated {
-                println!("... ({} lines omitted)", omitted);
-            }
-        }
-
-        if cmd_config.show_stderr && !stderr.is_empty() {
-            let (truncated, is_truncated, omitted) =
-                if let Some(max_lines) = cmd_config.max_output_lines {
-                    truncate_output(&stderr, max_lines)
-                } else {
-                    (stderr.to_string(), false, 0)
-                };
-            eprintln!("[stderr] {}", truncated);
-            if is_truncated {
-                eprintln!("... ({} lines omitted)", omitted);
-            }
+        if run.succeeded() {
+            report.notify_result(hook_name, &run);
+            report.log_output(&run);
+            continue;
         }
 
-        if !output.status.success() {
-            let exit_code = output.status.code().unwrap_or(1);
-
-            // Exit code 2 means "block this operation"
-            if exit_code == 2 {
-                let block_msg = cmd_config.message.clone().unwrap_or_else(|| {
-                    format!("{} hook blocked by command", hook_name)
-                });
-                println!("{} hook BLOCKED: {}", hook_name, block_msg);
-
-                if cmd_config.notify_per_command {
-                    send_notification(hook_name, "blocked", Some(&block_msg));
-                }
-
-                return Ok(Some(HookResult::blocked(block_msg)));
-            }
+        let (log, failure) = describe_failure(hook_name, report, &run);
+        eprintln!("{log}");
 
-            // Other non-zero exits are logged but don't block
-            if let Some(ref custom_msg) = cmd_config.message {
-                eprintln!(
-                    "{} command failed (exit code {}): {}",
-                    hook_name, exit_code, custom_msg
-                );
-            } else {
-                eprintln!("{} command failed (exit code {})", hook_name, exit_code);
-            }
-        } else if cmd_config.notify_per_command {
-            let context_msg = if cmd_config.show_command {
-                format!("Completed: {}", cmd_config.command)
-            } else {
-                "Command completed".to_string()
-            };
-            send_notification(hook_name, "success", Some(&context_msg));
+        // Exit code 2 means "block this operation" unless the command's policy says otherwise
+        let default_action = if run.exit_code() == Some(2) {
+            FailureAction::Block
+        } else {
+            FailureAction::Ignore
+        };
+        let action = cmd_config
+            .launch
+            .failure
+            .action(run.exit_code(), default_action);
+        let blocked = feedback.route(action, failure);
+        report.notify_result(hook_name, &run);
+        if let Some(failure) = blocked {
+            return Ok(Some(generic_block(&failure, hook_name)));
         }
     }
 
     Ok(None)
 }
 
+/// The result of a generic hook blocked by `failure`, with the output its command shows
+fn generic_block(failure: &CommandFailure, hook_name: &str) -> HookResult {
+    let block_msg = failure.block_message();
+    println!("{} hook BLOCKED: {}", hook_name, block_msg);
+    HookResult::blocked(block_msg)
+}
+
 /// Handles `PostToolUseFailure` hook events when a tool execution fails.
 /// This is an observational hook - it cannot block operations.
 ///
@@ -4101,7 +5373,7 @@ pub async fn handle_worktree_remove() -> Result<HookResult> {
 }
 
 /// Handles `TeammateIdle` hook events when a teammate agent becomes idle.
-/// Can block the idle if configured commands exit with code 2.
+/// Can block the idle if a configured command exits with code 2, or fails with a `block` action.
 ///
 /// # Errors
 ///
@@ -4126,6 +5398,7 @@ pub async fn handle_teammate_idle() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.teammate_idle.commands.is_empty() {
         let matching_patterns =
@@ -4142,9 +5415,14 @@ pub async fn handle_teammate_idle() -> Result<HookResult> {
 
             if !commands.is_empty() {
                 let env_vars = build_teammate_idle_env_vars(&payload, config_dir);
-                let result =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "TeammateIdle")
-                        .await?;
+                let result = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "TeammateIdle",
+                    &mut feedback,
+                )
+                .await?;
                 if let Some(blocked_result) = result {
                     return Ok(blocked_result);
                 }
@@ -4163,11 +5441,11 @@ pub async fn handle_teammate_idle() -> Result<HookResult> {
         Some(&format!("Teammate '{}' idle", payload.teammate_name)),
     );
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `TaskCompleted` hook events when a task is completed.
-/// Can block completion if configured commands exit with code 2.
+/// Can block completion if a configured command exits with code 2, or fails with a `block` action.
 ///
 /// # Errors
 ///
@@ -4196,6 +5474,7 @@ pub async fn handle_task_completed() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.task_completed.commands.is_empty() {
         let matching_patterns =
@@ -4212,9 +5491,14 @@ pub async fn handle_task_completed() -> Result<HookResult> {
 
             if !commands.is_empty() {
                 let env_vars = build_task_completed_env_vars(&payload, config_dir);
-                let result =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "TaskCompleted")
-                        .await?;
+                let result = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "TaskCompleted",
+                    &mut feedback,
+                )
+                .await?;
                 if let Some(blocked_result) = result {
                     return Ok(blocked_result);
                 }
@@ -4233,7 +5517,7 @@ pub async fn handle_task_completed() -> Result<HookResult> {
         Some(&format!("Task '{}' completed", payload.task_subject)),
     );
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `ConfigChange` hook events when configuration changes.
@@ -4265,8 +5549,13 @@ pub async fn handle_config_change() -> Result<HookResult> {
         payload.file_path.as_deref().unwrap_or(""),
     );
 
+    let rejected_reload = match payload.file_path.as_deref() {
+        Some(file_path) => reload_if_config_changed(file_path).await,
+        None => None,
+    };
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.config_change.commands.is_empty() {
         let matching_patterns =
@@ -4286,14 +5575,24 @@ pub async fn handle_config_change() -> Result<HookResult> {
 
                 // Policy settings cannot be blocked
                 if payload.source == ConfigChangeSource::PolicySettings {
-                    // Execute commands but ignore exit code 2 (don't block)
-                    let _ =
-                        execute_generic_commands(&commands, &env_vars, config_dir, "ConfigChange")
-                            .await;
+                    // Execute commands but ignore blocking failures
+                    let _ = execute_generic_commands(
+                        &commands,
+                        &env_vars,
+                        config_dir,
+                        "ConfigChange",
+                        &mut feedback,
+                    )
+                    .await;
                 } else {
-                    let result =
-                        execute_generic_commands(&commands, &env_vars, config_dir, "ConfigChange")
-                            .await?;
+                    let result = execute_generic_commands(
+                        &commands,
+                        &env_vars,
+                        config_dir,
+                        "ConfigChange",
+                        &mut feedback,
+                    )
+                    .await?;
                     if let Some(blocked_result) = result {
                         return Ok(blocked_result);
                     }
@@ -4308,7 +5607,10 @@ pub async fn handle_config_change() -> Result<HookResult> {
         Some(&format!("Config changed: source={}", source_str)),
     );
 
-    Ok(HookResult::success())
+    if let Some(rejected) = rejected_reload {
+        feedback.context.insert(0, rejected);
+    }
+    Ok(feedback.into_result())
 }
 
 /// Handles `Setup` hook events fired during Claude Code initialization.
@@ -4335,10 +5637,10 @@ pub async fn handle_setup() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.setup.commands.is_empty() {
-        let matching_patterns =
-            match_generic_patterns(&payload.trigger, &config.setup.commands)?;
+        let matching_patterns = match_generic_patterns(&payload.trigger, &config.setup.commands)?;
 
         if !matching_patterns.is_empty() {
             println!(
@@ -4351,8 +5653,14 @@ pub async fn handle_setup() -> Result<HookResult> {
             if !commands.is_empty() {
                 let env_vars = build_setup_env_vars(&payload, config_dir);
 
-                let result =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "Setup").await?;
+                let result = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "Setup",
+                    &mut feedback,
+                )
+                .await?;
                 if let Some(blocked_result) = result {
                     let context = blocked_result.message.as_deref().unwrap_or("Setup blocked");
                     send_notification("Setup", "failure", Some(context));
@@ -4368,11 +5676,11 @@ pub async fn handle_setup() -> Result<HookResult> {
         Some(&format!("Setup completed: trigger={}", payload.trigger)),
     );
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Collect commands from SetupConfig for matching patterns
-fn collect_setup_commands(
+pub(crate) fn collect_setup_commands(
     config: &SetupConfig,
     matching_patterns: &[&str],
 ) -> Result<Vec<GenericCommandConfig>> {
@@ -4380,17 +5688,18 @@ fn collect_setup_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                let extracted = extract_bash_commands(&cmd_config.run)?;
+                let extracted = split_run_commands(&cmd_config.run, cmd_config.launch.mode())?;
                 for cmd in extracted {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4414,10 +5723,7 @@ fn build_setup_env_vars(payload: &SetupPayload, config_dir: &Path) -> HashMap<St
         "CONCLAUDE_TRANSCRIPT_PATH".to_string(),
         payload.base.transcript_path.clone(),
     );
-    env_vars.insert(
-        "CONCLAUDE_HOOK_EVENT".to_string(),
-        "Setup".to_string(),
-    );
+    env_vars.insert("CONCLAUDE_HOOK_EVENT".to_string(), "Setup".to_string());
     env_vars.insert("CONCLAUDE_CWD".to_string(), payload.base.cwd.clone());
     env_vars.insert(
         "CONCLAUDE_CONFIG_DIR".to_string(),
@@ -4462,16 +5768,17 @@ fn collect_post_compact_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4489,16 +5796,17 @@ fn collect_cwd_changed_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4516,16 +5824,17 @@ fn collect_file_changed_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4543,16 +5852,17 @@ fn collect_instructions_loaded_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4586,13 +5896,13 @@ pub async fn handle_post_compact() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.post_compact.commands.is_empty() {
         let matching_patterns =
             match_generic_patterns(&trigger_str, &config.post_compact.commands)?;
         if !matching_patterns.is_empty() {
-            let commands =
-                collect_post_compact_commands(&config.post_compact, &matching_patterns)?;
+            let commands = collect_post_compact_commands(&config.post_compact, &matching_patterns)?;
             if !commands.is_empty() {
                 let mut env_vars = HashMap::new();
                 env_vars.insert("CONCLAUDE_COMPACT_TRIGGER".to_string(), trigger_str.clone());
@@ -4608,8 +5918,14 @@ pub async fn handle_post_compact() -> Result<HookResult> {
                     config_dir,
                 );
                 // Observational: run commands but never block.
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "PostCompact").await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "PostCompact",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
@@ -4619,7 +5935,7 @@ pub async fn handle_post_compact() -> Result<HookResult> {
         "success",
         Some(&format!("Compaction completed: trigger={trigger_str}")),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `CwdChanged` hook events fired when the working directory changes.
@@ -4646,6 +5962,7 @@ pub async fn handle_cwd_changed() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.cwd_changed.commands.is_empty() {
         let matching_patterns =
@@ -4663,8 +5980,14 @@ pub async fn handle_cwd_changed() -> Result<HookResult> {
                     "CwdChanged",
                     config_dir,
                 );
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "CwdChanged").await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "CwdChanged",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
@@ -4674,7 +5997,7 @@ pub async fn handle_cwd_changed() -> Result<HookResult> {
         "success",
         Some(&format!("Working directory changed to {}", payload.new_cwd)),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `FileChanged` hook events fired when a watched file changes on disk.
@@ -4700,8 +6023,10 @@ pub async fn handle_file_changed() -> Result<HookResult> {
     std::env::set_var("CONCLAUDE_FILE_PATH", &payload.file_path);
     std::env::set_var("CONCLAUDE_FILE_EVENT", &event_str);
 
+    let rejected_reload = reload_if_config_changed(&payload.file_path).await;
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.file_changed.commands.is_empty() {
         let matching_patterns =
@@ -4719,8 +6044,14 @@ pub async fn handle_file_changed() -> Result<HookResult> {
                     "FileChanged",
                     config_dir,
                 );
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "FileChanged").await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "FileChanged",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
@@ -4730,7 +6061,10 @@ pub async fn handle_file_changed() -> Result<HookResult> {
         "success",
         Some(&format!("File {} ({})", payload.file_path, event_str)),
     );
-    Ok(HookResult::success())
+    if let Some(rejected) = rejected_reload {
+        feedback.context.insert(0, rejected);
+    }
+    Ok(feedback.into_result())
 }
 
 /// Handles `InstructionsLoaded` hook events fired when an instructions/memory file loads.
@@ -4760,6 +6094,7 @@ pub async fn handle_instructions_loaded() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.instructions_loaded.commands.is_empty() {
         let matching_patterns =
@@ -4789,6 +6124,7 @@ pub async fn handle_instructions_loaded() -> Result<HookResult> {
                     &env_vars,
                     config_dir,
                     "InstructionsLoaded",
+                    &mut feedback,
                 )
                 .await;
             }
@@ -4803,7 +6139,7 @@ pub async fn handle_instructions_loaded() -> Result<HookResult> {
             payload.file_path
         )),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Collect commands from `PostToolBatchConfig` (flat command list).
@@ -4812,16 +6148,17 @@ fn collect_post_tool_batch_commands(
 ) -> Result<Vec<GenericCommandConfig>> {
     let mut commands = Vec::new();
     for cmd_config in &config.commands {
-        for cmd in extract_bash_commands(&cmd_config.run)? {
+        for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
             commands.push(GenericCommandConfig {
                 command: cmd,
                 message: cmd_config.message.clone(),
-                show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                max_output_lines: cmd_config.max_output_lines,
-                timeout: cmd_config.timeout,
-                show_command: cmd_config.show_command.unwrap_or(true),
-                notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                show_stdout: cmd_config.launch.show_stdout(),
+                show_stderr: cmd_config.launch.show_stderr(),
+                max_output_lines: cmd_config.launch.max_output_lines,
+                timeout: cmd_config.launch.timeout,
+                show_command: cmd_config.launch.show_command(),
+                notify_per_command: cmd_config.launch.notify_per_command(),
+                launch: CommandLaunch::from_options(&cmd_config.launch),
             });
         }
     }
@@ -4837,16 +6174,17 @@ fn collect_permission_denied_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4864,16 +6202,17 @@ fn collect_user_prompt_expansion_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -4913,6 +6252,7 @@ pub async fn handle_post_tool_batch() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.post_tool_batch.commands.is_empty() {
         let commands = collect_post_tool_batch_commands(&config.post_tool_batch)?;
@@ -4930,12 +6270,18 @@ pub async fn handle_post_tool_batch() -> Result<HookResult> {
                 "PostToolBatch",
                 config_dir,
             );
-            let _ =
-                execute_generic_commands(&commands, &env_vars, config_dir, "PostToolBatch").await;
+            let _ = execute_generic_commands(
+                &commands,
+                &env_vars,
+                config_dir,
+                "PostToolBatch",
+                &mut feedback,
+            )
+            .await;
         }
     }
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `PermissionDenied` hook events fired when a tool permission request is denied.
@@ -4963,6 +6309,7 @@ pub async fn handle_permission_denied() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.permission_denied.commands.is_empty() {
         let matching_patterns =
@@ -4985,9 +6332,14 @@ pub async fn handle_permission_denied() -> Result<HookResult> {
                     "PermissionDenied",
                     config_dir,
                 );
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "PermissionDenied")
-                        .await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "PermissionDenied",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
@@ -5000,7 +6352,7 @@ pub async fn handle_permission_denied() -> Result<HookResult> {
             payload.tool_name, payload.reason
         )),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `UserPromptExpansion` hook events fired when a slash command or MCP prompt expands.
@@ -5034,10 +6386,13 @@ pub async fn handle_user_prompt_expansion() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.user_prompt_expansion.commands.is_empty() {
-        let matching_patterns =
-            match_generic_patterns(&payload.command_name, &config.user_prompt_expansion.commands)?;
+        let matching_patterns = match_generic_patterns(
+            &payload.command_name,
+            &config.user_prompt_expansion.commands,
+        )?;
         if !matching_patterns.is_empty() {
             let commands = collect_user_prompt_expansion_commands(
                 &config.user_prompt_expansion,
@@ -5061,7 +6416,10 @@ pub async fn handle_user_prompt_expansion() -> Result<HookResult> {
                     "CONCLAUDE_COMMAND_SOURCE".to_string(),
                     payload.command_source.clone().unwrap_or_default(),
                 );
-                env_vars.insert("CONCLAUDE_EXPANDED_PROMPT".to_string(), payload.prompt.clone());
+                env_vars.insert(
+                    "CONCLAUDE_EXPANDED_PROMPT".to_string(),
+                    payload.prompt.clone(),
+                );
                 insert_base_env_vars(
                     &mut env_vars,
                     &payload.base,
@@ -5074,13 +6432,14 @@ pub async fn handle_user_prompt_expansion() -> Result<HookResult> {
                     &env_vars,
                     config_dir,
                     "UserPromptExpansion",
+                    &mut feedback,
                 )
                 .await;
             }
         }
     }
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Collect commands from `TaskCreatedConfig` for matching patterns.
@@ -5092,16 +6451,17 @@ fn collect_task_created_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -5119,16 +6479,17 @@ fn collect_elicitation_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -5146,16 +6507,17 @@ fn collect_elicitation_result_commands(
     for pattern in matching_patterns {
         if let Some(cmd_list) = config.commands.get(*pattern) {
             for cmd_config in cmd_list {
-                for cmd in extract_bash_commands(&cmd_config.run)? {
+                for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
                     commands.push(GenericCommandConfig {
                         command: cmd,
                         message: cmd_config.message.clone(),
-                        show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                        show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                        max_output_lines: cmd_config.max_output_lines,
-                        timeout: cmd_config.timeout,
-                        show_command: cmd_config.show_command.unwrap_or(true),
-                        notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                        show_stdout: cmd_config.launch.show_stdout(),
+                        show_stderr: cmd_config.launch.show_stderr(),
+                        max_output_lines: cmd_config.launch.max_output_lines,
+                        timeout: cmd_config.launch.timeout,
+                        show_command: cmd_config.launch.show_command(),
+                        notify_per_command: cmd_config.launch.notify_per_command(),
+                        launch: CommandLaunch::from_options(&cmd_config.launch),
                     });
                 }
             }
@@ -5170,16 +6532,17 @@ fn collect_message_display_commands(
 ) -> Result<Vec<GenericCommandConfig>> {
     let mut commands = Vec::new();
     for cmd_config in &config.commands {
-        for cmd in extract_bash_commands(&cmd_config.run)? {
+        for cmd in split_run_commands(&cmd_config.run, cmd_config.launch.mode())? {
             commands.push(GenericCommandConfig {
                 command: cmd,
                 message: cmd_config.message.clone(),
-                show_stdout: cmd_config.show_stdout.unwrap_or(false),
-                show_stderr: cmd_config.show_stderr.unwrap_or(false),
-                max_output_lines: cmd_config.max_output_lines,
-                timeout: cmd_config.timeout,
-                show_command: cmd_config.show_command.unwrap_or(true),
-                notify_per_command: cmd_config.notify_per_command.unwrap_or(false),
+                show_stdout: cmd_config.launch.show_stdout(),
+                show_stderr: cmd_config.launch.show_stderr(),
+                max_output_lines: cmd_config.launch.max_output_lines,
+                timeout: cmd_config.launch.timeout,
+                show_command: cmd_config.launch.show_command(),
+                notify_per_command: cmd_config.launch.notify_per_command(),
+                launch: CommandLaunch::from_options(&cmd_config.launch),
             });
         }
     }
@@ -5213,6 +6576,7 @@ pub async fn handle_task_created() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.task_created.commands.is_empty() {
         let matching_patterns =
@@ -5237,8 +6601,14 @@ pub async fn handle_task_created() -> Result<HookResult> {
                     "TaskCreated",
                     config_dir,
                 );
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "TaskCreated").await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "TaskCreated",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
@@ -5248,7 +6618,7 @@ pub async fn handle_task_created() -> Result<HookResult> {
         "success",
         Some(&format!("Task '{}' created", payload.task_subject)),
     );
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `Elicitation` hook events fired when an MCP server requests user input.
@@ -5261,7 +6631,11 @@ pub async fn handle_elicitation() -> Result<HookResult> {
     let payload: ElicitationPayload = read_payload_from_stdin()?;
     validate_elicitation_payload(&payload).map_err(|e| anyhow::anyhow!(e))?;
 
-    let mode_str = payload.mode.as_ref().map(ToString::to_string).unwrap_or_default();
+    let mode_str = payload
+        .mode
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
     println!(
         "Processing Elicitation hook: session_id={}, mcp_server_name={}, mode={}",
         payload.base.session_id, payload.mcp_server_name, mode_str
@@ -5281,6 +6655,7 @@ pub async fn handle_elicitation() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.elicitation.commands.is_empty() {
         let matching_patterns =
@@ -5309,13 +6684,19 @@ pub async fn handle_elicitation() -> Result<HookResult> {
                     "Elicitation",
                     config_dir,
                 );
-                let _ =
-                    execute_generic_commands(&commands, &env_vars, config_dir, "Elicitation").await;
+                let _ = execute_generic_commands(
+                    &commands,
+                    &env_vars,
+                    config_dir,
+                    "Elicitation",
+                    &mut feedback,
+                )
+                .await;
             }
         }
     }
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `ElicitationResult` hook events fired after a user responds to an MCP elicitation.
@@ -5329,7 +6710,11 @@ pub async fn handle_elicitation_result() -> Result<HookResult> {
     validate_elicitation_result_payload(&payload).map_err(|e| anyhow::anyhow!(e))?;
 
     let action_str = payload.action.to_string();
-    let mode_str = payload.mode.as_ref().map(ToString::to_string).unwrap_or_default();
+    let mode_str = payload
+        .mode
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
     println!(
         "Processing ElicitationResult hook: session_id={}, mcp_server_name={}, action={}",
         payload.base.session_id, payload.mcp_server_name, action_str
@@ -5349,10 +6734,13 @@ pub async fn handle_elicitation_result() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     if !config.elicitation_result.commands.is_empty() {
-        let matching_patterns =
-            match_generic_patterns(&payload.mcp_server_name, &config.elicitation_result.commands)?;
+        let matching_patterns = match_generic_patterns(
+            &payload.mcp_server_name,
+            &config.elicitation_result.commands,
+        )?;
         if !matching_patterns.is_empty() {
             let commands = collect_elicitation_result_commands(
                 &config.elicitation_result,
@@ -5385,13 +6773,14 @@ pub async fn handle_elicitation_result() -> Result<HookResult> {
                     &env_vars,
                     config_dir,
                     "ElicitationResult",
+                    &mut feedback,
                 )
                 .await;
             }
         }
     }
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `MessageDisplay` hook events fired as assistant messages stream.
@@ -5411,6 +6800,7 @@ pub async fn handle_message_display() -> Result<HookResult> {
 
     let (config, config_path) = get_config().await?;
     let config_dir = get_config_dir(config_path);
+    let mut feedback = CommandFeedback::default();
 
     // High-frequency hook: skip command execution unless there is a configured command,
     // and (by default) only run on the final flush of each message.
@@ -5420,7 +6810,10 @@ pub async fn handle_message_display() -> Result<HookResult> {
         let commands = collect_message_display_commands(&config.message_display)?;
         if !commands.is_empty() {
             let mut env_vars = HashMap::new();
-            env_vars.insert("CONCLAUDE_MESSAGE_ID".to_string(), payload.message_id.clone());
+            env_vars.insert(
+                "CONCLAUDE_MESSAGE_ID".to_string(),
+                payload.message_id.clone(),
+            );
             env_vars.insert("CONCLAUDE_TURN_ID".to_string(), payload.turn_id.clone());
             env_vars.insert(
                 "CONCLAUDE_MESSAGE_INDEX".to_string(),
@@ -5438,12 +6831,18 @@ pub async fn handle_message_display() -> Result<HookResult> {
                 "MessageDisplay",
                 config_dir,
             );
-            let _ =
-                execute_generic_commands(&commands, &env_vars, config_dir, "MessageDisplay").await;
+            let _ = execute_generic_commands(
+                &commands,
+                &env_vars,
+                config_dir,
+                "MessageDisplay",
+                &mut feedback,
+            )
+            .await;
         }
     }
 
-    Ok(HookResult::success())
+    Ok(feedback.into_result())
 }
 
 /// Handles `WorktreeCreate` hook events when a git worktree needs to be created.
@@ -5481,10 +6880,7 @@ pub async fn handle_worktree_create() -> Result<String> {
             let timeout_secs = config.worktree_create.timeout.unwrap_or(60);
 
             let mut env_vars = HashMap::new();
-            env_vars.insert(
-                "CONCLAUDE_WORKTREE_NAME".to_string(),
-                payload.name.clone(),
-            );
+            env_vars.insert("CONCLAUDE_WORKTREE_NAME".to_string(), payload.name.clone());
             env_vars.insert(
                 "CONCLAUDE_SESSION_ID".to_string(),
                 payload.base.session_id.clone(),
@@ -5495,26 +6891,27 @@ pub async fn handle_worktree_create() -> Result<String> {
                 "WorktreeCreate".to_string(),
             );
 
-            let child = TokioCommand::new("bash")
-                .arg("-c")
-                .arg(command)
-                .stdin(Stdio::null())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .envs(&env_vars)
-                .current_dir(config_dir)
-                .spawn()
+            // The worktree command is passed to bash as written, without script-mode strictness
+            let launch = CommandLaunch {
+                mode: RunMode::Lines,
+                ..Default::default()
+            };
+            let child = spawn_command(command, &launch, &env_vars, config_dir)
                 .context("Failed to spawn worktree create command")?;
 
-            let output = timeout(Duration::from_secs(timeout_secs), child.wait_with_output())
-                .await
-                .map_err(|_| {
-                    anyhow::anyhow!(
-                        "Worktree create command timed out after {} seconds",
-                        timeout_secs
-                    )
-                })?
-                .context("Failed to wait for worktree create command")?;
+            let outcome = wait_for_command(
+                child,
+                Some(timeout_secs),
+                Duration::from_secs(DEFAULT_GRACE_PERIOD_SECS),
+            )
+            .await
+            .context("Failed to wait for worktree create command")?;
+            let CommandOutcome::Exited(output) = outcome else {
+                return Err(anyhow::anyhow!(
+                    "Worktree create command timed out after {} seconds",
+                    timeout_secs
+                ));
+            };
 
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
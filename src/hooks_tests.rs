@@ -1,4 +1,4 @@
-use crate::config::ConclaudeConfig;
+use crate::config::{ConclaudeConfig, FailureAction, LaunchOptions, OutputMode};
 use crate::hooks::*;
 use serde_json::Value;
 use std::fs;
@@ -21,6 +21,27 @@ fn test_is_system_event_hook() {
     assert!(!is_system_event_hook("Stop"));
 }
 
+#[test]
+fn test_edits_recorded_by_parallel_hooks_are_all_kept() {
+    let session_id = format!("test-parallel-edits-{}", std::process::id());
+    let handles: Vec<_> = (0..16)
+        .map(|i| {
+            let session_id = session_id.clone();
+            std::thread::spawn(move || {
+                record_edited_file(&session_id, Path::new(&format!("/repo/pkg{i}/lib.rs")))
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap().unwrap();
+    }
+    record_edited_file(&session_id, Path::new("/repo/pkg0/lib.rs")).unwrap();
+
+    let edited = read_edited_files(&session_id);
+    let _ = fs::remove_file(get_edited_files_path(&session_id));
+    assert_eq!(edited.len(), 16);
+}
+
 #[test]
 fn test_is_system_event_hook_new_hooks() {
     // New system event hooks
@@ -45,74 +66,143 @@ fn test_is_system_event_hook_new_hooks() {
     assert!(!is_system_event_hook("PostToolUseFailure"));
 }
 
+fn excerpt(mode: OutputMode) -> OutputExcerpt {
+    OutputExcerpt {
+        mode,
+        error_patterns: None,
+    }
+}
+
 #[test]
-fn test_truncate_output_no_truncation() {
+fn test_excerpt_output_no_truncation() {
+    let head = excerpt(OutputMode::Head);
+
     // Test no truncation needed (fewer lines than limit)
-    let output = "line1\nline2\nline3";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 10);
-    assert_eq!(truncated, "line1\nline2\nline3");
-    assert!(!is_truncated);
-    assert_eq!(omitted, 0);
+    assert_eq!(
+        excerpt_output("line1\nline2\nline3", 10, &head),
+        "line1\nline2\nline3"
+    );
 
     // Test exact limit
-    let output = "line1\nline2\nline3";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 3);
-    assert_eq!(truncated, "line1\nline2\nline3");
-    assert!(!is_truncated);
-    assert_eq!(omitted, 0);
+    assert_eq!(
+        excerpt_output("line1\nline2\nline3", 3, &head),
+        "line1\nline2\nline3"
+    );
 
     // Test single line
-    let output = "single line";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 1);
-    assert_eq!(truncated, "single line");
-    assert!(!is_truncated);
-    assert_eq!(omitted, 0);
+    assert_eq!(excerpt_output("single line", 1, &head), "single line");
 
     // Test large limit
-    let output = "line1\nline2";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 10000);
-    assert_eq!(truncated, "line1\nline2");
-    assert!(!is_truncated);
-    assert_eq!(omitted, 0);
+    assert_eq!(excerpt_output("line1\nline2", 10000, &head), "line1\nline2");
+
+    // Every mode leaves output within the limit untouched
+    let errors = excerpt(OutputMode::Errors);
+    assert_eq!(excerpt_output("ok\nerror", 2, &errors), "ok\nerror");
 }
 
 #[test]
-fn test_truncate_output_with_truncation() {
+fn test_excerpt_output_head() {
     let output = "line1\nline2\nline3\nline4\nline5";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 2);
-    assert_eq!(truncated, "line1\nline2");
-    assert!(is_truncated);
-    assert_eq!(omitted, 3);
+    assert_eq!(
+        excerpt_output(output, 2, &excerpt(OutputMode::Head)),
+        "line1\nline2\n... (3 lines omitted: lines 3-5)"
+    );
 }
 
 #[test]
-fn test_truncate_output_empty() {
-    let output = "";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 10);
-    assert_eq!(truncated, "");
-    assert!(!is_truncated);
-    assert_eq!(omitted, 0);
+fn test_excerpt_output_empty() {
+    assert_eq!(excerpt_output("", 10, &excerpt(OutputMode::Head)), "");
 }
 
 #[test]
-fn test_truncate_output_multiple_lines_just_over_limit() {
+fn test_excerpt_output_single_line_omitted() {
     let output = "line1\nline2\nline3\nline4\nline5\nline6";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 5);
-    assert_eq!(truncated, "line1\nline2\nline3\nline4\nline5");
-    assert!(is_truncated);
-    assert_eq!(omitted, 1);
+    assert_eq!(
+        excerpt_output(output, 5, &excerpt(OutputMode::Head)),
+        "line1\nline2\nline3\nline4\nline5\n... (1 line omitted: line 6)"
+    );
 }
 
 #[test]
-fn test_truncate_output_preserves_content() {
+fn test_excerpt_output_preserves_content() {
     let output = "Line with special chars: !@#$%^&*()\nAnother line\n\nEmpty line above";
-    let (truncated, is_truncated, omitted) = truncate_output(output, 2);
     assert_eq!(
-        truncated,
-        "Line with special chars: !@#$%^&*()\nAnother line"
+        excerpt_output(output, 2, &excerpt(OutputMode::Head)),
+        "Line with special chars: !@#$%^&*()\nAnother line\n... (2 lines omitted: lines 3-4)"
+    );
+}
+
+#[test]
+fn test_excerpt_output_tail() {
+    let output = "line1\nline2\nline3\nline4\nline5";
+    assert_eq!(
+        excerpt_output(output, 2, &excerpt(OutputMode::Tail)),
+        "... (3 lines omitted: lines 1-3)\nline4\nline5"
+    );
+}
+
+#[test]
+fn test_excerpt_output_head_tail() {
+    let output = "line1\nline2\nline3\nline4\nline5\nline6";
+    assert_eq!(
+        excerpt_output(output, 3, &excerpt(OutputMode::HeadTail)),
+        "line1\nline2\n... (3 lines omitted: lines 3-5)\nline6"
+    );
+}
+
+#[test]
+fn test_excerpt_output_errors_keeps_context_around_matches() {
+    let mut lines: Vec<String> = (1..=30).map(|n| format!("line{n}")).collect();
+    lines[9] = "error[E0308]: mismatched types".to_string();
+    lines[24] = "test result: FAILED".to_string();
+    let output = lines.join("\n");
+
+    let excerpted = excerpt_output(&output, 20, &excerpt(OutputMode::Errors));
+    assert_eq!(
+        excerpted,
+        "... (6 lines omitted: lines 1-6)\n\
+         line7\nline8\nline9\nerror[E0308]: mismatched types\nline11\nline12\nline13\n\
+         ... (8 lines omitted: lines 14-21)\n\
+         line22\nline23\nline24\ntest result: FAILED\nline26\nline27\nline28\n\
+         ... (2 lines omitted: lines 29-30)"
+    );
+}
+
+#[test]
+fn test_excerpt_output_errors_caps_windows_at_limit() {
+    let output = (1..=20)
+        .map(|n| format!("error {n}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert_eq!(
+        excerpt_output(&output, 2, &excerpt(OutputMode::Errors)),
+        "error 1\nerror 2\n... (18 lines omitted: lines 3-20)"
+    );
+}
+
+#[test]
+fn test_excerpt_output_errors_falls_back_to_tail() {
+    let output = "a\nb\nc\nd";
+    assert_eq!(
+        excerpt_output(output, 1, &excerpt(OutputMode::Errors)),
+        "... (3 lines omitted: lines 1-3)\nd"
+    );
+}
+
+#[test]
+fn test_excerpt_output_errors_uses_configured_patterns() {
+    let launch = CommandLaunch::from_options(&LaunchOptions {
+        output_mode: Some(OutputMode::Errors),
+        error_patterns: Some(vec!["^FAIL ".to_string()]),
+        ..Default::default()
+    });
+    let output = "ok 1\nerror: ignored\nok 2\nok 3\nok 4\nok 5\nFAIL src/app.test.ts";
+
+    assert_eq!(
+        excerpt_output(output, 4, &launch.output),
+        "... (3 lines omitted: lines 1-3)\nok 3\nok 4\nok 5\nFAIL src/app.test.ts"
     );
-    assert!(is_truncated);
-    assert_eq!(omitted, 2);
 }
 
 #[test]
@@ -125,26 +215,39 @@ fn test_collect_stop_commands_with_output_config() {
                 StopCommand {
                     run: "echo hello".to_string(),
                     message: Some("Custom message".to_string()),
-                    show_command: None,
-                    show_stdout: Some(true),
-                    show_stderr: Some(false),
-                    max_output_lines: Some(10),
-                    timeout: None,
-                    notify_per_command: None,
+                    launch: LaunchOptions {
+                        show_stdout: Some(true),
+                        show_stderr: Some(false),
+                        max_output_lines: Some(10),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
                 StopCommand {
                     run: "ls -la".to_string(),
                     message: None,
-                    show_command: None,
-                    show_stdout: Some(false),
-                    show_stderr: Some(true),
-                    max_output_lines: Some(5),
-                    timeout: None,
-                    notify_per_command: None,
+                    launch: LaunchOptions {
+                        show_stdout: Some(false),
+                        show_stderr: Some(true),
+                        max_output_lines: Some(5),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
             ],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -174,15 +277,18 @@ fn test_collect_stop_commands_default_values() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -263,16 +369,17 @@ fn test_match_subagent_patterns_prefix_glob() {
         vec![SubagentStopCommand {
             run: "echo test".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     let matches = match_subagent_patterns("tester", &config).unwrap();
     assert_eq!(matches, vec!["test*"]);
@@ -298,16 +405,17 @@ fn test_match_subagent_patterns_suffix_glob() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     let matches = match_subagent_patterns("coder", &config).unwrap();
     assert_eq!(matches, vec!["*coder"]);
@@ -333,16 +441,17 @@ fn test_match_subagent_patterns_character_class() {
         vec![SubagentStopCommand {
             run: "echo agent".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     let matches = match_subagent_patterns("agent_1", &config).unwrap();
     assert_eq!(matches, vec!["agent_[0-9]*"]);
@@ -371,12 +480,10 @@ fn test_match_subagent_patterns_multiple_matches() {
         vec![SubagentStopCommand {
             run: "echo all".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
     commands.insert(
@@ -384,12 +491,10 @@ fn test_match_subagent_patterns_multiple_matches() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
     commands.insert(
@@ -397,16 +502,17 @@ fn test_match_subagent_patterns_multiple_matches() {
         vec![SubagentStopCommand {
             run: "echo suffix-coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     // "coder" should match all three patterns
     let matches = match_subagent_patterns("coder", &config).unwrap();
@@ -428,12 +534,10 @@ fn test_match_subagent_patterns_wildcard_first() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
     commands.insert(
@@ -441,16 +545,17 @@ fn test_match_subagent_patterns_wildcard_first() {
         vec![SubagentStopCommand {
             run: "echo all".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     let matches = match_subagent_patterns("coder", &config).unwrap();
     // Wildcard should always be first
@@ -468,12 +573,10 @@ fn test_match_subagent_patterns_no_match() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
     commands.insert(
@@ -481,16 +584,17 @@ fn test_match_subagent_patterns_no_match() {
         vec![SubagentStopCommand {
             run: "echo tester".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
 
     let matches = match_subagent_patterns("unknown-agent", &config).unwrap();
     assert!(matches.is_empty());
@@ -623,6 +727,113 @@ fn test_build_subagent_env_vars_all_expected_keys() {
     );
 }
 
+fn stop_test_base() -> crate::types::BasePayload {
+    crate::types::BasePayload {
+        session_id: "stop-session".to_string(),
+        transcript_path: "/transcript.jsonl".to_string(),
+        hook_event_name: "Stop".to_string(),
+        cwd: "/project".to_string(),
+        permission_mode: None,
+        agent_id: None,
+        agent_type: None,
+        effort: None,
+    }
+}
+
+#[test]
+fn test_build_stop_env_vars() {
+    use crate::types::{BackgroundTaskSummary, SessionCronSummary, StopPayload};
+
+    let payload = StopPayload {
+        base: stop_test_base(),
+        stop_hook_active: true,
+        last_assistant_message: Some("All tests pass.".to_string()),
+        background_tasks: Some(vec![BackgroundTaskSummary {
+            id: "task-1".to_string(),
+            task_type: "shell".to_string(),
+            status: "running".to_string(),
+            ..Default::default()
+        }]),
+        session_crons: Some(vec![SessionCronSummary {
+            id: "cron-1".to_string(),
+            schedule: "0 9 * * 1-5".to_string(),
+            recurring: true,
+            prompt: "check CI".to_string(),
+        }]),
+    };
+
+    let env_vars = build_stop_env_vars(&payload, Path::new("/test/config"));
+
+    assert_eq!(env_vars["CONCLAUDE_SESSION_ID"], "stop-session");
+    assert_eq!(env_vars["CONCLAUDE_TRANSCRIPT_PATH"], "/transcript.jsonl");
+    assert_eq!(env_vars["CONCLAUDE_CWD"], "/project");
+    assert_eq!(env_vars["CONCLAUDE_HOOK_EVENT"], "Stop");
+    assert_eq!(env_vars["CONCLAUDE_CONFIG_DIR"], "/test/config");
+    assert_eq!(env_vars["CONCLAUDE_STOP_HOOK_ACTIVE"], "true");
+    assert_eq!(
+        env_vars["CONCLAUDE_LAST_ASSISTANT_MESSAGE"],
+        "All tests pass."
+    );
+
+    let tasks: Value = serde_json::from_str(&env_vars["CONCLAUDE_BACKGROUND_TASKS"]).unwrap();
+    assert_eq!(tasks[0]["id"], "task-1");
+    assert_eq!(tasks[0]["type"], "shell");
+    let crons: Value = serde_json::from_str(&env_vars["CONCLAUDE_SESSION_CRONS"]).unwrap();
+    assert_eq!(crons[0]["schedule"], "0 9 * * 1-5");
+
+    let payload_json: Value = serde_json::from_str(&env_vars["CONCLAUDE_PAYLOAD_JSON"]).unwrap();
+    assert_eq!(payload_json["last_assistant_message"], "All tests pass.");
+}
+
+#[test]
+fn test_build_stop_env_vars_defaults_for_missing_fields() {
+    use crate::types::StopPayload;
+
+    let payload = StopPayload {
+        base: stop_test_base(),
+        stop_hook_active: false,
+        last_assistant_message: None,
+        background_tasks: None,
+        session_crons: None,
+    };
+
+    let env_vars = build_stop_env_vars(&payload, Path::new("."));
+
+    assert_eq!(env_vars["CONCLAUDE_STOP_HOOK_ACTIVE"], "false");
+    assert_eq!(env_vars["CONCLAUDE_LAST_ASSISTANT_MESSAGE"], "");
+    assert_eq!(env_vars["CONCLAUDE_BACKGROUND_TASKS"], "[]");
+    assert_eq!(env_vars["CONCLAUDE_SESSION_CRONS"], "[]");
+}
+
+#[test]
+fn test_build_stop_failure_env_vars() {
+    use crate::types::StopFailurePayload;
+
+    let payload = StopFailurePayload {
+        base: stop_test_base(),
+        stop_hook_active: false,
+        error: "rate_limit".to_string(),
+        error_details: Some("Too many requests".to_string()),
+        last_assistant_message: Some("Working on it".to_string()),
+    };
+
+    let env_vars = build_stop_failure_env_vars(&payload, Path::new("/test/config"));
+
+    assert_eq!(env_vars["CONCLAUDE_HOOK_EVENT"], "StopFailure");
+    assert_eq!(env_vars["CONCLAUDE_SESSION_ID"], "stop-session");
+    assert_eq!(env_vars["CONCLAUDE_STOP_ERROR"], "rate_limit");
+    assert_eq!(
+        env_vars["CONCLAUDE_STOP_ERROR_DETAILS"],
+        "Too many requests"
+    );
+    assert_eq!(
+        env_vars["CONCLAUDE_LAST_ASSISTANT_MESSAGE"],
+        "Working on it"
+    );
+    assert_eq!(env_vars["CONCLAUDE_STOP_HOOK_ACTIVE"], "false");
+    assert!(env_vars["CONCLAUDE_PAYLOAD_JSON"].contains("\"error\":\"rate_limit\""));
+}
+
 #[test]
 fn test_collect_subagent_stop_commands_single_pattern() {
     use crate::config::{SubagentStopCommand, SubagentStopConfig};
@@ -634,27 +845,31 @@ fn test_collect_subagent_stop_commands_single_pattern() {
             SubagentStopCommand {
                 run: "echo first".to_string(),
                 message: Some("First command".to_string()),
-                show_command: None,
-                show_stdout: Some(true),
-                show_stderr: Some(false),
-                max_output_lines: Some(10),
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions {
+                    show_stdout: Some(true),
+                    show_stderr: Some(false),
+                    max_output_lines: Some(10),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
             },
             SubagentStopCommand {
                 run: "echo second".to_string(),
                 message: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
+                parser: None,
+                report_file: None,
+                fix: None,
             },
         ],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     let matching_patterns = vec!["coder"];
 
     let collected = collect_subagent_stop_commands(&config, &matching_patterns).unwrap();
@@ -683,12 +898,10 @@ fn test_collect_subagent_stop_commands_multiple_patterns() {
         vec![SubagentStopCommand {
             run: "echo wildcard".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
     commands.insert(
@@ -696,16 +909,17 @@ fn test_collect_subagent_stop_commands_multiple_patterns() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     // Wildcard first, then specific pattern (as match_subagent_patterns returns)
     let matching_patterns = vec!["*", "coder"];
 
@@ -727,16 +941,17 @@ fn test_collect_subagent_stop_commands_no_matching_patterns() {
         vec![SubagentStopCommand {
             run: "echo coder".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     let matching_patterns: Vec<&str> = vec![];
 
     let collected = collect_subagent_stop_commands(&config, &matching_patterns).unwrap();
@@ -756,15 +971,22 @@ fn test_collect_stop_commands_with_notify_per_command_true() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: Some("Test message".to_string()),
-                show_command: Some(true),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(true),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -787,15 +1009,22 @@ fn test_collect_stop_commands_with_notify_per_command_false() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: None,
-                show_command: Some(false),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(false),
+                launch: LaunchOptions {
+                    show_command: Some(false),
+                    notify_per_command: Some(false),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -817,15 +1046,19 @@ fn test_collect_stop_commands_notify_per_command_defaults_to_false() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None, // Not specified - should default to false
+                // notifyPerCommand not specified - should default to false
+                launch: LaunchOptions::default(),
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -847,36 +1080,48 @@ fn test_collect_stop_commands_mixed_notify_per_command_settings() {
                 StopCommand {
                     run: "echo first".to_string(),
                     message: None,
-                    show_command: Some(true),
-                    show_stdout: None,
-                    show_stderr: None,
-                    max_output_lines: None,
-                    timeout: None,
-                    notify_per_command: Some(true),
+                    launch: LaunchOptions {
+                        show_command: Some(true),
+                        notify_per_command: Some(true),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
                 StopCommand {
                     run: "echo second".to_string(),
                     message: None,
-                    show_command: Some(false),
-                    show_stdout: None,
-                    show_stderr: None,
-                    max_output_lines: None,
-                    timeout: None,
-                    notify_per_command: Some(false),
+                    launch: LaunchOptions {
+                        show_command: Some(false),
+                        notify_per_command: Some(false),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
                 StopCommand {
                     run: "echo third".to_string(),
                     message: None,
-                    show_command: None,
-                    show_stdout: None,
-                    show_stderr: None,
-                    max_output_lines: None,
-                    timeout: None,
-                    notify_per_command: None, // Should default to false
+                    // notifyPerCommand not specified - should default to false
+                    launch: LaunchOptions::default(),
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
             ],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -910,27 +1155,34 @@ fn test_collect_subagent_stop_commands_with_notify_per_command() {
             SubagentStopCommand {
                 run: "echo coder first".to_string(),
                 message: None,
-                show_command: Some(true),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(true),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
             },
             SubagentStopCommand {
                 run: "echo coder second".to_string(),
                 message: None,
-                show_command: Some(false),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(false),
+                launch: LaunchOptions {
+                    show_command: Some(false),
+                    notify_per_command: Some(false),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
             },
         ],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     let matching_patterns = vec!["coder"];
 
     let collected = collect_subagent_stop_commands(&config, &matching_patterns).unwrap();
@@ -958,16 +1210,18 @@ fn test_collect_subagent_stop_commands_notify_per_command_defaults_to_false() {
         vec![SubagentStopCommand {
             run: "echo test".to_string(),
             message: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None, // Not specified - should default to false
+            // notifyPerCommand not specified - should default to false
+            launch: LaunchOptions::default(),
+            parser: None,
+            report_file: None,
+            fix: None,
         }],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     let matching_patterns = vec!["tester"];
 
     let collected = collect_subagent_stop_commands(&config, &matching_patterns).unwrap();
@@ -993,15 +1247,22 @@ fn test_notify_per_command_respects_show_command_flag() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: None,
-                show_command: Some(true),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(true),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -1017,15 +1278,22 @@ fn test_notify_per_command_respects_show_command_flag() {
             commands: vec![StopCommand {
                 run: "echo test".to_string(),
                 message: None,
-                show_command: Some(false),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(false),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
+                name: None,
+                needs: None,
             }],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -1050,26 +1318,37 @@ fn test_per_command_notification_flag_propagation() {
                 StopCommand {
                     run: "echo with-notifications".to_string(),
                     message: None,
-                    show_command: Some(true),
-                    show_stdout: None,
-                    show_stderr: None,
-                    max_output_lines: None,
-                    timeout: None,
-                    notify_per_command: Some(true),
+                    launch: LaunchOptions {
+                        show_command: Some(true),
+                        notify_per_command: Some(true),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
                 StopCommand {
                     run: "echo without-notifications".to_string(),
                     message: None,
-                    show_command: Some(true),
-                    show_stdout: None,
-                    show_stderr: None,
-                    max_output_lines: None,
-                    timeout: None,
-                    notify_per_command: Some(false),
+                    launch: LaunchOptions {
+                        show_command: Some(true),
+                        notify_per_command: Some(false),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
                 },
             ],
             infinite: false,
             infinite_message: None,
+            parallel: false,
+            max_parallel: None,
+            fail_fast: true,
         },
         ..Default::default()
     };
@@ -1097,27 +1376,34 @@ fn test_subagent_stop_notify_per_command_with_show_command() {
             SubagentStopCommand {
                 run: "echo visible".to_string(),
                 message: None,
-                show_command: Some(true),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(true),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
             },
             SubagentStopCommand {
                 run: "echo hidden".to_string(),
                 message: None,
-                show_command: Some(false),
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    show_command: Some(false),
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
+                parser: None,
+                report_file: None,
+                fix: None,
             },
         ],
     );
 
-    let config = SubagentStopConfig { commands };
+    let config = SubagentStopConfig {
+        commands,
+        fail_fast: true,
+    };
     let matching_patterns = vec!["coder"];
 
     let collected = collect_subagent_stop_commands(&config, &matching_patterns).unwrap();
@@ -1383,23 +1669,13 @@ mod user_prompt_submit_command_tests {
                 run: "echo deploy".to_string(),
                 pattern: Some("deploy|release".to_string()),
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
             },
             UserPromptSubmitCommand {
                 run: "echo test".to_string(),
                 pattern: Some("test".to_string()),
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
             },
         ];
 
@@ -1472,12 +1748,7 @@ mod user_prompt_submit_command_tests {
             run: "echo always".to_string(),
             pattern: None, // No pattern = match all
             case_insensitive: None,
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
         }];
 
         // Should match any prompt
@@ -1501,12 +1772,7 @@ mod user_prompt_submit_command_tests {
             run: "echo database".to_string(),
             pattern: Some("database".to_string()),
             case_insensitive: Some(true),
-            show_command: None,
-            show_stdout: None,
-            show_stderr: None,
-            max_output_lines: None,
-            timeout: None,
-            notify_per_command: None,
+            launch: LaunchOptions::default(),
         }];
 
         // Should match with different cases
@@ -1536,23 +1802,13 @@ mod user_prompt_submit_command_tests {
                 run: "echo deploy".to_string(),
                 pattern: Some("deploy".to_string()),
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
             },
             UserPromptSubmitCommand {
                 run: "echo always".to_string(),
                 pattern: None, // Match all
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None,
+                launch: LaunchOptions::default(),
             },
         ];
 
@@ -1574,34 +1830,26 @@ mod user_prompt_submit_command_tests {
                 run: "echo with-notify".to_string(),
                 pattern: None,
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(true),
+                launch: LaunchOptions {
+                    notify_per_command: Some(true),
+                    ..Default::default()
+                },
             },
             UserPromptSubmitCommand {
                 run: "echo without-notify".to_string(),
                 pattern: None,
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: Some(false),
+                launch: LaunchOptions {
+                    notify_per_command: Some(false),
+                    ..Default::default()
+                },
             },
             UserPromptSubmitCommand {
                 run: "echo default-notify".to_string(),
                 pattern: None,
                 case_insensitive: None,
-                show_command: None,
-                show_stdout: None,
-                show_stderr: None,
-                max_output_lines: None,
-                timeout: None,
-                notify_per_command: None, // Should default to false
+                // notifyPerCommand not specified - should default to false
+                launch: LaunchOptions::default(),
             },
         ];
 
@@ -1682,3 +1930,1324 @@ mod user_prompt_submit_command_tests {
         assert_eq!(env_vars.get("CONCLAUDE_USER_PROMPT"), Some(&"".to_string()));
     }
 }
+
+#[cfg(test)]
+mod command_launch_tests {
+    use super::*;
+    use crate::config::{CommandShell, RunMode, StopCommand, StopConfig};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    async fn run_to_string(
+        command: &str,
+        launch: &CommandLaunch,
+        config_dir: &Path,
+    ) -> anyhow::Result<String> {
+        let child = spawn_command(command, launch, &HashMap::new(), config_dir)?;
+        let output = child.wait_with_output().await?;
+        assert!(output.status.success());
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    #[test]
+    fn test_collect_stop_commands_carries_launch_settings() {
+        let config = ConclaudeConfig {
+            stop: StopConfig {
+                commands: vec![StopCommand {
+                    run: "cargo test".to_string(),
+                    message: None,
+                    launch: LaunchOptions {
+                        env: Some(HashMap::from([(
+                            "RUST_LOG".to_string(),
+                            "debug".to_string(),
+                        )])),
+                        env_file: Some(".env.test".to_string()),
+                        cwd: Some("backend".to_string()),
+                        shell: Some(CommandShell::Sh),
+                        ..Default::default()
+                    },
+                    parser: None,
+                    report_file: None,
+                    fix: None,
+                    name: None,
+                    needs: None,
+                }],
+                infinite: false,
+                infinite_message: None,
+                parallel: false,
+                max_parallel: None,
+                fail_fast: true,
+            },
+            ..Default::default()
+        };
+
+        let commands = collect_stop_commands(&config).unwrap();
+        let launch = &commands[0].launch;
+        assert_eq!(launch.env.get("RUST_LOG"), Some(&"debug".to_string()));
+        assert_eq!(launch.env_file.as_deref(), Some(".env.test"));
+        assert_eq!(launch.cwd.as_deref(), Some("backend"));
+        assert_eq!(launch.shell, CommandShell::Sh);
+        assert_eq!(launch.mode, RunMode::Script);
+    }
+
+    #[test]
+    fn test_read_env_file_parses_dotenv_syntax() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        fs::write(
+            &path,
+            "# comment\n\nPLAIN=value # trailing\nexport EXPORTED=yes\nSINGLE='a # b'\nDOUBLE=\"line\\nbreak\"\n",
+        )
+        .unwrap();
+
+        let vars: HashMap<String, String> = read_env_file(&path).unwrap().into_iter().collect();
+        assert_eq!(vars.len(), 4);
+        assert_eq!(vars["PLAIN"], "value");
+        assert_eq!(vars["EXPORTED"], "yes");
+        assert_eq!(vars["SINGLE"], "a # b");
+        assert_eq!(vars["DOUBLE"], "line\nbreak");
+    }
+
+    #[test]
+    fn test_read_env_file_rejects_lines_without_assignment() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        fs::write(&path, "VALID=1\nnot an assignment\n").unwrap();
+
+        let err = read_env_file(&path).unwrap_err().to_string();
+        assert!(err.contains(":2:"), "error should name the line: {err}");
+    }
+
+    #[tokio::test]
+    async fn test_spawn_command_env_overrides_env_file() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(
+            temp_dir.path().join(".env"),
+            "FROM_FILE=file\nSHARED=file\n",
+        )?;
+        let launch = CommandLaunch {
+            env: HashMap::from([("SHARED".to_string(), "entry".to_string())]),
+            env_file: Some(".env".to_string()),
+            ..Default::default()
+        };
+
+        let stdout = run_to_string("echo $FROM_FILE-$SHARED", &launch, temp_dir.path()).await?;
+        assert_eq!(stdout, "file-entry");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_command_cwd_is_relative_to_config_dir() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir(temp_dir.path().join("backend"))?;
+        let launch = CommandLaunch {
+            cwd: Some("backend".to_string()),
+            ..Default::default()
+        };
+
+        let stdout = run_to_string("pwd", &launch, temp_dir.path()).await?;
+        assert!(stdout.ends_with("/backend"), "unexpected cwd: {stdout}");
+
+        let missing = CommandLaunch {
+            cwd: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(spawn_command("pwd", &missing, &HashMap::new(), temp_dir.path()).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_command_shell_none_executes_argv() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let launch = CommandLaunch {
+            shell: CommandShell::None,
+            ..Default::default()
+        };
+
+        // Without a shell, `$HOME` and `;` are passed through as literal arguments
+        let stdout = run_to_string("echo '$HOME' ';' done", &launch, temp_dir.path()).await?;
+        assert_eq!(stdout, "$HOME ; done");
+
+        assert!(spawn_command(
+            "echo 'unbalanced",
+            &launch,
+            &HashMap::new(),
+            temp_dir.path()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_command_uses_configured_shell() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let launch = CommandLaunch {
+            shell: CommandShell::Sh,
+            ..Default::default()
+        };
+
+        let stdout = run_to_string("echo sh-ran", &launch, temp_dir.path()).await?;
+        assert_eq!(stdout, "sh-ran");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_script_mode_runs_block_in_one_shell() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir(temp_dir.path().join("sub"))?;
+        let script = "cd sub\nif [ -d . ]; then\n  greet() { echo \"hi from $(basename \"$PWD\")\"; }\nfi\ngreet";
+
+        let stdout = run_to_string(script, &CommandLaunch::default(), temp_dir.path()).await?;
+        assert_eq!(stdout, "hi from sub");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_script_mode_reports_failing_line() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let script = "echo first\nfalse | cat\necho unreachable";
+
+        let child = spawn_command(
+            script,
+            &CommandLaunch::default(),
+            &HashMap::new(),
+            temp_dir.path(),
+        )?;
+        let output = child.wait_with_output().await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(
+            !stdout.contains("unreachable"),
+            "pipefail should stop the script"
+        );
+        assert_eq!(
+            script_failed_line(script, &stderr),
+            Some((2, "false | cat".to_string()))
+        );
+        assert!(
+            !strip_failure_markers(&stderr).contains("script failed at line"),
+            "the trap's marker should not be shown as stderr"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lines_mode_runs_command_without_strict_mode() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let launch = CommandLaunch {
+            mode: RunMode::Lines,
+            ..Default::default()
+        };
+
+        let stdout = run_to_string("false; echo continued", &launch, temp_dir.path()).await?;
+        assert_eq!(stdout, "continued");
+        Ok(())
+    }
+
+    #[test]
+    fn test_shell_none_rejects_multi_line_script() {
+        let temp_dir = TempDir::new().unwrap();
+        let launch = CommandLaunch {
+            shell: CommandShell::None,
+            ..Default::default()
+        };
+
+        let err = spawn_command("echo a\necho b", &launch, &HashMap::new(), temp_dir.path())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("mode: lines"), "unexpected error: {err}");
+    }
+}
+
+#[cfg(test)]
+mod stop_schedule_tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    fn stop_commands(yaml: &str) -> Vec<StopCommandConfig> {
+        let config: ConclaudeConfig = serde_yaml::from_str(yaml).unwrap();
+        collect_stop_commands(&config).unwrap()
+    }
+
+    fn concurrency(concurrency: usize) -> StopRunOptions {
+        StopRunOptions {
+            concurrency,
+            ..Default::default()
+        }
+    }
+
+    fn run_all() -> StopRunOptions {
+        StopRunOptions {
+            fail_fast: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collect_stop_commands_resolves_needs_to_command_indices() {
+        let commands = stop_commands(
+            r##"stop:
+  commands:
+    - run: "npm test"
+      needs: [build]
+    - run: |
+        npm ci
+        npm run build
+      mode: lines
+      name: build
+    - run: "# nothing to run"
+      name: empty
+      needs: [build]
+    - run: "npm run e2e"
+      needs: [empty]
+"##,
+        );
+
+        let needs: Vec<&[usize]> = commands.iter().map(|c| c.needs.as_slice()).collect();
+        // The test waits for the last build line, lines of an entry run in order, and an
+        // entry without commands forwards its own needs
+        assert_eq!(needs, vec![&[2][..], &[][..], &[1][..], &[2][..]]);
+    }
+
+    #[test]
+    fn test_collect_stop_commands_rejects_unknown_needs() {
+        let config: ConclaudeConfig = serde_yaml::from_str(
+            "stop:\n  commands:\n    - run: \"npm test\"\n      needs: [lint]\n",
+        )
+        .unwrap();
+        let err = collect_stop_commands(&config).err().unwrap().to_string();
+        assert!(err.contains("needs 'lint'"), "unexpected error: {err}");
+    }
+
+    #[tokio::test]
+    async fn test_parallel_stop_commands_run_concurrently() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            "stop:\n  commands:\n    - run: \"sleep 1\"\n    - run: \"sleep 1\"\n    - run: \"sleep 1\"\n",
+        );
+
+        let started = Instant::now();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(3),
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        assert!(result.is_none());
+        assert!(
+            started.elapsed() < Duration::from_millis(2500),
+            "commands should overlap, took {:?}",
+            started.elapsed()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_commands_receive_payload_env_vars() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "test \"$CONCLAUDE_SESSION_ID:$CONCLAUDE_STOP_HOOK_ACTIVE\" = s1:true"
+    - run: "test \"$CONCLAUDE_CONFIG_DIR\" = \"$PWD\""
+"#,
+        );
+        let env_vars = HashMap::from([
+            ("CONCLAUDE_SESSION_ID".to_string(), "s1".to_string()),
+            ("CONCLAUDE_STOP_HOOK_ACTIVE".to_string(), "true".to_string()),
+            ("CONCLAUDE_CONFIG_DIR".to_string(), "/elsewhere".to_string()),
+        ]);
+
+        let dir = temp_dir.path().canonicalize()?;
+        let result = execute_stop_commands(
+            &commands,
+            &env_vars,
+            &dir,
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        assert!(result.is_none(), "unexpected block: {:?}", result);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parser_diagnostics_replace_raw_output() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "printf 'checking...\nsrc/a.py:3: error: Name x is not defined\nsrc/a.py:3: error: Name x is not defined\n' && exit 1"
+      message: "Type check failed"
+      showStdout: true
+      parser: generic-file-line
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command should block");
+        assert_eq!(
+            result.message.as_deref(),
+            Some(
+                "Type check failed\n1 diagnostic in 1 file:\nsrc/a.py\n  3: error: Name x is not defined"
+            )
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parser_falls_back_to_raw_output() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "echo 'segmentation fault' && exit 1"
+      showStdout: true
+      parser: tsc
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command should block");
+        let message = result.message.unwrap();
+        assert!(
+            message.contains("Stdout: segmentation fault"),
+            "unexpected message: {message}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parser_reads_report_file_written_by_the_run() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: |
+        cat > report.xml <<'XML'
+        <testsuite><testcase name="test_login" file="tests/test_auth.py" line="12"><failure message="assert 401 == 200"/></testcase></testsuite>
+        XML
+        exit 1
+      showCommand: false
+      parser: junit-xml
+      reportFile: report.xml
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command should block");
+        assert_eq!(
+            result.message.as_deref(),
+            Some(
+                "Command failed with exit code 1\n1 diagnostic in 1 file:\ntests/test_auth.py\n  12: test_login: assert 401 == 200"
+            )
+        );
+
+        // The report left over from the previous run is not read again
+        let commands = stop_commands(
+            "stop:\n  commands:\n    - run: \"exit 1\"\n      showCommand: false\n      parser: junit-xml\n      reportFile: report.xml\n",
+        );
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command should block");
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Command failed with exit code 1")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parallel_failures_are_reported_in_config_order() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "sleep 0.5; exit 1"
+      message: "slow check failed"
+    - run: "exit 1"
+      message: "fast check failed"
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(2),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("both commands fail");
+        let message = result.message.unwrap();
+        let slow = message.find("slow check failed").unwrap();
+        let fast = message.find("fast check failed").unwrap();
+        assert!(slow < fast, "unexpected order: {message}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_command_is_skipped_when_its_needs_fail() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "touch independent"
+    - run: "exit 1"
+      name: build
+    - run: "touch dependent"
+      needs: [build]
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(2),
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        assert!(result.is_some());
+        assert!(temp_dir.path().join("independent").exists());
+        assert!(!temp_dir.path().join("dependent").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sequential_stop_commands_wait_for_later_needs() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "test -f generated"
+      needs: [generate]
+    - run: "touch generated"
+      name: generate
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(1),
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        assert!(result.is_none());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_fail_fast_stops_at_first_failure() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "exit 1"
+      message: "lint failed"
+    - run: "touch tested"
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(1),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("lint fails");
+        assert_eq!(result.message.as_deref(), Some("lint failed"));
+        assert!(!temp_dir.path().join("tested").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_all_reports_every_failure() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "echo 'src/lib.rs: unused import'; exit 1"
+      message: "Lint failed"
+      showStdout: true
+    - run: "touch passed"
+    - run: "echo 'test_add failed' >&2; exit 2"
+      showStderr: true
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            run_all(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("two commands fail");
+        assert!(temp_dir.path().join("passed").exists());
+        let message = result.message.unwrap();
+        assert!(
+            message.starts_with("2 of 3 stop commands failed:"),
+            "unexpected report: {message}"
+        );
+        assert!(message.contains(
+            "[1] Command failed with exit code 1: echo 'src/lib.rs: unused import'; exit 1\nLint failed\nStdout: src/lib.rs: unused import"
+        ));
+        assert!(message.contains(
+            "[2] Command failed with exit code 2: echo 'test_add failed' >&2; exit 2\nStderr: test_add failed"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_all_lists_commands_skipped_by_failed_needs() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "exit 1"
+      name: build
+    - run: "touch dependent"
+      needs: [build]
+    - run: "touch independent"
+"#,
+        );
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            run_all(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("build fails");
+        assert!(temp_dir.path().join("independent").exists());
+        assert!(!temp_dir.path().join("dependent").exists());
+        let message = result.message.unwrap();
+        assert!(
+            message.ends_with("Skipped because a command they need failed:\n  - touch dependent"),
+            "unexpected report: {message}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subagent_stop_run_all_returns_every_failure() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: crate::config::SubagentStopConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"exit 1\"\n      onFailure: block\n    - run: \"exit 0\"\n    - run: \"exit 3\"\n      onFailure: block\n",
+        )?;
+        let commands = collect_subagent_stop_commands(&config, &["*"])?;
+
+        let failures = execute_subagent_stop_commands(
+            &commands,
+            &std::collections::HashMap::new(),
+            temp_dir.path(),
+            false,
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        let summaries: Vec<&str> = failures.iter().map(|f| f.summary.as_str()).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                "Command failed with exit code 1: exit 1",
+                "Command failed with exit code 3: exit 3"
+            ]
+        );
+
+        let failures = execute_subagent_stop_commands(
+            &commands,
+            &std::collections::HashMap::new(),
+            temp_dir.path(),
+            true,
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        assert_eq!(failures.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failure_policy_precedence() {
+        let policy = CommandLaunch::from_options(&LaunchOptions {
+            on_failure: Some(FailureAction::Warn),
+            exit_codes: Some(BTreeMap::from([(2, FailureAction::Block)])),
+            ..Default::default()
+        })
+        .failure;
+        assert_eq!(
+            policy.action(Some(2), FailureAction::Ignore),
+            FailureAction::Block
+        );
+        assert_eq!(
+            policy.action(Some(1), FailureAction::Block),
+            FailureAction::Warn
+        );
+        assert_eq!(
+            policy.action(None, FailureAction::Block),
+            FailureAction::Warn
+        );
+        assert_eq!(
+            FailurePolicy::default().action(Some(1), FailureAction::Ignore),
+            FailureAction::Ignore
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_blocking_stop_failures_go_to_feedback() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "exit 1"
+      message: "Lint found style issues"
+      onFailure: warn
+    - run: "echo 'src/a.rs:1: unused import'; exit 3"
+      showStdout: true
+      exitCodes: {3: context}
+    - run: "exit 4"
+      onFailure: ignore
+      name: flaky
+    - run: "touch after-flaky"
+      needs: [flaky]
+"#,
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            run_all(),
+            &mut feedback,
+        )
+        .await?;
+        assert!(result.is_none());
+        // A tolerated failure satisfies the commands that need it
+        assert!(temp_dir.path().join("after-flaky").exists());
+        assert_eq!(feedback.warnings, vec!["Lint found style issues"]);
+        assert_eq!(feedback.context.len(), 1);
+        assert!(
+            feedback.context[0].starts_with("Command failed with exit code 3: echo")
+                && feedback.context[0].contains("\nStdout: src/a.rs:1: unused import"),
+            "unexpected context: {:?}",
+            feedback.context
+        );
+
+        let result = feedback.into_result();
+        assert_eq!(result.blocked, Some(false));
+        assert_eq!(result.message.as_deref(), Some("Lint found style issues"));
+        assert!(result.system_prompt.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exit_codes_override_on_failure() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            "stop:\n  commands:\n    - run: \"exit 2\"\n      onFailure: warn\n      exitCodes: {2: block}\n",
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut feedback,
+        )
+        .await?
+        .expect("exit code 2 blocks");
+        assert_eq!(result.blocked, Some(true));
+        assert!(feedback.warnings.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subagent_stop_failures_block_only_through_policy() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: crate::config::SubagentStopConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"exit 1\"\n    - run: \"exit 3\"\n      onFailure: block\n",
+        )?;
+        let commands = collect_subagent_stop_commands(&config, &["*"])?;
+
+        let failures = execute_subagent_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            true,
+            &mut CommandFeedback::default(),
+        )
+        .await?;
+        let summaries: Vec<&str> = failures.iter().map(|f| f.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Command failed with exit code 3: exit 3"]);
+        Ok(())
+    }
+
+    fn git_init(dir: &Path) {
+        let status = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[tokio::test]
+    async fn test_stop_fix_repairs_check_and_reports_modified_files() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        git_init(temp_dir.path());
+        fs::create_dir(temp_dir.path().join("src"))?;
+        fs::write(temp_dir.path().join("src/style.txt"), "messy\n")?;
+        fs::write(temp_dir.path().join("notes.txt"), "untouched\n")?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "grep -q tidy src/style.txt"
+      fix: "echo tidy > src/style.txt"
+"#,
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            run_all(),
+            &mut feedback,
+        )
+        .await?;
+        assert!(result.is_none());
+        assert_eq!(feedback.context.len(), 1);
+        // Files that were already dirty but left alone by the fix are not listed
+        assert!(
+            feedback.context[0]
+                .starts_with("A failing stop command passed after its fix ran: grep -q tidy")
+                && feedback.context[0].contains(
+                    "\nFix `echo tidy > src/style.txt` modified 1 file:\n  src/style.txt\n"
+                ),
+            "unexpected context: {:?}",
+            feedback.context
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parallel_fixes_report_only_their_own_files() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        git_init(temp_dir.path());
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "test -f a.txt"
+      fix: "sleep 0.3; touch a.txt"
+    - run: "test -f b.txt"
+      fix: "touch b.txt"
+    - run: "sleep 0.5; touch c.txt"
+"#,
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(3),
+            &mut feedback,
+        )
+        .await?;
+        assert!(result.is_none());
+        assert_eq!(feedback.context.len(), 2);
+        assert!(
+            feedback.context[0].contains("modified 1 file:\n  a.txt\n")
+                && feedback.context[1].contains("modified 1 file:\n  b.txt\n"),
+            "unexpected context: {:?}",
+            feedback.context
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_skips_fixes_once_another_failure_blocks() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "sleep 0.3; exit 1"
+    - run: "test -f fixed.txt"
+      fix: "touch fixed.txt"
+"#,
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(2),
+            &mut feedback,
+        )
+        .await?
+        .expect("the first command fails");
+        assert!(!temp_dir.path().join("fixed.txt").exists());
+        assert!(feedback.context.is_empty());
+        let message = result.message.unwrap();
+        assert!(
+            message.contains("Command failed with exit code 1: sleep 0.3; exit 1")
+                && message.contains("Command failed with exit code 1: test -f fixed.txt"),
+            "unexpected message: {message}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_blocks_when_check_still_fails_after_fix() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let commands = stop_commands(
+            r#"stop:
+  commands:
+    - run: "test -f fixed"
+      fix: "touch attempted; exit 1"
+"#,
+        );
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            concurrency(1),
+            &mut feedback,
+        )
+        .await?
+        .expect("check still fails after the fix");
+        assert!(temp_dir.path().join("attempted").exists());
+        let message = result.message.unwrap();
+        assert!(
+            message.contains("Command failed with exit code 1: test -f fixed")
+                && message.contains(
+                    "Fix `touch attempted; exit 1` exited with code 1 and ran outside a git repository"
+                ),
+            "unexpected message: {message}"
+        );
+        assert!(feedback.context.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subagent_stop_fix_avoids_block() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        git_init(temp_dir.path());
+        let config: crate::config::SubagentStopConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"test -f generated.rs\"\n      fix: \"touch generated.rs\"\n",
+        )?;
+        let commands = collect_subagent_stop_commands(&config, &["*"])?;
+
+        let mut feedback = CommandFeedback::default();
+        let failures = execute_subagent_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            true,
+            &mut feedback,
+        )
+        .await?;
+        assert!(failures.is_empty());
+        assert_eq!(feedback.context.len(), 1);
+        assert!(
+            feedback.context[0].starts_with("A failing subagent stop command passed")
+                && feedback.context[0]
+                    .contains("Fix `touch generated.rs` modified 1 file:\n  generated.rs"),
+            "unexpected context: {:?}",
+            feedback.context
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generic_commands_follow_exit_code_policy() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: crate::config::SetupConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"exit 2\"\n      message: \"Dependencies are stale\"\n      exitCodes: {2: warn}\n    - run: \"exit 1\"\n",
+        )?;
+        let commands = collect_setup_commands(&config, &["*"])?;
+
+        let mut feedback = CommandFeedback::default();
+        let result = execute_generic_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            "Setup",
+            &mut feedback,
+        )
+        .await?;
+        assert!(result.is_none());
+        assert_eq!(feedback.warnings, vec!["Dependencies are stale"]);
+
+        let config: crate::config::SetupConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"exit 1\"\n      onFailure: block\n",
+        )?;
+        let commands = collect_setup_commands(&config, &["*"])?;
+        let result = execute_generic_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            "Setup",
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("onFailure: block blocks on exit code 1");
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Command failed with exit code 1: exit 1")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generic_block_includes_shown_output() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: crate::config::SetupConfig = serde_yaml::from_str(
+            "commands:\n  \"*\":\n    - run: \"echo 'lockfile is out of date' >&2; exit 1\"\n      message: \"Dependencies are stale\"\n      showStderr: true\n      onFailure: block\n",
+        )?;
+        let commands = collect_setup_commands(&config, &["*"])?;
+
+        let result = execute_generic_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            "Setup",
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("onFailure: block blocks on exit code 1");
+        let message = result.message.expect("a blocked result has a message");
+        assert!(
+            message.starts_with("Dependencies are stale")
+                && message.contains("lockfile is out of date"),
+            "unexpected block message: {message}"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod command_retry_tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn retrying(retries: u32, retry_on: Option<&[i32]>) -> CommandLaunch {
+        CommandLaunch::from_options(&LaunchOptions {
+            retries: Some(retries),
+            retry_delay: Some(0),
+            retry_on: retry_on.map(<[i32]>::to_vec),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_the_command_succeeds() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let run = run_with_retries(
+            "echo x >> attempts; test $(wc -l < attempts) -ge 3",
+            &retrying(5, None),
+            None,
+            &HashMap::new(),
+            temp_dir.path(),
+            None,
+            None,
+        )
+        .await?;
+
+        assert_eq!(run.attempts, 3);
+        assert_eq!(run.attempts_suffix(), " after 3 attempts");
+        assert!(matches!(run.outcome, CommandOutcome::Exited(ref o) if o.status.success()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_the_last_retry() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let run = run_with_retries(
+            "exit 1",
+            &retrying(2, None),
+            None,
+            &HashMap::new(),
+            temp_dir.path(),
+            None,
+            None,
+        )
+        .await?;
+
+        assert_eq!(run.attempts, 3);
+        assert!(matches!(run.outcome, CommandOutcome::Exited(ref o) if o.status.code() == Some(1)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_retry_on_exit_codes_are_retried() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let launch = retrying(3, Some(&[75]));
+
+        let run = run_with_retries(
+            "exit 1",
+            &launch,
+            None,
+            &HashMap::new(),
+            temp_dir.path(),
+            None,
+            None,
+        )
+        .await?;
+        assert_eq!(run.attempts, 1);
+
+        let run = run_with_retries(
+            "exit 75",
+            &launch,
+            None,
+            &HashMap::new(),
+            temp_dir.path(),
+            None,
+            None,
+        )
+        .await?;
+        assert_eq!(run.attempts, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_exit_code_is_not_retried() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let run = run_with_retries(
+            "exit 2",
+            &retrying(3, None),
+            None,
+            &HashMap::new(),
+            temp_dir.path(),
+            Some(2),
+            None,
+        )
+        .await?;
+
+        assert_eq!(run.attempts, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_attempts_are_retried() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let run = run_with_retries(
+            "sleep 5",
+            &retrying(1, None),
+            Some(1),
+            &HashMap::new(),
+            temp_dir.path(),
+            None,
+            None,
+        )
+        .await?;
+
+        assert_eq!(run.attempts, 2);
+        assert!(matches!(run.outcome, CommandOutcome::TimedOut { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_after_each_attempt() {
+        let launch = CommandLaunch::from_options(&LaunchOptions {
+            retries: Some(3),
+            retry_delay: Some(2),
+            ..Default::default()
+        });
+        let delays: Vec<u64> = (1..=3)
+            .map(|attempt| launch.retry.delay_after(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![2, 4, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_failure_reports_attempts() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: ConclaudeConfig = serde_yaml::from_str(
+            "stop:\n  commands:\n    - run: \"exit 1\"\n      retries: 2\n      retryDelay: 0\n",
+        )?;
+        let commands = collect_stop_commands(&config)?;
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command fails");
+        assert_eq!(
+            result.message.as_deref(),
+            Some("Command failed with exit code 1 after 3 attempts: exit 1")
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod command_timeout_tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    async fn run_with_timeout(
+        command: &str,
+        timeout_secs: u64,
+        grace_period: u64,
+        dir: &Path,
+    ) -> anyhow::Result<CommandOutcome> {
+        let launch = CommandLaunch::from_options(&LaunchOptions {
+            grace_period: Some(grace_period),
+            ..Default::default()
+        });
+        let child = spawn_command(command, &launch, &HashMap::new(), dir)?;
+        wait_for_command(child, Some(timeout_secs), Duration::from_secs(grace_period)).await
+    }
+
+    #[tokio::test]
+    async fn test_timeout_keeps_partial_output() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let outcome = run_with_timeout(
+            "echo before; echo oops >&2; sleep 30",
+            1,
+            0,
+            temp_dir.path(),
+        )
+        .await?;
+
+        let CommandOutcome::TimedOut { stdout, stderr } = outcome else {
+            panic!("command should time out");
+        };
+        assert_eq!(String::from_utf8_lossy(&stdout), "before\n");
+        assert_eq!(String::from_utf8_lossy(&stderr), "oops\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_terminates_the_whole_process_group() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        run_with_timeout(
+            "(sleep 2; touch survived) & sleep 30",
+            1,
+            0,
+            temp_dir.path(),
+        )
+        .await?;
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(!temp_dir.path().join("survived").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sigterm_lets_the_command_clean_up() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let outcome = run_with_timeout(
+            "trap 'echo cleaned up; exit 0' TERM; sleep 30 & wait",
+            1,
+            5,
+            temp_dir.path(),
+        )
+        .await?;
+
+        let CommandOutcome::TimedOut { stdout, .. } = outcome else {
+            panic!("command should time out");
+        };
+        assert_eq!(String::from_utf8_lossy(&stdout), "cleaned up\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sigkill_after_the_grace_period() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let started = Instant::now();
+        let outcome = run_with_timeout("trap '' TERM; sleep 30", 1, 1, temp_dir.path()).await?;
+
+        assert!(matches!(outcome, CommandOutcome::TimedOut { .. }));
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "command should be killed, took {:?}",
+            started.elapsed()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_timeout_reports_partial_output() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config: ConclaudeConfig = serde_yaml::from_str(
+            r#"stop:
+  commands:
+    - run: "echo 'running 12 tests'; sleep 30"
+      timeout: 1
+      gracePeriod: 0
+      showStdout: true
+"#,
+        )?;
+        let commands = collect_stop_commands(&config)?;
+
+        let result = execute_stop_commands(
+            &commands,
+            &HashMap::new(),
+            temp_dir.path(),
+            StopRunOptions::default(),
+            &mut CommandFeedback::default(),
+        )
+        .await?
+        .expect("command times out");
+        assert_eq!(
+            result.message.as_deref(),
+            Some(
+                "Command timed out after 1 seconds: echo 'running 12 tests'; sleep 30\nStdout: running 12 tests\n"
+            )
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 // Export modules for testing
 pub mod config;
+pub mod diagnostics;
 pub mod gitignore;
 pub mod hooks;
+pub mod migrate;
+pub mod rules;
 
 #[cfg(test)]
 mod config_test;
@@ -34,3 +37,4 @@ mod hooks_tests;
 /// ```
 pub mod schema;
 pub mod types;
+pub mod yaml_edit;
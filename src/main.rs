@@ -1,9 +1,13 @@
 // Testing GitHub Actions workflow fixes
 mod config;
+mod diagnostics;
 mod gitignore;
 mod hooks;
+mod migrate;
+mod rules;
 mod schema;
 mod types;
+mod yaml_edit;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
@@ -32,6 +36,13 @@ fn set_agent_env(agent: Option<&str>) {
     }
 }
 
+/// Selects a configuration profile for the hook handlers via `CONCLAUDE_PROFILE`
+fn set_profile_env(profile: Option<&str>) {
+    if let Some(name) = profile {
+        std::env::set_var(config::PROFILE_ENV_VAR, name);
+    }
+}
+
 /// Claude Code hook handler CLI tool that processes hook events and manages lifecycle hooks
 #[derive(Parser)]
 #[command(
@@ -68,6 +79,10 @@ enum Commands {
     /// Hook commands for Claude Code integration
     #[clap(name = "Hooks")]
     Hooks {
+        /// Configuration profile to apply (overrides `CONCLAUDE_PROFILE`)
+        #[arg(long, global = true)]
+        profile: Option<String>,
+
         #[command(subcommand)]
         command: HooksCommands,
     },
@@ -86,9 +101,141 @@ enum Commands {
         /// Path to configuration file to validate
         #[arg(long)]
         config_path: Option<String>,
+
+        /// Output format for the validation report
+        #[arg(long, value_enum, default_value_t = ValidateFormat::Text)]
+        format: ValidateFormat,
+    },
+    /// Inspect the effective configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Rewrite legacy configuration fields to the current schema
+    Migrate {
+        /// Path to a configuration file, or a directory to search from
+        #[arg(long)]
+        config_path: Option<String>,
+
+        /// Write the migrated configuration instead of only printing the diff
+        #[arg(long)]
+        write: bool,
+    },
+    /// Add, list and remove file protection and tool rules
+    Rules {
+        /// Path to a configuration file, or a directory to search from
+        #[arg(long, global = true)]
+        config_path: Option<String>,
+
+        #[command(subcommand)]
+        command: RulesCommands,
     },
 }
 
+#[derive(Subcommand)]
+enum RulesCommands {
+    /// List the rules in the configuration file with their IDs
+    List,
+    /// Add a rule
+    Add {
+        #[command(subcommand)]
+        rule: AddRuleCommands,
+    },
+    /// Remove a rule by the ID shown by `conclaude rules list`
+    Remove {
+        /// Rule ID, e.g. "uneditable-2" or "tool-1"
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AddRuleCommands {
+    /// Protect files matching a glob pattern from edits (`preToolUse.uneditableFiles`)
+    Uneditable {
+        /// Glob pattern of the files to protect (e.g. "*.lock")
+        pattern: String,
+
+        /// Message shown when an edit is blocked
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Apply the rule only to agents matching this pattern (e.g. "coder")
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// Block a tool on matching files or Bash commands (`preToolUse.toolUsageValidation`)
+    ToolBlock {
+        /// Tool name to block (e.g. "Bash" or "Write"), or "*" for every tool
+        tool: String,
+
+        /// Glob pattern of the Bash commands to block (e.g. "git push --force*")
+        #[arg(long)]
+        command: Option<String>,
+
+        /// Glob pattern of the file paths to block
+        #[arg(long)]
+        pattern: Option<String>,
+
+        /// How --command is matched: "full" (default) or "prefix"
+        #[arg(long)]
+        match_mode: Option<String>,
+
+        /// Message shown when the tool is blocked
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Apply the rule only to agents matching this pattern (e.g. "coder")
+        #[arg(long)]
+        agent: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the merged configuration
+    Show {
+        /// Include every default value and mark the ones not set in any file
+        #[arg(long)]
+        resolved: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ShowFormat::Yaml)]
+        format: ShowFormat,
+
+        /// Show only the rules that apply to this agent (e.g. "coder" or "main")
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Configuration profile to apply (overrides `CONCLAUDE_PROFILE`)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Path to a configuration file, or a directory to search from
+        #[arg(long)]
+        config_path: Option<String>,
+    },
+}
+
+/// Output formats supported by `conclaude config show`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ShowFormat {
+    /// YAML, with `# default` comments on values that come from defaults
+    Yaml,
+    /// JSON, with the default values listed by path under `defaults`
+    Json,
+}
+
+/// Output formats supported by `conclaude validate`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ValidateFormat {
+    /// Human-readable report
+    Text,
+    /// Structured diagnostics as JSON
+    Json,
+    /// SARIF 2.1.0, for code scanning and CI annotations
+    Sarif,
+}
+
 #[derive(Subcommand)]
 enum HooksCommands {
     /// Process `PreToolUse` hook - fired before tool execution
@@ -307,6 +454,10 @@ enum HooksCommands {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Commands::Hooks { profile, .. } = &cli.command {
+        set_profile_env(profile.as_deref());
+    }
+
     match cli.command {
         Commands::Init {
             config_path,
@@ -314,7 +465,7 @@ async fn main() -> Result<()> {
             force,
             schema_url,
         } => handle_init(config_path, claude_path, force, schema_url).await,
-        Commands::Hooks { command } => match command {
+        Commands::Hooks { command, .. } => match command {
             HooksCommands::PreToolUse { agent } => {
                 set_agent_env(agent.as_deref());
                 handle_hook_result(handle_pre_tool_use).await
@@ -437,7 +588,27 @@ async fn main() -> Result<()> {
             }
         },
         Commands::Visualize { rule, show_matches } => handle_visualize(rule, show_matches).await,
-        Commands::Validate { config_path } => handle_validate(config_path).await,
+        Commands::Validate {
+            config_path,
+            format,
+        } => handle_validate(config_path, format).await,
+        Commands::Config { command } => match command {
+            ConfigCommands::Show {
+                resolved,
+                format,
+                agent,
+                profile,
+                config_path,
+            } => {
+                set_profile_env(profile.as_deref());
+                handle_config_show(config_path, resolved, format, agent.as_deref()).await
+            }
+        },
+        Commands::Migrate { config_path, write } => handle_migrate(config_path, write).await,
+        Commands::Rules {
+            config_path,
+            command,
+        } => handle_rules(config_path, command).await,
     }
 }
 
@@ -1148,8 +1319,10 @@ async fn handle_visualize(rule: Option<String>, show_matches: bool) -> Result<()
 ///
 /// Returns an error if configuration loading or validation fails.
 #[allow(clippy::unused_async)]
-async fn handle_validate(config_path: Option<String>) -> Result<()> {
-    println!("Validating conclaude configuration...");
+async fn handle_validate(config_path: Option<String>, format: ValidateFormat) -> Result<()> {
+    if format == ValidateFormat::Text {
+        println!("Validating conclaude configuration...");
+    }
 
     // Load and validate configuration
     let result = if let Some(custom_path) = config_path {
@@ -1167,12 +1340,17 @@ async fn handle_validate(config_path: Option<String>) -> Result<()> {
                 .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
             // Parse and validate using shared logic with enhanced error messages
-            let config = config::parse_and_validate_config(&content, &path)?;
-
-            Ok((config, path))
+            config::parse_and_validate_config(&content, &path).and_then(|_| {
+                // Treat the file as the project layer so global and local overrides still apply
+                config::load_layered_config_from(
+                    &path,
+                    config::global_config_path().as_deref(),
+                    config::active_profile().as_deref(),
+                )
+            })
         } else if path.is_dir() {
             // It's a directory - use the standard search from that directory
-            config::load_conclaude_config(Some(&path)).await
+            config::load_layered_config(Some(&path)).await
         } else {
             // Not a regular file or directory
             anyhow::bail!(
@@ -1182,13 +1360,65 @@ async fn handle_validate(config_path: Option<String>) -> Result<()> {
         }
     } else {
         // No custom path, use standard search from current directory
-        config::load_conclaude_config(None).await
+        config::load_layered_config(None).await
     };
 
+    if format != ValidateFormat::Text {
+        let diagnostics = match &result {
+            Ok(layered) => layered_diagnostics(layered),
+            Err(e) => error_diagnostics(e, None),
+        };
+        let report = if format == ValidateFormat::Json {
+            json_report(result.as_ref().ok(), &diagnostics)
+        } else {
+            sarif_report(&diagnostics)
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if diagnostics
+            .iter()
+            .any(|d| d.issue.severity == config::Severity::Error)
+        {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     match result {
-        Ok((config, found_path)) => {
+        Ok(layered) => {
+            // Every profile must produce a valid configuration, not just the active one
+            let mut profile_names: Vec<&String> = layered.config.profiles.keys().collect();
+            profile_names.sort();
+            let profile_errors = profile_errors(&layered);
+            if !profile_errors.is_empty() {
+                eprintln!("[ERROR] Configuration validation failed:\n");
+                for (name, error) in &profile_errors {
+                    eprintln!("Profile '{name}':\n{error}\n");
+                }
+                std::process::exit(1);
+            }
+
+            let config = &layered.config;
             println!("[OK] Configuration is valid!");
-            println!("   Config file: {}", found_path.display());
+            println!("   Config file: {}", layered.config_path.display());
+            if let Some(search) = &layered.search {
+                println!(
+                    "   Search: started at {}, stopped because it {}",
+                    search.start_dir.display(),
+                    search.boundary
+                );
+            }
+            if config.root {
+                println!("   Root: true (global configuration not applied)");
+            }
+            if let Some(profile) = &layered.profile {
+                println!("   Active profile: {profile}");
+            }
+            if !profile_names.is_empty() {
+                let names: Vec<&str> = profile_names.iter().map(|n| n.as_str()).collect();
+                println!("   Profiles validated: {}", names.join(", "));
+            }
+            println!();
+            print_config_layers(&layered);
             println!();
             println!("Configuration summary:");
             println!(
@@ -1205,6 +1435,18 @@ async fn handle_validate(config_path: Option<String>) -> Result<()> {
             );
             println!("   Infinite mode: {}", config.stop.infinite);
             println!("   Notifications enabled: {}", config.notifications.enabled);
+
+            let warnings = warning_diagnostics(&layered);
+            if !warnings.is_empty() {
+                println!();
+                println!("Warnings ({}):", warnings.len());
+                for warning in &warnings {
+                    println!("   [WARN] {}", warning.location());
+                    for line in warning.issue.message.lines() {
+                        println!("          {line}");
+                    }
+                }
+            }
             Ok(())
         }
         Err(e) => {
@@ -1214,3 +1456,601 @@ async fn handle_validate(config_path: Option<String>) -> Result<()> {
         }
     }
 }
+
+/// A problem reported by `conclaude validate`, with the file it was found in
+struct Diagnostic {
+    file: Option<PathBuf>,
+    issue: config::ConfigIssue,
+}
+
+impl Diagnostic {
+    /// Renders `file:line:column path`, leaving out the parts that are unknown
+    fn location(&self) -> String {
+        let mut location = match (&self.file, self.issue.line, self.issue.column) {
+            (Some(file), Some(line), Some(column)) => format!("{}:{line}:{column}", file.display()),
+            (Some(file), _, _) => file.display().to_string(),
+            (None, _, _) => String::new(),
+        };
+        if !self.issue.path.is_empty() {
+            if !location.is_empty() {
+                location.push(' ');
+            }
+            location.push_str(&self.issue.path);
+        }
+        location
+    }
+}
+
+/// Validates every profile of a loaded configuration, returning the profiles that fail
+fn profile_errors(layered: &config::LayeredConfig) -> Vec<(&String, anyhow::Error)> {
+    let mut profile_names: Vec<&String> = layered.config.profiles.keys().collect();
+    profile_names.sort();
+    profile_names
+        .into_iter()
+        .filter_map(|name| layered.config_for_profile(name).err().map(|e| (name, e)))
+        .collect()
+}
+
+/// Collects the profile errors and warnings of a configuration that loaded successfully
+fn layered_diagnostics(layered: &config::LayeredConfig) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = profile_errors(layered)
+        .iter()
+        .flat_map(|(name, error)| error_diagnostics(error, Some(name)))
+        .collect();
+    diagnostics.extend(warning_diagnostics(layered));
+    diagnostics
+}
+
+/// Finds settings that have no effect, located in the layer that contributed them
+fn warning_diagnostics(layered: &config::LayeredConfig) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> =
+        config::collect_config_warnings(&layered.config, Some(layered))
+            .into_iter()
+            .map(|mut issue| {
+                let (file, position) = layered.locate(&issue.path);
+                if let Some((line, column)) = position {
+                    issue.line = Some(line);
+                    issue.column = Some(column);
+                }
+                Diagnostic {
+                    file: Some(file),
+                    issue,
+                }
+            })
+            .collect();
+    diagnostics.sort_by(|a, b| (&a.file, a.issue.line).cmp(&(&b.file, b.issue.line)));
+    diagnostics
+}
+
+/// Converts a configuration loading error into diagnostics, keeping the structure of
+/// validation and parse errors
+fn error_diagnostics(error: &anyhow::Error, profile: Option<&str>) -> Vec<Diagnostic> {
+    let mut diagnostics =
+        if let Some(validation) = error.downcast_ref::<config::ConfigValidationError>() {
+            validation
+                .issues
+                .iter()
+                .map(|issue| Diagnostic {
+                    file: Some(validation.config_path.clone()),
+                    issue: issue.clone(),
+                })
+                .collect()
+        } else if let Some(parse) = error.downcast_ref::<config::ConfigParseError>() {
+            vec![Diagnostic {
+                file: Some(parse.config_path.clone()),
+                issue: parse.issue.clone(),
+            }]
+        } else {
+            let code = if error.downcast_ref::<config::ConfigNotFound>().is_some() {
+                "config-not-found"
+            } else {
+                "config-error"
+            };
+            vec![Diagnostic {
+                file: None,
+                issue: config::ConfigIssue::new(code, "", error.to_string()),
+            }]
+        };
+
+    if let Some(profile) = profile {
+        for diagnostic in &mut diagnostics {
+            diagnostic.issue.message = format!("Profile '{profile}': {}", diagnostic.issue.message);
+        }
+    }
+    diagnostics
+}
+
+/// Builds the `--format json` report
+fn json_report(
+    layered: Option<&config::LayeredConfig>,
+    diagnostics: &[Diagnostic],
+) -> serde_json::Value {
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.issue.severity == severity)
+            .count()
+    };
+    let errors = count(config::Severity::Error);
+
+    serde_json::json!({
+        "valid": errors == 0,
+        "configFile": layered.map(|l| l.config_path.display().to_string()),
+        "errorCount": errors,
+        "warningCount": count(config::Severity::Warning),
+        "diagnostics": diagnostics
+            .iter()
+            .map(|d| {
+                serde_json::json!({
+                    "severity": d.issue.severity.to_string(),
+                    "code": d.issue.code,
+                    "file": d.file.as_ref().map(|f| f.display().to_string()),
+                    "path": (!d.issue.path.is_empty()).then_some(&d.issue.path),
+                    "line": d.issue.line,
+                    "column": d.issue.column,
+                    "message": d.issue.message,
+                    "suggestions": d.issue.suggestions,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Builds the `--format sarif` report (SARIF 2.1.0)
+///
+/// File locations are relative to the current directory when possible, which is what
+/// code scanning services expect when `conclaude validate` runs from the repository root.
+fn sarif_report(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let cwd = std::env::current_dir().ok();
+
+    let mut rule_ids: Vec<&str> = diagnostics.iter().map(|d| d.issue.code).collect();
+    rule_ids.sort_unstable();
+    rule_ids.dedup();
+
+    let results: Vec<serde_json::Value> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut location = serde_json::Map::new();
+            if let Some(file) = &d.file {
+                let uri = cwd
+                    .as_deref()
+                    .and_then(|cwd| file.strip_prefix(cwd).ok())
+                    .unwrap_or(file);
+                let mut physical = serde_json::json!({
+                    "artifactLocation": { "uri": uri.display().to_string() }
+                });
+                if let (Some(line), Some(column)) = (d.issue.line, d.issue.column) {
+                    physical["region"] =
+                        serde_json::json!({ "startLine": line, "startColumn": column });
+                }
+                location.insert("physicalLocation".to_string(), physical);
+            }
+            if !d.issue.path.is_empty() {
+                location.insert(
+                    "logicalLocations".to_string(),
+                    serde_json::json!([{ "fullyQualifiedName": d.issue.path }]),
+                );
+            }
+
+            let mut result = serde_json::json!({
+                "ruleId": d.issue.code,
+                "level": d.issue.severity.to_string(),
+                "message": { "text": d.issue.message },
+            });
+            if !location.is_empty() {
+                result["locations"] = serde_json::json!([location]);
+            }
+            result
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "conclaude",
+                    "version": VERSION,
+                    "informationUri": "https://github.com/connerohnesorge/conclaude",
+                    "rules": rule_ids
+                        .iter()
+                        .map(|id| serde_json::json!({ "id": id }))
+                        .collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// Prints the configuration layers that were merged and which layer contributed each value
+fn print_config_layers(layered: &config::LayeredConfig) {
+    println!("Configuration layers (lowest to highest precedence):");
+    for layer in &layered.layers {
+        println!(
+            "   {:<8} {}",
+            format!("{}:", layer.kind),
+            layer.path.display()
+        );
+    }
+
+    if layered.layers.len() == 1 {
+        println!(
+            "   All values come from the {} layer",
+            layered.layers[0].kind
+        );
+        return;
+    }
+
+    println!();
+    println!("Value sources:");
+    for (yaml_path, layer_idx) in &layered.sources {
+        let layer = &layered.layers[*layer_idx];
+        println!("   {yaml_path}: {} ({})", layer.kind, layer.path.display());
+    }
+}
+
+/// Handles `conclaude config show`
+///
+/// Prints the merged configuration as written, or with `--resolved` the complete
+/// configuration the hooks run with, where every value that comes from a default is
+/// marked. `--agent` narrows agent-scoped rules to the ones that agent sees.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or serialized.
+async fn handle_config_show(
+    config_path: Option<String>,
+    resolved: bool,
+    format: ShowFormat,
+    agent: Option<&str>,
+) -> Result<()> {
+    let layered = match config_path.map(PathBuf::from) {
+        Some(path) if path.is_file() => config::load_layered_config_from(
+            &path,
+            config::global_config_path().as_deref(),
+            config::active_profile().as_deref(),
+        )?,
+        Some(path) if path.is_dir() => config::load_layered_config(Some(&path)).await?,
+        Some(path) => anyhow::bail!("Path not found: {}", path.display()),
+        None => config::load_layered_config(None).await?,
+    };
+
+    let mut config = layered.config.clone();
+    let mut explicit = layered.merged_value()?;
+    if let Some(agent) = agent {
+        apply_agent_view(&mut config, &mut explicit, agent)?;
+    }
+
+    let mut shown = if resolved {
+        let mut value = config::resolved_config_value(&config)?;
+        // The active profile is already folded into the effective configuration
+        if let Some(map) = value.as_mapping_mut() {
+            map.remove("profiles");
+        }
+        value
+    } else {
+        explicit.clone()
+    };
+    config::sort_config_maps(&mut shown);
+
+    match format {
+        ShowFormat::Yaml => {
+            println!("# Configuration: {}", layered.config_path.display());
+            if let Some(profile) = &layered.profile {
+                println!("# Profile: {profile}");
+            }
+            if let Some(agent) = agent {
+                println!("# Agent view: {agent}");
+            }
+            if resolved {
+                println!("# Values marked `# default` are not set in any configuration file");
+                print!(
+                    "{}",
+                    config::render_yaml_with_default_markers(&shown, &explicit)?
+                );
+            } else {
+                print!("{}", serde_yaml::to_string(&shown)?);
+            }
+        }
+        ShowFormat::Json => {
+            let mut report = serde_json::json!({
+                "configFile": layered.config_path.display().to_string(),
+                "profile": layered.profile,
+                "agent": agent,
+                "config": shown,
+            });
+            if resolved {
+                report["defaults"] =
+                    serde_json::json!(config::default_value_paths(&shown, &explicit));
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the agent-scoped rules that do not apply to `agent` from both the effective
+/// configuration and the configuration as written, keeping their entries aligned
+///
+/// Covers `preToolUse.uneditableFiles`, `preToolUse.toolUsageValidation` and the
+/// `subagentStop.commands` patterns, using the same matching as the hook handlers.
+///
+/// # Errors
+///
+/// Returns an error if a `subagentStop` pattern is not a valid glob.
+fn apply_agent_view(
+    config: &mut config::ConclaudeConfig,
+    explicit: &mut serde_yaml::Value,
+    agent: &str,
+) -> Result<()> {
+    fn retain_items(value: &mut serde_yaml::Value, path: [&str; 2], keep: &[bool]) {
+        let items = value
+            .get_mut(path[0])
+            .and_then(|section| section.get_mut(path[1]))
+            .and_then(serde_yaml::Value::as_sequence_mut);
+        if let Some(items) = items {
+            let mut flags = keep.iter();
+            items.retain(|_| flags.next().copied().unwrap_or(true));
+        }
+    }
+
+    let pre_tool_use = &mut config.pre_tool_use;
+    let keep: Vec<bool> = pre_tool_use
+        .uneditable_files
+        .iter()
+        .map(|rule| hooks::matches_agent_pattern(agent, rule.agent().unwrap_or("*")))
+        .collect();
+    let mut flags = keep.iter();
+    pre_tool_use
+        .uneditable_files
+        .retain(|_| *flags.next().unwrap_or(&true));
+    retain_items(explicit, ["preToolUse", "uneditableFiles"], &keep);
+
+    let keep: Vec<bool> = pre_tool_use
+        .tool_usage_validation
+        .iter()
+        .map(|rule| hooks::matches_agent_pattern(agent, rule.agent.as_deref().unwrap_or("*")))
+        .collect();
+    let mut flags = keep.iter();
+    pre_tool_use
+        .tool_usage_validation
+        .retain(|_| *flags.next().unwrap_or(&true));
+    retain_items(explicit, ["preToolUse", "toolUsageValidation"], &keep);
+
+    let matching: Vec<String> = hooks::match_subagent_patterns(agent, &config.subagent_stop)?
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    config
+        .subagent_stop
+        .commands
+        .retain(|pattern, _| matching.contains(pattern));
+    if let Some(commands) = explicit
+        .get_mut("subagentStop")
+        .and_then(|section| section.get_mut("commands"))
+        .and_then(serde_yaml::Value::as_mapping_mut)
+    {
+        commands.retain(|pattern, _| {
+            pattern
+                .as_str()
+                .is_some_and(|pattern| matching.iter().any(|m| m == pattern))
+        });
+    }
+
+    Ok(())
+}
+
+/// Handles `conclaude migrate`
+///
+/// Rewrites legacy fields in a configuration file to the current schema and prints a diff.
+/// Nothing is written unless `write` is set.
+///
+/// # Errors
+///
+/// Returns an error if no configuration file is found or it cannot be read, parsed or
+/// written.
+async fn handle_migrate(config_path: Option<String>, write: bool) -> Result<()> {
+    let path = config_file_to_edit(config_path)?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let migration = migrate::migrate_config(&content)
+        .with_context(|| format!("Failed to migrate {}", path.display()))?;
+
+    if migration.is_empty() {
+        println!(
+            "✅ {} already uses the current configuration format",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    println!("Migrations for {}:", path.display());
+    for step in &migration.steps {
+        println!("   - {step}");
+    }
+    if !migration.comments_preserved {
+        println!("⚠️  The file had to be re-rendered; comments and formatting were not kept");
+    }
+    println!();
+    let label = path.display().to_string();
+    print!(
+        "{}",
+        migrate::unified_diff(&content, &migration.migrated, &label, &label)
+    );
+    println!();
+
+    let remaining = config::parse_and_validate_config(&migration.migrated, &path).err();
+    if write {
+        fs::write(&path, &migration.migrated)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+        println!("✅ Updated {}", path.display());
+    } else {
+        println!("Dry run: re-run with --write to update {}", path.display());
+    }
+
+    if let Some(e) = remaining {
+        eprintln!("❌ The migrated configuration still has problems:");
+        eprintln!("{e:#}");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Resolves the configuration file that `migrate` and `rules` work on
+///
+/// `config_path` may name the file itself or a directory to search from; without it the
+/// search starts in the current directory.
+///
+/// # Errors
+///
+/// Returns an error if the path does not exist or the search finds no configuration file.
+fn config_file_to_edit(config_path: Option<String>) -> Result<PathBuf> {
+    let start_dir = match config_path.map(PathBuf::from) {
+        Some(path) if path.is_file() => return Ok(path),
+        Some(path) if path.is_dir() => Some(path),
+        Some(path) => anyhow::bail!("Path not found: {}", path.display()),
+        None => None,
+    };
+    let search = config::search_config(start_dir.as_deref())?;
+    match search.boundary {
+        config::SearchBoundary::ConfigFound(path) => Ok(path),
+        boundary => anyhow::bail!(
+            "No configuration file found; the search from {} {boundary}. Run `conclaude init` to create one",
+            search.start_dir.display()
+        ),
+    }
+}
+
+/// Handles `conclaude rules`
+///
+/// `add` and `remove` edit the configuration file in place, keeping its comments, and
+/// only write it once the edited configuration passes validation.
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be found, read or written, the rule
+/// ID does not exist, or the edited configuration is invalid.
+async fn handle_rules(config_path: Option<String>, command: RulesCommands) -> Result<()> {
+    let path = config_file_to_edit(config_path)?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    let (verb, edit) = match command {
+        RulesCommands::List => {
+            let listed = rules::list_rules(&content)?;
+            if listed.is_empty() {
+                println!(
+                    "No uneditableFiles or toolUsageValidation rules in {}",
+                    path.display()
+                );
+                return Ok(());
+            }
+            println!("Rules in {}:", path.display());
+            let width = listed
+                .iter()
+                .map(|r| r.id.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let mut section = None;
+            for rule in &listed {
+                if section != Some(rule.id.list) {
+                    println!();
+                    println!("preToolUse.{}:", rule.id.list.field());
+                    section = Some(rule.id.list);
+                }
+                println!("   {:<width$}  {}", rule.id.to_string(), rule.description);
+            }
+            return Ok(());
+        }
+        RulesCommands::Add { rule } => {
+            let (list, rule) = new_rule_value(rule)?;
+            ("Added", rules::add_rule(&content, &path, list, rule)?)
+        }
+        RulesCommands::Remove { id } => {
+            ("Removed", rules::remove_rule(&content, &path, id.parse()?)?)
+        }
+    };
+
+    fs::write(&path, &edit.content)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    println!(
+        "✅ {verb} {} in {}: {}",
+        edit.rule.id,
+        path.display(),
+        edit.rule.description
+    );
+    if !edit.comments_preserved {
+        println!("⚠️  The file had to be re-rendered; comments and formatting were not kept");
+    }
+    for warning in config::collect_config_warnings(&edit.config, None) {
+        println!("⚠️  {}: {}", warning.path, warning.message);
+    }
+    Ok(())
+}
+
+/// Builds the YAML value of a rule from `conclaude rules add` arguments
+///
+/// Options that were not given are left out; an uneditable rule with only a pattern is
+/// written as a plain string.
+///
+/// # Errors
+///
+/// Returns an error if a `tool-block` rule has neither a command nor a file pattern.
+fn new_rule_value(command: AddRuleCommands) -> Result<(rules::RuleList, serde_yaml::Value)> {
+    fn insert_given<const N: usize>(
+        map: &mut serde_yaml::Mapping,
+        fields: [(&str, Option<String>); N],
+    ) {
+        for (key, value) in fields {
+            if let Some(value) = value {
+                map.insert(key.into(), value.into());
+            }
+        }
+    }
+
+    match command {
+        AddRuleCommands::Uneditable {
+            pattern,
+            message: None,
+            agent: None,
+        } => Ok((rules::RuleList::Uneditable, pattern.into())),
+        AddRuleCommands::Uneditable {
+            pattern,
+            message,
+            agent,
+        } => {
+            let mut map = serde_yaml::Mapping::new();
+            map.insert("pattern".into(), pattern.into());
+            insert_given(&mut map, [("message", message), ("agent", agent)]);
+            Ok((rules::RuleList::Uneditable, map.into()))
+        }
+        AddRuleCommands::ToolBlock {
+            tool,
+            command,
+            pattern,
+            match_mode,
+            message,
+            agent,
+        } => {
+            if command.is_none() && pattern.is_none() {
+                anyhow::bail!(
+                    "A tool-block rule needs --command (Bash commands) or --pattern (file paths)"
+                );
+            }
+            let mut map = serde_yaml::Mapping::new();
+            map.insert("tool".into(), tool.into());
+            map.insert("pattern".into(), pattern.unwrap_or_default().into());
+            insert_given(
+                &mut map,
+                [("commandPattern", command), ("matchMode", match_mode)],
+            );
+            map.insert("action".into(), "block".into());
+            insert_given(&mut map, [("message", message), ("agent", agent)]);
+            Ok((rules::RuleList::Tool, map.into()))
+        }
+    }
+}
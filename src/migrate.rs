@@ -0,0 +1,390 @@
+//! Migration of configuration files written for older versions of conclaude.
+//!
+//! Several releases removed or moved configuration fields (see `spectr/changes/archive`).
+//! Because every section uses `deny_unknown_fields`, a file that still uses one of those
+//! shapes fails to load. [`migrate_config`] recognises the known legacy shapes and rewrites
+//! them to the current schema:
+//!
+//! - `stop.run` becomes one `stop.commands` entry per line
+//! - `rules.preventRootAdditions`, `rules.uneditableFiles` and `rules.toolUsageValidation`
+//!   move to `preToolUse`
+//! - `stop.rounds`, `preToolUse.preventGeneratedFileEdits`, `preToolUse.generatedFileMessage`
+//!   and `gitWorktree` are removed
+//!
+//! Edits are made to the text with [`YamlDocument`] so comments and ordering survive. If the
+//! edited text does not parse to the migrated value (for example because a section uses flow
+//! style), the whole file is re-rendered from the value instead.
+
+use crate::yaml_edit::{finish_edit, indent_lines, render_entry, render_item, YamlDocument};
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+
+/// Fields that moved from the `rules` section to `preToolUse`
+const RULES_FIELDS: [&str; 3] = [
+    "preventRootAdditions",
+    "uneditableFiles",
+    "toolUsageValidation",
+];
+
+/// Legacy fields that are removed without a replacement value, with what to use instead
+const REMOVED_FIELDS: [(&[&str], &str); 4] = [
+    (
+        &["stop", "rounds"],
+        "removed; use `stop.infinite: true` to keep running the stop commands",
+    ),
+    (
+        &["preToolUse", "preventGeneratedFileEdits"],
+        "removed; protect generated files with `preToolUse.uneditableFiles`",
+    ),
+    (
+        &["preToolUse", "generatedFileMessage"],
+        "removed; set `message` on a `preToolUse.uneditableFiles` rule instead",
+    ),
+    (
+        &["gitWorktree"],
+        "removed; git worktree support was dropped",
+    ),
+];
+
+/// One rewrite applied to a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    /// Dotted path of the legacy field
+    pub path: String,
+    /// What happened to it
+    pub description: String,
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+/// Result of migrating a configuration file
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Rewrites applied, in order; empty when the file needs no migration
+    pub steps: Vec<MigrationStep>,
+    /// The migrated file content
+    pub migrated: String,
+    /// Whether comments and ordering were kept, or the file had to be re-rendered
+    pub comments_preserved: bool,
+}
+
+impl Migration {
+    /// Whether the file already uses the current configuration format
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// Rewrites the legacy fields in a configuration file to the current schema
+///
+/// # Errors
+///
+/// Returns an error if `content` is not valid YAML.
+pub fn migrate_config(content: &str) -> Result<Migration> {
+    let original: Value =
+        serde_yaml::from_str(content).context("Failed to parse configuration as YAML")?;
+    let mut value = original.clone();
+    let mut doc = YamlDocument::parse(content);
+    let mut steps = Vec::new();
+
+    if value.is_mapping() {
+        let mut edited = migrate_stop_run(&mut value, &mut doc, &mut steps);
+        edited &= migrate_rules(&mut value, &mut doc, &mut steps);
+        for (path, description) in REMOVED_FIELDS {
+            edited &= remove_field(&mut value, &mut doc, &mut steps, path, description);
+        }
+
+        if !steps.is_empty() {
+            let (migrated, comments_preserved) = finish_edit(&doc, edited, content, &value)?;
+            return Ok(Migration {
+                steps,
+                migrated,
+                comments_preserved,
+            });
+        }
+    }
+
+    Ok(Migration {
+        steps,
+        migrated: content.to_string(),
+        comments_preserved: true,
+    })
+}
+
+/// Replaces the legacy `stop.run` script with one `stop.commands` entry per line
+///
+/// Blank lines and `#` comments are skipped. The new commands run before any existing ones,
+/// as the script did. Returns whether the text edit succeeded.
+fn migrate_stop_run(
+    value: &mut Value,
+    doc: &mut YamlDocument,
+    steps: &mut Vec<MigrationStep>,
+) -> bool {
+    let Some(stop) = value.get_mut("stop").and_then(Value::as_mapping_mut) else {
+        return true;
+    };
+    let Some(run) = stop.remove("run") else {
+        return true;
+    };
+
+    let commands: Vec<Value> = run
+        .as_str()
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut command = Mapping::new();
+            command.insert(Value::from("run"), Value::from(line));
+            Value::Mapping(command)
+        })
+        .collect();
+    let existing = stop
+        .get("commands")
+        .and_then(Value::as_sequence)
+        .cloned()
+        .unwrap_or_default();
+    let had_commands = stop.contains_key("commands");
+    let merged: Vec<Value> = commands.iter().cloned().chain(existing).collect();
+    stop.insert(Value::from("commands"), Value::Sequence(merged.clone()));
+    steps.push(MigrationStep {
+        path: "stop.run".to_string(),
+        description: format!(
+            "split into {} `stop.commands` entr{}",
+            commands.len(),
+            if commands.len() == 1 { "y" } else { "ies" }
+        ),
+    });
+
+    let Ok(items) = commands.iter().map(render_item).collect::<Result<Vec<_>>>() else {
+        return false;
+    };
+    if !had_commands {
+        let mut lines = vec![if items.is_empty() {
+            "commands: []".to_string()
+        } else {
+            "commands:".to_string()
+        }];
+        let unit = doc.indent_unit();
+        for item in &items {
+            lines.extend(indent_lines(item, unit));
+        }
+        return doc.replace(&["stop", "run"], &lines);
+    }
+    let moved = doc.insert_items(&["stop", "commands"], Some(0), &items)
+        // The existing commands are empty or in flow style
+        || render_entry("commands", &Value::Sequence(merged))
+            .is_ok_and(|lines| doc.replace(&["stop", "commands"], &lines));
+    moved && doc.remove(&["stop", "run"]).is_some()
+}
+
+/// Moves the fields of the legacy `rules` section to `preToolUse`
+///
+/// Lists already present under `preToolUse` are extended; other fields already set there
+/// win. The `rules` section is removed once it is empty. Returns whether the text edit
+/// succeeded.
+fn migrate_rules(
+    value: &mut Value,
+    doc: &mut YamlDocument,
+    steps: &mut Vec<MigrationStep>,
+) -> bool {
+    let Some(root) = value.as_mapping_mut() else {
+        return true;
+    };
+    let Some(mut rules) = root.get("rules").and_then(Value::as_mapping).cloned() else {
+        return true;
+    };
+    let moved: Vec<String> = rules
+        .keys()
+        .filter_map(Value::as_str)
+        .filter(|key| RULES_FIELDS.contains(key))
+        .map(String::from)
+        .collect();
+    if moved.is_empty() {
+        return true;
+    }
+
+    let had_pre_tool_use = root
+        .get("preToolUse")
+        .is_some_and(|section| !section.is_null());
+    if !root.get("preToolUse").is_some_and(Value::is_mapping) {
+        root.insert(Value::from("preToolUse"), Value::Mapping(Mapping::new()));
+    }
+    let whole_section = moved.len() == rules.len();
+    let mut edited = true;
+
+    // Without a preToolUse section the rules section can simply be renamed
+    let rename = !had_pre_tool_use && whole_section && doc.find(&["preToolUse"]).is_none();
+    if rename {
+        edited = doc.rename(&["rules"], "preToolUse");
+    }
+
+    for key in &moved {
+        let Some(legacy) = rules.remove(key.as_str()) else {
+            continue;
+        };
+        let Some(pre_tool_use) = root.get_mut("preToolUse").and_then(Value::as_mapping_mut) else {
+            continue;
+        };
+        let path = ["rules", key.as_str()];
+        let target = ["preToolUse", key.as_str()];
+        let description = if !pre_tool_use.contains_key(key.as_str()) {
+            pre_tool_use.insert(Value::from(key.as_str()), legacy);
+            if !rename {
+                edited &= doc
+                    .find(&path)
+                    .map(|block| doc.block_lines(block))
+                    .is_some_and(|lines| doc.insert(&["preToolUse"], &lines));
+            }
+            format!("moved to `preToolUse.{key}`")
+        } else if let (Some(Value::Sequence(existing)), Value::Sequence(legacy)) =
+            (pre_tool_use.get_mut(key.as_str()), legacy)
+        {
+            existing.extend(legacy);
+            let items: Vec<Vec<String>> = doc
+                .items(&path)
+                .into_iter()
+                .map(|item| doc.block_lines(item))
+                .collect();
+            edited &= items.is_empty() || doc.insert_items(&target, None, &items);
+            format!("appended to `preToolUse.{key}`")
+        } else {
+            format!("dropped; `preToolUse.{key}` is already set")
+        };
+        if !rename && !whole_section {
+            edited &= doc.remove(&path).is_some();
+        }
+        steps.push(MigrationStep {
+            path: format!("rules.{key}"),
+            description,
+        });
+    }
+
+    if rules.is_empty() {
+        root.remove("rules");
+        if !rename {
+            edited &= doc.remove(&["rules"]).is_some();
+        }
+    } else {
+        root.insert(Value::from("rules"), Value::Mapping(rules));
+    }
+    edited
+}
+
+/// Removes a legacy field that has no replacement value
+///
+/// Returns whether the text edit succeeded.
+fn remove_field(
+    value: &mut Value,
+    doc: &mut YamlDocument,
+    steps: &mut Vec<MigrationStep>,
+    path: &[&str],
+    description: &str,
+) -> bool {
+    let Some((field, parents)) = path.split_last() else {
+        return true;
+    };
+    let parent = parents
+        .iter()
+        .try_fold(&mut *value, |section, key| section.get_mut(*key));
+    let Some(parent) = parent.and_then(Value::as_mapping_mut) else {
+        return true;
+    };
+    if parent.remove(*field).is_none() {
+        return true;
+    }
+    steps.push(MigrationStep {
+        path: path.join("."),
+        description: description.to_string(),
+    });
+    doc.remove(path).is_some()
+}
+
+/// Line-based unified diff between two texts, with three lines of context
+///
+/// Returns an empty string when the texts are identical.
+#[must_use]
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != ' ')
+        .map(|(i, _)| i)
+        .collect();
+    let Some(&first_change) = changes.first() else {
+        return String::new();
+    };
+
+    // Group changes whose unchanged gap fits within the context of both hunks
+    let mut hunks = vec![(first_change, first_change)];
+    for &i in &changes[1..] {
+        let last = hunks.last_mut().expect("hunks starts non-empty");
+        if i - last.1 <= 2 * CONTEXT + 1 {
+            last.1 = i;
+        } else {
+            hunks.push((i, i));
+        }
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(ops.len());
+        let old_before = ops[..start].iter().filter(|(op, _)| *op != '+').count();
+        let new_before = ops[..start].iter().filter(|(op, _)| *op != '-').count();
+        let old_len = ops[start..end].iter().filter(|(op, _)| *op != '+').count();
+        let new_len = ops[start..end].iter().filter(|(op, _)| *op != '-').count();
+        out.push_str(&format!(
+            "@@ -{},{old_len} +{},{new_len} @@\n",
+            old_before + usize::from(old_len > 0),
+            new_before + usize::from(new_len > 0),
+        ));
+        for (op, line) in &ops[start..end] {
+            out.push_str(&format!("{op}{line}\n"));
+        }
+    }
+    out
+}
+
+/// Longest-common-subsequence diff, as lines tagged `' '`, `'-'` or `'+'`
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    // lcs[i][j] is the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', new[j]));
+            j += 1;
+        }
+    }
+    ops
+}
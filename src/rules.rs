@@ -0,0 +1,314 @@
+//! Editing of the `preToolUse` rule lists in a configuration file.
+//!
+//! Backs `conclaude rules`. A rule is addressed by its list and 1-based position in the
+//! file, e.g. `uneditable-2` or `tool-1`, as printed by `conclaude rules list`. Edits go
+//! through [`YamlDocument`] so the rest of the file, comments included, stays as written,
+//! and every edited file is validated before it is returned.
+
+use crate::config::{
+    parse_and_validate_config, ConclaudeConfig, ToolUsageRule, UnEditableFileRule,
+};
+use crate::yaml_edit::{finish_edit, indent_lines, render_item, YamlDocument};
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A `preToolUse` list that `conclaude rules` manages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleList {
+    /// `preToolUse.uneditableFiles`
+    Uneditable,
+    /// `preToolUse.toolUsageValidation`
+    Tool,
+}
+
+impl RuleList {
+    const ALL: [RuleList; 2] = [RuleList::Uneditable, RuleList::Tool];
+
+    /// Field name of the list under `preToolUse`
+    #[must_use]
+    pub fn field(self) -> &'static str {
+        match self {
+            RuleList::Uneditable => "uneditableFiles",
+            RuleList::Tool => "toolUsageValidation",
+        }
+    }
+
+    /// Prefix of the IDs of the rules in this list
+    fn prefix(self) -> &'static str {
+        match self {
+            RuleList::Uneditable => "uneditable",
+            RuleList::Tool => "tool",
+        }
+    }
+}
+
+/// Position of a rule within its list, written as e.g. `uneditable-1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleId {
+    pub list: RuleList,
+    /// 0-based index into the list
+    pub index: usize,
+}
+
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.list.prefix(), self.index + 1)
+    }
+}
+
+impl FromStr for RuleId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = s.rsplit_once('-').and_then(|(prefix, number)| {
+            let list = RuleList::ALL.into_iter().find(|l| l.prefix() == prefix)?;
+            let position = number.parse::<usize>().ok().filter(|&n| n > 0)?;
+            Some(RuleId {
+                list,
+                index: position - 1,
+            })
+        });
+        parsed.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid rule ID '{s}'; expected an ID such as uneditable-1 or tool-2 (see `conclaude rules list`)"
+            )
+        })
+    }
+}
+
+/// A rule in a configuration file, with a one-line description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSummary {
+    pub id: RuleId,
+    pub description: String,
+}
+
+/// Result of adding or removing a rule
+#[derive(Debug, Clone)]
+pub struct RuleEdit {
+    /// The rule that was added or removed
+    pub rule: RuleSummary,
+    /// The edited file content
+    pub content: String,
+    /// The configuration the edited file loads to
+    pub config: ConclaudeConfig,
+    /// Whether comments and ordering were kept, or the file had to be re-rendered
+    pub comments_preserved: bool,
+}
+
+/// Lists the `uneditableFiles` and `toolUsageValidation` rules written in a file
+///
+/// # Errors
+///
+/// Returns an error if `content` is not valid YAML or a rule does not have the shape of
+/// its list.
+pub fn list_rules(content: &str) -> Result<Vec<RuleSummary>> {
+    let value = parse_value(content)?;
+    let mut rules = Vec::new();
+    for list in RuleList::ALL {
+        for (index, rule) in list_items(&value, list).iter().enumerate() {
+            let id = RuleId { list, index };
+            rules.push(RuleSummary {
+                id,
+                description: describe_rule(list, rule)
+                    .with_context(|| format!("Invalid rule {id}"))?,
+            });
+        }
+    }
+    Ok(rules)
+}
+
+/// Appends `rule` to a list and validates the result
+///
+/// # Errors
+///
+/// Returns an error if `content` is not valid YAML, or if the file with the new rule fails
+/// validation.
+pub fn add_rule(
+    content: &str,
+    config_path: &Path,
+    list: RuleList,
+    rule: Value,
+) -> Result<RuleEdit> {
+    let mut value = parse_value(content)?;
+    let description = describe_rule(list, &rule)?;
+    let items = list_items(&value, list).to_vec();
+    let id = RuleId {
+        list,
+        index: items.len(),
+    };
+
+    let mut doc = YamlDocument::parse(content);
+    let path = ["preToolUse", list.field()];
+    let unit = doc.indent_unit();
+    let item = render_item(&rule)?;
+    let edited = if doc.insert_items(&path, None, std::slice::from_ref(&item)) {
+        true
+    } else {
+        let mut all = items.clone();
+        all.push(rule.clone());
+        let entry = sequence_entry(list.field(), &all, unit)?;
+        if doc.find(&path).is_some() {
+            doc.replace(&path, &entry)
+        } else {
+            doc.insert(&["preToolUse"], &entry)
+        }
+    };
+
+    let mut all = items;
+    all.push(rule);
+    set_list(&mut value, list, all)?;
+    finish(
+        &doc,
+        edited,
+        content,
+        &value,
+        config_path,
+        RuleSummary { id, description },
+    )
+}
+
+/// Removes the rule `id` and validates the result
+///
+/// # Errors
+///
+/// Returns an error if `content` is not valid YAML, `id` does not exist, or the file
+/// without the rule fails validation.
+pub fn remove_rule(content: &str, config_path: &Path, id: RuleId) -> Result<RuleEdit> {
+    let mut value = parse_value(content)?;
+    let mut items = list_items(&value, id.list).to_vec();
+    if id.index >= items.len() {
+        anyhow::bail!(
+            "No rule {id}: preToolUse.{} has {} rule(s)",
+            id.list.field(),
+            items.len()
+        );
+    }
+    let removed = items.remove(id.index);
+    let description = describe_rule(id.list, &removed)?;
+
+    let mut doc = YamlDocument::parse(content);
+    let edited = doc
+        .remove_item(&["preToolUse", id.list.field()], id.index)
+        .is_some();
+    set_list(&mut value, id.list, items)?;
+    finish(
+        &doc,
+        edited,
+        content,
+        &value,
+        config_path,
+        RuleSummary { id, description },
+    )
+}
+
+/// Checks an edited document and validates the configuration it produces
+fn finish(
+    doc: &YamlDocument,
+    edited: bool,
+    original: &str,
+    expected: &Value,
+    config_path: &Path,
+    rule: RuleSummary,
+) -> Result<RuleEdit> {
+    let (content, comments_preserved) = finish_edit(doc, edited, original, expected)?;
+    let config = parse_and_validate_config(&content, config_path).with_context(|| {
+        format!(
+            "The configuration would be invalid after this change to {}",
+            rule.id
+        )
+    })?;
+    Ok(RuleEdit {
+        rule,
+        content,
+        config,
+        comments_preserved,
+    })
+}
+
+/// Parses a configuration file as a YAML value, treating an empty file as an empty mapping
+fn parse_value(content: &str) -> Result<Value> {
+    let value: Value =
+        serde_yaml::from_str(content).context("Failed to parse configuration as YAML")?;
+    Ok(if value.is_null() {
+        Value::Mapping(Mapping::new())
+    } else {
+        value
+    })
+}
+
+/// The rules written in a list, or none when the list is missing
+fn list_items(value: &Value, list: RuleList) -> &[Value] {
+    value
+        .get("preToolUse")
+        .and_then(|section| section.get(list.field()))
+        .and_then(Value::as_sequence)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Replaces a list in a configuration value, creating `preToolUse` when needed
+fn set_list(value: &mut Value, list: RuleList, items: Vec<Value>) -> Result<()> {
+    let root = value
+        .as_mapping_mut()
+        .context("The configuration file is not a YAML mapping")?;
+    let section = root
+        .entry(Value::from("preToolUse"))
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    if section.is_null() {
+        *section = Value::Mapping(Mapping::new());
+    }
+    section
+        .as_mapping_mut()
+        .context("preToolUse is not a YAML mapping")?
+        .insert(Value::from(list.field()), Value::Sequence(items));
+    Ok(())
+}
+
+/// Renders `key:` followed by `items` as an indented block sequence
+fn sequence_entry(key: &str, items: &[Value], indent: usize) -> Result<Vec<String>> {
+    let mut lines = vec![format!("{key}:")];
+    for item in items {
+        lines.extend(indent_lines(&render_item(item)?, indent));
+    }
+    Ok(lines)
+}
+
+/// One-line description of a rule, as shown by `conclaude rules list`
+fn describe_rule(list: RuleList, rule: &Value) -> Result<String> {
+    let description = match list {
+        RuleList::Uneditable => {
+            let rule: UnEditableFileRule = serde_yaml::from_value(rule.clone())?;
+            let mut text = format!("\"{}\"", rule.pattern());
+            push_details(&mut text, rule.message(), rule.agent());
+            text
+        }
+        RuleList::Tool => {
+            let rule: ToolUsageRule = serde_yaml::from_value(rule.clone())?;
+            let mut text = format!("{} {}", rule.action, rule.tool);
+            match &rule.command_pattern {
+                Some(command) => {
+                    text.push_str(&format!(" command \"{command}\""));
+                    if let Some(mode) = &rule.match_mode {
+                        text.push_str(&format!(" ({mode})"));
+                    }
+                }
+                None => text.push_str(&format!(" files \"{}\"", rule.pattern)),
+            }
+            push_details(&mut text, rule.message.as_deref(), rule.agent.as_deref());
+            text
+        }
+    };
+    Ok(description)
+}
+
+fn push_details(text: &mut String, message: Option<&str>, agent: Option<&str>) {
+    if let Some(agent) = agent {
+        text.push_str(&format!(" [agent: {agent}]"));
+    }
+    if let Some(message) = message {
+        text.push_str(&format!(" - {}", message.trim().replace('\n', " ")));
+    }
+}
@@ -0,0 +1,479 @@
+//! Comment-preserving edits to block-style YAML documents.
+//!
+//! Round-tripping a configuration file through `serde_yaml` drops every comment and blank
+//! line the user wrote. [`YamlDocument`] edits the text instead: it locates a mapping entry
+//! or sequence item by its indentation and removes, replaces or inserts whole blocks of
+//! lines, leaving the rest of the file untouched.
+//!
+//! Only block-style mappings and sequences can be navigated. Callers should parse the edited
+//! text again and fall back to re-rendering the value when a document uses flow style
+//! (`{ ... }` or `[ ... ]`) for a section that had to change.
+
+use anyhow::Result;
+use serde_yaml::Value;
+use std::fmt;
+
+/// Lines of a mapping entry or sequence item within a [`YamlDocument`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    /// First line of the comments directly above the entry
+    pub start: usize,
+    /// Line holding the key, or the `-` of a sequence item
+    pub line: usize,
+    /// One past the last line of the entry's value
+    pub end: usize,
+    /// Column of the key or `-`
+    pub indent: usize,
+}
+
+/// A YAML document held as lines of text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct YamlDocument {
+    lines: Vec<String>,
+}
+
+impl YamlDocument {
+    /// Splits `content` into lines for editing
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(String::from).collect(),
+        }
+    }
+
+    /// Finds the mapping entry at `path`, e.g. `["preToolUse", "uneditableFiles"]`
+    #[must_use]
+    pub fn find(&self, path: &[&str]) -> Option<Block> {
+        let (mut from, mut to) = (0, self.lines.len());
+        let mut found = None;
+        for key in path {
+            let (_, block) = self
+                .entries(from, to)
+                .into_iter()
+                .find(|(name, _)| name == key)?;
+            (from, to) = (block.line + 1, block.end);
+            found = Some(block);
+        }
+        found
+    }
+
+    /// Items of the block sequence at `path`, in document order
+    ///
+    /// Returns an empty list when the entry is missing, empty or written in flow style.
+    #[must_use]
+    pub fn items(&self, path: &[&str]) -> Vec<Block> {
+        let Some((from, to)) = self.region(path) else {
+            return Vec::new();
+        };
+        let Some(first) = (from..to).find(|&i| is_content(&self.lines[i])) else {
+            return Vec::new();
+        };
+        if !is_item(&self.lines[first]) {
+            return Vec::new();
+        }
+        let indent = indent_of(&self.lines[first]);
+        (first..to)
+            .filter(|&i| {
+                let line = &self.lines[i];
+                is_content(line) && is_item(line) && indent_of(line) == indent
+            })
+            .map(|line| Block {
+                start: self.leading_comments_start(line, indent),
+                line,
+                end: self.block_end(line, indent, true),
+                indent,
+            })
+            .collect()
+    }
+
+    /// Lines of `block`, including the comments above it, with its indentation removed
+    #[must_use]
+    pub fn block_lines(&self, block: Block) -> Vec<String> {
+        dedent(&self.lines[block.start..block.end], block.indent)
+    }
+
+    /// Removes the entry at `path` together with the comments directly above it
+    ///
+    /// A parent mapping left without entries is rewritten as `key: {}` so that it still
+    /// parses as a mapping. Returns the removed lines with their indentation stripped.
+    pub fn remove(&mut self, path: &[&str]) -> Option<Vec<String>> {
+        let block = self.find(path)?;
+        let removed = self.remove_block(block);
+        if let Some((_, parent_path)) = path.split_last() {
+            self.close_if_empty(parent_path);
+        }
+        Some(removed)
+    }
+
+    /// Removes item `index` of the block sequence at `path`
+    ///
+    /// Returns the removed lines with their indentation stripped.
+    pub fn remove_item(&mut self, path: &[&str], index: usize) -> Option<Vec<String>> {
+        let items = self.items(path);
+        let block = *items.get(index)?;
+        let removed = self.remove_block(block);
+        if items.len() == 1 {
+            if let Some(entry) = self.find(path) {
+                self.lines[entry.line] = format!("{}: []", key_prefix(&self.lines[entry.line]));
+            }
+        }
+        Some(removed)
+    }
+
+    /// Renames the key of the entry at `path`, keeping its value and any trailing comment
+    pub fn rename(&mut self, path: &[&str], new_key: &str) -> bool {
+        let Some(block) = self.find(path) else {
+            return false;
+        };
+        let line = &self.lines[block.line];
+        let prefix = key_prefix(line);
+        let rest = &line[prefix.len() + 1..];
+        self.lines[block.line] = format!("{}{new_key}:{rest}", " ".repeat(block.indent));
+        true
+    }
+
+    /// Replaces the entry at `path` with `lines`, which start at column zero
+    ///
+    /// Comments above the entry are kept.
+    pub fn replace(&mut self, path: &[&str], lines: &[String]) -> bool {
+        let Some(block) = self.find(path) else {
+            return false;
+        };
+        let lines = indent_lines(lines, block.indent);
+        self.lines.splice(block.line..block.end, lines);
+        true
+    }
+
+    /// Appends an entry (`lines`, starting at column zero) to the mapping at `parent`
+    ///
+    /// Missing parent mappings are created, and an empty `key: {}` parent is opened up.
+    pub fn insert(&mut self, parent: &[&str], lines: &[String]) -> bool {
+        let unit = self.indent_unit();
+        let Some((last, ancestors)) = parent.split_last() else {
+            let at = self
+                .lines
+                .iter()
+                .rposition(|line| is_content(line))
+                .map_or(0, |i| i + 1);
+            let mut block = lines.to_vec();
+            if at > 0 {
+                block.insert(0, String::new());
+            }
+            self.lines.splice(at..at, block);
+            return true;
+        };
+
+        let Some(block) = self.find(parent) else {
+            let mut nested = vec![format!("{last}:")];
+            nested.extend(indent_lines(lines, unit));
+            return self.insert(ancestors, &nested);
+        };
+
+        let header = &self.lines[block.line];
+        let inline = header[key_prefix(header).len() + 1..].trim();
+        if inline == "{}" {
+            self.lines[block.line] = format!("{}:", key_prefix(header));
+        } else if !inline.is_empty() && !inline.starts_with('#') {
+            // A flow mapping or scalar cannot take block entries
+            return false;
+        }
+
+        let indent = self
+            .entries(block.line + 1, block.end)
+            .first()
+            .map_or(block.indent + unit, |(_, child)| child.indent);
+        self.lines
+            .splice(block.end..block.end, indent_lines(lines, indent));
+        true
+    }
+
+    /// Inserts sequence items into the block sequence at `path`
+    ///
+    /// Each item is given as lines starting at column zero, the first beginning with `- `.
+    /// Items go before item `before`, or after the last item when `before` is `None`.
+    /// Returns `false` when the sequence has no block items to line the new ones up with.
+    pub fn insert_items(
+        &mut self,
+        path: &[&str],
+        before: Option<usize>,
+        items: &[Vec<String>],
+    ) -> bool {
+        let existing = self.items(path);
+        let Some(first) = existing.first() else {
+            return false;
+        };
+        let at = match before {
+            Some(index) => existing.get(index).map_or(first.start, |item| item.start),
+            None => existing.last().map_or(first.end, |item| item.end),
+        };
+        let lines: Vec<String> = items
+            .iter()
+            .flat_map(|item| indent_lines(item, first.indent))
+            .collect();
+        self.lines.splice(at..at, lines);
+        true
+    }
+
+    /// Indentation step used by the document, from its first indented line
+    #[must_use]
+    pub fn indent_unit(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| is_content(line))
+            .map(|line| indent_of(line))
+            .find(|&indent| indent > 0)
+            .unwrap_or(2)
+    }
+
+    /// Lines spanned by the value of the entry at `path`, or the whole document
+    fn region(&self, path: &[&str]) -> Option<(usize, usize)> {
+        if path.is_empty() {
+            return Some((0, self.lines.len()));
+        }
+        self.find(path).map(|block| (block.line + 1, block.end))
+    }
+
+    /// Mapping entries whose keys sit at the indentation of the first line in `from..to`
+    fn entries(&self, from: usize, to: usize) -> Vec<(String, Block)> {
+        let Some(first) = (from..to).find(|&i| is_content(&self.lines[i])) else {
+            return Vec::new();
+        };
+        if is_item(&self.lines[first]) {
+            return Vec::new();
+        }
+        let indent = indent_of(&self.lines[first]);
+        (first..to)
+            .filter(|&i| {
+                let line = &self.lines[i];
+                is_content(line) && !is_item(line) && indent_of(line) == indent
+            })
+            .filter_map(|line| {
+                let key = parse_key(&self.lines[line])?;
+                Some((
+                    key,
+                    Block {
+                        start: self.leading_comments_start(line, indent),
+                        line,
+                        end: self.block_end(line, indent, false),
+                        indent,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// One past the last line belonging to the entry or item starting at `line`
+    ///
+    /// Blank lines and comments at or left of the entry's column after its last value line
+    /// belong to whatever follows.
+    fn block_end(&self, line: usize, indent: usize, item: bool) -> usize {
+        let mut end = line + 1;
+        for (i, text) in self.lines.iter().enumerate().skip(line + 1) {
+            if is_content(text) {
+                let column = indent_of(text);
+                // A key's block sequence may sit at the key's own column
+                if column < indent || (column == indent && (item || !is_item(text))) {
+                    break;
+                }
+                end = i + 1;
+            } else if !text.trim().is_empty() && indent_of(text) > indent {
+                end = i + 1;
+            }
+        }
+        end
+    }
+
+    /// First line of the run of comments directly above `line` at the same column
+    fn leading_comments_start(&self, line: usize, indent: usize) -> usize {
+        let mut start = line;
+        while let Some(previous) = start.checked_sub(1).map(|i| &self.lines[i]) {
+            let trimmed = previous.trim();
+            if !trimmed.starts_with('#')
+                || indent_of(previous) != indent
+                || trimmed.contains("yaml-language-server")
+            {
+                break;
+            }
+            start -= 1;
+        }
+        start
+    }
+
+    /// Deletes a block's lines, collapsing the blank lines that surrounded it into one
+    fn remove_block(&mut self, block: Block) -> Vec<String> {
+        let removed = self.block_lines(block);
+        self.lines.drain(block.start..block.end);
+        let at = block.start;
+        let blank_before = at == 0 || self.lines[at - 1].trim().is_empty();
+        let blank_after = self.lines.get(at).is_none_or(|line| line.trim().is_empty());
+        if blank_before && blank_after {
+            if at < self.lines.len() {
+                self.lines.remove(at);
+            } else if at > 0 {
+                self.lines.remove(at - 1);
+            }
+        }
+        removed
+    }
+
+    /// Rewrites the mapping at `path` as `key: {}` when it no longer has entries
+    fn close_if_empty(&mut self, path: &[&str]) {
+        let Some(block) = self.find(path) else {
+            return;
+        };
+        let empty = !self.lines[block.line + 1..block.end]
+            .iter()
+            .any(|line| is_content(line));
+        let header = &self.lines[block.line];
+        let inline = header[key_prefix(header).len() + 1..].trim();
+        if empty && (inline.is_empty() || inline.starts_with('#')) {
+            self.lines[block.line] = format!("{}: {{}}", key_prefix(header));
+        }
+    }
+}
+
+impl fmt::Display for YamlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Text of an edited document, checked against the value the edit should produce
+///
+/// Returns the edited text when `edited` is set and the text parses to `expected`. Otherwise
+/// `expected` is rendered from scratch below the leading comments of `original`. The flag
+/// in the result tells whether the original layout and comments were kept.
+///
+/// # Errors
+///
+/// Returns an error if `expected` cannot be serialized.
+pub fn finish_edit(
+    doc: &YamlDocument,
+    edited: bool,
+    original: &str,
+    expected: &Value,
+) -> Result<(String, bool)> {
+    let text = doc.to_string();
+    if edited && serde_yaml::from_str::<Value>(&text).is_ok_and(|parsed| parsed == *expected) {
+        return Ok((text, true));
+    }
+    let header: String = original
+        .lines()
+        .take_while(|line| line.trim_start().starts_with('#'))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    Ok((
+        format!("{header}{}", serde_yaml::to_string(expected)?),
+        false,
+    ))
+}
+
+/// Renders `key: value` as block YAML lines starting at column zero
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn render_entry(key: &str, value: &Value) -> Result<Vec<String>> {
+    let mut mapping = serde_yaml::Mapping::new();
+    mapping.insert(Value::String(key.to_string()), value.clone());
+    let text = serde_yaml::to_string(&Value::Mapping(mapping))?;
+    Ok(text.lines().map(String::from).collect())
+}
+
+/// Renders `value` as the lines of a block sequence item, starting with `- `
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn render_item(value: &Value) -> Result<Vec<String>> {
+    let text = serde_yaml::to_string(value)?;
+    Ok(text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match (i, line.is_empty()) {
+            (0, _) => format!("- {line}"),
+            (_, true) => String::new(),
+            _ => format!("  {line}"),
+        })
+        .collect())
+}
+
+/// Shifts `lines` right by `indent` columns, leaving blank lines empty
+#[must_use]
+pub fn indent_lines(lines: &[String], indent: usize) -> Vec<String> {
+    let pad = " ".repeat(indent);
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{pad}{line}")
+            }
+        })
+        .collect()
+}
+
+/// Shifts `lines` left by up to `indent` columns
+fn dedent(lines: &[String], indent: usize) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line[indent_of(line).min(indent)..].to_string())
+        .collect()
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Whether a line holds YAML content rather than a comment, blank or document marker
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "---" && trimmed != "..."
+}
+
+fn is_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed == "-" || trimmed.starts_with("- ")
+}
+
+/// The key of a `key: value` line, unquoted
+fn parse_key(line: &str) -> Option<String> {
+    let prefix = key_prefix(line);
+    let key = prefix.trim_start();
+    if prefix.len() == line.len() || key.is_empty() {
+        return None;
+    }
+    let unquoted = key
+        .strip_prefix('"')
+        .and_then(|k| k.strip_suffix('"'))
+        .or_else(|| key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')))
+        .unwrap_or(key);
+    Some(unquoted.to_string())
+}
+
+/// The part of a `key: value` line before the separating colon, including indentation
+///
+/// Returns the whole line when it has no separating colon.
+fn key_prefix(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    let offset = line.len() - trimmed.len();
+    let key_end = match trimmed.chars().next() {
+        Some(quote @ ('"' | '\'')) => trimmed[1..].find(quote).map(|i| i + 2),
+        _ => Some(0),
+    };
+    let Some(key_end) = key_end else {
+        return line;
+    };
+    let colon = trimmed[key_end..].char_indices().find(|&(i, c)| {
+        let next = trimmed[key_end + i + 1..].chars().next();
+        c == ':' && next.is_none_or(|n| n == ' ' || n == '\t')
+    });
+    match colon {
+        Some((i, _)) => &line[..offset + key_end + i],
+        None => line,
+    }
+}
@@ -1,5 +1,7 @@
 use conclaude::config::{
-    extract_bash_commands, generate_default_config, load_conclaude_config, ConclaudeConfig,
+    collect_config_warnings, extract_bash_commands, find_nearest_config, generate_default_config,
+    load_conclaude_config, load_layered_config_from, search_config, split_run_commands,
+    ConclaudeConfig, ConfigFingerprint, ConfigLayerKind, ConfigStore, RunMode, SearchBoundary,
 };
 use std::fs;
 use tempfile::tempdir;
@@ -68,6 +70,35 @@ fn test_extract_bash_commands_handles_mixed_whitespace_and_comments() {
     assert_eq!(commands, vec!["echo hello", "npm test", "   echo world   "]);
 }
 
+#[test]
+fn test_split_run_commands_script_mode_keeps_block_whole() {
+    let script = "if true; then\n  echo hello\nfi\n";
+    let commands = split_run_commands(script, RunMode::Script).unwrap();
+    assert_eq!(commands, vec!["if true; then\n  echo hello\nfi"]);
+}
+
+#[test]
+fn test_split_run_commands_script_mode_skips_comment_only_block() {
+    let commands = split_run_commands("# nothing to run\n\n", RunMode::Script).unwrap();
+    assert_eq!(commands, Vec::<String>::new());
+}
+
+#[test]
+fn test_split_run_commands_lines_mode_splits_lines() {
+    let script = "echo hello\n# comment\nnpm test";
+    let commands = split_run_commands(script, RunMode::Lines).unwrap();
+    assert_eq!(commands, vec!["echo hello", "npm test"]);
+}
+
+#[test]
+fn test_run_mode_parses_from_yaml() {
+    let config: ConclaudeConfig = serde_yaml::from_str(
+        "stop:\n  commands:\n    - run: |\n        npm ci\n        npm test\n      mode: lines\n",
+    )
+    .unwrap();
+    assert_eq!(config.stop.commands[0].launch.mode, Some(RunMode::Lines));
+}
+
 #[tokio::test]
 async fn test_load_config_not_found() {
     let temp_dir = tempdir().unwrap();
@@ -854,6 +885,30 @@ async fn test_config_search_monorepo_nested_package_json() {
     assert!(config.pre_tool_use.prevent_root_additions);
 }
 
+#[test]
+fn test_find_nearest_config_from_missing_directory() {
+    let temp_dir = tempdir().unwrap();
+    fs::create_dir(temp_dir.path().join(".git")).unwrap();
+    fs::write(
+        temp_dir.path().join(".conclaude.yaml"),
+        "stop:\n  commands: []\n",
+    )
+    .unwrap();
+    let package_dir = temp_dir.path().join("packages").join("api");
+    fs::create_dir_all(&package_dir).unwrap();
+    fs::write(
+        package_dir.join(".conclaude.yaml"),
+        "stop:\n  commands: []\n",
+    )
+    .unwrap();
+
+    // A file about to be created in new directories belongs to the package around them
+    assert_eq!(
+        find_nearest_config(&package_dir.join("src").join("handlers")),
+        Some(package_dir.join(".conclaude.yaml"))
+    );
+}
+
 #[tokio::test]
 async fn test_config_search_stops_at_filesystem_root() {
     let temp_dir = tempdir().unwrap();
@@ -870,3 +925,708 @@ async fn test_config_search_stops_at_filesystem_root() {
     let error_message = result.unwrap_err().to_string();
     assert!(error_message.contains("Configuration file not found"));
 }
+
+#[tokio::test]
+async fn test_config_search_stops_at_git_root() {
+    let temp_dir = tempdir().unwrap();
+    let repo = temp_dir.path().join("repo");
+    let search_dir = repo.join("src");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(&search_dir).unwrap();
+
+    // A config above the repository must not govern it
+    fs::write(
+        temp_dir.path().join(".conclaude.yaml"),
+        generate_default_config(),
+    )
+    .unwrap();
+
+    let search = search_config(Some(&search_dir)).unwrap();
+    assert_eq!(search.boundary, SearchBoundary::GitRoot(repo.clone()));
+    assert_eq!(search.paths.last().unwrap(), &repo.join(".conclaude.yml"));
+
+    let error_message = load_conclaude_config(Some(&search_dir))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error_message.contains("Configuration file not found"));
+    assert!(
+        error_message.contains("reached the git repository root"),
+        "error should name the boundary: {error_message}"
+    );
+
+    // A config at the repository root itself is still found
+    fs::write(repo.join(".conclaude.yaml"), generate_default_config()).unwrap();
+    let (_, found_path) = load_conclaude_config(Some(&search_dir)).await.unwrap();
+    assert_eq!(found_path, repo.join(".conclaude.yaml"));
+}
+
+#[test]
+fn test_root_config_skips_global_layer() {
+    let temp_dir = tempdir().unwrap();
+    let global_path = temp_dir.path().join("global.yaml");
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&global_path, "stop:\n  infinite: true\n").unwrap();
+    fs::write(&project_path, "root: true\nstop:\n  commands: []\n").unwrap();
+
+    let layered = load_layered_config_from(&project_path, Some(&global_path), None).unwrap();
+
+    assert!(layered.config.root);
+    assert!(!layered.config.stop.infinite);
+    assert_eq!(layered.layers.len(), 1);
+    assert_eq!(layered.layers[0].kind, ConfigLayerKind::Project);
+}
+
+// ========== Layered Configuration Tests ==========
+
+#[test]
+fn test_layered_config_project_only() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "stop:\n  infinite: true\n").unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+
+    assert_eq!(layered.layers.len(), 1);
+    assert_eq!(layered.layers[0].kind, ConfigLayerKind::Project);
+    assert_eq!(layered.config_path, project_path);
+    assert!(layered.config.stop.infinite);
+}
+
+#[test]
+fn test_layered_config_merges_global_project_and_local() {
+    let temp_dir = tempdir().unwrap();
+    let global_path = temp_dir.path().join("global.yaml");
+    let project_dir = temp_dir.path().join("project");
+    fs::create_dir_all(&project_dir).unwrap();
+    let project_path = project_dir.join(".conclaude.yaml");
+    let local_path = project_dir.join(".conclaude.local.yaml");
+
+    fs::write(
+        &global_path,
+        r#"
+notifications:
+  enabled: true
+  hooks: ["Stop"]
+stop:
+  commands:
+    - run: "echo global"
+"#,
+    )
+    .unwrap();
+    fs::write(
+        &project_path,
+        r#"
+stop:
+  commands:
+    - run: "cargo test"
+  infinite: false
+preToolUse:
+  uneditableFiles:
+    - "*.lock"
+"#,
+    )
+    .unwrap();
+    fs::write(
+        &local_path,
+        r#"
+stop:
+  infinite: true
+notifications:
+  hooks: ["PreToolUse"]
+"#,
+    )
+    .unwrap();
+
+    let layered = load_layered_config_from(&project_path, Some(&global_path), None).unwrap();
+    let config = &layered.config;
+
+    let kinds: Vec<ConfigLayerKind> = layered.layers.iter().map(|l| l.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ConfigLayerKind::Global,
+            ConfigLayerKind::Project,
+            ConfigLayerKind::Local
+        ]
+    );
+
+    // Lists append in layer order
+    let runs: Vec<&str> = config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+    assert_eq!(runs, vec!["echo global", "cargo test"]);
+    assert_eq!(config.notifications.hooks, vec!["Stop", "PreToolUse"]);
+
+    // Scalars from later layers override earlier ones
+    assert!(config.stop.infinite);
+    assert!(config.notifications.enabled);
+    assert_eq!(config.pre_tool_use.uneditable_files.len(), 1);
+
+    // The config path stays the project file so hooks run from the project directory
+    assert_eq!(layered.config_path, project_path);
+}
+
+#[test]
+fn test_layered_config_records_value_sources() {
+    let temp_dir = tempdir().unwrap();
+    let global_path = temp_dir.path().join("global.yaml");
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    let local_path = temp_dir.path().join(".conclaude.local.yaml");
+
+    fs::write(&global_path, "notifications:\n  enabled: true\n").unwrap();
+    fs::write(
+        &project_path,
+        "stop:\n  infinite: false\n  commands:\n    - run: \"echo a\"\n",
+    )
+    .unwrap();
+    fs::write(&local_path, "stop:\n  infinite: true\n").unwrap();
+
+    let layered = load_layered_config_from(&project_path, Some(&global_path), None).unwrap();
+    let source = |path: &str| {
+        layered
+            .sources
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, idx)| layered.layers[*idx].kind)
+    };
+
+    assert_eq!(
+        source("notifications.enabled"),
+        Some(ConfigLayerKind::Global)
+    );
+    assert_eq!(source("stop.commands[0]"), Some(ConfigLayerKind::Project));
+    assert_eq!(source("stop.infinite"), Some(ConfigLayerKind::Local));
+    assert_eq!(
+        layered
+            .sources
+            .iter()
+            .filter(|(p, _)| p == "stop.infinite")
+            .count(),
+        1,
+        "Overridden values should only be attributed to the winning layer"
+    );
+}
+
+#[test]
+fn test_missing_config_outside_global_layer_is_flagged() {
+    let temp_dir = tempdir().unwrap();
+    let global_path = temp_dir.path().join("global.yaml");
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+
+    fs::write(&global_path, "missingConfig:\n  mode: block\n").unwrap();
+    fs::write(&project_path, generate_default_config()).unwrap();
+    let layered = load_layered_config_from(&project_path, Some(&global_path), None).unwrap();
+    let warnings = collect_config_warnings(&layered.config, Some(&layered));
+    assert!(!warnings.iter().any(|w| w.path == "missingConfig"));
+
+    fs::write(&global_path, "notifications:\n  enabled: true\n").unwrap();
+    fs::write(
+        &project_path,
+        format!(
+            "{}\nmissingConfig:\n  sessionStartHint: true\n",
+            generate_default_config()
+        ),
+    )
+    .unwrap();
+    let layered = load_layered_config_from(&project_path, Some(&global_path), None).unwrap();
+    let warnings = collect_config_warnings(&layered.config, Some(&layered));
+    let warning = warnings
+        .iter()
+        .find(|w| w.path == "missingConfig")
+        .expect("missingConfig in the project file should be flagged");
+    assert_eq!(warning.code, "ignored-setting");
+}
+
+#[test]
+fn test_layered_config_missing_global_is_ignored() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, generate_default_config()).unwrap();
+
+    let missing_global = temp_dir.path().join("does-not-exist.yaml");
+    let layered = load_layered_config_from(&project_path, Some(&missing_global), None).unwrap();
+
+    assert_eq!(layered.layers.len(), 1);
+}
+
+#[test]
+fn test_layered_config_reports_errors_against_originating_layer() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    let local_path = temp_dir.path().join(".conclaude.local.yaml");
+
+    fs::write(&project_path, "stop:\n  infinite: false\n").unwrap();
+    fs::write(&local_path, "stop:\n  infinit: true\n").unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains(".conclaude.local.yaml"));
+    assert!(error.contains("unknown field"));
+}
+
+#[tokio::test]
+async fn test_load_conclaude_config_applies_local_override() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join(".conclaude.yaml"),
+        "stop:\n  infinite: false\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join(".conclaude.local.yaml"),
+        "stop:\n  infinite: true\n",
+    )
+    .unwrap();
+
+    let (config, config_path) = load_conclaude_config(Some(temp_dir.path())).await.unwrap();
+
+    assert!(config.stop.infinite);
+    assert_eq!(config_path, temp_dir.path().join(".conclaude.yaml"));
+}
+
+// ========== extends / include Tests ==========
+
+#[test]
+fn test_extends_merges_base_before_project() {
+    let temp_dir = tempdir().unwrap();
+    let shared_dir = temp_dir.path().join("shared");
+    let project_dir = temp_dir.path().join("project");
+    fs::create_dir_all(&shared_dir).unwrap();
+    fs::create_dir_all(&project_dir).unwrap();
+
+    fs::write(
+        shared_dir.join("base.yaml"),
+        r#"
+stop:
+  infinite: true
+  commands:
+    - run: "echo base"
+preToolUse:
+  uneditableFiles:
+    - "*.lock"
+"#,
+    )
+    .unwrap();
+    let project_path = project_dir.join(".conclaude.yaml");
+    fs::write(
+        &project_path,
+        r#"
+extends: "../shared/base.yaml"
+stop:
+  infinite: false
+  commands:
+    - run: "echo project"
+"#,
+    )
+    .unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    let config = &layered.config;
+
+    let runs: Vec<&str> = config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+    assert_eq!(runs, vec!["echo base", "echo project"]);
+    assert!(!config.stop.infinite);
+    assert_eq!(config.pre_tool_use.uneditable_files.len(), 1);
+    assert!(config.extends.is_none());
+
+    // The base file is recorded as its own layer ahead of the project file
+    assert_eq!(layered.layers.len(), 2);
+    assert!(layered.layers[0].path.ends_with("base.yaml"));
+    assert_eq!(layered.layers[1].path, project_path);
+}
+
+#[test]
+fn test_config_dir_resolves_to_the_declaring_fragment() {
+    let temp_dir = tempdir().unwrap();
+    let shared_dir = temp_dir.path().join("shared");
+    fs::create_dir_all(&shared_dir).unwrap();
+    fs::write(
+        shared_dir.join("base.yaml"),
+        "stop:\n  commands:\n    - run: \"${CONFIG_DIR}/lint.sh\"\n",
+    )
+    .unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &project_path,
+        "extends: \"shared/base.yaml\"\nstop:\n  commands:\n    - run: \"${CONFIG_DIR}/test.sh\"\n",
+    )
+    .unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let runs: Vec<&str> = layered
+        .config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+    assert_eq!(
+        runs,
+        vec![
+            format!("{}/lint.sh", root.join("shared").display()),
+            format!("{}/test.sh", root.display()),
+        ]
+    );
+}
+
+#[test]
+fn test_include_fragment_directory_in_name_order() {
+    let temp_dir = tempdir().unwrap();
+    let fragments_dir = temp_dir.path().join(".conclaude.d");
+    fs::create_dir_all(&fragments_dir).unwrap();
+
+    fs::write(
+        fragments_dir.join("20-tests.yaml"),
+        "stop:\n  commands:\n    - run: \"cargo test\"\n",
+    )
+    .unwrap();
+    fs::write(
+        fragments_dir.join("10-lint.yaml"),
+        "stop:\n  commands:\n    - run: \"cargo clippy\"\n",
+    )
+    .unwrap();
+    fs::write(fragments_dir.join("README.md"), "not a fragment").unwrap();
+
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &project_path,
+        "include:\n  - \".conclaude.d\"\nstop:\n  commands:\n    - run: \"cargo fmt --check\"\n",
+    )
+    .unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    let runs: Vec<&str> = layered
+        .config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+
+    assert_eq!(
+        runs,
+        vec!["cargo fmt --check", "cargo clippy", "cargo test"]
+    );
+    assert!(layered.config.include.is_empty());
+    assert_eq!(layered.layers.len(), 3);
+}
+
+#[test]
+fn test_include_glob_pattern() {
+    let temp_dir = tempdir().unwrap();
+    let fragments_dir = temp_dir.path().join(".conclaude.d");
+    fs::create_dir_all(&fragments_dir).unwrap();
+    fs::write(
+        fragments_dir.join("notify.yaml"),
+        "notifications:\n  enabled: true\n",
+    )
+    .unwrap();
+
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "include:\n  - \".conclaude.d/*.yaml\"\n").unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    assert!(layered.config.notifications.enabled);
+}
+
+#[test]
+fn test_include_missing_file_reports_declaring_file() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "include:\n  - \"missing.yaml\"\n").unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("Included configuration not found"));
+    assert!(error.contains("missing.yaml"));
+    assert!(error.contains(".conclaude.yaml"));
+}
+
+#[test]
+fn test_extends_missing_base_reports_error() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "extends: \"base.yaml\"\n").unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("Base configuration not found"));
+}
+
+#[test]
+fn test_include_cycle_is_detected() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "include:\n  - \"a.yaml\"\n").unwrap();
+    fs::write(
+        temp_dir.path().join("a.yaml"),
+        "include:\n  - \".conclaude.yaml\"\n",
+    )
+    .unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("Circular extends/include"));
+}
+
+#[test]
+fn test_diamond_include_merges_shared_fragment_once() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "include:\n  - \"b.yaml\"\n  - \"c.yaml\"\n").unwrap();
+    for name in ["b.yaml", "c.yaml"] {
+        fs::write(temp_dir.path().join(name), "include:\n  - \"d.yaml\"\n").unwrap();
+    }
+    fs::write(
+        temp_dir.path().join("d.yaml"),
+        "stop:\n  commands:\n    - run: \"cargo test\"\n",
+    )
+    .unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    assert_eq!(layered.config.stop.commands.len(), 1);
+    assert_eq!(layered.layers.len(), 4);
+}
+
+#[test]
+fn test_fragment_errors_report_fragment_file_and_line() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "include:\n  - \"checks.yaml\"\n").unwrap();
+    fs::write(
+        temp_dir.path().join("checks.yaml"),
+        "stop:\n  commands:\n    - run: \"echo ok\"\n      showStdOut: true\n",
+    )
+    .unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("checks.yaml"), "error: {error}");
+    assert!(error.contains("line 4"), "error: {error}");
+}
+
+// ========== Profile Tests ==========
+
+const PROFILE_CONFIG: &str = r#"
+stop:
+  infinite: false
+  commands:
+    - run: "cargo check"
+notifications:
+  enabled: false
+profiles:
+  strict:
+    stop:
+      commands:
+        - run: "cargo test --all-features"
+    preToolUse:
+      preventUpdateGitIgnored: true
+  overnight:
+    stop:
+      infinite: true
+    notifications:
+      enabled: true
+"#;
+
+#[test]
+fn test_profile_not_applied_by_default() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, PROFILE_CONFIG).unwrap();
+
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+
+    assert!(layered.profile.is_none());
+    assert!(!layered.config.stop.infinite);
+    assert_eq!(layered.config.stop.commands.len(), 1);
+    assert_eq!(layered.config.profiles.len(), 2);
+}
+
+#[test]
+fn test_profile_overlays_base_sections() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, PROFILE_CONFIG).unwrap();
+
+    let strict = load_layered_config_from(&project_path, None, Some("strict")).unwrap();
+    let runs: Vec<&str> = strict
+        .config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+    assert_eq!(runs, vec!["cargo check", "cargo test --all-features"]);
+    assert!(strict.config.pre_tool_use.prevent_update_git_ignored);
+    assert_eq!(strict.profile.as_deref(), Some("strict"));
+    assert_eq!(
+        strict.layers.last().map(|l| l.kind),
+        Some(ConfigLayerKind::Profile)
+    );
+
+    let overnight = load_layered_config_from(&project_path, None, Some("overnight")).unwrap();
+    assert!(overnight.config.stop.infinite);
+    assert!(overnight.config.notifications.enabled);
+    assert_eq!(overnight.config.stop.commands.len(), 1);
+}
+
+#[test]
+fn test_unknown_profile_lists_available_profiles() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, PROFILE_CONFIG).unwrap();
+
+    let error = load_layered_config_from(&project_path, None, Some("nightly"))
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("Unknown configuration profile: nightly"));
+    assert!(error.contains("overnight, strict"));
+}
+
+#[test]
+fn test_config_for_profile_validates_merged_result() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &project_path,
+        r#"
+stop:
+  commands:
+    - run: "cargo check"
+profiles:
+  broken:
+    stop:
+      commands:
+        - run: "cargo test"
+          timeout: 0
+"#,
+    )
+    .unwrap();
+
+    // The base config loads because profile constraints are only checked when merged
+    let layered = load_layered_config_from(&project_path, None, None).unwrap();
+    let error = layered
+        .config_for_profile("broken")
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("stop.commands[1].timeout"), "error: {error}");
+}
+
+#[test]
+fn test_nested_profiles_are_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &project_path,
+        "profiles:\n  outer:\n    profiles:\n      inner:\n        stop:\n          infinite: true\n",
+    )
+    .unwrap();
+
+    let error = load_layered_config_from(&project_path, None, None)
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("profiles.outer"));
+    assert!(error.contains("not allowed inside a profile"));
+}
+
+fn load_into_store(store: &ConfigStore, project_path: &std::path::Path) -> anyhow::Result<()> {
+    let layered = load_layered_config_from(project_path, None, None)?;
+    let fingerprint = ConfigFingerprint::capture(&layered.watched_files());
+    store.replace((layered.config, layered.config_path), fingerprint);
+    Ok(())
+}
+
+#[test]
+fn test_config_store_reloads_after_edit() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "stop:\n  infinite: false\n").unwrap();
+
+    let store = ConfigStore::new();
+    assert!(store.fresh().is_none());
+    load_into_store(&store, &project_path).unwrap();
+    assert!(!store.fresh().unwrap().0.stop.infinite);
+
+    // Rewriting identical content is not an edit
+    fs::write(&project_path, "stop:\n  infinite: false\n").unwrap();
+    assert!(store.fresh().is_some());
+
+    fs::write(&project_path, "stop:\n  infinite: true\n").unwrap();
+    assert!(store.fresh().is_none());
+    load_into_store(&store, &project_path).unwrap();
+    assert!(store.fresh().unwrap().0.stop.infinite);
+}
+
+#[test]
+fn test_config_store_notices_new_local_override() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "stop:\n  infinite: false\n").unwrap();
+
+    let store = ConfigStore::new();
+    load_into_store(&store, &project_path).unwrap();
+    assert!(store.watches(&temp_dir.path().join(".conclaude.local.yaml")));
+
+    fs::write(
+        temp_dir.path().join(".conclaude.local.yaml"),
+        "stop:\n  infinite: true\n",
+    )
+    .unwrap();
+    assert!(store.fresh().is_none());
+}
+
+#[test]
+fn test_config_store_keeps_last_good_config_after_broken_edit() {
+    let temp_dir = tempdir().unwrap();
+    let project_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(&project_path, "stop:\n  infinite: true\n").unwrap();
+
+    let store = ConfigStore::new();
+    load_into_store(&store, &project_path).unwrap();
+
+    fs::write(&project_path, "stop:\n  infinit: true\n").unwrap();
+    assert!(store.fresh().is_none());
+    let error = load_into_store(&store, &project_path).unwrap_err();
+
+    let kept = store.keep(&error).unwrap();
+    assert!(kept.0.stop.infinite);
+    assert!(store.rejected_change().unwrap().contains("infinit"));
+    // The broken edit is acknowledged, so lookups stop retrying it
+    assert!(store.fresh().is_some());
+
+    // Fixing the file triggers a reload that clears the rejection
+    fs::write(&project_path, "stop:\n  infinite: false\n").unwrap();
+    assert!(store.fresh().is_none());
+    load_into_store(&store, &project_path).unwrap();
+    assert!(!store.fresh().unwrap().0.stop.infinite);
+    assert!(store.rejected_change().is_none());
+}
+
+#[test]
+fn test_config_store_without_config_cannot_keep() {
+    let store = ConfigStore::new();
+    assert!(store.keep(&anyhow::anyhow!("broken")).is_none());
+}
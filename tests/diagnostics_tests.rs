@@ -0,0 +1,203 @@
+use conclaude::config::DiagnosticParser;
+use conclaude::diagnostics::{format_diagnostics, parse_diagnostics, Diagnostic};
+
+fn diagnostic(file: &str, line: u32, column: Option<u32>, message: &str) -> Diagnostic {
+    Diagnostic {
+        file: file.to_string(),
+        line: Some(line),
+        column,
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_cargo_json_keeps_errors_and_warnings_with_primary_spans() {
+    let output = r#"{"reason":"compiler-artifact","package_id":"foo"}
+{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/other.rs","line_start":3,"column_start":1,"is_primary":false},{"file_name":"src/main.rs","line_start":12,"column_start":9,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"unused variable: `x`","code":null,"spans":[{"file_name":"src/lib.rs","line_start":4,"column_start":5,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","code":null,"spans":[]}}
+{"reason":"compiler-message","message":{"level":"note","message":"a note","code":null,"spans":[{"file_name":"src/lib.rs","line_start":1,"column_start":1,"is_primary":true}]}}
+{"reason":"build-finished","success":false}"#;
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::CargoJson, output),
+        vec![
+            diagnostic("src/main.rs", 12, Some(9), "error[E0308]: mismatched types"),
+            diagnostic("src/lib.rs", 4, Some(5), "warning: unused variable: `x`"),
+        ]
+    );
+}
+
+#[test]
+fn test_tsc_plain_and_pretty_output() {
+    let output = "src/app.ts(12,5): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+                  \x1b[96msrc/util.ts\x1b[0m:\x1b[93m3\x1b[0m:\x1b[93m1\x1b[0m - \x1b[91merror\x1b[0m\x1b[90m TS2304: \x1b[0mCannot find name 'foo'.\n\
+                  \n\
+                  Found 2 errors in 2 files.";
+
+    let diagnostics = parse_diagnostics(DiagnosticParser::Tsc, output);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        diagnostics[0],
+        diagnostic(
+            "src/app.ts",
+            12,
+            Some(5),
+            "error TS2322: Type 'string' is not assignable to type 'number'."
+        )
+    );
+    assert_eq!(
+        diagnostics[1],
+        diagnostic(
+            "src/util.ts",
+            3,
+            Some(1),
+            "error TS2304: Cannot find name 'foo'."
+        )
+    );
+}
+
+#[test]
+fn test_eslint_json_results() {
+    let output = r#"[{"filePath":"/repo/src/a.js","messages":[{"ruleId":"no-unused-vars","severity":2,"message":"'x' is defined but never used.","line":1,"column":7},{"ruleId":null,"severity":1,"message":"Unused eslint-disable directive.","line":9,"column":1}]},{"filePath":"/repo/src/b.js","messages":[]}]"#;
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::EslintJson, output),
+        vec![
+            diagnostic(
+                "/repo/src/a.js",
+                1,
+                Some(7),
+                "error: 'x' is defined but never used. (no-unused-vars)"
+            ),
+            diagnostic(
+                "/repo/src/a.js",
+                9,
+                Some(1),
+                "warning: Unused eslint-disable directive."
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_eslint_json_after_other_output() {
+    let output = "> lint\n> eslint --format json src\n[{\"filePath\":\"src/a.js\",\"messages\":[{\"ruleId\":\"semi\",\"severity\":2,\"message\":\"Missing semicolon.\",\"line\":2,\"column\":10}]}]\n";
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::EslintJson, output),
+        vec![diagnostic(
+            "src/a.js",
+            2,
+            Some(10),
+            "error: Missing semicolon. (semi)"
+        )]
+    );
+}
+
+#[test]
+fn test_junit_xml_failures_and_errors() {
+    let output = r#"============ 1 failed, 1 error, 1 passed ============
+<?xml version="1.0" encoding="utf-8"?>
+<testsuites><testsuite name="pytest" errors="1" failures="1" tests="3">
+<testcase classname="tests.test_math" name="test_add" file="tests/test_math.py" line="4" time="0.001" />
+<testcase classname="tests.test_math" name="test_div" file="tests/test_math.py" line="10" time="0.001"><failure message="assert 1 == 2&#10; +  where 1 = div(2, 2)">def test_div():
+&gt;       assert div(2, 2) == 2</failure></testcase>
+<testcase classname="tests.test_io" name="test_read" time="0.002"><error>FileNotFoundError: data.csv
+more detail</error></testcase>
+</testsuite></testsuites>"#;
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::JunitXml, output),
+        vec![
+            diagnostic(
+                "tests/test_math.py",
+                10,
+                None,
+                "test_div: assert 1 == 2\n +  where 1 = div(2, 2)"
+            ),
+            Diagnostic {
+                file: "tests.test_io".to_string(),
+                line: None,
+                column: None,
+                message: "test_read: FileNotFoundError: data.csv".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_generic_file_line_requires_a_path() {
+    let output = "main.c:10:5: error: expected ';' before '}' token\n\
+                  pkg/server.go:42: unreachable code\n\
+                  Error: 3 problems found\n\
+                  src/app.py:7: error: Incompatible return value type  [return-value]";
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::GenericFileLine, output),
+        vec![
+            diagnostic(
+                "main.c",
+                10,
+                Some(5),
+                "error: expected ';' before '}' token"
+            ),
+            diagnostic("pkg/server.go", 42, None, "unreachable code"),
+            diagnostic(
+                "src/app.py",
+                7,
+                None,
+                "error: Incompatible return value type  [return-value]"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_duplicate_diagnostics_are_dropped() {
+    let output = "src/a.rs:1:1: error: boom\nsrc/a.rs:1:1: error: boom\nsrc/a.rs:2:1: error: boom";
+
+    assert_eq!(
+        parse_diagnostics(DiagnosticParser::GenericFileLine, output).len(),
+        2
+    );
+}
+
+#[test]
+fn test_unrecognised_output_yields_no_diagnostics() {
+    assert!(parse_diagnostics(DiagnosticParser::CargoJson, "error: could not compile").is_empty());
+    assert!(
+        parse_diagnostics(DiagnosticParser::EslintJson, "Oops! Something went wrong").is_empty()
+    );
+    assert!(parse_diagnostics(DiagnosticParser::JunitXml, "1 failed").is_empty());
+}
+
+#[test]
+fn test_format_groups_by_file_in_order_of_first_appearance() {
+    let diagnostics = vec![
+        diagnostic("src/b.ts", 3, Some(1), "error TS1: first"),
+        diagnostic("src/a.ts", 1, None, "error TS2: second"),
+        diagnostic("src/b.ts", 9, Some(2), "error TS3: third"),
+    ];
+
+    assert_eq!(
+        format_diagnostics(&diagnostics, 50),
+        "3 diagnostics in 2 files:\n\
+         src/b.ts\n  3:1: error TS1: first\n  9:2: error TS3: third\n\
+         src/a.ts\n  1: error TS2: second"
+    );
+}
+
+#[test]
+fn test_format_caps_the_list() {
+    let diagnostics: Vec<Diagnostic> = (1..=5)
+        .map(|line| diagnostic("src/a.rs", line, None, "warning: unused"))
+        .collect();
+
+    assert_eq!(
+        format_diagnostics(&diagnostics, 2),
+        "5 diagnostics in 1 file:\n\
+         src/a.rs\n  1: warning: unused\n  2: warning: unused\n\
+         ... and 3 more diagnostics"
+    );
+}
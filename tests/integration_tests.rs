@@ -160,6 +160,449 @@ notifications:
     assert!(stdout.contains("Configuration summary:"));
 }
 
+#[test]
+fn test_validate_reports_config_layers() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let global_path = temp_path.join("global-config.yaml");
+    let config_path = temp_path.join(".conclaude.yaml");
+
+    fs::write(&global_path, "notifications:\n  enabled: true\n")
+        .expect("Failed to write global config");
+    fs::write(&config_path, "stop:\n  infinite: false\n").expect("Failed to write config");
+    fs::write(
+        temp_path.join(".conclaude.local.yaml"),
+        "stop:\n  infinite: true\n",
+    )
+    .expect("Failed to write local config");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "validate",
+            "--config-path",
+            &config_path.to_string_lossy(),
+        ])
+        .env("CONCLAUDE_GLOBAL_CONFIG", &global_path)
+        .output()
+        .expect("Failed to run validate command");
+
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+
+    assert!(
+        output.status.success(),
+        "Validate should succeed with layered config. stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("Configuration layers"));
+    assert!(stdout.contains("notifications.enabled: global"));
+    assert!(stdout.contains("stop.infinite: local"));
+    assert!(stdout.contains("Infinite mode: true"));
+}
+
+#[test]
+fn test_validate_explains_search_boundary() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let repo = temp_dir.path().join("repo");
+    let package = repo.join("packages").join("app");
+    fs::create_dir_all(repo.join(".git")).expect("Failed to create .git");
+    fs::create_dir_all(&package).expect("Failed to create package dir");
+
+    let run_validate = || {
+        Command::new("cargo")
+            .args(["run", "--", "validate"])
+            .env("CONCLAUDE_SEARCH_START", &package)
+            .env(
+                "CONCLAUDE_GLOBAL_CONFIG",
+                temp_dir.path().join("no-global.yaml"),
+            )
+            .output()
+            .expect("Failed to run validate command")
+    };
+
+    // Nothing inside the repository: the search stops at the git root
+    let missing = run_validate();
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    assert!(
+        stderr.contains("reached the git repository root"),
+        "stderr: {stderr}"
+    );
+
+    fs::write(repo.join(".conclaude.yaml"), "root: true\n").expect("Failed to write config");
+    let found = run_validate();
+    let stdout = String::from_utf8_lossy(&found.stdout);
+    assert!(
+        found.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&found.stderr)
+    );
+    assert!(stdout.contains(&format!("Search: started at {}", package.display())));
+    assert!(stdout.contains("stopped because it found"));
+    assert!(stdout.contains("Root: true"));
+}
+
+#[test]
+fn test_validate_json_and_sarif_formats() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+
+    let run_validate = |format: &str| {
+        let output = Command::new("cargo")
+            .args(["run", "--", "validate", "--format", format, "--config-path"])
+            .arg(&config_path)
+            .env(
+                "CONCLAUDE_GLOBAL_CONFIG",
+                temp_dir.path().join("no-global.yaml"),
+            )
+            .output()
+            .expect("Failed to run validate command");
+        let report: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("validate should print JSON");
+        (output.status.success(), report)
+    };
+
+    // Warnings are reported but do not fail validation
+    fs::write(
+        &config_path,
+        "preToolUse:\n  uneditableFiles:\n    - \"*.lock\"\n    - \"*.lock\"\n",
+    )
+    .expect("Failed to write config");
+    let (success, report) = run_validate("json");
+    assert!(success, "report: {report}");
+    assert_eq!(report["valid"], true);
+    assert_eq!(report["warningCount"], 1);
+    let warning = &report["diagnostics"][0];
+    assert_eq!(warning["severity"], "warning");
+    assert_eq!(warning["code"], "shadowed-rule");
+    assert_eq!(warning["path"], "preToolUse.uneditableFiles[1]");
+    assert_eq!(warning["line"], 4);
+    assert_eq!(warning["column"], 5);
+
+    fs::write(
+        &config_path,
+        "notifications:\n  enabled: true\n  hooks:\n    - \"Stopp\"\n",
+    )
+    .expect("Failed to write config");
+    let (success, report) = run_validate("json");
+    assert!(!success);
+    assert_eq!(report["valid"], false);
+    let error = &report["diagnostics"][0];
+    assert_eq!(error["severity"], "error");
+    assert_eq!(error["path"], "notifications.hooks[0]");
+    assert_eq!(error["line"], 4);
+    assert_eq!(error["suggestions"][0], "Stop");
+
+    let (success, sarif) = run_validate("sarif");
+    assert!(!success);
+    assert_eq!(sarif["version"], "2.1.0");
+    let result = &sarif["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "unknown-hook");
+    assert_eq!(result["level"], "error");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startLine"],
+        4
+    );
+    assert_eq!(
+        result["locations"][0]["logicalLocations"][0]["fullyQualifiedName"],
+        "notifications.hooks[0]"
+    );
+}
+
+#[test]
+fn test_config_show_resolved_agent_view() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &config_path,
+        r#"stop:
+  commands:
+    - run: "cargo test"
+subagentStop:
+  commands:
+    "code*":
+      - run: "cargo clippy"
+    tester:
+      - run: "cargo test"
+preToolUse:
+  uneditableFiles:
+    - "Cargo.lock"
+    - pattern: "tests/**"
+      agent: "coder"
+    - pattern: "src/**"
+      agent: "tester"
+"#,
+    )
+    .expect("Failed to write config");
+
+    let run_show = |args: &[&str]| {
+        let output = Command::new("cargo")
+            .args(["run", "--", "config", "show", "--config-path"])
+            .arg(&config_path)
+            .args(args)
+            .env(
+                "CONCLAUDE_GLOBAL_CONFIG",
+                temp_dir.path().join("no-global.yaml"),
+            )
+            .output()
+            .expect("Failed to run config show");
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).expect("Invalid UTF-8")
+    };
+
+    // Without --resolved only the values written in the file are shown
+    let written = run_show(&[]);
+    assert!(written.contains("run: cargo test"));
+    assert!(!written.contains("infinite"));
+
+    let resolved = run_show(&["--resolved"]);
+    assert!(resolved.contains("  infinite: false  # default"));
+    assert!(resolved.contains("  - run: cargo test\n"));
+
+    let report: serde_json::Value = serde_json::from_str(&run_show(&[
+        "--resolved",
+        "--format",
+        "json",
+        "--agent",
+        "coder",
+    ]))
+    .expect("config show should print JSON");
+    assert_eq!(report["agent"], "coder");
+    let uneditable = report["config"]["preToolUse"]["uneditableFiles"]
+        .as_array()
+        .unwrap();
+    assert_eq!(uneditable.len(), 2);
+    assert_eq!(uneditable[1]["pattern"], "tests/**");
+    let subagent_commands = report["config"]["subagentStop"]["commands"]
+        .as_object()
+        .unwrap();
+    assert!(subagent_commands.contains_key("code*"));
+    assert!(!subagent_commands.contains_key("tester"));
+    let defaults = report["defaults"].as_array().unwrap();
+    assert!(defaults.contains(&serde_json::json!("stop.infinite")));
+    assert!(defaults.contains(&serde_json::json!("preToolUse.uneditableFiles[1].message")));
+}
+
+#[test]
+fn test_migrate_dry_run_and_write() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+    let legacy = r#"# Checks
+stop:
+  run: "cargo test"
+rules:
+  preventRootAdditions: true
+"#;
+    fs::write(&config_path, legacy).expect("Failed to write config");
+
+    let run_migrate = |args: &[&str]| {
+        let output = Command::new("cargo")
+            .args(["run", "--", "migrate", "--config-path"])
+            .arg(&config_path)
+            .args(args)
+            .env(
+                "CONCLAUDE_GLOBAL_CONFIG",
+                temp_dir.path().join("no-global.yaml"),
+            )
+            .output()
+            .expect("Failed to run migrate");
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).expect("Invalid UTF-8")
+    };
+
+    // A dry run prints the diff and leaves the file alone
+    let dry_run = run_migrate(&[]);
+    assert!(dry_run.contains("-  run: \"cargo test\""));
+    assert!(dry_run.contains("+    - run: cargo test"));
+    assert!(dry_run.contains("+preToolUse:"));
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), legacy);
+
+    run_migrate(&["--write"]);
+    assert_eq!(
+        fs::read_to_string(&config_path).unwrap(),
+        "# Checks\nstop:\n  commands:\n    - run: cargo test\npreToolUse:\n  preventRootAdditions: true\n"
+    );
+
+    let rerun = run_migrate(&[]);
+    assert!(rerun.contains("already uses the current configuration format"));
+}
+
+#[test]
+fn test_rules_add_list_remove() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+    fs::write(
+        &config_path,
+        "# Protected files\npreToolUse:\n  uneditableFiles:\n    - \"Cargo.lock\"\n",
+    )
+    .expect("Failed to write config");
+
+    let run_rules = |args: &[&str]| {
+        Command::new("cargo")
+            .args(["run", "--", "rules", "--config-path"])
+            .arg(&config_path)
+            .args(args)
+            .env(
+                "CONCLAUDE_GLOBAL_CONFIG",
+                temp_dir.path().join("no-global.yaml"),
+            )
+            .output()
+            .expect("Failed to run rules")
+    };
+
+    let added = run_rules(&[
+        "add",
+        "tool-block",
+        "Bash",
+        "--command",
+        "git push --force*",
+        "--message",
+        "No force pushes",
+    ]);
+    assert!(
+        added.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&added.stderr)
+    );
+    assert!(String::from_utf8_lossy(&added.stdout).contains("Added tool-1"));
+
+    // An invalid pattern is rejected and the file is left alone
+    let before = fs::read_to_string(&config_path).unwrap();
+    let rejected = run_rules(&["add", "uneditable", "[bad"]);
+    assert!(!rejected.status.success());
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), before);
+
+    let listed = String::from_utf8(run_rules(&["list"]).stdout).unwrap();
+    assert!(listed.contains("uneditable-1  \"Cargo.lock\""));
+    assert!(
+        listed.contains("tool-1        block Bash command \"git push --force*\" - No force pushes")
+    );
+
+    let removed = run_rules(&["remove", "uneditable-1"]);
+    assert!(removed.status.success());
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(content.starts_with("# Protected files\npreToolUse:\n  uneditableFiles: []\n"));
+    assert!(content.contains("commandPattern: git push --force*"));
+}
+
+#[test]
+fn test_validate_checks_every_profile() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let config_path = temp_dir.path().join(".conclaude.yaml");
+
+    let config = r#"
+stop:
+  commands:
+    - run: "echo base"
+profiles:
+  quick:
+    stop:
+      infinite: false
+  strict:
+    stop:
+      commands:
+        - run: "echo strict"
+          maxOutputLines: 0
+"#;
+    fs::write(&config_path, config).expect("Failed to write config file");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "validate",
+            "--config-path",
+            &config_path.to_string_lossy(),
+        ])
+        .output()
+        .expect("Failed to run validate command");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success(),
+        "Validate should fail when a profile is invalid"
+    );
+    assert!(stderr.contains("Profile 'strict'"), "stderr: {stderr}");
+    assert!(stderr.contains("stop.commands[1].maxOutputLines"));
+    assert!(!stderr.contains("Profile 'quick'"));
+}
+
+#[test]
+fn test_hooks_profile_flag_selects_profile() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let project_root = temp_dir.path();
+
+    let config = r#"
+stop:
+  commands:
+    - run: "true"
+profiles:
+  strict:
+    stop:
+      commands:
+        - run: "exit 1"
+          message: "strict profile check failed"
+"#;
+    fs::write(project_root.join(".conclaude.yaml"), config).expect("Failed to write config");
+
+    let payload = serde_json::json!({
+        "session_id": "test-session-profile",
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "Stop",
+        "cwd": project_root.to_string_lossy(),
+        "permission_mode": "default",
+        "stop_hook_active": false
+    })
+    .to_string();
+
+    let run_stop = |extra_args: &[&str]| {
+        let mut args = vec!["run", "--quiet", "--manifest-path"];
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        args.push(manifest);
+        args.extend(["--", "Hooks", "Stop"]);
+        args.extend(extra_args);
+
+        let mut child = Command::new("cargo")
+            .args(&args)
+            .current_dir(project_root)
+            .env_remove("CONCLAUDE_PROFILE")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn Stop hook");
+        child
+            .stdin
+            .as_mut()
+            .expect("Failed to open stdin")
+            .write_all(payload.as_bytes())
+            .expect("Failed to write payload");
+        child
+            .wait_with_output()
+            .expect("Failed to wait for Stop hook")
+    };
+
+    let base = run_stop(&[]);
+    assert!(
+        base.status.success(),
+        "Base config should allow stopping. stderr: {}",
+        String::from_utf8_lossy(&base.stderr)
+    );
+
+    let strict = run_stop(&["--profile", "strict"]);
+    assert_eq!(strict.status.code(), Some(2), "Strict profile should block");
+    assert!(String::from_utf8_lossy(&strict.stdout).contains("strict profile check failed"));
+}
+
 #[test]
 fn test_validate_with_missing_configuration() {
     let temp_dir = tempdir().expect("Failed to create temp directory");
@@ -653,6 +1096,286 @@ preToolUse:
     let _ = fs::remove_file(&config_dir_file);
 }
 
+// ========== Monorepo Config Resolution Tests ==========
+
+fn run_hook(
+    dir: &std::path::Path,
+    hook: &str,
+    payload: &serde_json::Value,
+) -> std::process::Output {
+    run_hook_with_env(dir, hook, payload, &[])
+}
+
+fn run_hook_with_env(
+    dir: &std::path::Path,
+    hook: &str,
+    payload: &serde_json::Value,
+    envs: &[(&str, &std::path::Path)],
+) -> std::process::Output {
+    let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    let mut child = Command::new("cargo")
+        .args([
+            "run",
+            "--quiet",
+            "--manifest-path",
+            manifest,
+            "--",
+            "Hooks",
+            hook,
+        ])
+        .current_dir(dir)
+        .env_remove("CONCLAUDE_PROFILE")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn hook");
+    child
+        .stdin
+        .as_mut()
+        .expect("Failed to open stdin")
+        .write_all(payload.to_string().as_bytes())
+        .expect("Failed to write payload");
+    child.wait_with_output().expect("Failed to wait for hook")
+}
+
+fn write_monorepo(root: &std::path::Path) {
+    fs::create_dir_all(root.join("packages/api/src")).expect("Failed to create api package");
+    fs::create_dir_all(root.join("packages/web/src")).expect("Failed to create web package");
+    fs::write(
+        root.join(".conclaude.yaml"),
+        "stop:\n  commands:\n    - run: \"true\"\n",
+    )
+    .expect("Failed to write root config");
+    fs::write(
+        root.join("packages/api/.conclaude.yaml"),
+        r#"
+preToolUse:
+  uneditableFiles:
+    - pattern: "src/generated.rs"
+      message: "api generated code is read-only"
+stop:
+  commands:
+    - run: "test -f src/ok"
+      message: "api checks failed"
+"#,
+    )
+    .expect("Failed to write api config");
+    fs::write(
+        root.join("packages/web/.conclaude.yaml"),
+        r#"
+stop:
+  commands:
+    - run: "exit 1"
+      message: "web checks should not run"
+"#,
+    )
+    .expect("Failed to write web config");
+}
+
+#[test]
+fn test_pre_tool_use_resolves_nearest_config_for_target_file() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let root = temp_dir.path();
+    write_monorepo(root);
+
+    let write_payload = |file_path: &str| {
+        serde_json::json!({
+            "session_id": "test-session-monorepo-pre",
+            "transcript_path": "/tmp/test-transcript.jsonl",
+            "hook_event_name": "PreToolUse",
+            "cwd": root.to_string_lossy(),
+            "permission_mode": "default",
+            "tool_name": "Edit",
+            "tool_input": { "file_path": file_path }
+        })
+    };
+
+    // The api package pattern is relative to packages/api, not the session cwd
+    let blocked = run_hook(
+        root,
+        "PreToolUse",
+        &write_payload("packages/api/src/generated.rs"),
+    );
+    assert_eq!(blocked.status.code(), Some(2), "api rule should block");
+    assert!(String::from_utf8_lossy(&blocked.stdout).contains("api generated code is read-only"));
+
+    // The same relative path in another package is not covered by the api rules
+    let allowed = run_hook(
+        root,
+        "PreToolUse",
+        &write_payload("packages/web/src/generated.rs"),
+    );
+    assert!(
+        allowed.status.success(),
+        "web package should not inherit api rules. stderr: {}",
+        String::from_utf8_lossy(&allowed.stderr)
+    );
+}
+
+#[test]
+fn test_stop_runs_commands_for_edited_packages() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let root = temp_dir.path();
+    write_monorepo(root);
+
+    let session_id = format!(
+        "test-session-monorepo-stop-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    let stop_payload = serde_json::json!({
+        "session_id": session_id,
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "Stop",
+        "cwd": root.to_string_lossy(),
+        "permission_mode": "default",
+        "stop_hook_active": false
+    });
+
+    // Without edits only the root config runs
+    let clean = run_hook(root, "Stop", &stop_payload);
+    assert!(
+        clean.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&clean.stderr)
+    );
+
+    let edit_payload = serde_json::json!({
+        "session_id": session_id,
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "PostToolUse",
+        "cwd": root.to_string_lossy(),
+        "permission_mode": "default",
+        "tool_name": "Write",
+        "tool_input": { "file_path": "packages/api/src/lib.rs" },
+        "tool_response": {}
+    });
+    let recorded = run_hook(root, "PostToolUse", &edit_payload);
+    assert!(recorded.status.success());
+
+    // The api package was edited, so its checks run from its own directory
+    let blocked = run_hook(root, "Stop", &stop_payload);
+    assert_eq!(blocked.status.code(), Some(2), "api checks should block");
+    let stdout = String::from_utf8_lossy(&blocked.stdout);
+    assert!(stdout.contains("api checks failed"), "stdout: {stdout}");
+    assert!(!stdout.contains("web checks should not run"));
+
+    // Once the api checks pass, the recorded edits are cleared
+    fs::write(root.join("packages/api/src/ok"), "").expect("Failed to write marker");
+    let passed = run_hook(root, "Stop", &stop_payload);
+    assert!(
+        passed.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&passed.stderr)
+    );
+    fs::remove_file(root.join("packages/api/src/ok")).expect("Failed to remove marker");
+    let after = run_hook(root, "Stop", &stop_payload);
+    assert!(
+        after.status.success(),
+        "edits should be cleared after a passing stop"
+    );
+}
+
+// ========== Missing Configuration Tests ==========
+
+#[test]
+fn test_hooks_allow_everything_without_config() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(project.join(".git")).expect("Failed to create .git");
+    let global = temp_dir.path().join("global.yaml");
+    let envs = [("CONCLAUDE_GLOBAL_CONFIG", global.as_path())];
+
+    let session_id = format!(
+        "test-session-no-config-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    let pre_tool_use = serde_json::json!({
+        "session_id": session_id,
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "PreToolUse",
+        "cwd": project.to_string_lossy(),
+        "permission_mode": "default",
+        "tool_name": "Write",
+        "tool_input": { "file_path": "new-root-file.txt" }
+    });
+    let stop = serde_json::json!({
+        "session_id": session_id,
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "Stop",
+        "cwd": project.to_string_lossy(),
+        "permission_mode": "default",
+        "stop_hook_active": false
+    });
+
+    // Only the first hook of the session logs the warning
+    for (hook, payload, warns) in [("PreToolUse", &pre_tool_use, true), ("Stop", &stop, false)] {
+        let output = run_hook_with_env(&project, hook, payload, &envs);
+        assert!(
+            output.status.success(),
+            "{hook} should be allowed without a config. stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr).contains("No conclaude configuration found"),
+            warns,
+            "{hook} warning"
+        );
+    }
+
+    // Strict mode from the global configuration fails closed
+    fs::write(&global, "missingConfig:\n  mode: block\n").expect("Failed to write global");
+    let blocked = run_hook_with_env(&project, "PreToolUse", &pre_tool_use, &envs);
+    assert_eq!(
+        blocked.status.code(),
+        Some(2),
+        "block mode should fail closed"
+    );
+    assert!(String::from_utf8_lossy(&blocked.stderr).contains("Configuration file not found"));
+}
+
+#[test]
+fn test_session_start_hint_without_config_is_shown_once() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(project.join(".git")).expect("Failed to create .git");
+    let global = temp_dir.path().join("global.yaml");
+    fs::write(&global, "missingConfig:\n  sessionStartHint: true\n")
+        .expect("Failed to write global");
+    let envs = [("CONCLAUDE_GLOBAL_CONFIG", global.as_path())];
+
+    let session_id = format!(
+        "test-session-no-config-hint-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    let payload = serde_json::json!({
+        "session_id": session_id,
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "SessionStart",
+        "cwd": project.to_string_lossy(),
+        "permission_mode": "default",
+        "source": "startup"
+    });
+
+    let first = run_hook_with_env(&project, "SessionStart", &payload, &envs);
+    assert!(first.status.success());
+    assert!(String::from_utf8_lossy(&first.stdout).contains("conclaude init"));
+
+    let second = run_hook_with_env(&project, "SessionStart", &payload, &envs);
+    assert!(second.status.success());
+    assert!(!String::from_utf8_lossy(&second.stdout).contains("conclaude init"));
+}
+
 // ========== Per-Command Notifications Integration Tests ==========
 
 #[test]
@@ -954,7 +1677,10 @@ fn test_cli_hooks_post_tool_use_failure_exists() {
         .args(["run", "--", "Hooks", "PostToolUseFailure", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "PostToolUseFailure subcommand should exist");
+    assert!(
+        output.status.success(),
+        "PostToolUseFailure subcommand should exist"
+    );
 }
 
 #[test]
@@ -963,7 +1689,10 @@ fn test_cli_hooks_teammate_idle_exists() {
         .args(["run", "--", "Hooks", "TeammateIdle", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "TeammateIdle subcommand should exist");
+    assert!(
+        output.status.success(),
+        "TeammateIdle subcommand should exist"
+    );
 }
 
 #[test]
@@ -972,7 +1701,10 @@ fn test_cli_hooks_task_completed_exists() {
         .args(["run", "--", "Hooks", "TaskCompleted", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "TaskCompleted subcommand should exist");
+    assert!(
+        output.status.success(),
+        "TaskCompleted subcommand should exist"
+    );
 }
 
 #[test]
@@ -981,7 +1713,10 @@ fn test_cli_hooks_config_change_exists() {
         .args(["run", "--", "Hooks", "ConfigChange", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "ConfigChange subcommand should exist");
+    assert!(
+        output.status.success(),
+        "ConfigChange subcommand should exist"
+    );
 }
 
 #[test]
@@ -990,7 +1725,10 @@ fn test_cli_hooks_worktree_create_exists() {
         .args(["run", "--", "Hooks", "WorktreeCreate", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "WorktreeCreate subcommand should exist");
+    assert!(
+        output.status.success(),
+        "WorktreeCreate subcommand should exist"
+    );
 }
 
 #[test]
@@ -999,7 +1737,10 @@ fn test_cli_hooks_worktree_remove_exists() {
         .args(["run", "--", "Hooks", "WorktreeRemove", "--help"])
         .output()
         .expect("Failed to run CLI");
-    assert!(output.status.success(), "WorktreeRemove subcommand should exist");
+    assert!(
+        output.status.success(),
+        "WorktreeRemove subcommand should exist"
+    );
 }
 
 #[test]
@@ -1010,3 +1751,40 @@ fn test_cli_hooks_setup_exists() {
         .expect("Failed to run CLI");
     assert!(output.status.success(), "Setup subcommand should exist");
 }
+
+#[test]
+fn test_broken_config_edit_is_reported_by_later_hooks() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let root = temp_dir.path();
+    let config_path = root.join(".conclaude.yaml");
+    fs::write(
+        &config_path,
+        "preToolUse:\n  uneditableFiles:\n    - pattern: \"secret.txt\"\n      message: \"secret.txt is read-only\"\n",
+    )
+    .expect("Failed to write config");
+
+    let edit_payload = serde_json::json!({
+        "session_id": "test-session-reload",
+        "transcript_path": "/tmp/test-transcript.jsonl",
+        "hook_event_name": "PreToolUse",
+        "cwd": root.to_string_lossy(),
+        "permission_mode": "default",
+        "tool_name": "Edit",
+        "tool_input": { "file_path": "secret.txt" }
+    });
+    let blocked = run_hook(root, "PreToolUse", &edit_payload);
+    assert_eq!(blocked.status.code(), Some(2), "rule should block");
+
+    // The last good configuration lives in memory only, so a new hook process reports
+    // the broken edit instead of reusing rules saved by an earlier one
+    fs::write(
+        &config_path,
+        "preToolUse:\n  uneditableFile:\n    - \"secret.txt\"\n",
+    )
+    .expect("Failed to write config");
+    let broken = run_hook(root, "PreToolUse", &edit_payload);
+    let stderr = String::from_utf8_lossy(&broken.stderr);
+    assert!(!broken.status.success(), "stderr: {stderr}");
+    assert!(!stderr.contains("keeping the last good configuration"));
+    assert!(stderr.contains("uneditableFile"));
+}
@@ -0,0 +1,159 @@
+use conclaude::config::parse_and_validate_config;
+use conclaude::migrate::{migrate_config, unified_diff};
+use std::path::Path;
+
+#[test]
+fn test_migrate_current_config_is_unchanged() {
+    let content = "stop:\n  commands:\n    - run: cargo test\n";
+    let migration = migrate_config(content).unwrap();
+    assert!(migration.is_empty());
+    assert_eq!(migration.migrated, content);
+}
+
+#[test]
+fn test_migrate_stop_run_keeps_comments_and_order() {
+    let content = r#"# Project checks
+stop:
+  # Run before finishing
+  run: |
+    # lint first
+    npm run lint
+    npm test
+  infinite: false
+  commands:
+    - run: cargo build # compile
+"#;
+    let migration = migrate_config(content).unwrap();
+    assert!(migration.comments_preserved);
+    assert_eq!(
+        migration.migrated,
+        r#"# Project checks
+stop:
+  infinite: false
+  commands:
+    - run: npm run lint
+    - run: npm test
+    - run: cargo build # compile
+"#
+    );
+    assert_eq!(migration.steps.len(), 1);
+    assert_eq!(migration.steps[0].path, "stop.run");
+    parse_and_validate_config(&migration.migrated, Path::new(".conclaude.yaml")).unwrap();
+}
+
+#[test]
+fn test_migrate_rules_section_is_renamed_when_pre_tool_use_is_missing() {
+    let content = r#"rules:
+  # No new files at the root
+  preventRootAdditions: true
+  uneditableFiles:
+    - "Cargo.lock"
+"#;
+    let migration = migrate_config(content).unwrap();
+    assert!(migration.comments_preserved);
+    assert_eq!(
+        migration.migrated,
+        r#"preToolUse:
+  # No new files at the root
+  preventRootAdditions: true
+  uneditableFiles:
+    - "Cargo.lock"
+"#
+    );
+}
+
+#[test]
+fn test_migrate_rules_merge_into_existing_pre_tool_use() {
+    let content = r#"rules:
+  preventRootAdditions: false
+  uneditableFiles:
+    - "*.lock"
+    - pattern: ".env"
+      message: "Secrets"
+
+preToolUse:
+  preventRootAdditions: true
+  uneditableFiles:
+    - "package.json"
+"#;
+    let migration = migrate_config(content).unwrap();
+    assert!(migration.comments_preserved);
+    assert_eq!(
+        migration.migrated,
+        r#"preToolUse:
+  preventRootAdditions: true
+  uneditableFiles:
+    - "package.json"
+    - "*.lock"
+    - pattern: ".env"
+      message: "Secrets"
+"#
+    );
+    let descriptions: Vec<String> = migration.steps.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        descriptions,
+        vec![
+            "rules.preventRootAdditions: dropped; `preToolUse.preventRootAdditions` is already set",
+            "rules.uneditableFiles: appended to `preToolUse.uneditableFiles`",
+        ]
+    );
+}
+
+#[test]
+fn test_migrate_removes_dropped_fields() {
+    let content = r#"stop:
+  rounds: 3
+preToolUse:
+  preventGeneratedFileEdits: true
+  generatedFileMessage: "generated"
+  preventRootAdditions: true
+
+gitWorktree:
+  enabled: true
+"#;
+    let migration = migrate_config(content).unwrap();
+    assert!(migration.comments_preserved);
+    assert_eq!(
+        migration.migrated,
+        "stop: {}\npreToolUse:\n  preventRootAdditions: true\n"
+    );
+    let paths: Vec<&str> = migration.steps.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "stop.rounds",
+            "preToolUse.preventGeneratedFileEdits",
+            "preToolUse.generatedFileMessage",
+            "gitWorktree",
+        ]
+    );
+    parse_and_validate_config(&migration.migrated, Path::new(".conclaude.yaml")).unwrap();
+}
+
+#[test]
+fn test_migrate_flow_style_falls_back_to_rendering() {
+    let content = "# header\nstop: {run: \"a\\nb\", rounds: 2}\n";
+    let migration = migrate_config(content).unwrap();
+    assert!(!migration.comments_preserved);
+    assert!(migration.migrated.starts_with("# header\n"));
+    let config =
+        parse_and_validate_config(&migration.migrated, Path::new(".conclaude.yaml")).unwrap();
+    let runs: Vec<&str> = config
+        .stop
+        .commands
+        .iter()
+        .map(|c| c.run.as_str())
+        .collect();
+    assert_eq!(runs, vec!["a", "b"]);
+}
+
+#[test]
+fn test_unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    assert_eq!(
+        unified_diff(old, new, "old", "new"),
+        "--- old\n+++ new\n@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n@@ -7,3 +7,4 @@\n g\n h\n i\n+j\n"
+    );
+    assert_eq!(unified_diff(old, old, "old", "new"), "");
+}
@@ -39,7 +39,7 @@ preToolUse:
 
         let config = result.unwrap();
         assert_eq!(
-            config.stop.commands[0].max_output_lines,
+            config.stop.commands[0].launch.max_output_lines,
             Some(value),
             "maxOutputLines should be {} ({})",
             value,
@@ -69,9 +69,9 @@ preToolUse:
 
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 1);
-    assert_eq!(config.stop.commands[0].max_output_lines, None);
-    assert_eq!(config.stop.commands[0].show_stdout, Some(true));
-    assert_eq!(config.stop.commands[0].show_stderr, Some(true));
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, None);
+    assert_eq!(config.stop.commands[0].launch.show_stdout, Some(true));
+    assert_eq!(config.stop.commands[0].launch.show_stderr, Some(true));
 }
 
 /// Test interaction with showStdout/showStderr flags
@@ -157,19 +157,19 @@ preToolUse:
 
         let config = result.unwrap();
         assert_eq!(
-            config.stop.commands[0].show_stdout,
+            config.stop.commands[0].launch.show_stdout,
             Some(expected_stdout),
             "Test: {}",
             test_name
         );
         assert_eq!(
-            config.stop.commands[0].show_stderr,
+            config.stop.commands[0].launch.show_stderr,
             Some(expected_stderr),
             "Test: {}",
             test_name
         );
         assert_eq!(
-            config.stop.commands[0].max_output_lines, expected_max_lines,
+            config.stop.commands[0].launch.max_output_lines, expected_max_lines,
             "Test: {}",
             test_name
         );
@@ -207,10 +207,10 @@ preToolUse:
 
     let config = result.unwrap();
     assert_eq!(config.stop.commands.len(), 4);
-    assert_eq!(config.stop.commands[0].max_output_lines, Some(5));
-    assert_eq!(config.stop.commands[1].max_output_lines, Some(50));
-    assert_eq!(config.stop.commands[2].max_output_lines, Some(100));
-    assert_eq!(config.stop.commands[3].max_output_lines, None);
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, Some(5));
+    assert_eq!(config.stop.commands[1].launch.max_output_lines, Some(50));
+    assert_eq!(config.stop.commands[2].launch.max_output_lines, Some(100));
+    assert_eq!(config.stop.commands[3].launch.max_output_lines, None);
 }
 
 /// Test that empty commands array works
@@ -254,9 +254,9 @@ preToolUse:
     assert_eq!(config.stop.commands.len(), 1);
     assert_eq!(config.stop.commands[0].run, "echo minimal");
     assert_eq!(config.stop.commands[0].message, None);
-    assert_eq!(config.stop.commands[0].show_stdout, None);
-    assert_eq!(config.stop.commands[0].show_stderr, None);
-    assert_eq!(config.stop.commands[0].max_output_lines, None);
+    assert_eq!(config.stop.commands[0].launch.show_stdout, None);
+    assert_eq!(config.stop.commands[0].launch.show_stderr, None);
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, None);
 }
 
 /// Test that maxOutputLines can be explicitly set to null
@@ -279,7 +279,7 @@ preToolUse:
     );
 
     let config = result.unwrap();
-    assert_eq!(config.stop.commands[0].max_output_lines, None);
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, None);
 }
 
 /// Test full config file with all features combined
@@ -332,9 +332,9 @@ notifications:
         config.stop.commands[0].message,
         Some("Tests failed. Please fix before continuing.".to_string())
     );
-    assert_eq!(config.stop.commands[0].show_stdout, Some(true));
-    assert_eq!(config.stop.commands[0].show_stderr, Some(true));
-    assert_eq!(config.stop.commands[0].max_output_lines, Some(50));
+    assert_eq!(config.stop.commands[0].launch.show_stdout, Some(true));
+    assert_eq!(config.stop.commands[0].launch.show_stderr, Some(true));
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, Some(50));
 
     // Second command
     assert_eq!(config.stop.commands[1].run, "npm run lint");
@@ -342,14 +342,14 @@ notifications:
         config.stop.commands[1].message,
         Some("Linting failed".to_string())
     );
-    assert_eq!(config.stop.commands[1].show_stdout, Some(false));
-    assert_eq!(config.stop.commands[1].show_stderr, Some(true));
-    assert_eq!(config.stop.commands[1].max_output_lines, Some(100));
+    assert_eq!(config.stop.commands[1].launch.show_stdout, Some(false));
+    assert_eq!(config.stop.commands[1].launch.show_stderr, Some(true));
+    assert_eq!(config.stop.commands[1].launch.max_output_lines, Some(100));
 
     // Third command (no maxOutputLines)
     assert_eq!(config.stop.commands[2].run, "echo no limits");
-    assert_eq!(config.stop.commands[2].show_stdout, Some(true));
-    assert_eq!(config.stop.commands[2].max_output_lines, None);
+    assert_eq!(config.stop.commands[2].launch.show_stdout, Some(true));
+    assert_eq!(config.stop.commands[2].launch.max_output_lines, None);
 
     // Verify other sections
     assert!(config.pre_tool_use.prevent_root_additions);
@@ -411,7 +411,7 @@ preToolUse:
 
     let (config, loaded_path) = result.unwrap();
     assert_eq!(config.stop.commands.len(), 1);
-    assert_eq!(config.stop.commands[0].max_output_lines, Some(75));
+    assert_eq!(config.stop.commands[0].launch.max_output_lines, Some(75));
     assert!(loaded_path.ends_with(".conclaude.yaml"));
 }
 
@@ -0,0 +1,125 @@
+use conclaude::rules::{add_rule, list_rules, remove_rule, RuleId, RuleList};
+use serde_yaml::Value;
+use std::path::Path;
+
+const CONFIG: &str = r#"# Project rules
+preToolUse:
+  # Root stays clean
+  preventRootAdditions: true
+  uneditableFiles:
+    # Lock files
+    - "Cargo.lock"
+    - pattern: ".env*"
+      message: "No secrets"
+"#;
+
+fn config_path() -> &'static Path {
+    Path::new(".conclaude.yaml")
+}
+
+#[test]
+fn test_rule_id_round_trip() {
+    let id: RuleId = "tool-2".parse().unwrap();
+    assert_eq!(id.list, RuleList::Tool);
+    assert_eq!(id.index, 1);
+    assert_eq!(id.to_string(), "tool-2");
+
+    assert!("uneditable-0".parse::<RuleId>().is_err());
+    assert!("files-1".parse::<RuleId>().is_err());
+    assert!("uneditable".parse::<RuleId>().is_err());
+}
+
+#[test]
+fn test_list_rules() {
+    let rules = list_rules(CONFIG).unwrap();
+    let listed: Vec<(String, &str)> = rules
+        .iter()
+        .map(|r| (r.id.to_string(), r.description.as_str()))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("uneditable-1".to_string(), "\"Cargo.lock\""),
+            ("uneditable-2".to_string(), "\".env*\" - No secrets"),
+        ]
+    );
+}
+
+#[test]
+fn test_add_rule_appends_and_keeps_comments() {
+    let rule: Value = serde_yaml::from_str("pattern: '*.pem'\nagent: coder").unwrap();
+    let edit = add_rule(CONFIG, config_path(), RuleList::Uneditable, rule).unwrap();
+    assert!(edit.comments_preserved);
+    assert_eq!(edit.rule.id.to_string(), "uneditable-3");
+    assert_eq!(
+        edit.content,
+        format!("{CONFIG}    - pattern: '*.pem'\n      agent: coder\n")
+    );
+    assert_eq!(edit.config.pre_tool_use.uneditable_files.len(), 3);
+}
+
+#[test]
+fn test_add_rule_creates_missing_list() {
+    let rule: Value = serde_yaml::from_str(
+        "tool: Bash\npattern: ''\ncommandPattern: git push --force*\naction: block",
+    )
+    .unwrap();
+    let edit = add_rule(CONFIG, config_path(), RuleList::Tool, rule).unwrap();
+    assert!(edit.comments_preserved);
+    assert!(edit.content.starts_with(CONFIG));
+    assert!(edit.content.ends_with(
+        "  toolUsageValidation:\n    - tool: Bash\n      pattern: ''\n      commandPattern: git push --force*\n      action: block\n"
+    ));
+    assert_eq!(edit.rule.id.to_string(), "tool-1");
+
+    // An empty file gets a new preToolUse section
+    let edit = add_rule(
+        "",
+        config_path(),
+        RuleList::Uneditable,
+        Value::from("*.lock"),
+    )
+    .unwrap();
+    assert_eq!(
+        edit.content,
+        "preToolUse:\n  uneditableFiles:\n    - '*.lock'\n"
+    );
+}
+
+#[test]
+fn test_add_rule_rejects_invalid_configuration() {
+    let error = add_rule(
+        CONFIG,
+        config_path(),
+        RuleList::Uneditable,
+        Value::from("[bad"),
+    )
+    .unwrap_err();
+    assert!(format!("{error:#}").contains("Invalid glob pattern"));
+}
+
+#[test]
+fn test_remove_rule_takes_its_comments() {
+    let id = "uneditable-1".parse().unwrap();
+    let edit = remove_rule(CONFIG, config_path(), id).unwrap();
+    assert!(edit.comments_preserved);
+    assert_eq!(edit.rule.description, "\"Cargo.lock\"");
+    assert_eq!(
+        edit.content,
+        r#"# Project rules
+preToolUse:
+  # Root stays clean
+  preventRootAdditions: true
+  uneditableFiles:
+    - pattern: ".env*"
+      message: "No secrets"
+"#
+    );
+
+    // Removing the last rule leaves an empty list
+    let edit = remove_rule(&edit.content, config_path(), id).unwrap();
+    assert!(edit.content.ends_with("  uneditableFiles: []\n"));
+
+    let error = remove_rule(CONFIG, config_path(), "tool-1".parse().unwrap()).unwrap_err();
+    assert!(error.to_string().contains("No rule tool-1"));
+}